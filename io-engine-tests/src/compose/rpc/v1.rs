@@ -44,12 +44,16 @@ impl PartialEq for SharedRpcHandle {
 pub struct RpcHandle {
     pub name: String,
     pub endpoint: SocketAddr,
+    pub audit: audit::AuditRpcClient<Channel>,
     pub bdev: bdev::BdevRpcClient<Channel>,
+    pub discovery: discovery::DiscoveryRpcClient<Channel>,
     pub json: json::JsonRpcClient<Channel>,
     pub pool: pool::PoolRpcClient<Channel>,
     pub replica: replica::ReplicaRpcClient<Channel>,
     pub host: host::HostRpcClient<Channel>,
+    pub initiator: initiator::InitiatorRpcClient<Channel>,
     pub nexus: nexus::NexusRpcClient<Channel>,
+    pub operation: operation::OperationRpcClient<Channel>,
     pub snapshot: snapshot::SnapshotRpcClient<Channel>,
     pub stats: stats::StatsRpcClient<Channel>,
     pub test: test::TestRpcClient<Channel>,
@@ -76,10 +80,21 @@ impl RpcHandle {
             }
         }
 
+        let audit =
+            audit::AuditRpcClient::connect(format!("http://{endpoint}"))
+                .await
+                .unwrap();
+
         let bdev = bdev::BdevRpcClient::connect(format!("http://{endpoint}"))
             .await
             .unwrap();
 
+        let discovery = discovery::DiscoveryRpcClient::connect(format!(
+            "http://{endpoint}"
+        ))
+        .await
+        .unwrap();
+
         let json = json::JsonRpcClient::connect(format!("http://{endpoint}"))
             .await
             .unwrap();
@@ -97,11 +112,23 @@ impl RpcHandle {
             .await
             .unwrap();
 
+        let initiator = initiator::InitiatorRpcClient::connect(format!(
+            "http://{endpoint}"
+        ))
+        .await
+        .unwrap();
+
         let nexus =
             nexus::NexusRpcClient::connect(format!("http://{endpoint}"))
                 .await
                 .unwrap();
 
+        let operation = operation::OperationRpcClient::connect(format!(
+            "http://{endpoint}"
+        ))
+        .await
+        .unwrap();
+
         let snapshot =
             snapshot::SnapshotRpcClient::connect(format!("http://{endpoint}"))
                 .await
@@ -119,12 +146,16 @@ impl RpcHandle {
         Ok(Self {
             name,
             endpoint,
+            audit,
             bdev,
+            discovery,
             json,
             pool,
             replica,
             host,
+            initiator,
             nexus,
+            operation,
             snapshot,
             stats,
             test,
@@ -151,7 +151,7 @@ pub fn mayastor_test_init_ex(log_format: LogFormat) {
             }
         });
 
-    logger::init_ex("info,io_engine=DEBUG", log_format, None);
+    logger::init_ex("info,io_engine=DEBUG", log_format, None, None);
 
     io_engine::CPS_INIT!();
 }
@@ -18,6 +18,7 @@ use super::{
             RebuildHistoryRequest,
             RemoveChildNexusRequest,
             ResizeNexusRequest,
+            SetNexusNvmeParamsRequest,
             ShutdownNexusRequest,
         },
         snapshot::SnapshotInfo,
@@ -212,6 +213,11 @@ impl NexusBuilder {
                 nexus_info_key: self.nexus_info_key.as_ref().unwrap().clone(),
                 resv_type: self.resv_type,
                 preempt_policy: self.preempt_policy,
+                layout: 0,
+                stripe_size: 0,
+                mirror_copies: 0,
+                cache: None,
+                pi_policy: 0,
             })
             .await
             .map(|r| r.into_inner().nexus.unwrap())
@@ -236,6 +242,7 @@ impl NexusBuilder {
             .nexus
             .destroy_nexus(DestroyNexusRequest {
                 uuid: self.uuid(),
+                purge: false,
             })
             .await
             .map(|_| ())
@@ -269,6 +276,22 @@ impl NexusBuilder {
             .map(|r| r.into_inner().nexus.unwrap())
     }
 
+    pub async fn set_nvme_params(
+        &self,
+        params: SetNexusNvmeParamsRequest,
+    ) -> Result<Nexus, Status> {
+        self.rpc()
+            .lock()
+            .await
+            .nexus
+            .set_nexus_nvme_params(SetNexusNvmeParamsRequest {
+                uuid: self.uuid(),
+                ..params
+            })
+            .await
+            .map(|r| r.into_inner().nexus.unwrap())
+    }
+
     pub async fn add_child(
         &self,
         bdev: &str,
@@ -282,6 +305,7 @@ impl NexusBuilder {
                 uuid: self.uuid(),
                 uri: bdev.to_owned(),
                 norebuild,
+                replace_uri: None,
             })
             .await
             .map(|r| r.into_inner().nexus.unwrap())
@@ -188,3 +188,44 @@ pub fn get_nvme_resv_report(nvme_dev: &str) -> serde_json::Value {
         serde_json::from_str(&resv_rep).expect("JSON was not well-formatted");
     v
 }
+
+/// Registers a reservation key on the namespace 1 of the device, activating
+/// Persist Through Power Loss if `ptpl` is set, or leaving it unchanged.
+pub fn nvme_resv_register(nvme_dev: &str, key: u64, ptpl: bool) {
+    let cptpl = if ptpl { "3" } else { "0" };
+    let output_resv = Command::new("nvme")
+        .args(["resv-register"])
+        .args([nvme_dev])
+        .args(["-n", "1"])
+        .args(["--nrkey", &key.to_string()])
+        .args(["--rrega", "0"])
+        .args(["--cptpl", cptpl])
+        .output()
+        .unwrap();
+    assert!(
+        output_resv.status.success(),
+        "failed to register reservation key on {}: {}",
+        nvme_dev,
+        output_resv.status
+    );
+}
+
+/// Acquires a reservation of the given type on the namespace 1 of the device
+/// with a registered key.
+pub fn nvme_resv_acquire(nvme_dev: &str, key: u64, rtype: u8) {
+    let output_resv = Command::new("nvme")
+        .args(["resv-acquire"])
+        .args([nvme_dev])
+        .args(["-n", "1"])
+        .args(["--crkey", &key.to_string()])
+        .args(["--rtype", &rtype.to_string()])
+        .args(["--racqa", "0"])
+        .output()
+        .unwrap();
+    assert!(
+        output_resv.status.success(),
+        "failed to acquire reservation on {}: {}",
+        nvme_dev,
+        output_resv.status
+    );
+}
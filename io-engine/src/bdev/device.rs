@@ -4,6 +4,7 @@
 use std::{
     collections::HashMap,
     convert::TryFrom,
+    ops::Range,
     os::raw::c_void,
     sync::{Arc, Mutex},
 };
@@ -52,6 +53,7 @@ use crate::core::{
     IoCompletionCallbackArg,
     IoCompletionStatus,
     NvmeStatus,
+    ProtectionInfo,
     ReadOptions,
     SnapshotParams,
     ToErrno,
@@ -67,7 +69,11 @@ use crate::core::fault_injection::{
     FaultDomain,
     InjectIoCtx,
 };
-use crate::replica_backend::ReplicaFactory;
+use crate::{
+    core::logical_volume::LogicalVolume,
+    lvs::Lvol,
+    replica_backend::ReplicaFactory,
+};
 
 /// TODO
 type EventDispatcherMap = HashMap<String, DeviceEventDispatcher>;
@@ -172,6 +178,28 @@ impl BlockDevice for SpdkBlockDevice {
         disp.add_listener(listener);
         Ok(())
     }
+    /// returns the allocated ranges, for logical volumes only
+    fn allocated_ranges(&self, range: Range<u64>) -> Option<Vec<Range<u64>>> {
+        Lvol::try_from(self.0)
+            .ok()
+            .map(|lvol| lvol.allocated_ranges(range))
+    }
+    /// returns the instance identifier, for logical volumes only
+    fn instance_id(&self) -> Option<u64> {
+        Lvol::try_from(self.0)
+            .ok()
+            .and_then(|lvol| lvol.instance_id())
+    }
+    /// returns the metadata and protection information format
+    fn protection_info(&self) -> ProtectionInfo {
+        let bdev = unsafe { &*self.0.unsafe_inner_ptr() };
+        ProtectionInfo {
+            md_len: bdev.md_len,
+            md_interleave: bdev.md_interleave,
+            dif_type: bdev.dif_type.into(),
+            dif_is_head_of_md: bdev.dif_is_head_of_md,
+        }
+    }
 }
 
 /// Wrapper around native SPDK block device descriptor, which mimics target SPDK
@@ -21,7 +21,6 @@ use spdk_rs::{
         delete_malloc_disk,
         malloc_bdev_opts,
         spdk_bdev,
-        SPDK_DIF_DISABLE,
     },
     UntypedBdev,
 };
@@ -29,7 +28,7 @@ use spdk_rs::{
 use crate::{
     bdev::{dev::reject_unknown_parameters, util::uri, CreateDestroy, GetName},
     bdev_api::{self, BdevError},
-    core::VerboseError,
+    core::{DifType, VerboseError},
     ffihelper::{cb_arg, done_errno_cb, ErrnoResult, IntoCString},
 };
 
@@ -45,6 +44,10 @@ pub struct Malloc {
     blk_size: u32,
     /// uuid of the spdk bdev
     uuid: Option<uuid::Uuid>,
+    /// size of the metadata interleaved with the data of each block
+    md_size: u32,
+    /// type of the protection information held in the metadata
+    dif_type: DifType,
 }
 
 impl Debug for Malloc {
@@ -99,6 +102,40 @@ impl TryFrom<&Url> for Malloc {
                 0
             };
 
+        let md_size: u32 = if let Some(value) = parameters.remove("md_size") {
+            value.parse().context(bdev_api::IntParamParseFailed {
+                uri: uri.to_string(),
+                parameter: String::from("md_size"),
+                value: value.clone(),
+            })?
+        } else {
+            0
+        };
+
+        let dif_type = if let Some(value) = parameters.remove("dif_type") {
+            match value.parse::<u32>().context(
+                bdev_api::IntParamParseFailed {
+                    uri: uri.to_string(),
+                    parameter: String::from("dif_type"),
+                    value: value.clone(),
+                },
+            )? {
+                0 => DifType::Disabled,
+                1 => DifType::Type1,
+                2 => DifType::Type2,
+                3 => DifType::Type3,
+                _ => {
+                    return Err(BdevError::InvalidUri {
+                        uri: uri.to_string(),
+                        message: "'dif_type' must be one of: 0, 1, 2, 3"
+                            .to_string(),
+                    })
+                }
+            }
+        } else {
+            DifType::Disabled
+        };
+
         let uuid = uri::uuid(parameters.remove("uuid")).context(
             bdev_api::UuidParamParseFailed {
                 uri: uri.to_string(),
@@ -115,6 +152,20 @@ impl TryFrom<&Url> for Malloc {
             });
         }
 
+        if md_size != 0 && md_size != 8 && md_size != 16 {
+            return Err(BdevError::InvalidUri {
+                uri: uri.to_string(),
+                message: "'md_size' must be one of: 0, 8, 16".to_string(),
+            });
+        }
+
+        if dif_type != DifType::Disabled && md_size == 0 {
+            return Err(BdevError::InvalidUri {
+                uri: uri.to_string(),
+                message: "'dif_type' requires 'md_size'".to_string(),
+            });
+        }
+
         if size != 0 && num_blocks != 0 {
             return Err(BdevError::InvalidUri {
                 uri: uri.to_string(),
@@ -141,6 +192,8 @@ impl TryFrom<&Url> for Malloc {
             } as u64,
             blk_size,
             uuid,
+            md_size,
+            dif_type,
         })
     }
 }
@@ -175,9 +228,10 @@ impl CreateDestroy for Malloc {
                 block_size: self.blk_size,
                 physical_block_size: 0,
                 optimal_io_boundary: 0,
-                md_size: 0,
-                md_interleave: false,
-                dif_type: SPDK_DIF_DISABLE,
+                md_size: self.md_size,
+                // The metadata is interleaved, as with NVMe-oF namespaces.
+                md_interleave: self.md_size != 0,
+                dif_type: self.dif_type.into(),
                 dif_is_head_of_md: false,
             };
 
@@ -4,6 +4,8 @@ pub use dev::{device_create, device_destroy, device_lookup, device_open};
 pub use device::{bdev_event_callback, bdev_io_ctx_pool_init, SpdkBlockDevice};
 pub use nexus::{Nexus, NexusInfo, NexusState};
 pub use nvmx::{
+    nvme_bdev_running_config,
+    nvme_bdev_update_running_config,
     nvme_io_ctx_pool_init,
     NvmeController,
     NvmeControllerState,
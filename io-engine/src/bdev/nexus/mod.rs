@@ -11,16 +11,19 @@ mod nexus_bdev_children;
 mod nexus_bdev_error;
 mod nexus_bdev_rebuild;
 mod nexus_bdev_snapshot;
+mod nexus_cache;
 mod nexus_channel;
 mod nexus_child;
 mod nexus_io;
 mod nexus_io_log;
 mod nexus_io_subsystem;
 mod nexus_iter;
+mod nexus_layout;
 mod nexus_module;
 mod nexus_nbd;
 mod nexus_persistence;
 mod nexus_share;
+mod nexus_spares;
 
 use crate::{
     bdev::nexus::nexus_iter::NexusIterMut,
@@ -34,6 +37,7 @@ pub use nexus_bdev::{
     NexusNvmeParams,
     NexusNvmePreemption,
     NexusOperation,
+    NexusPiPolicy,
     NexusState,
     NexusStatus,
     NexusTarget,
@@ -42,6 +46,13 @@ pub use nexus_bdev::{
 };
 pub(crate) use nexus_bdev_error::nexus_err;
 pub use nexus_bdev_error::Error;
+use nexus_cache::NexusCache;
+pub use nexus_cache::{
+    CachePolicy,
+    CacheState,
+    NexusCacheConfig,
+    NexusCacheStats,
+};
 pub(crate) use nexus_channel::{DrEvent, IoMode, NexusChannel};
 pub use nexus_child::{
     ChildError,
@@ -65,11 +76,18 @@ pub use nexus_iter::{
     nexus_lookup_nqn_mut,
     nexus_lookup_uuid_mut,
 };
+pub use nexus_layout::{NexusLayout, StripeGeometry};
 pub(crate) use nexus_module::{NexusModule, NEXUS_MODULE_NAME};
 pub(crate) use nexus_nbd::{NbdDisk, NbdError};
 pub(crate) use nexus_persistence::PersistOp;
-pub use nexus_persistence::{ChildInfo, NexusInfo};
+pub use nexus_persistence::{
+    ChildInfo,
+    NexusInfo,
+    RegistrantInfo,
+    ReservationInfo,
+};
 pub(crate) use nexus_share::NexusPtpl;
+pub use nexus_spares::SparePolicy;
 
 pub use nexus_bdev_snapshot::{
     NexusReplicaSnapshotDescriptor,
@@ -24,16 +24,23 @@ use snafu::ResultExt;
 use uuid::Uuid;
 
 use super::{
+    nexus_bdev_rebuild::PendingRebuilds,
     nexus_err,
     nexus_lookup_name_uuid,
+    nexus_spares::NexusSpares,
     DrEvent,
     Error,
+    IOLog,
     NbdDisk,
     NexusBio,
+    NexusCache,
+    NexusCacheConfig,
     NexusChannel,
     NexusChild,
+    NexusLayout,
     NexusModule,
     PersistOp,
+    StripeGeometry,
 };
 
 use crate::{
@@ -41,17 +48,19 @@ use crate::{
         device_destroy,
         nexus::{
             nexus_io_subsystem::NexusPauseState,
-            nexus_persistence::PersistentNexusInfo,
+            nexus_persistence::{NexusReservations, PersistentNexusInfo},
             NexusIoSubsystem,
             ENABLE_NEXUS_RESET,
         },
         PtplFileOps,
     },
+    constants::TRACING_TARGET,
     core::{
         partition,
         Bdev,
         DeviceEventSink,
         IoType,
+        ProtectionInfo,
         Protocol,
         Reactor,
         Reactors,
@@ -174,8 +183,26 @@ pub enum NexusNvmePreemption {
     Holder,
 }
 
+/// Handling of the protection information of the blocks written and read
+/// through a nexus whose children have a block format with protection
+/// information.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum NexusPiPolicy {
+    /// The protection information is passed through as is, and only the
+    /// children check it.
+    Passthrough,
+    /// The protection information of the written and read blocks is
+    /// verified by the nexus.
+    #[default]
+    Verify,
+    /// The protection information of the written blocks is generated by the
+    /// nexus, for the hosts which don't provide it, and the one of the read
+    /// blocks is verified.
+    Generate,
+}
+
 /// NVMe-specific parameters for the Nexus.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct NexusNvmeParams {
     /// The minimum NVMe controller ID for sharing over NVMf.
     pub(crate) min_cntlid: u16,
@@ -189,6 +216,8 @@ pub struct NexusNvmeParams {
     pub(crate) resv_type: NvmeReservation,
     /// NVMe Preempting policy.
     pub(crate) preempt_policy: NexusNvmePreemption,
+    /// Protection information policy.
+    pub(crate) pi_policy: NexusPiPolicy,
 }
 
 impl Default for NexusNvmeParams {
@@ -200,6 +229,7 @@ impl Default for NexusNvmeParams {
             preempt_key: None,
             resv_type: NvmeReservation::WriteExclusiveAllRegs,
             preempt_policy: NexusNvmePreemption::ArgKey,
+            pi_policy: NexusPiPolicy::default(),
         }
     }
 }
@@ -232,6 +262,10 @@ impl NexusNvmeParams {
     pub fn set_preempt_policy(&mut self, preempt_policy: NexusNvmePreemption) {
         self.preempt_policy = preempt_policy;
     }
+    /// Set the protection information policy.
+    pub fn set_pi_policy(&mut self, pi_policy: NexusPiPolicy) {
+        self.pi_policy = pi_policy;
+    }
     /// Check if reservations are enabled.
     pub fn reservations_enabled(&self) -> bool {
         self.resv_key != 0
@@ -244,6 +278,27 @@ impl NexusNvmeParams {
             || (matches!(self.preempt_policy, NexusNvmePreemption::Holder)
                 && self.preempt_key.is_some()))
     }
+    /// Check the parameters of the given nexus.
+    fn validate(&self, name: &str) -> Result<(), Error> {
+        let args = if self.min_cntlid < NVME_MIN_CNTLID
+            || self.min_cntlid > self.max_cntlid
+            || self.max_cntlid > NVME_MAX_CNTLID
+        {
+            format!(
+                "invalid NVMe controller ID range [{:x}h, {:x}h]",
+                self.min_cntlid, self.max_cntlid
+            )
+        } else if self.reservations_enabled() && !self.reservations_valid() {
+            "invalid NVMe reservation parameters".to_string()
+        } else {
+            return Ok(());
+        };
+
+        Err(Error::InvalidArguments {
+            name: name.to_owned(),
+            args,
+        })
+    }
 }
 
 /// The main nexus structure
@@ -258,6 +313,15 @@ pub struct Nexus<'n> {
     pub(super) children: Vec<NexusChild<'n>>,
     /// NVMe parameters
     pub(crate) nvme_params: NexusNvmeParams,
+    /// Data layout of the nexus.
+    layout: NexusLayout,
+    /// Number of stripe sets the children are grouped in. Always 1 for a
+    /// mirrored nexus.
+    num_sets: usize,
+    /// Stripe geometry, for striped layouts once the nexus is open.
+    stripe: Option<StripeGeometry>,
+    /// Metadata and protection information format of the children.
+    protection: ProtectionInfo,
     /// uuid of the nexus (might not be the same as the nexus bdev!)
     nexus_uuid: Uuid,
     /// Bdev wrapper instance.
@@ -280,8 +344,22 @@ pub struct Nexus<'n> {
     event_sink: Option<DeviceEventSink>,
     /// Rebuild history of all children of this nexus instance.
     pub(super) rebuild_history: parking_lot::Mutex<Vec<HistoryRecord>>,
+    /// Spare replicas used in place of the retired children.
+    pub(super) spares: parking_lot::Mutex<NexusSpares>,
+    /// Log of the writes yet to be shipped to the asynchronous replica.
+    pub(super) replication_log: parking_lot::Mutex<Option<IOLog>>,
+    /// Log of the writes made since the nexus was created, for the children
+    /// whose rebuild by a previous nexus of the volume can be resumed.
+    pub(super) pending_rebuilds: parking_lot::Mutex<Option<PendingRebuilds>>,
+    /// Cache tier in front of the children.
+    pub(super) cache: Option<NexusCache>,
+    /// Reservations of the published nexus on their way from or to the
+    /// persistent store.
+    pub(super) reservations: parking_lot::Mutex<NexusReservations>,
     /// Flag to control shutdown from I/O path.
     pub(crate) shutdown_requested: AtomicCell<bool>,
+    /// Set once the nexus gets its first write-like I/O.
+    data_written: AtomicCell<bool>,
     /// Last child I/O error.
     pub(super) last_error: IoCompletionStatus,
     /// Prevent auto-Unpin.
@@ -378,6 +456,7 @@ impl BdevStater for Nexus<'_> {
 impl<'n> Nexus<'n> {
     /// create a new nexus instance with optionally directly attaching
     /// children to it.
+    #[allow(clippy::too_many_arguments)]
     fn new(
         name: &str,
         size: u64,
@@ -385,6 +464,8 @@ impl<'n> Nexus<'n> {
         nexus_uuid: Option<uuid::Uuid>,
         nvme_params: NexusNvmeParams,
         nexus_info_key: Option<String>,
+        layout: NexusLayout,
+        cache: Option<NexusCacheConfig>,
     ) -> spdk_rs::Bdev<Nexus<'n>> {
         let n = Nexus {
             name: name.to_string(),
@@ -395,6 +476,10 @@ impl<'n> Nexus<'n> {
             req_size: size,
             nexus_target: None,
             nvme_params,
+            layout,
+            num_sets: 1,
+            stripe: None,
+            protection: ProtectionInfo::default(),
             has_io_device: false,
             initiators: parking_lot::Mutex::new(HashSet::new()),
             nexus_info: futures::lock::Mutex::new(PersistentNexusInfo::new(
@@ -404,7 +489,13 @@ impl<'n> Nexus<'n> {
             nexus_uuid: Default::default(),
             event_sink: None,
             rebuild_history: parking_lot::Mutex::new(Vec::new()),
+            spares: parking_lot::Mutex::new(NexusSpares::default()),
+            replication_log: parking_lot::Mutex::new(None),
+            pending_rebuilds: parking_lot::Mutex::new(None),
+            cache: cache.map(NexusCache::new),
+            reservations: parking_lot::Mutex::new(NexusReservations::default()),
             shutdown_requested: AtomicCell::new(false),
+            data_written: AtomicCell::new(false),
             last_error: IoCompletionStatus::Success,
             _pin: Default::default(),
         };
@@ -555,6 +646,64 @@ impl<'n> Nexus<'n> {
         self.req_size
     }
 
+    /// Returns the data layout of the nexus.
+    pub fn layout(&self) -> NexusLayout {
+        self.layout
+    }
+
+    /// Returns the number of stripe sets of the nexus.
+    pub fn num_sets(&self) -> usize {
+        self.num_sets
+    }
+
+    /// Returns the stripe geometry of a striped nexus.
+    pub(crate) fn stripe_geometry(&self) -> Option<StripeGeometry> {
+        self.stripe
+    }
+
+    /// Returns the metadata and protection information format of the
+    /// nexus, which is the one of all its children.
+    pub fn protection_info(&self) -> ProtectionInfo {
+        self.protection
+    }
+
+    /// Returns the protection information policy of the nexus.
+    pub fn pi_policy(&self) -> NexusPiPolicy {
+        self.nvme_params.pi_policy
+    }
+
+    /// Checks if the nexus has had any data modified since it was created.
+    pub(crate) fn is_data_written(&self) -> bool {
+        self.data_written.load()
+    }
+
+    /// Marks the nexus as having data modified.
+    #[inline(always)]
+    pub(super) fn set_data_written(&self) {
+        if !self.data_written.load() {
+            self.data_written.store(true);
+        }
+    }
+
+    /// Returns the size in bytes each child must provide to hold its part of
+    /// the nexus data. For striped layouts, this is rounded up to a whole
+    /// number of stripes.
+    pub fn child_req_size(&self) -> u64 {
+        match self.layout.stripe_size() {
+            None => self.req_size,
+            Some(stripe_size) => {
+                let sets = self.num_sets as u64;
+                let set_size = (self.req_size + sets - 1) / sets;
+                (set_size + stripe_size - 1) / stripe_size * stripe_size
+            }
+        }
+    }
+
+    /// Returns the number of data blocks the nexus occupies on each child.
+    pub(crate) fn child_data_blocks(&self) -> u64 {
+        self.num_blocks() / self.num_sets as u64
+    }
+
     /// Returns the actual size of the Nexus instance, in bytes.
     pub fn size_in_bytes(&self) -> u64 {
         unsafe { self.bdev().size_in_bytes() }
@@ -595,7 +744,14 @@ impl<'n> Nexus<'n> {
         self: Pin<&mut Self>,
         child: NexusChild<'n>,
     ) {
-        self.unpin_mut().children.push(child)
+        // Keep the children of a stripe set together, so that a child
+        // replacing another ends up at its position once it is removed.
+        let children = &mut self.unpin_mut().children;
+        let pos = children
+            .iter()
+            .rposition(|c| c.stripe_set() == child.stripe_set())
+            .map_or(children.len(), |i| i + 1);
+        children.insert(pos, child)
     }
 
     /// Removes a child with given URI.
@@ -704,10 +860,17 @@ impl<'n> Nexus<'n> {
             });
         }
 
+        if !resizing {
+            let num_sets = self.layout.num_sets(self.children().len());
+            unsafe { self.as_mut().set_num_sets(num_sets) };
+        }
+        let child_size = self.child_req_size();
+
         // Determine Nexus block size and data start and end offsets.
         let mut start_blk = 0;
         let mut end_blk = 0;
         let mut blk_size = 0;
+        let mut pi = None;
         let mut min_dev_size = u64::MAX;
 
         for child in self.children_iter() {
@@ -737,7 +900,27 @@ impl<'n> Nexus<'n> {
                 });
             }
 
-            match partition::calc_data_partition(self.req_size(), nb, bs) {
+            // Blocks are copied as they are between children, protection
+            // information included, so all must have the same format.
+            let child_pi = dev.protection_info();
+            if !child_pi.is_supported() {
+                return Err(Error::UnsupportedProtectionInfo {
+                    child: child.uri().to_owned(),
+                    name,
+                    reason: child_pi.to_string(),
+                });
+            }
+            match pi {
+                None => pi = Some(child_pi),
+                Some(pi) if pi != child_pi => {
+                    return Err(Error::MixedProtectionInfo {
+                        name,
+                    });
+                }
+                _ => {}
+            }
+
+            match partition::calc_data_partition(child_size, nb, bs) {
                 Some((start, end, req_blocks)) => {
                     // During expansion - if the requested number of blocks
                     // aren't available on any child device,
@@ -746,7 +929,7 @@ impl<'n> Nexus<'n> {
                     // returned is greater than the current end block number.
                     // XXX: A shrink operation has to be taken care of later, if
                     // needed.
-                    if resizing && (end <= (start + self.num_blocks())) {
+                    if resizing && (end <= (start + self.child_data_blocks())) {
                         return Err(Error::ChildTooSmall {
                             child: child.uri().to_owned(),
                             name,
@@ -775,23 +958,73 @@ impl<'n> Nexus<'n> {
                         name,
                         num_blocks: nb,
                         block_size: bs,
-                        req_blocks: self.req_size() / bs,
+                        req_blocks: child_size / bs,
                     })
                 }
             }
         }
 
+        let pi = pi.unwrap_or_default();
+        if resizing && pi != self.protection {
+            return Err(Error::MixedProtectionInfo {
+                name,
+            });
+        }
+
+        // For striped layouts, only whole stripes are used on each child,
+        // and the nexus spans the data of all stripe sets.
+        let mut data_blocks = end_blk - start_blk;
+        let stripe = match self.layout.stripe_size() {
+            Some(stripe_size) => {
+                // The reference tags can't follow the nexus LBAs once they
+                // are spread over the stripe sets.
+                if pi.is_enabled() {
+                    return Err(Error::InvalidArguments {
+                        name,
+                        args: "protection information is not supported \
+                            with a striped layout"
+                            .to_string(),
+                    });
+                }
+                if stripe_size % blk_size != 0 {
+                    return Err(Error::InvalidArguments {
+                        name,
+                        args: format!(
+                            "stripe size {stripe_size} is not a multiple of \
+                            block size {blk_size}"
+                        ),
+                    });
+                }
+                let geom =
+                    StripeGeometry::new(stripe_size / blk_size, self.num_sets);
+                data_blocks = data_blocks / geom.stripe_blocks
+                    * geom.stripe_blocks
+                    * geom.num_sets;
+                Some(geom)
+            }
+            None => None,
+        };
+
         unsafe {
             self.as_mut().set_data_ent_offset(start_blk);
             self.as_mut().set_block_len(blk_size as u32);
+            self.as_mut().set_stripe(stripe);
+            self.as_mut().set_protection(pi);
             let nbdev = self.as_mut().bdev_mut().unsafe_inner_mut_ptr();
+            (*nbdev).md_len = pi.md_len;
+            (*nbdev).md_interleave = pi.md_interleave;
+            (*nbdev).dif_type = pi.dif_type.into();
+            (*nbdev).dif_is_head_of_md = pi.dif_is_head_of_md;
+            if let Some(geom) = stripe {
+                // Let the bdev layer split I/Os at stripe boundaries, so that
+                // every I/O touches each stripe set at most once.
+                (*nbdev).optimal_io_boundary = geom.stripe_blocks as u32;
+                (*nbdev).split_on_optimal_io_boundary = true;
+            }
             if !resizing {
-                self.as_mut().set_num_blocks(end_blk - start_blk);
+                self.as_mut().set_num_blocks(data_blocks);
             } else {
-                let rc = spdk_bdev_notify_blockcnt_change(
-                    nbdev,
-                    end_blk - start_blk,
-                );
+                let rc = spdk_bdev_notify_blockcnt_change(nbdev, data_blocks);
                 if rc != 0 {
                     error!(
                         "{self:?}: failed to notify block cnt change on nexus"
@@ -809,8 +1042,10 @@ impl<'n> Nexus<'n> {
             requested={req_blk} blocks ({req} bytes) \
             start block={start_blk}, end block={end_blk}, \
             block size={blk_size}, \
-            smallest devices size={min_dev_size} blocks",
+            smallest devices size={min_dev_size} blocks, \
+            layout={layout}, metadata={pi}",
             action = if resizing { "resized" } else { "initialized" },
+            layout = self.layout,
             req_blk = self.req_size() / blk_size,
             req = self.req_size(),
         );
@@ -861,6 +1096,18 @@ impl<'n> Nexus<'n> {
             }
         };
 
+        if let Err(err) = nex.open_cache().await {
+            error!("{:?} failed to open cache: {}", nex, err.verbose());
+            bdev.unregister_bdev();
+            return Err(err);
+        }
+
+        if let Err(err) = nex.check_cache_destaged().await {
+            nex.close_cache().await;
+            bdev.unregister_bdev();
+            return Err(err);
+        }
+
         // Persist the fact that the nexus is now successfully open.
         // We have to do this before setting the nexus to open so that
         // nexus list does not return this nexus until it is persisted.
@@ -869,10 +1116,18 @@ impl<'n> Nexus<'n> {
                 "{nex:?}: failed to create nexus because of \
                 persistent store update failure, unregistering bdev: {e}"
             );
+            nex.close_cache().await;
             bdev.unregister_bdev();
             return Err(e);
         }
 
+        // Carry over the rebuild history and the spares of the previous
+        // nexuses of this volume, and log the writes for the rebuilds they
+        // left to resume.
+        nex.load_rebuild_history().await;
+        nex.load_spares().await;
+        nex.start_pending_rebuild_logs().await;
+
         nex.as_mut().set_state(NexusState::Open);
         info!("{:?}: nexus bdev registered successfully", nex);
 
@@ -884,6 +1139,20 @@ impl<'n> Nexus<'n> {
         self.destroy_ext(false).await
     }
 
+    /// Destroys the nexus for good, along with the state it keeps in the
+    /// persistent store for the next nexus of the volume: the rebuild
+    /// checkpoints and history, and the reservations. This is for volumes
+    /// which are gone, while the nexus of a volume which moves elsewhere is
+    /// destroyed with `destroy`.
+    pub async fn destroy_purge(self: Pin<&mut Self>) -> Result<(), Error> {
+        let keys = self.volume_state_keys().await;
+        self.destroy_ext(false).await?;
+        if let Some(keys) = keys {
+            keys.delete().await;
+        }
+        Ok(())
+    }
+
     /// Destroy the Nexus.
     /// # Arguments
     /// * `sigterm`: Indicates whether this is as a result of process
@@ -903,6 +1172,9 @@ impl<'n> Nexus<'n> {
             self.as_mut().cancel_rebuild_jobs(&child).await;
         }
 
+        // Destage the cache while the children are still open.
+        self.close_cache().await;
+
         self.close_children().await;
 
         // Persist the fact that the nexus destruction has completed.
@@ -982,6 +1254,11 @@ impl<'n> Nexus<'n> {
     /// Resumes I/O to the Bdev.
     /// Note: in order to handle concurrent resumes properly, this function must
     /// be called only from the master core.
+    #[tracing::instrument(
+        target = TRACING_TARGET,
+        skip_all,
+        fields(nexus = %self.name)
+    )]
     pub async fn resume(self: Pin<&mut Self>) -> Result<(), Error> {
         // If we are faulted then rather than failing all IO back to the
         // initiator we can instead leave the subsystem frozen, and wait
@@ -1128,6 +1405,11 @@ impl<'n> Nexus<'n> {
     /// with the nexus paused once they are awakened via resume().
     /// Note: in order to handle concurrent pauses properly, this function must
     /// be called only from the master core.
+    #[tracing::instrument(
+        target = TRACING_TARGET,
+        skip_all,
+        fields(nexus = %self.name)
+    )]
     pub async fn pause(mut self: Pin<&mut Self>) -> Result<(), Error> {
         EventWithMeta::event(
             self.deref(),
@@ -1199,6 +1481,94 @@ impl<'n> Nexus<'n> {
         })
     }
 
+    /// Changes the NVMe parameters of the nexus, but its protection
+    /// information policy. The reservations of the children are acquired
+    /// again with the new ones, with the I/O paused. If the nexus is
+    /// published, the new controller ID range applies at once, once the
+    /// reservations are acquired: its subsystem is restarted, and its hosts
+    /// reconnect. The previous parameters are restored if either fails.
+    pub async fn set_nvme_params(
+        mut self: Pin<&mut Self>,
+        mut params: NexusNvmeParams,
+    ) -> Result<(), Error> {
+        params.validate(&self.name)?;
+        params.pi_policy = self.nvme_params.pi_policy;
+
+        info!("{self:?}: changing NVMe parameters to {params:?}...");
+
+        let range = (params.min_cntlid, params.max_cntlid);
+        let range_changed =
+            range != (self.nvme_params.min_cntlid, self.nvme_params.max_cntlid);
+        let prev = self.as_mut().update_reservations(params).await?;
+
+        if range_changed && self.shared() == Some(Protocol::Nvmf) {
+            if let Some(subsystem) = NvmfSubsystem::nqn_lookup(&self.name) {
+                if let Err(error) =
+                    subsystem.change_cntlid_range(range.0, range.1).await
+                {
+                    // Go back to the previous reservations, as far as
+                    // possible.
+                    if let Err(error) =
+                        self.as_mut().update_reservations(prev).await
+                    {
+                        error!(
+                            "{self:?}: failed to restore the previous \
+                            reservations: {error}"
+                        );
+                    }
+                    return Err(error.into());
+                }
+            }
+        }
+
+        info!("{self:?}: changed NVMe parameters");
+        Ok(())
+    }
+
+    /// Replaces the NVMe parameters of the nexus, and acquires the
+    /// reservations of its children again with the new ones, with the I/O
+    /// paused. The previous parameters are restored on failure.
+    /// Returns the previous parameters.
+    async fn update_reservations(
+        mut self: Pin<&mut Self>,
+        params: NexusNvmeParams,
+    ) -> Result<NexusNvmeParams, Error> {
+        self.as_mut().pause().await?;
+        let prev = std::mem::replace(
+            unsafe { &mut self.as_mut().get_unchecked_mut().nvme_params },
+            params,
+        );
+        let res = self.reacquire_reservations().await;
+        if res.is_err() {
+            // Go back to the previous reservations, as far as possible.
+            unsafe {
+                self.as_mut().get_unchecked_mut().nvme_params = prev.clone();
+            }
+            if let Err(error) = self.reacquire_reservations().await {
+                error!(
+                    "{self:?}: failed to restore the previous reservations: \
+                    {error}"
+                );
+            }
+        }
+        self.as_mut().resume().await?;
+        res.map(|_| prev)
+    }
+
+    /// Acquires the reservations of the open children again, after a change
+    /// of the NVMe parameters.
+    async fn reacquire_reservations(&self) -> Result<(), Error> {
+        for child in self.children_iter().filter(|c| c.is_opened()) {
+            child.reservation_update(&self.nvme_params).await.context(
+                nexus_err::ChildWriteExclusiveResvFailed {
+                    child: child.uri().to_owned(),
+                    name: self.name.clone(),
+                },
+            )?;
+        }
+        Ok(())
+    }
+
     /// determine if any of the children do not support the requested
     /// io type. Break the loop on first occurrence.
     /// TODO: optionally add this check during nexus creation
@@ -1239,12 +1609,13 @@ impl<'n> Nexus<'n> {
                     .all(|c| c.is_healthy())
                 {
                     NexusStatus::Online
-                } else if self
-                    .children
-                    .iter()
-                    // at least one child online, so the Nexus is also online
-                    .any(|c| c.is_healthy())
-                {
+                } else if (0 .. self.num_sets).all(|set| {
+                    self.children
+                        .iter()
+                        // at least one child online in every stripe set, so
+                        // the Nexus is also online
+                        .any(|c| c.stripe_set() == set && c.is_healthy())
+                }) {
                     NexusStatus::Degraded
                 } else {
                     // nexus has no children or at least no child is online
@@ -1337,6 +1708,21 @@ impl<'n> Nexus<'n> {
     pub(crate) unsafe fn set_req_size(self: Pin<&mut Self>, val: u64) {
         self.get_unchecked_mut().req_size = val;
     }
+
+    /// Sets the number of stripe sets.
+    unsafe fn set_num_sets(self: Pin<&mut Self>, val: usize) {
+        self.get_unchecked_mut().num_sets = val;
+    }
+
+    /// Sets the stripe geometry.
+    unsafe fn set_stripe(self: Pin<&mut Self>, val: Option<StripeGeometry>) {
+        self.get_unchecked_mut().stripe = val;
+    }
+
+    /// Sets the metadata and protection information format.
+    unsafe fn set_protection(self: Pin<&mut Self>, val: ProtectionInfo) {
+        self.get_unchecked_mut().protection = val;
+    }
 }
 
 impl Drop for Nexus<'_> {
@@ -1511,8 +1897,10 @@ pub async fn nexus_create(
         uuid,
         None,
         NexusNvmeParams::default(),
+        NexusLayout::Mirror,
         children,
         None,
+        None,
     )
     .await
 }
@@ -1520,40 +1908,33 @@ pub async fn nexus_create(
 /// As create_nexus with additional parameters:
 /// min_cntlid, max_cntldi: NVMe controller ID range when sharing over NVMf
 /// resv_key: NVMe reservation key for children
+/// layout: data layout, i.e. mirrored or striped across the children
+/// cache: optional cache tier in front of the children
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(
+    target = TRACING_TARGET,
+    skip_all,
+    fields(nexus = name, uuid = uuid)
+)]
 pub async fn nexus_create_v2(
     name: &str,
     size: u64,
     uuid: &str,
     nvme_params: NexusNvmeParams,
+    layout: NexusLayout,
     children: &[String],
     nexus_info_key: Option<String>,
+    cache: Option<NexusCacheConfig>,
 ) -> Result<(), Error> {
-    if nvme_params.min_cntlid < NVME_MIN_CNTLID
-        || nvme_params.min_cntlid > nvme_params.max_cntlid
-        || nvme_params.max_cntlid > NVME_MAX_CNTLID
-    {
-        let args = format!(
-            "invalid NVMe controller ID range [{:x}h, {:x}h]",
-            nvme_params.min_cntlid, nvme_params.max_cntlid
-        );
-        error!("failed to create nexus {}: {}", name, args);
-        return Err(Error::InvalidArguments {
-            name: name.to_owned(),
-            args,
-        });
+    if let Err(error) = nvme_params.validate(name) {
+        error!("failed to create nexus {}: {}", name, error);
+        return Err(error);
     }
     if !nvme_params.reservations_enabled() {
         warn!(
             "Not using nvme reservations for nexus {}: {:?}",
             name, nvme_params
         );
-    } else if !nvme_params.reservations_valid() {
-        let args = "invalid NVMe reservation parameters";
-        error!("failed to create nexus {}: {}", name, args);
-        return Err(Error::InvalidArguments {
-            name: name.to_owned(),
-            args: args.to_string(),
-        });
     }
 
     match uuid::Uuid::parse_str(name) {
@@ -1570,8 +1951,10 @@ pub async fn nexus_create_v2(
                 Some(bdev_uuid.as_str()),
                 Some(nexus_uuid),
                 nvme_params,
+                layout,
                 children,
                 nexus_info_key,
+                cache,
             )
             .await
         }
@@ -1582,30 +1965,44 @@ pub async fn nexus_create_v2(
                 Some(uuid),
                 None,
                 nvme_params,
+                layout,
                 children,
                 nexus_info_key,
+                cache,
             )
             .await
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn nexus_create_internal(
     name: &str,
     size: u64,
     bdev_uuid: Option<&str>,
     nexus_uuid: Option<Uuid>,
     nvme_params: NexusNvmeParams,
+    layout: NexusLayout,
     children: &[String],
     nexus_info_key: Option<String>,
+    cache: Option<NexusCacheConfig>,
 ) -> Result<(), Error> {
     info!(
-        "Creating new nexus '{}' ({} child(ren): {:?})...",
+        "Creating new {} nexus '{}' ({} child(ren): {:?})...",
+        layout,
         name,
         children.len(),
         children
     );
 
+    if let Err(args) = layout.validate(children.len()) {
+        error!("failed to create nexus {}: {}", name, args);
+        return Err(Error::InvalidArguments {
+            name: name.to_owned(),
+            args,
+        });
+    }
+
     if let Some(nexus) = nexus_lookup_name_uuid(name, nexus_uuid) {
         // FIXME: Instead of error, we return Ok without checking
         // that the children match, which seems wrong.
@@ -1622,6 +2019,13 @@ async fn nexus_create_internal(
                 nexus: name.to_string(),
             });
         }
+        if nexus.layout() != layout {
+            return Err(Error::LayoutMismatch {
+                name: name.to_owned(),
+                layout: nexus.layout(),
+                requested: layout,
+            });
+        }
         return Ok(());
     }
 
@@ -1637,6 +2041,8 @@ async fn nexus_create_internal(
         nexus_uuid,
         nvme_params,
         nexus_info_key,
+        layout,
+        cache,
     );
 
     for uri in children {
@@ -34,6 +34,7 @@ use super::{
     nexus_lookup_mut,
     ChildState,
     ChildSyncState,
+    DrEvent,
     Error,
     FaultReason,
     IOLogChannel,
@@ -50,6 +51,7 @@ use super::{
 use crate::{
     bdev::{dev::device_name, device_create, device_destroy, device_lookup},
     bdev_api::BdevError,
+    constants::TRACING_TARGET,
     core::{
         device_cmd_queue,
         DeviceCommand,
@@ -84,11 +86,12 @@ impl<'n> Nexus<'n> {
         let nexus_name = self.nexus_name().to_owned();
         let device_name = device_create(uri).await?;
 
-        let c = NexusChild::new(
+        let mut c = NexusChild::new(
             uri.to_string(),
             nexus_name,
             device_lookup(&device_name),
         );
+        c.set_stripe_set(self.layout().child_set(self.child_count()));
 
         info!("{:?}: added to nexus", c);
 
@@ -108,14 +111,101 @@ impl<'n> Nexus<'n> {
     /// The rebuild flag dictates wether we attempt to start the rebuild or not
     /// If the rebuild fails to start the child remains degraded until such
     /// time the rebuild is retried and complete
+    #[tracing::instrument(
+        target = TRACING_TARGET,
+        skip_all,
+        fields(nexus = %self.name, child = uri)
+    )]
     pub async fn add_child(
+        self: Pin<&mut Self>,
+        uri: &str,
+        norebuild: bool,
+    ) -> Result<NexusStatus, Error> {
+        self.add_child_to_set(uri, None, norebuild).await
+    }
+
+    /// Adds a new child to the stripe set of an existing child, which can be
+    /// removed once the new child has been rebuilt from it. This is the way
+    /// to replace a child of a striped nexus, whose stripe sets otherwise
+    /// don't accept more children than the layout requires.
+    #[tracing::instrument(
+        target = TRACING_TARGET,
+        skip_all,
+        fields(nexus = %self.name, child = uri, replaced = old_uri)
+    )]
+    pub async fn replace_child(
+        self: Pin<&mut Self>,
+        old_uri: &str,
+        uri: &str,
+        norebuild: bool,
+    ) -> Result<NexusStatus, Error> {
+        let stripe_set = self.child(old_uri)?.stripe_set();
+        self.add_child_to_set(uri, Some(stripe_set), norebuild)
+            .await
+    }
+
+    /// Replaces a child with a device which already holds the very same data,
+    /// such as the destination of a copy job, without rebuilding it.
+    /// The nexus I/O must be paused by the caller, so that the data of the
+    /// two can't diverge before the child is gone.
+    #[tracing::instrument(
+        target = TRACING_TARGET,
+        skip_all,
+        fields(nexus = %self.name, child = uri, replaced = old_uri)
+    )]
+    pub async fn swap_child(
+        mut self: Pin<&mut Self>,
+        old_uri: &str,
+        uri: &str,
+    ) -> Result<NexusStatus, Error> {
+        if self.io_subsystem_state() != Some(NexusPauseState::Paused) {
+            return Err(Error::OperationNotAllowed {
+                reason: "The nexus I/O must be paused to swap a child"
+                    .to_string(),
+            });
+        }
+
+        let stripe_set = self.child(old_uri)?.stripe_set();
+        self.as_mut()
+            .add_child_only(uri, Some(stripe_set), true)
+            .await?;
+
+        let child = self.child(uri)?;
+        child.set_sync_state(ChildSyncState::Synced);
+        if let Err(e) = self
+            .persist(PersistOp::Update {
+                child_uri: uri.to_owned(),
+                healthy: child.is_healthy(),
+            })
+            .await
+        {
+            error!(
+                "{self:?}: failed to swap child '{old_uri}' with '{uri}' \
+                because of persistent store update failure: {e}"
+            );
+            unsafe {
+                self.as_mut().child_remove_unsafe(uri);
+            }
+            return Err(e);
+        }
+        self.reconfigure(DrEvent::ChildRebuild).await;
+
+        self.as_mut().remove_child(old_uri).await?;
+        Ok(self.status())
+    }
+
+    /// Adds a new child to the given stripe set, or to the first incomplete
+    /// one, and starts rebuilding it unless told otherwise.
+    async fn add_child_to_set(
         mut self: Pin<&mut Self>,
         uri: &str,
+        stripe_set: Option<usize>,
         norebuild: bool,
     ) -> Result<NexusStatus, Error> {
         self.check_nexus_operation(NexusOperation::ReplicaAdd)?;
 
-        let status = self.as_mut().add_child_only(uri).await?;
+        let status =
+            self.as_mut().add_child_only(uri, stripe_set, false).await?;
 
         if !norebuild {
             match self.start_rebuild(uri).await {
@@ -151,16 +241,35 @@ impl<'n> Nexus<'n> {
 
     /// The child may require a rebuild first, so the nexus will
     /// transition to degraded mode when the addition has been successful.
+    /// The device of an existing child is neither created nor destroyed on
+    /// failure, as it belongs to its creator.
     async fn add_child_only(
         mut self: Pin<&mut Self>,
         uri: &str,
+        stripe_set: Option<usize>,
+        existing: bool,
     ) -> Result<NexusStatus, Error> {
         self.check_nexus_operation(NexusOperation::ReplicaAdd)?;
 
-        let name =
-            device_create(uri).await.context(nexus_err::CreateChild {
-                name: self.name.clone(),
-            })?;
+        let Some(stripe_set) = stripe_set.or_else(|| self.free_stripe_set())
+        else {
+            return Err(Error::OperationNotAllowed {
+                reason: format!(
+                    "All stripe sets of the {} nexus are complete, a child \
+                    can only replace one of their children",
+                    self.layout()
+                ),
+            });
+        };
+
+        let name = if existing {
+            device_name(uri)
+        } else {
+            device_create(uri).await
+        }
+        .context(nexus_err::CreateChild {
+            name: self.name.clone(),
+        })?;
 
         assert!(self.num_blocks() > 0);
         assert!(self.block_len() > 0);
@@ -168,15 +277,19 @@ impl<'n> Nexus<'n> {
         let child_bdev = match device_lookup(&name) {
             Some(child) => {
                 if child.block_len() != self.block_len()
+                    || child.protection_info() != self.protection_info()
                     || self
                         .min_num_blocks()
                         .map_or(true, |n| n > child.num_blocks())
                 {
-                    if let Err(err) = device_destroy(uri).await {
-                        error!(
-                            "Failed to destroy child bdev with wrong geometry: {}",
-                            err.to_string()
-                        );
+                    if !existing {
+                        if let Err(err) = device_destroy(uri).await {
+                            error!(
+                                "Failed to destroy child bdev with wrong \
+                                geometry: {}",
+                                err.to_string()
+                            );
+                        }
                     }
 
                     return Err(Error::ChildGeometry {
@@ -207,10 +320,12 @@ impl<'n> Nexus<'n> {
             self.nexus_name().to_owned(),
             Some(child_bdev),
         );
+        child.set_stripe_set(stripe_set);
 
         // it can never take part in the IO path
         // of the nexus until it's rebuilt from a healthy child.
-        let mut res = child.open(self.req_size(), ChildSyncState::OutOfSync);
+        let mut res =
+            child.open(self.child_req_size(), ChildSyncState::OutOfSync);
 
         if res.is_ok() {
             // we have created the bdev, and created a nexusChild struct. To
@@ -255,14 +370,16 @@ impl<'n> Nexus<'n> {
                 }
             }
             Err(e) => {
-                if let Err(err) = device_destroy(uri).await {
-                    error!(
-                        "{:?}: failed to destroy child '{}' which \
-                        failed to open: {}",
-                        self,
-                        uri,
-                        err.to_string()
-                    );
+                if !existing {
+                    if let Err(err) = device_destroy(uri).await {
+                        error!(
+                            "{:?}: failed to destroy child '{}' which \
+                            failed to open: {}",
+                            self,
+                            uri,
+                            err.to_string()
+                        );
+                    }
                 }
                 Err(e).context(nexus_err::OpenChild {
                     child: uri.to_owned(),
@@ -272,6 +389,22 @@ impl<'n> Nexus<'n> {
         }
     }
 
+    /// Returns the stripe set a new child is to be added to: the first set
+    /// which has less children than the layout requires. A mirrored nexus
+    /// accepts any number of children.
+    fn free_stripe_set(&self) -> Option<usize> {
+        let Some(copies) = self.layout().copies() else {
+            return Some(0);
+        };
+
+        (0 .. self.num_sets()).find(|&set| {
+            self.children_iter()
+                .filter(|c| c.stripe_set() == set)
+                .count()
+                < copies
+        })
+    }
+
     /// Checks if the nexus contains the given child uri.
     pub fn contains_child_uri(&self, uri: &str) -> bool {
         self.children_iter().any(|c| c.uri() == uri)
@@ -406,17 +539,24 @@ impl<'n> Nexus<'n> {
         &self,
         child_uri: &str,
     ) -> Result<(), Error> {
-        let _ = self.child(child_uri)?;
+        let set = self.child(child_uri)?.stripe_set();
+
+        // With a striped layout, each stripe set holds its own part of the
+        // data, so the checks apply to the child's set only.
+        let set_children = self
+            .children_iter()
+            .filter(|c| c.stripe_set() == set)
+            .collect::<Vec<_>>();
 
-        if self.child_count() == 1 {
+        if set_children.len() == 1 {
             return Err(Error::RemoveLastChild {
                 name: self.name.clone(),
                 child: child_uri.to_owned(),
             });
         }
 
-        let healthy = self
-            .children_iter()
+        let healthy = set_children
+            .into_iter()
             .filter(|c| c.is_healthy())
             .collect::<Vec<_>>();
 
@@ -438,7 +578,7 @@ impl<'n> Nexus<'n> {
         child_uri: &str,
     ) -> Result<NexusStatus, Error> {
         let nexus_name = self.name.clone();
-        let nexus_size = self.req_size();
+        let nexus_size = self.child_req_size();
 
         self.check_nexus_operation(NexusOperation::ReplicaOnline)?;
 
@@ -505,6 +645,11 @@ impl<'n> Nexus<'n> {
     /// Tries to open all the child devices.
     /// Opens children, determines and validates block size and block count
     /// of underlying devices.
+    #[tracing::instrument(
+        target = TRACING_TARGET,
+        skip_all,
+        fields(nexus = %self.name)
+    )]
     pub(crate) async fn try_open_children(
         mut self: Pin<&mut Self>,
     ) -> Result<(), Error> {
@@ -512,7 +657,7 @@ impl<'n> Nexus<'n> {
 
         let name = self.name.clone();
 
-        let size = self.req_size();
+        let size = self.child_req_size();
 
         // Take the child vec, try open and re-add.
         // NOTE: self.child_count is not affected by this algorithm!
@@ -891,11 +1036,16 @@ impl<'n> Nexus<'n> {
         }
     }
 
-    /// Returns list of I/O log channels of all children for the current core.
+    /// Returns list of I/O log channels of all children for the current core,
+    /// including the copy logs, the replication log and the pending rebuild
+    /// logs.
     pub(super) fn io_log_channels(&self) -> Vec<IOLogChannel> {
         self.children_iter()
             .filter(|c| !c.is_rebuilding())
             .filter_map(|c| c.io_log_channel())
+            .chain(self.children_iter().filter_map(|c| c.copy_log_channel()))
+            .chain(self.replication_log_channel())
+            .chain(self.pending_rebuild_log_channels())
             .collect()
     }
 
@@ -961,6 +1111,9 @@ impl<'n> Nexus<'n> {
             return;
         };
 
+        let retired_uri =
+            nex.lookup_child_by_device(&dev).map(|c| c.uri().to_owned());
+
         // Error indicates it is already paused and another
         // thread is processing the fault.
         if let Err(err) = nex.as_mut().do_child_retire(dev.clone()).await {
@@ -987,6 +1140,10 @@ impl<'n> Nexus<'n> {
         if matches!(nex.status(), NexusStatus::Faulted) {
             error!("{nex:?}: failed to retire '{dev}': nexus is faulted");
         }
+
+        if let Some(uri) = retired_uri {
+            nex.replace_with_spare(&uri).await;
+        }
     }
 
     /// Retires a child with the given device.
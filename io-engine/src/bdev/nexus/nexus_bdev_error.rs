@@ -2,7 +2,7 @@ use nix::errno::Errno;
 use snafu::Snafu;
 use tonic::{Code, Status};
 
-use super::{ChildError, NbdError, NexusPauseState};
+use super::{ChildError, NbdError, NexusLayout, NexusPauseState};
 
 use crate::{
     bdev_api::BdevError,
@@ -31,6 +31,17 @@ pub enum Error {
     UuidExists { uuid: String, nexus: String },
     #[snafu(display("Nexus with name \"{}\" already exists", name))]
     NameExists { name: String },
+    #[snafu(display(
+        "Nexus {} already exists with the {} layout, not {}",
+        name,
+        layout,
+        requested
+    ))]
+    LayoutMismatch {
+        name: String,
+        layout: NexusLayout,
+        requested: NexusLayout,
+    },
     #[snafu(display("Invalid encryption key"))]
     InvalidKey {},
     #[snafu(display("Failed to create crypto bdev for nexus {}", name))]
@@ -84,6 +95,23 @@ pub enum Error {
     },
     #[snafu(display("Children of nexus {} have mixed block sizes", name))]
     MixedBlockSizes { name: String },
+    #[snafu(display(
+        "Children of nexus {} have mixed metadata or protection information \
+        formats",
+        name
+    ))]
+    MixedProtectionInfo { name: String },
+    #[snafu(display(
+        "Child {} of nexus {} has an unsupported metadata format: {}",
+        child,
+        name,
+        reason
+    ))]
+    UnsupportedProtectionInfo {
+        child: String,
+        name: String,
+        reason: String,
+    },
     #[snafu(display(
         "Child {} of nexus {} has incompatible size or block size",
         child,
@@ -222,6 +250,28 @@ pub enum Error {
     UpdateShareProperties { source: CoreError, name: String },
     #[snafu(display("failed to save nexus state {}", name))]
     SaveStateFailed { source: StoreError, name: String },
+    #[snafu(display(
+        "Failed to open cache {} of nexus {}: {}",
+        uri,
+        name,
+        reason
+    ))]
+    OpenCache {
+        uri: String,
+        name: String,
+        reason: String,
+    },
+    #[snafu(display(
+        "The write-back cache of the previous nexus {} was not destaged",
+        name
+    ))]
+    CacheNotDestaged { name: String },
+    #[snafu(display("Failed to replay cache {} of nexus {}", uri, name))]
+    ReplayCache {
+        source: RebuildError,
+        uri: String,
+        name: String,
+    },
 }
 
 impl From<NvmfError> for Error {
@@ -262,6 +312,12 @@ impl From<Error> for tonic::Status {
             Error::MixedBlockSizes {
                 ..
             } => Status::invalid_argument(e.to_string()),
+            Error::MixedProtectionInfo {
+                ..
+            } => Status::invalid_argument(e.to_string()),
+            Error::UnsupportedProtectionInfo {
+                ..
+            } => Status::invalid_argument(e.to_string()),
             Error::ChildGeometry {
                 ..
             } => Status::invalid_argument(e.to_string()),
@@ -271,6 +327,12 @@ impl From<Error> for tonic::Status {
             Error::OpenChild {
                 ..
             } => Status::invalid_argument(e.to_string()),
+            Error::OpenCache {
+                ..
+            } => Status::invalid_argument(e.to_string()),
+            Error::CacheNotDestaged {
+                ..
+            } => Status::failed_precondition(e.to_string()),
             Error::OperationNotAllowed {
                 ..
             } => Status::failed_precondition(e.to_string()),
@@ -301,6 +363,9 @@ impl From<Error> for tonic::Status {
             Error::NameExists {
                 ..
             } => Status::already_exists(e.to_string()),
+            Error::LayoutMismatch {
+                ..
+            } => Status::already_exists(e.to_string()),
             Error::InvalidArguments {
                 ..
             } => Status::invalid_argument(e.to_string()),
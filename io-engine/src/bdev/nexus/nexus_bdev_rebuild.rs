@@ -1,6 +1,12 @@
-use futures::channel::oneshot::Receiver;
+use futures::channel::{oneshot, oneshot::Receiver};
 use snafu::ResultExt;
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    collections::HashSet,
+    marker::PhantomData,
+    ops::Range,
+    sync::Arc,
+    time::Duration,
+};
 
 use super::{
     nexus_err,
@@ -10,24 +16,47 @@ use super::{
     DrEvent,
     Error,
     FaultReason,
+    IOLog,
+    IOLogChannel,
     Nexus,
+    NexusChild,
+    NexusState,
 };
 
 use crate::{
-    core::{Reactors, VerboseError},
+    core::{MayastorEnvironment, Reactors, VerboseError},
     eventing::{EventMetaGen, EventWithMeta},
     rebuild::{
         HistoryRecord,
         NexusRebuildJob,
         NexusRebuildJobStarter,
+        RebuildCheckpoint,
         RebuildError,
         RebuildJobOptions,
+        RebuildMap,
         RebuildState,
         RebuildStats,
         RebuildVerifyMode,
     },
 };
 use events_api::event::EventAction;
+use spdk_rs::{ChannelTraverseStatus, IoDeviceChannelTraverse};
+
+/// Interval between checkpoints of the progress of nexus rebuilds.
+const REBUILD_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Writes made to a nexus since it was created, for the children that a
+/// previous nexus of the volume was rebuilding. Their rebuild can only be
+/// resumed from its checkpoint once the blocks written meanwhile are marked
+/// to be rebuilt again.
+pub(super) struct PendingRebuilds {
+    /// UUIDs of the children whose rebuild can be resumed.
+    children: HashSet<String>,
+    /// Log of the writes of each stripe set, in child blocks.
+    logs: Vec<IOLog>,
+    /// Blocks of each stripe set written before the current logs started.
+    written: Vec<Vec<Range<u64>>>,
+}
 
 /// Rebuild pause guard ensures rebuild jobs are resumed before it is dropped.
 pub(crate) struct RebuildPauseGuard<'a> {
@@ -86,8 +115,14 @@ impl<'n> Nexus<'n> {
         let name = self.name.clone();
         info!("{self:?}: start rebuild request for {child_uri}");
 
-        // Find a healthy child to rebuild from.
-        let Some(src_child_uri) = self.find_src_replica(child_uri) else {
+        // Resume an interrupted rebuild from its last checkpoint, if possible,
+        // or find a healthy child to rebuild from.
+        let mut checkpoint = self.resumable_checkpoint(child_uri).await;
+        let Some(src_child_uri) = checkpoint
+            .as_ref()
+            .map(|cp| cp.src_uri.clone())
+            .or_else(|| self.find_src_replica(child_uri))
+        else {
             return Err(Error::NoRebuildSource {
                 name: name.clone(),
             });
@@ -144,6 +179,44 @@ impl<'n> Nexus<'n> {
             .lookup_child(&dst_child_uri)
             .and_then(|c| c.stop_io_log());
 
+        // The blocks written since the nexus was created must be rebuilt
+        // again, the checkpoint is of no use if they are not known.
+        match self.take_pending_rebuild_writes(&dst_child_uri).await {
+            Some(written) => {
+                if let Some(cp) = checkpoint.as_mut() {
+                    cp.remaining.extend(written);
+                }
+            }
+            None if checkpoint.is_some() && self.is_data_written() => {
+                warn!(
+                    "{self:?}: writes since the nexus was created are not \
+                    known for '{dst_child_uri}', not resuming the rebuild"
+                );
+                checkpoint = None;
+            }
+            None => {}
+        }
+
+        // Blocks rebuilt before the checkpoint don't need to be rebuilt again,
+        // but the blocks written since the I/O log started do.
+        let map = match checkpoint.as_ref().and_then(|cp| {
+            self.lookup_child(&dst_child_uri)
+                .and_then(|c| c.checkpoint_map(cp))
+        }) {
+            Some(cp_map) => {
+                info!(
+                    "{self:?}: resuming rebuild of '{dst_child_uri}' from \
+                    checkpoint, {blks} blocks remaining",
+                    blks = cp_map.count_dirty_blks()
+                );
+                Some(match map {
+                    Some(map) => cp_map.merge(&map),
+                    None => cp_map,
+                })
+            }
+            None => map,
+        };
+
         starter
             .start(self.rebuild_job_mut(&dst_child_uri)?, map)
             .await
@@ -153,11 +226,76 @@ impl<'n> Nexus<'n> {
             })
     }
 
+    /// Loads the rebuild checkpoint of the given child and checks whether the
+    /// rebuild can be resumed from it. This is the case when the destination
+    /// is the very instance of the child which was being rebuilt, the source
+    /// child is still healthy, the rebuild range is unchanged, and the writes
+    /// made to the nexus since it was created are known: the destination
+    /// child is not part of the nexus until re-added, so these writes must be
+    /// rebuilt again.
+    /// Returns the checkpoint with the current URIs of the children.
+    async fn resumable_checkpoint(
+        &self,
+        dst_uri: &str,
+    ) -> Option<RebuildCheckpoint> {
+        let mut checkpoint = self.load_rebuild_checkpoint(dst_uri).await?;
+        let msg = format!("{self:?}: rebuild checkpoint of '{dst_uri}'");
+
+        if self.is_data_written() && !self.has_pending_rebuild(dst_uri) {
+            info!("{msg}: nexus has been written since created, ignoring");
+            return None;
+        }
+
+        let dst_instance = self
+            .lookup_child(dst_uri)
+            .and_then(|c| c.get_device().ok())
+            .and_then(|d| d.instance_id());
+        if dst_instance.is_none() || dst_instance != checkpoint.dst_instance {
+            info!("{msg}: destination is another instance, ignoring");
+            return None;
+        }
+
+        let range = self.data_ent_offset
+            .. self.child_data_blocks() + self.data_ent_offset;
+        if checkpoint.range != range {
+            info!("{msg}: rebuild range has changed, ignoring");
+            return None;
+        }
+
+        let src_uuid = NexusChild::uuid(&checkpoint.src_uri);
+        let set = self.lookup_child(dst_uri).map_or(0, |c| c.stripe_set());
+        let Some(src) = self.children_iter().find(|c| {
+            c.uri() != dst_uri
+                && c.stripe_set() == set
+                && match (&src_uuid, NexusChild::uuid(c.uri())) {
+                    (Some(a), Some(b)) => *a == b,
+                    _ => c.uri() == checkpoint.src_uri,
+                }
+        }) else {
+            info!("{msg}: source child is gone, ignoring");
+            return None;
+        };
+
+        if !src.is_healthy() {
+            info!("{msg}: source child is not healthy, ignoring");
+            return None;
+        }
+
+        checkpoint.src_uri = src.uri().to_owned();
+        checkpoint.dst_uri = dst_uri.to_owned();
+        Some(checkpoint)
+    }
+
     /// Finds the best suited source replica for the given destination.
+    /// With a striped layout, only the children of the same stripe set hold
+    /// the data of the destination.
     fn find_src_replica(&self, dst_uri: &str) -> Option<String> {
+        let set = self.lookup_child(dst_uri).map_or(0, |c| c.stripe_set());
         let candidates: Vec<_> = self
             .children_iter()
-            .filter(|c| c.is_healthy() && c.uri() != dst_uri)
+            .filter(|c| {
+                c.is_healthy() && c.uri() != dst_uri && c.stripe_set() == set
+            })
             .collect();
 
         candidates
@@ -167,6 +305,26 @@ impl<'n> Nexus<'n> {
             .map(|c| c.uri().to_owned())
     }
 
+    /// Returns the URIs of all the children the given destination can be
+    /// rebuilt from, starting with the given source. Reading from all the
+    /// healthy children at once spreads the rebuild load across them.
+    fn rebuild_sources(&self, src_uri: &str, dst_uri: &str) -> Vec<String> {
+        let set = self.lookup_child(dst_uri).map_or(0, |c| c.stripe_set());
+
+        std::iter::once(src_uri.to_owned())
+            .chain(
+                self.children_iter()
+                    .filter(|c| {
+                        c.is_healthy()
+                            && c.uri() != dst_uri
+                            && c.uri() != src_uri
+                            && c.stripe_set() == set
+                    })
+                    .map(|c| c.uri().to_owned()),
+            )
+            .collect()
+    }
+
     /// TODO
     async fn create_rebuild_job(
         &self,
@@ -197,16 +355,20 @@ impl<'n> Nexus<'n> {
         let opts = RebuildJobOptions {
             verify_mode,
             read_opts: crate::core::ReadOptions::UnwrittenFail,
+            skip_unallocated: true,
+            checkpoint_interval: Some(REBUILD_CHECKPOINT_INTERVAL),
         };
 
         NexusRebuildJob::new_starter(
             &self.name,
-            src_child_uri,
+            &self.rebuild_sources(src_child_uri, dst_child_uri),
             dst_child_uri,
             std::ops::Range::<u64> {
                 start: self.data_ent_offset,
-                end: self.num_blocks() + self.data_ent_offset,
+                end: self.child_data_blocks() + self.data_ent_offset,
             },
+            self.stripe_geometry()
+                .zip(self.lookup_child(dst_child_uri).map(|c| c.stripe_set())),
             opts,
             |nexus, job| {
                 Reactors::current().send_future(async move {
@@ -215,6 +377,15 @@ impl<'n> Nexus<'n> {
             },
         )
         .await
+        .map(|starter| {
+            starter.with_checkpoint_fn(|nexus, checkpoint| {
+                Reactors::current().send_future(async move {
+                    if let Some(nexus) = nexus_lookup_mut(&nexus) {
+                        nexus.persist_rebuild_checkpoint(&checkpoint).await;
+                    }
+                });
+            })
+        })
         .and_then(NexusRebuildJobStarter::store)
         .context(nexus_err::CreateRebuild {
             child: dst_child_uri.to_owned(),
@@ -230,14 +401,25 @@ impl<'n> Nexus<'n> {
             return;
         };
 
-        self.rebuild_history.lock().push(rec);
+        let num = {
+            let mut history = self.rebuild_history.lock();
+            history.push(rec);
+            Self::apply_history_retention(&mut history);
+            history.len()
+        };
 
         debug!(
             "{self:?}: new rebuild history record for '{dst}'; \
             total {num} records",
             dst = job.dst_uri,
-            num = self.rebuild_history.lock().len()
         );
+
+        let name = self.name.clone();
+        Reactors::current().send_future(async move {
+            if let Some(nexus) = nexus_lookup_mut(&name) {
+                nexus.persist_rebuild_history().await;
+            }
+        });
     }
 
     /// Terminates a rebuild in the background.
@@ -310,14 +492,25 @@ impl<'n> Nexus<'n> {
 
     /// Return a clone of the replica rebuild history.
     pub fn rebuild_history(&self) -> Vec<HistoryRecord> {
-        self.rebuild_history.lock().clone()
+        self.rebuild_history_guard().clone()
     }
 
     /// Return a mutex guard of the replica rebuild history.
+    /// The records which have expired since the last rebuild are dropped
+    /// first.
     pub fn rebuild_history_guard(
         &self,
     ) -> parking_lot::MutexGuard<Vec<HistoryRecord>> {
-        self.rebuild_history.lock()
+        let mut history = self.rebuild_history.lock();
+        Self::apply_history_retention(&mut history);
+        history
+    }
+
+    /// Applies the configured retention policy to the rebuild history.
+    pub(super) fn apply_history_retention(history: &mut Vec<HistoryRecord>) {
+        MayastorEnvironment::global_or_default()
+            .rebuild_history
+            .apply(history);
     }
 
     /// Returns the rebuild progress of a rebuild job for the given destination.
@@ -328,6 +521,257 @@ impl<'n> Nexus<'n> {
         self.rebuild_stats(dst_uri).await.map(|s| s.progress as u32)
     }
 
+    /// Starts logging the writes made to the child with the given device, so
+    /// that a copy of the device taken while the nexus is live can be brought
+    /// up to date afterwards.
+    pub(crate) async fn start_copy_log(
+        &self,
+        device_name: &str,
+    ) -> Result<(), Error> {
+        let child = self.child_by_device(device_name)?;
+
+        if !child.start_copy_log() {
+            return Err(Error::OperationNotAllowed {
+                reason: format!(
+                    "Child '{device_name}' already has a copy log or no device"
+                ),
+            });
+        }
+
+        self.reconnect_io_logs().await;
+        Ok(())
+    }
+
+    /// Stops logging the writes made to the child with the given device, and
+    /// returns a map of the segments written since the log was started.
+    pub(crate) async fn stop_copy_log(
+        &self,
+        device_name: &str,
+    ) -> Option<RebuildMap> {
+        let log = self
+            .lookup_child_by_device(device_name)
+            .and_then(|c| c.take_copy_log())?;
+
+        // The channels must let go of the log before it can be finalized.
+        self.reconnect_io_logs().await;
+        Some(log.finalize())
+    }
+
+    /// Starts logging the writes made to the nexus, so that they can be
+    /// shipped to an asynchronous replica. The log is kept in the child
+    /// coordinates of a mirrored nexus, so any healthy child can be the
+    /// source of the shipped segments.
+    pub(crate) async fn start_replication_log(&self) -> Result<(), Error> {
+        if self.num_sets() > 1 {
+            return Err(Error::OperationNotAllowed {
+                reason: format!(
+                    "Nexus '{}' is striped and can't be replicated",
+                    self.name
+                ),
+            });
+        }
+        if self.has_write_back_cache() {
+            return Err(Error::OperationNotAllowed {
+                reason: format!(
+                    "Nexus '{}' has a write-back cache and can't be replicated",
+                    self.name
+                ),
+            });
+        }
+
+        {
+            let mut log = self.replication_log.lock();
+            if log.is_some() {
+                return Err(Error::OperationNotAllowed {
+                    reason: format!(
+                        "Nexus '{}' already has a replication log",
+                        self.name
+                    ),
+                });
+            }
+            *log = Some(self.new_replication_log());
+        }
+
+        self.reconnect_io_logs().await;
+        Ok(())
+    }
+
+    /// Replaces the replication log with a new one, and returns a map of the
+    /// segments written since the previous log was started.
+    pub(crate) async fn swap_replication_log(&self) -> Option<RebuildMap> {
+        let log = {
+            let mut log = self.replication_log.lock();
+            if log.is_none() {
+                return None;
+            }
+            log.replace(self.new_replication_log())
+        }?;
+
+        // The writes go to either log until all the channels are reconnected,
+        // the previous one can only be finalized after that.
+        self.reconnect_io_logs().await;
+        Some(log.finalize())
+    }
+
+    /// Stops logging the writes made to the nexus, and returns a map of the
+    /// segments written since the replication log was started.
+    pub(crate) async fn stop_replication_log(&self) -> Option<RebuildMap> {
+        let log = self.replication_log.lock().take()?;
+
+        self.reconnect_io_logs().await;
+        Some(log.finalize())
+    }
+
+    /// Returns the replication log channel for the current core.
+    pub(super) fn replication_log_channel(&self) -> Option<IOLogChannel> {
+        self.replication_log
+            .lock()
+            .as_ref()
+            .map(|log| log.current_channel())
+    }
+
+    /// Creates an empty replication log covering the data of the children.
+    fn new_replication_log(&self) -> IOLog {
+        IOLog::new(
+            &self.name,
+            0,
+            self.child_data_blocks() + self.data_ent_offset,
+            self.block_len(),
+        )
+    }
+
+    /// Starts logging the writes made to the nexus for the children which a
+    /// previous nexus of the volume was rebuilding, if any. These children
+    /// are not part of the nexus yet, they are added back later on.
+    pub(super) async fn start_pending_rebuild_logs(&self) {
+        let children = self
+            .load_rebuild_checkpoints()
+            .await
+            .into_iter()
+            .filter_map(|cp| NexusChild::uuid(&cp.dst_uri))
+            .filter(|uuid| {
+                !self.children_iter().any(|c| {
+                    c.is_healthy()
+                        && NexusChild::uuid(c.uri()).as_ref() == Some(uuid)
+                })
+            })
+            .collect::<HashSet<_>>();
+        if children.is_empty() {
+            return;
+        }
+
+        info!(
+            "{self:?}: logging writes for the rebuilds of {children:?} to \
+            resume"
+        );
+        *self.pending_rebuilds.lock() = Some(PendingRebuilds {
+            children,
+            logs: (0 .. self.num_sets())
+                .map(|set| self.new_pending_rebuild_log(set))
+                .collect(),
+            written: vec![Vec::new(); self.num_sets()],
+        });
+        self.reconnect_io_logs().await;
+    }
+
+    /// Checks if the writes made since the nexus was created are logged for
+    /// the given child.
+    fn has_pending_rebuild(&self, dst_uri: &str) -> bool {
+        let Some(uuid) = NexusChild::uuid(dst_uri) else {
+            return false;
+        };
+        self.pending_rebuilds
+            .lock()
+            .as_ref()
+            .map_or(false, |p| p.children.contains(&uuid))
+    }
+
+    /// Returns the blocks of the given child written since the nexus was
+    /// created, if they are logged for the child. The logs are stopped once
+    /// no other child needs them.
+    async fn take_pending_rebuild_writes(
+        &self,
+        dst_uri: &str,
+    ) -> Option<Vec<Range<u64>>> {
+        let uuid = NexusChild::uuid(dst_uri)?;
+        let set = self.lookup_child(dst_uri)?.stripe_set();
+
+        // Swap the log of the set, and stop all of them for the last child.
+        let (log, last) = {
+            let mut guard = self.pending_rebuilds.lock();
+            let pending = guard.as_mut()?;
+            if !pending.children.remove(&uuid) {
+                return None;
+            }
+            let log = std::mem::replace(
+                &mut pending.logs[set],
+                self.new_pending_rebuild_log(set),
+            );
+            let last = if pending.children.is_empty() {
+                guard.take()
+            } else {
+                None
+            };
+            (log, last)
+        };
+
+        // The channels must let go of the log before it can be finalized.
+        self.reconnect_io_logs().await;
+        let ranges = log.finalize().dirty_ranges();
+
+        match last {
+            Some(mut pending) => {
+                pending.written[set].extend(ranges);
+                Some(std::mem::take(&mut pending.written[set]))
+            }
+            None => {
+                let mut guard = self.pending_rebuilds.lock();
+                let written = &mut guard.as_mut()?.written[set];
+                written.extend(ranges);
+                Some(written.clone())
+            }
+        }
+    }
+
+    /// Returns the pending rebuild log channels for the current core.
+    pub(super) fn pending_rebuild_log_channels(&self) -> Vec<IOLogChannel> {
+        self.pending_rebuilds
+            .lock()
+            .as_ref()
+            .map_or(Vec::new(), |p| {
+                p.logs.iter().map(|log| log.current_channel()).collect()
+            })
+    }
+
+    /// Creates an empty pending rebuild log of the given stripe set.
+    fn new_pending_rebuild_log(&self, set: usize) -> IOLog {
+        IOLog::new(
+            &self.name,
+            set,
+            self.child_data_blocks() + self.data_ent_offset,
+            self.block_len(),
+        )
+    }
+
+    /// Reconnects the I/O logs of all the I/O channels and waits for it to
+    /// complete.
+    pub(super) async fn reconnect_io_logs(&self) {
+        let (sender, recv) = oneshot::channel::<ChannelTraverseStatus>();
+
+        self.traverse_io_channels(
+            sender,
+            |chan, _sender| -> ChannelTraverseStatus {
+                chan.reconnect_io_logs();
+                ChannelTraverseStatus::Ok
+            },
+            |status, sender| {
+                sender.send(status).ok();
+            },
+        );
+
+        recv.await.ok();
+    }
+
     /// Pauses rebuild jobs, returning rebuild pause guard.
     pub(super) async fn pause_rebuild_jobs<'a>(
         &self,
@@ -339,6 +783,9 @@ impl<'n> Nexus<'n> {
     }
 
     /// Cancels all rebuilds jobs associated with the child.
+    /// A job which reads from several sources stops reading from the child
+    /// and keeps going, and is only cancelled once it has no other healthy
+    /// source.
     /// Returns a list of rebuilding children whose rebuild job was cancelled.
     pub async fn cancel_rebuild_jobs(&self, src_uri: &str) -> Vec<String> {
         info!("{:?}: cancel rebuild jobs from '{}'...", self, src_uri);
@@ -347,11 +794,18 @@ impl<'n> Nexus<'n> {
         let mut terminated_jobs = Vec::new();
         let mut rebuilding_children = Vec::new();
 
-        // terminate all jobs with the child as a source
-        src_jobs.into_iter().for_each(|j| {
+        // terminate all jobs with the child as their last healthy source
+        for j in src_jobs {
+            if j.src_uris().len() > 1 && j.fail_source(src_uri).await {
+                info!(
+                    "{:?}: rebuild of '{}' goes on without '{}'",
+                    self, j.dst_uri, src_uri
+                );
+                continue;
+            }
             terminated_jobs.push(j.force_stop());
             rebuilding_children.push(j.dst_uri.clone());
-        });
+        }
 
         // wait for the jobs to complete terminating
         for job in terminated_jobs {
@@ -499,6 +953,15 @@ impl<'n> Nexus<'n> {
             }
         }
 
+        // Keep the last checkpoint when the rebuild is interrupted by a nexus
+        // shutdown, so that it can be resumed later on.
+        if !matches!(
+            *self.state.lock(),
+            NexusState::ShuttingDown | NexusState::Shutdown
+        ) {
+            self.delete_rebuild_checkpoint(child_uri).await;
+        }
+
         // TODO: Should this be done only after reconfigure?
         // Reason being if we remove the rebuild job then another rebuild could
         // potentially be triggered even though we haven't reconfigured
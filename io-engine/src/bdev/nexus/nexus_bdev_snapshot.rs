@@ -1,11 +1,15 @@
 //! Implements snapshot operations on a nexus.
-use std::collections::{HashMap, HashSet};
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+};
 
 use futures::future::join_all;
 
 use super::{Error, Nexus, NexusOperation, NexusState};
 use crate::{
     bdev::nexus::{nexus_lookup, NexusChild},
+    constants::TRACING_TARGET,
     core::{
         snapshot::ISnapshotDescriptor,
         CoreError,
@@ -87,6 +91,7 @@ impl ReplicaSnapshotExecutor {
         let mut seen_replicas: HashSet<String> = HashSet::new();
         let mut replica_ctx: Vec<SnapshotExecutorReplicaCtx> = Vec::new();
         let mut skipped_replicas = Vec::new();
+        let mut snapshot_sets: HashSet<usize> = HashSet::new();
 
         for r in &replicas {
             let replica = match nexus_replicas.get(&r.replica_uuid) {
@@ -148,11 +153,28 @@ impl ReplicaSnapshotExecutor {
                     replica_uuid: r.replica_uuid.clone(),
                     snapshot_uuid,
                 });
+                snapshot_sets.insert(replica.stripe_set());
             } else {
                 skipped_replicas.push(r.replica_uuid.clone());
             }
         }
 
+        // With a striped layout, every stripe set holds a different part of
+        // the data, so each set must have at least one replica snapshotted.
+        if nexus.layout().is_striped() {
+            if let Some(set) =
+                (0 .. nexus.num_sets()).find(|s| !snapshot_sets.contains(s))
+            {
+                return Err(Error::FailedCreateSnapshot {
+                    name: nexus.bdev_name(),
+                    reason: format!(
+                        "No replica of stripe set {set} participates in \
+                        snapshot"
+                    ),
+                });
+            }
+        }
+
         Ok(Self {
             replica_ctx,
             skipped_replicas,
@@ -160,11 +182,18 @@ impl ReplicaSnapshotExecutor {
         })
     }
 
-    /// Take snapshots for all replicas participating in the operation.
+    /// Take snapshots for all replicas participating in the operation,
+    /// reporting the number of replica snapshots done out of the total as
+    /// they complete.
     async fn take_snapshot(
         &self,
         snapshot: &SnapshotParams,
+        progress: &dyn Fn(usize, usize),
     ) -> (Vec<NexusReplicaSnapshotStatus>, Vec<String>) {
+        let total = self.replica_ctx.len();
+        let done = Cell::new(0);
+        progress(0, total);
+
         let futures = self
             .replica_ctx
             .iter()
@@ -236,11 +265,12 @@ impl ReplicaSnapshotExecutor {
                 .expect("Can't schedule replica snapshot operation");
 
                 // Snapshot operation future shall be able to track back to the replica.
+                let done = &done;
                 async move {
-                    (
-                        ctx.replica_uuid.clone(),
-                        rx.await.expect("Snapshot sender disappeared"),
-                    )
+                    let res = rx.await.expect("Snapshot sender disappeared");
+                    done.set(done.get() + 1);
+                    progress(done.get(), total);
+                    (ctx.replica_uuid.clone(), res)
                 }
             })
             .collect::<Vec<_>>();
@@ -291,11 +321,12 @@ impl<'n> Nexus<'n> {
         self: Pin<&mut Self>,
         snapshot: SnapshotParams,
         replicas: Vec<NexusReplicaSnapshotDescriptor>,
+        progress: &dyn Fn(usize, usize),
     ) -> Result<NexusSnapshotStatus, Error> {
         let (replicas_done, replicas_skipped) =
             ReplicaSnapshotExecutor::new(self.as_ref(), replicas)
                 .await?
-                .take_snapshot(&snapshot)
+                .take_snapshot(&snapshot, progress)
                 .await;
         Ok(NexusSnapshotStatus {
             replicas_done,
@@ -308,9 +339,26 @@ impl<'n> Nexus<'n> {
 
     /// Create a snapshot on all children
     pub async fn create_snapshot(
+        self: Pin<&mut Self>,
+        snapshot: SnapshotParams,
+        replicas: Vec<NexusReplicaSnapshotDescriptor>,
+    ) -> Result<NexusSnapshotStatus, Error> {
+        self.create_snapshot_with_progress(snapshot, replicas, &|_, _| {})
+            .await
+    }
+
+    /// Create a snapshot on all children, reporting the number of replica
+    /// snapshots done out of the total as they complete.
+    #[tracing::instrument(
+        target = TRACING_TARGET,
+        skip_all,
+        fields(nexus = %self.name)
+    )]
+    pub async fn create_snapshot_with_progress(
         mut self: Pin<&mut Self>,
         snapshot: SnapshotParams,
         replicas: Vec<NexusReplicaSnapshotDescriptor>,
+        progress: &dyn Fn(usize, usize),
     ) -> Result<NexusSnapshotStatus, Error> {
         if snapshot.name().is_none() {
             return Err(Error::FailedCreateSnapshot {
@@ -319,6 +367,14 @@ impl<'n> Nexus<'n> {
             });
         }
 
+        // The replicas may miss the blocks not destaged from the cache yet.
+        if self.has_write_back_cache() {
+            return Err(Error::FailedCreateSnapshot {
+                name: self.bdev_name(),
+                reason: "Nexus has a write-back cache".to_string(),
+            });
+        }
+
         self.check_nexus_state()?;
 
         // Step 1: Pause I/O subsystem for nexus.
@@ -332,7 +388,10 @@ impl<'n> Nexus<'n> {
         })?;
 
         // Step 2: Create snapshots on all replicas.
-        let res = self.as_mut().do_nexus_snapshot(snapshot, replicas).await;
+        let res = self
+            .as_mut()
+            .do_nexus_snapshot(snapshot, replicas, progress)
+            .await;
 
         // Step 3: Resume I/O.
         if let Err(error) = self.as_mut().resume().await {
@@ -0,0 +1,971 @@
+//! The cache tier of a nexus keeps a copy of the nexus data on a local fast
+//! device, such as a local lvol or a malloc or uring bdev. The reads are served
+//! from the cache, and in write-back mode the writes are acknowledged once
+//! written to the cache only, which saves the network round trip to the
+//! children when they are all remote. A write-back cache must be persistent,
+//! which rules out the malloc bdevs.
+//!
+//! The cache device mirrors the data partition of the children, so that its
+//! blocks are copied to the children at the same offsets, and its last block
+//! holds a superblock. The blocks written to the cache only are logged, and
+//! destaged to the children in the background. The superblock is marked dirty
+//! before the first write is acknowledged from the cache only, and clean once
+//! everything has been destaged when the nexus is destroyed. As the log of the
+//! blocks yet to be destaged is kept in memory, a nexus created over a dirty
+//! cache copies the whole cache to its children before it opens.
+//! The dirty flag is saved along with the nexus information as well, so that
+//! a nexus of the volume created elsewhere, which can't replay the cache,
+//! refuses to open over the out of date children.
+//!
+//! A cache I/O error switches the cache back to write-through: the failed
+//! write-back writes are resubmitted to the children as well, and the cache
+//! is detached from the nexus once the blocks written to it only have been
+//! destaged.
+
+use crossbeam::atomic::AtomicCell;
+use futures::channel::oneshot;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    ops::Range,
+    sync::Arc,
+    time::Duration,
+};
+
+use super::{
+    nexus_lookup,
+    DrEvent,
+    Error,
+    IOLog,
+    IOLogChannel,
+    Nexus,
+    PersistOp,
+};
+use crate::{
+    bdev::{dev::device_name, device_create, device_destroy, device_open},
+    bdev_api::BdevError,
+    core::{
+        BlockDeviceDescriptor,
+        BlockDeviceHandle,
+        CoreError,
+        Reactors,
+        ReadOptions,
+        SegmentMap,
+    },
+    rebuild::{
+        BdevRebuildJob,
+        NexusRebuildJob,
+        RebuildError,
+        RebuildJobOptions,
+        RebuildMap,
+        RebuildState,
+    },
+    sleep::mayastor_sleep,
+};
+
+/// Magic number of the superblock of a cache device.
+const CACHE_MAGIC: u64 = 0x4e58_4341_4348_4531;
+
+/// Default interval between two destages of the cache.
+const DEFAULT_DESTAGE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Drivers of the devices which lose their data on restart, and which can't
+/// hold a write-back cache.
+const VOLATILE_DRIVERS: [&str; 2] = ["malloc", "null"];
+
+/// Write policy of the cache of a nexus.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Writes are acknowledged once written to both the cache and the
+    /// children: the cache only speeds up the reads.
+    WriteThrough,
+    /// Writes are acknowledged once written to the cache, and are destaged to
+    /// the children in the background.
+    #[default]
+    WriteBack,
+}
+
+/// Cache tier of a nexus, given when the nexus is created.
+#[derive(Debug, Clone)]
+pub struct NexusCacheConfig {
+    /// URI of the local cache device.
+    pub uri: String,
+    /// Write policy of the cache.
+    pub policy: CachePolicy,
+    /// Interval between two destages of the written blocks to the children.
+    pub destage_interval: Duration,
+}
+
+impl NexusCacheConfig {
+    /// Creates a cache configuration with the default destage interval.
+    pub fn new(uri: &str, policy: CachePolicy) -> Self {
+        Self {
+            uri: uri.to_string(),
+            policy,
+            destage_interval: DEFAULT_DESTAGE_INTERVAL,
+        }
+    }
+
+    /// Sets the interval between two destages.
+    pub fn with_destage_interval(mut self, interval: Duration) -> Self {
+        self.destage_interval = interval;
+        self
+    }
+}
+
+/// State of the cache of a nexus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheState {
+    /// The cache device is not open.
+    Closed,
+    /// The cache is being filled with the data of the children. The reads
+    /// are served by the children, and the writes go to both.
+    Filling,
+    /// The cache holds the data of the nexus and serves the reads.
+    Ready,
+}
+
+impl Display for CacheState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                CacheState::Closed => "closed",
+                CacheState::Filling => "filling",
+                CacheState::Ready => "ready",
+            }
+        )
+    }
+}
+
+/// Statistics of the cache of a nexus.
+#[derive(Debug, Clone)]
+pub struct NexusCacheStats {
+    /// URI of the cache device.
+    pub uri: String,
+    /// Write policy of the cache.
+    pub policy: CachePolicy,
+    /// Current state of the cache.
+    pub state: CacheState,
+    /// Set while the writes are acknowledged from the cache only.
+    pub write_back: bool,
+    /// Set once a cache I/O failed, until the cache is detached.
+    pub failed: bool,
+    /// Number of blocks held back by the previous destages, not counting the
+    /// blocks written since the last destage.
+    pub pending_blocks: u64,
+    /// Number of blocks destaged to the children so far.
+    pub destaged_blocks: u64,
+    /// Number of completed destages.
+    pub destages: u64,
+    /// Number of failed destages, whose blocks are destaged again by the next
+    /// one.
+    pub failed_destages: u64,
+    /// Set if the cache was copied to the children when the nexus was
+    /// created, after the previous nexus failed to destage it.
+    pub replayed: bool,
+}
+
+/// Superblock held in the last block of a cache device.
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheSuperblock {
+    magic: u64,
+    /// Uuid of the nexus the cache belongs to.
+    nexus_uuid: [u8; 16],
+    /// Set while the cache may hold blocks which are not on the children.
+    dirty: bool,
+    /// First cached data block.
+    data_start: u64,
+    /// End of the cached data blocks.
+    data_end: u64,
+}
+
+/// Mutable state of the cache of a nexus.
+#[derive(Default)]
+struct NexusCacheInner {
+    /// Descriptor of the cache device, once open.
+    descriptor: Option<Box<dyn BlockDeviceDescriptor>>,
+    /// Name of the cache device.
+    device_name: String,
+    /// Set if the cache device was created by the nexus.
+    created_device: bool,
+    /// Log of the blocks written to the cache only, in write-back mode.
+    dirty_log: Option<IOLog>,
+    /// Blocks held back by the previous destages.
+    pending: Option<SegmentMap>,
+    /// Job copying the blocks of the current fill or destage.
+    job: Option<Arc<NexusRebuildJob>>,
+    /// Set to stop the background worker.
+    stop: bool,
+    /// Signalled once the background worker is over.
+    worker_done: Option<oneshot::Receiver<()>>,
+    /// Number of blocks destaged so far.
+    destaged_blocks: u64,
+    /// Number of completed destages.
+    destages: u64,
+    /// Number of failed destages.
+    failed_destages: u64,
+    /// Set if the cache was replayed when opened.
+    replayed: bool,
+}
+
+/// Cache tier of a nexus.
+pub(super) struct NexusCache {
+    /// Configuration of the cache.
+    config: NexusCacheConfig,
+    /// Current state of the cache.
+    state: AtomicCell<CacheState>,
+    /// Set while the writes are acknowledged from the cache only.
+    write_back: AtomicCell<bool>,
+    /// Set once a cache I/O failed: the cache no longer goes write-back, and
+    /// is detached once destaged.
+    failed: AtomicCell<bool>,
+    /// Mutable state of the cache.
+    inner: parking_lot::Mutex<NexusCacheInner>,
+}
+
+impl NexusCache {
+    /// Creates the cache tier of a nexus, to be opened along with the nexus.
+    pub(super) fn new(config: NexusCacheConfig) -> Self {
+        Self {
+            config,
+            state: AtomicCell::new(CacheState::Closed),
+            write_back: AtomicCell::new(false),
+            failed: AtomicCell::new(false),
+            inner: parking_lot::Mutex::new(NexusCacheInner::default()),
+        }
+    }
+
+    /// Returns a new I/O handle of the cache device.
+    fn io_handle(&self) -> Result<Box<dyn BlockDeviceHandle>, CoreError> {
+        match self.inner.lock().descriptor.as_ref() {
+            Some(desc) => desc.get_io_handle(),
+            None => Err(CoreError::NoDevicesAvailable {}),
+        }
+    }
+}
+
+impl<'n> Nexus<'n> {
+    /// Returns the configuration of the cache, if the nexus has one.
+    pub fn cache_config(&self) -> Option<NexusCacheConfig> {
+        self.cache.as_ref().map(|c| c.config.clone())
+    }
+
+    /// Returns the statistics of the cache, if the nexus has one.
+    pub fn cache_stats(&self) -> Option<NexusCacheStats> {
+        let cache = self.cache.as_ref()?;
+        let inner = cache.inner.lock();
+        Some(NexusCacheStats {
+            uri: cache.config.uri.clone(),
+            policy: cache.config.policy,
+            state: cache.state.load(),
+            write_back: cache.write_back.load(),
+            failed: cache.failed.load(),
+            pending_blocks: inner
+                .pending
+                .as_ref()
+                .map_or(0, |map| map.count_dirty_blks()),
+            destaged_blocks: inner.destaged_blocks,
+            destages: inner.destages,
+            failed_destages: inner.failed_destages,
+            replayed: inner.replayed,
+        })
+    }
+
+    /// Checks if the nexus has a write-back cache, whose blocks may not have
+    /// been destaged to the children yet.
+    pub(crate) fn has_write_back_cache(&self) -> bool {
+        self.cache
+            .as_ref()
+            .map_or(false, |c| c.config.policy == CachePolicy::WriteBack)
+    }
+
+    /// Checks if the reads are to be served from the cache.
+    pub(super) fn is_cache_readable(&self) -> bool {
+        self.cache
+            .as_ref()
+            .map_or(false, |c| c.state.load() == CacheState::Ready)
+    }
+
+    /// Checks if the writes are to be acknowledged from the cache only.
+    pub(super) fn is_cache_write_back(&self) -> bool {
+        self.cache.as_ref().map_or(false, |c| c.write_back.load())
+    }
+
+    /// Switches the cache back to write-through after a cache I/O error. The
+    /// cache worker detaches the cache once destaged.
+    pub(super) fn cache_io_failed(&self) {
+        let Some(cache) = self.cache.as_ref() else {
+            return;
+        };
+        cache.write_back.store(false);
+        if !cache.failed.swap(true) {
+            error!(
+                "{self:?}: cache '{uri}' failed, switching to write-through",
+                uri = cache.config.uri
+            );
+        }
+    }
+
+    /// Checks if the given device is the cache device.
+    pub(super) fn is_cache_device(&self, device_name: &str) -> bool {
+        self.cache
+            .as_ref()
+            .map_or(false, |c| c.inner.lock().device_name == device_name)
+    }
+
+    /// Returns a new I/O handle of the cache device, if it is open.
+    pub(super) fn cache_io_handle(&self) -> Option<Box<dyn BlockDeviceHandle>> {
+        let cache = self.cache.as_ref()?;
+        if cache.state.load() == CacheState::Closed {
+            return None;
+        }
+
+        match cache.io_handle() {
+            Ok(hdl) => Some(hdl),
+            Err(error) => {
+                error!("{self:?}: failed to get I/O handle for cache: {error}");
+                None
+            }
+        }
+    }
+
+    /// Returns the log channel of the blocks written to the cache only, for
+    /// the current core.
+    pub(super) fn cache_log_channel(&self) -> Option<IOLogChannel> {
+        let cache = self.cache.as_ref()?;
+        let inner = cache.inner.lock();
+        inner.dirty_log.as_ref().map(|log| log.current_channel())
+    }
+
+    /// Opens the cache device of the nexus, if any, once the children are
+    /// open. If the previous nexus over this cache failed to destage it, the
+    /// cache is copied to the children first.
+    pub(super) async fn open_cache(&self) -> Result<(), Error> {
+        let Some(cache) = self.cache.as_ref() else {
+            return Ok(());
+        };
+        let uri = cache.config.uri.clone();
+
+        info!("{self:?}: opening cache '{uri}'...");
+
+        let superblock = match self.attach_cache_device(cache).await {
+            Ok(superblock) => superblock,
+            Err(reason) => {
+                self.detach_cache_device(cache).await;
+                return Err(Error::OpenCache {
+                    uri,
+                    name: self.name.clone(),
+                    reason,
+                });
+            }
+        };
+
+        let dirty = superblock
+            .filter(|sb| sb.dirty && sb.nexus_uuid == *self.uuid().as_bytes());
+        let state = match dirty {
+            Some(sb) => {
+                warn!(
+                    "{self:?}: cache '{uri}' was not destaged by the previous \
+                    nexus, replaying it..."
+                );
+                if let Err(error) = self.replay_cache(cache, &sb).await {
+                    self.detach_cache_device(cache).await;
+                    return Err(error);
+                }
+                cache.inner.lock().replayed = true;
+                CacheState::Ready
+            }
+            None => CacheState::Filling,
+        };
+
+        // The children now hold all the blocks of the cache, if any.
+        if let Err(error) = self.write_cache_superblock(cache, false).await {
+            self.detach_cache_device(cache).await;
+            return Err(Error::OpenCache {
+                uri,
+                name: self.name.clone(),
+                reason: format!("failed to write superblock: {error}"),
+            });
+        }
+
+        cache.state.store(state);
+        cache.failed.store(false);
+        self.reconfigure(DrEvent::CacheChange).await;
+
+        let (sender, recv) = oneshot::channel();
+        {
+            let mut inner = cache.inner.lock();
+            inner.stop = false;
+            inner.worker_done = Some(recv);
+        }
+        let name = self.name.clone();
+        Reactors::master().send_future(async move {
+            Nexus::run_cache_worker(name).await;
+            sender.send(()).ok();
+        });
+
+        info!("{self:?}: cache '{uri}' opened: {state}");
+        Ok(())
+    }
+
+    /// Closes the cache device of the nexus, if it is open. The blocks
+    /// written to the cache only are destaged first: if they can't be, the
+    /// cache is left dirty to be replayed by the next nexus.
+    pub(super) async fn close_cache(&self) {
+        let Some(cache) = self.cache.as_ref() else {
+            return;
+        };
+        if cache.state.load() == CacheState::Closed {
+            return;
+        }
+        let uri = &cache.config.uri;
+
+        info!("{self:?}: closing cache '{uri}'...");
+
+        let (job, worker_done) = {
+            let mut inner = cache.inner.lock();
+            inner.stop = true;
+            (inner.job.clone(), inner.worker_done.take())
+        };
+        if let Some(job) = job {
+            job.force_stop().await.ok();
+        }
+        if let Some(worker_done) = worker_done {
+            worker_done.await.ok();
+        }
+        // Unlike the background worker, the last destage must not be stopped.
+        cache.inner.lock().stop = false;
+
+        let dirty = cache.inner.lock().dirty_log.is_some();
+        cache.write_back.store(false);
+        if dirty {
+            if self.destage_cache(cache).await {
+                self.mark_cache_clean(cache).await;
+            } else {
+                warn!(
+                    "{self:?}: cache '{uri}' could not be destaged, it will \
+                    be replayed by the next nexus"
+                );
+            }
+        }
+
+        cache.state.store(CacheState::Closed);
+        cache.inner.lock().dirty_log = None;
+        self.reconfigure(DrEvent::CacheChange).await;
+        self.detach_cache_device(cache).await;
+
+        info!("{self:?}: cache '{uri}' closed");
+    }
+
+    /// Marks the cache clean once destaged: in the nexus information first,
+    /// as a dirty superblock only leads to a needless replay.
+    async fn mark_cache_clean(&self, cache: &NexusCache) {
+        let uri = &cache.config.uri;
+
+        if let Err(error) = self
+            .persist(PersistOp::CacheDirty {
+                dirty: false,
+            })
+            .await
+        {
+            error!("{self:?}: failed to save cache '{uri}' as clean: {error}");
+        }
+        if let Err(error) = self.write_cache_superblock(cache, false).await {
+            error!(
+                "{self:?}: failed to mark cache '{uri}' clean, it will be \
+                replayed by the next nexus: {error}"
+            );
+        }
+    }
+
+    /// Checks that the previous nexus of the volume did not leave a dirty
+    /// write-back cache behind, unless this nexus replayed it: the children
+    /// miss the blocks held by that cache otherwise.
+    pub(super) async fn check_cache_destaged(&self) -> Result<(), Error> {
+        let replayed = self
+            .cache
+            .as_ref()
+            .map_or(false, |c| c.inner.lock().replayed);
+        if replayed {
+            return Ok(());
+        }
+
+        match self.load_nexus_info().await {
+            Some(info) if info.cache_dirty => {
+                error!(
+                    "{self:?}: the write-back cache of the previous nexus \
+                    was not destaged, and can't be replayed"
+                );
+                Err(Error::CacheNotDestaged {
+                    name: self.name.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the range of the cached data blocks, which is the data
+    /// partition of the children.
+    fn cache_data_range(&self) -> Range<u64> {
+        self.data_ent_offset .. self.data_ent_offset + self.child_data_blocks()
+    }
+
+    /// Creates and opens the cache device, and reads its superblock.
+    async fn attach_cache_device(
+        &self,
+        cache: &NexusCache,
+    ) -> Result<Option<CacheSuperblock>, String> {
+        let uri = &cache.config.uri;
+
+        if self.num_sets() > 1 {
+            return Err("a striped nexus can't be cached".to_string());
+        }
+
+        // The superblock and the blocks copied within the cache don't carry
+        // valid protection information.
+        if self.protection_info().is_enabled() {
+            return Err("a nexus with protection information can't be cached"
+                .to_string());
+        }
+
+        let is_child = self.contains_child_uri(uri)
+            || device_name(uri)
+                .map(|name| self.contains_child_name(&name))
+                .unwrap_or(false);
+        if is_child {
+            return Err("the cache device is a child of the nexus".to_string());
+        }
+
+        let (name, created) = match device_create(uri).await {
+            Ok(name) => (name, true),
+            Err(BdevError::BdevExists {
+                ..
+            }) => (device_name(uri).map_err(|e| e.to_string())?, false),
+            Err(error) => return Err(error.to_string()),
+        };
+        {
+            let mut inner = cache.inner.lock();
+            inner.device_name = name.clone();
+            inner.created_device = created;
+        }
+
+        let desc = device_open(&name, true).map_err(|e| e.to_string())?;
+        let dev = desc.get_device();
+        if cache.config.policy == CachePolicy::WriteBack
+            && VOLATILE_DRIVERS.contains(&dev.driver_name().as_str())
+        {
+            return Err(format!(
+                "a {} device can't hold a write-back cache",
+                dev.driver_name()
+            ));
+        }
+        if dev.block_len() != self.block_len() {
+            return Err(format!(
+                "block size {} differs from the nexus block size {}",
+                dev.block_len(),
+                self.block_len()
+            ));
+        }
+        let needed = self.cache_data_range().end + 1;
+        if dev.num_blocks() < needed {
+            return Err(format!(
+                "{needed} blocks are needed, the device has {}",
+                dev.num_blocks()
+            ));
+        }
+        cache.inner.lock().descriptor = Some(desc);
+
+        self.read_cache_superblock(cache)
+            .await
+            .map_err(|e| format!("failed to read superblock: {e}"))
+    }
+
+    /// Closes the cache device, and destroys it if it was created by the
+    /// nexus.
+    async fn detach_cache_device(&self, cache: &NexusCache) {
+        let created = {
+            let mut inner = cache.inner.lock();
+            inner.descriptor = None;
+            std::mem::take(&mut inner.created_device)
+        };
+
+        if created {
+            if let Err(error) = device_destroy(&cache.config.uri).await {
+                error!("{self:?}: failed to destroy cache device: {error}");
+            }
+        }
+    }
+
+    /// Reads the superblock of the cache device, if it has a valid one.
+    async fn read_cache_superblock(
+        &self,
+        cache: &NexusCache,
+    ) -> Result<Option<CacheSuperblock>, CoreError> {
+        let hdl = cache.io_handle()?;
+        let blk = hdl.get_device().num_blocks() - 1;
+        let mut buf = hdl.dma_malloc(self.block_len()).map_err(|_| {
+            CoreError::DmaAllocationFailed {
+                size: self.block_len(),
+            }
+        })?;
+
+        hdl.read_buf_blocks_async(&mut buf, blk, 1, ReadOptions::None)
+            .await?;
+
+        let Ok(sb) = bincode::deserialize::<CacheSuperblock>(buf.as_slice())
+        else {
+            return Ok(None);
+        };
+        Ok(Some(sb).filter(|sb| sb.magic == CACHE_MAGIC))
+    }
+
+    /// Writes the superblock of the cache device, and flushes it.
+    async fn write_cache_superblock(
+        &self,
+        cache: &NexusCache,
+        dirty: bool,
+    ) -> Result<(), CoreError> {
+        let range = self.cache_data_range();
+        let sb = CacheSuperblock {
+            magic: CACHE_MAGIC,
+            nexus_uuid: *self.uuid().as_bytes(),
+            dirty,
+            data_start: range.start,
+            data_end: range.end,
+        };
+        let data =
+            bincode::serialize(&sb).expect("cache superblock must serialize");
+
+        let hdl = cache.io_handle()?;
+        let blk = hdl.get_device().num_blocks() - 1;
+        let mut buf = hdl.dma_malloc(self.block_len()).map_err(|_| {
+            CoreError::DmaAllocationFailed {
+                size: self.block_len(),
+            }
+        })?;
+        buf.as_mut_slice().fill(0);
+        buf.as_mut_slice()[.. data.len()].copy_from_slice(&data);
+
+        hdl.write_buf_blocks_async(&buf, blk, 1).await?;
+        hdl.flush_io_async().await
+    }
+
+    /// Copies the whole cache to the healthy children, before the nexus
+    /// opens.
+    async fn replay_cache(
+        &self,
+        cache: &NexusCache,
+        sb: &CacheSuperblock,
+    ) -> Result<(), Error> {
+        let uri = &cache.config.uri;
+        let range = self.cache_data_range();
+
+        if sb.data_start .. sb.data_end != range {
+            return Err(Error::OpenCache {
+                uri: uri.clone(),
+                name: self.name.clone(),
+                reason: format!(
+                    "cached blocks {}..{} don't match the nexus data \
+                    blocks {range:?}",
+                    sb.data_start, sb.data_end
+                ),
+            });
+        }
+
+        let children: Vec<String> = self
+            .children_iter()
+            .filter(|c| c.is_healthy())
+            .map(|c| c.uri().to_string())
+            .collect();
+        if children.is_empty() {
+            return Err(Error::OpenCache {
+                uri: uri.clone(),
+                name: self.name.clone(),
+                reason: "no healthy child to replay the cache to".to_string(),
+            });
+        }
+
+        for dst_uri in children {
+            info!("{self:?}: replaying cache '{uri}' to '{dst_uri}'...");
+
+            let result = async {
+                let job = BdevRebuildJob::builder()
+                    .with_range(range.clone())
+                    .build(uri, &dst_uri)
+                    .await?;
+                let state =
+                    job.start().await?.await.unwrap_or(RebuildState::Failed);
+                match state {
+                    RebuildState::Completed => Ok(()),
+                    _ => Err(job.error().unwrap_or(RebuildError::BackendGone)),
+                }
+            }
+            .await;
+
+            result.map_err(|source| Error::ReplayCache {
+                source,
+                uri: uri.clone(),
+                name: self.name.clone(),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs the background work of the cache of the given nexus until the
+    /// cache is closed: fills the cache, then destages the blocks written to
+    /// the cache only, periodically.
+    async fn run_cache_worker(name: String) {
+        loop {
+            let Some(nexus) = nexus_lookup(&name) else {
+                break;
+            };
+            let Some(cache) = nexus.cache.as_ref() else {
+                break;
+            };
+            if cache.inner.lock().stop {
+                break;
+            }
+
+            if cache.failed.load() {
+                if nexus.drain_failed_cache(cache).await {
+                    break;
+                }
+                mayastor_sleep(cache.config.destage_interval).await.ok();
+                continue;
+            }
+
+            match cache.state.load() {
+                CacheState::Closed => break,
+                CacheState::Filling => nexus.fill_cache(cache).await,
+                CacheState::Ready
+                    if cache.config.policy == CachePolicy::WriteBack =>
+                {
+                    if cache.write_back.load() {
+                        nexus.destage_cache(cache).await;
+                    } else {
+                        nexus.enable_write_back(cache).await;
+                    }
+                }
+                CacheState::Ready => {}
+            }
+
+            mayastor_sleep(cache.config.destage_interval).await.ok();
+        }
+
+        debug!("Nexus '{name}': cache worker stopped");
+    }
+
+    /// Destages the blocks written to the failed cache only, if any, then
+    /// detaches the cache so that the nexus I/Os bypass it.
+    /// Returns true once the cache is detached.
+    async fn drain_failed_cache(&self, cache: &NexusCache) -> bool {
+        let uri = &cache.config.uri;
+
+        if cache.inner.lock().dirty_log.is_some() {
+            if !self.destage_cache(cache).await {
+                warn!(
+                    "{self:?}: failed cache '{uri}' could not be destaged \
+                    yet, will retry"
+                );
+                return false;
+            }
+            self.mark_cache_clean(cache).await;
+        }
+
+        cache.state.store(CacheState::Closed);
+        cache.inner.lock().dirty_log = None;
+        self.reconfigure(DrEvent::CacheChange).await;
+        self.detach_cache_device(cache).await;
+
+        warn!("{self:?}: failed cache '{uri}' detached");
+        true
+    }
+
+    /// Copies the data of the children to the cache, with the nexus live.
+    async fn fill_cache(&self, cache: &NexusCache) {
+        let uri = &cache.config.uri;
+        let src_uris: Vec<String> = self
+            .children_iter()
+            .filter(|c| c.is_healthy())
+            .map(|c| c.uri().to_string())
+            .collect();
+        if src_uris.is_empty() {
+            warn!("{self:?}: no healthy child to fill cache '{uri}' from");
+            return;
+        }
+
+        info!("{self:?}: filling cache '{uri}'...");
+
+        match self.copy_locked(cache, &src_uris, uri, None).await {
+            Ok(true) => {
+                cache.state.store(CacheState::Ready);
+                info!("{self:?}: cache '{uri}' filled");
+            }
+            Ok(false) => info!("{self:?}: filling cache '{uri}' stopped"),
+            Err(error) => {
+                error!("{self:?}: failed to fill cache '{uri}': {error}")
+            }
+        }
+    }
+
+    /// Starts acknowledging the writes from the cache only, once the cache is
+    /// marked dirty.
+    async fn enable_write_back(&self, cache: &NexusCache) {
+        let uri = &cache.config.uri;
+
+        if let Err(error) = self.write_cache_superblock(cache, true).await {
+            error!("{self:?}: failed to mark cache '{uri}' dirty: {error}");
+            return;
+        }
+        if let Err(error) = self
+            .persist(PersistOp::CacheDirty {
+                dirty: true,
+            })
+            .await
+        {
+            error!("{self:?}: failed to save cache '{uri}' as dirty: {error}");
+            return;
+        }
+
+        cache.inner.lock().dirty_log = Some(self.new_cache_log());
+        self.reconnect_io_logs().await;
+        if cache.failed.load() {
+            return;
+        }
+        cache.write_back.store(true);
+
+        info!("{self:?}: cache '{uri}' switched to write-back");
+    }
+
+    /// Destages the blocks written to the cache only since the previous
+    /// destage, along with the blocks held back, to the children.
+    /// The blocks are held back while any child is not healthy, as a child
+    /// being rebuilt gets its data from another child.
+    /// Returns true if no block is left to destage.
+    async fn destage_cache(&self, cache: &NexusCache) -> bool {
+        let uri = &cache.config.uri;
+
+        let Some(map) = self.swap_cache_log(cache).await else {
+            return false;
+        };
+        let mut segments = SegmentMap::from(map);
+        if let Some(pending) = cache.inner.lock().pending.take() {
+            segments = segments.merge(&pending);
+        }
+
+        let blocks = segments.count_dirty_blks();
+        if blocks == 0 {
+            return true;
+        }
+
+        if !self.children_iter().all(|c| c.is_healthy()) {
+            cache.inner.lock().pending = Some(segments);
+            return false;
+        }
+
+        let children: Vec<String> =
+            self.children_iter().map(|c| c.uri().to_string()).collect();
+        for dst_uri in children {
+            let result = self
+                .copy_locked(
+                    cache,
+                    &[uri.clone()],
+                    &dst_uri,
+                    Some(segments.clone()),
+                )
+                .await;
+
+            let mut inner = cache.inner.lock();
+            match result {
+                Ok(true) => {}
+                Ok(false) => {
+                    inner.pending = Some(segments);
+                    return false;
+                }
+                Err(error) => {
+                    warn!(
+                        "{self:?}: failed to destage cache '{uri}' to \
+                        '{dst_uri}', will retry: {error}"
+                    );
+                    inner.failed_destages += 1;
+                    inner.pending = Some(segments);
+                    return false;
+                }
+            }
+        }
+
+        let mut inner = cache.inner.lock();
+        inner.destages += 1;
+        inner.destaged_blocks += blocks;
+        true
+    }
+
+    /// Replaces the log of the blocks written to the cache only with a new
+    /// one, and returns a map of the blocks logged by the previous one.
+    async fn swap_cache_log(&self, cache: &NexusCache) -> Option<RebuildMap> {
+        let log = {
+            let mut inner = cache.inner.lock();
+            if inner.dirty_log.is_none() {
+                return None;
+            }
+            inner.dirty_log.replace(self.new_cache_log())
+        }?;
+
+        // Once the channels are reconnected, the previous log no longer gets
+        // any write.
+        self.reconnect_io_logs().await;
+        Some(log.finalize())
+    }
+
+    /// Creates a log of the blocks written to the cache only.
+    fn new_cache_log(&self) -> IOLog {
+        IOLog::new(&self.name, 0, self.cache_data_range().end, self.block_len())
+    }
+
+    /// Copies the given segments, or all the data blocks, from the given
+    /// sources to the destination, locking the nexus ranges being copied so
+    /// that no nexus write goes in between.
+    /// Returns false if the copy was stopped.
+    async fn copy_locked(
+        &self,
+        cache: &NexusCache,
+        src_uris: &[String],
+        dst_uri: &str,
+        segments: Option<SegmentMap>,
+    ) -> Result<bool, RebuildError> {
+        let starter = NexusRebuildJob::new_starter(
+            &self.name,
+            src_uris,
+            dst_uri,
+            self.cache_data_range(),
+            None,
+            RebuildJobOptions::default(),
+            |_, _| {},
+        )
+        .await?;
+
+        let map = segments.map(|segments| RebuildMap::new(dst_uri, segments));
+        let (job, chan) = starter.start_detached(map).await?;
+        {
+            let mut inner = cache.inner.lock();
+            if inner.stop {
+                drop(job.force_stop());
+            }
+            inner.job = Some(job.clone());
+        }
+
+        let state = chan.await.unwrap_or(RebuildState::Failed);
+        cache.inner.lock().job = None;
+
+        match state {
+            RebuildState::Completed => Ok(true),
+            RebuildState::Failed => {
+                Err(job.error().unwrap_or(RebuildError::BackendGone))
+            }
+            _ => Ok(false),
+        }
+    }
+}
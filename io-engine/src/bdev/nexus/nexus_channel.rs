@@ -17,8 +17,16 @@ use spdk_rs::Thread;
 pub struct NexusChannel<'n> {
     writers: Vec<Box<dyn BlockDeviceHandle>>,
     readers: Vec<Box<dyn BlockDeviceHandle>>,
+    /// Stripe sets of the writers, in the same order.
+    writer_sets: Vec<usize>,
+    /// Stripe sets of the readers, in the same order.
+    reader_sets: Vec<usize>,
     detached: Vec<Box<dyn BlockDeviceHandle>>,
     io_logs: Vec<IOLogChannel>,
+    /// Handle of the cache device, if the nexus has one.
+    cache: Option<Box<dyn BlockDeviceHandle>>,
+    /// Log of the blocks written to the cache only.
+    cache_log: Option<IOLogChannel>,
     previous_reader: UnsafeCell<usize>,
     fail_fast: u32,
     io_mode: IoMode,
@@ -63,6 +71,8 @@ pub enum DrEvent {
     ChildUnplug,
     /// Child rebuild event.
     ChildRebuild,
+    /// Cache device opened or closed.
+    CacheChange,
 }
 
 impl Display for DrEvent {
@@ -73,6 +83,7 @@ impl Display for DrEvent {
             match self {
                 Self::ChildUnplug => "unplug",
                 Self::ChildRebuild => "rebuild",
+                Self::CacheChange => "cache change",
             }
         )
     }
@@ -116,8 +127,12 @@ impl<'n> NexusChannel<'n> {
         let mut res = Self {
             writers: Vec::new(),
             readers: Vec::new(),
+            writer_sets: Vec::new(),
+            reader_sets: Vec::new(),
             detached: Vec::new(),
             io_logs: nexus.io_log_channels(),
+            cache: None,
+            cache_log: nexus.cache_log_channel(),
             previous_reader: UnsafeCell::new(0),
             nexus: unsafe { nexus.pinned_mut() },
             fail_fast: 0,
@@ -146,8 +161,12 @@ impl<'n> NexusChannel<'n> {
         );
         self.writers.clear();
         self.readers.clear();
+        self.writer_sets.clear();
+        self.reader_sets.clear();
         self.detached.clear();
         self.io_logs.clear();
+        self.cache = None;
+        self.cache_log = None;
     }
 
     /// Returns reference to channel's Nexus.
@@ -163,9 +182,15 @@ impl<'n> NexusChannel<'n> {
         self.nexus.as_mut()
     }
 
-    /// Returns the total number of available readers in this channel.
-    pub(crate) fn num_readers(&self) -> usize {
-        self.readers.len()
+    /// Returns the number of available readers of the given stripe set in
+    /// this channel.
+    pub(crate) fn num_readers(&self, set: usize) -> usize {
+        self.reader_sets.iter().filter(|&&s| s == set).count()
+    }
+
+    /// Checks if this channel has a writer for the given stripe set.
+    pub(crate) fn has_writer(&self, set: usize) -> bool {
+        self.writer_sets.contains(&set)
     }
 
     // Returns a bool indicating whether this channel is setup for normal IOs.
@@ -173,13 +198,36 @@ impl<'n> NexusChannel<'n> {
         self.is_io_chan
     }
 
-    /// Calls the given callback for each active writer.
+    /// Calls the given callback for each active writer, along with the
+    /// writer's stripe set.
     #[inline(always)]
     pub(super) fn for_each_writer<F>(&self, mut f: F) -> Result<(), CoreError>
     where
-        F: FnMut(&dyn BlockDeviceHandle) -> Result<(), CoreError>,
+        F: FnMut(&dyn BlockDeviceHandle, usize) -> Result<(), CoreError>,
     {
-        self.writers.iter().try_for_each(|h| f(h.as_ref()))
+        self.writers
+            .iter()
+            .zip(self.writer_sets.iter())
+            .try_for_each(|(h, &set)| f(h.as_ref(), set))
+    }
+
+    /// Returns the handle of the cache device, if any.
+    #[inline(always)]
+    pub(super) fn cache(&self) -> Option<&dyn BlockDeviceHandle> {
+        self.cache.as_deref()
+    }
+
+    /// Returns the handle of the cache device if the reads are to be served
+    /// from the cache.
+    #[inline(always)]
+    pub(super) fn cache_reader(&self) -> Option<&dyn BlockDeviceHandle> {
+        self.cache().filter(|_| self.nexus().is_cache_readable())
+    }
+
+    /// Returns the log of the blocks written to the cache only, if any.
+    #[inline(always)]
+    pub(super) fn cache_log(&self) -> Option<&IOLogChannel> {
+        self.cache_log.as_ref()
     }
 
     /// Calls the given callback for each active I/O log.
@@ -196,21 +244,21 @@ impl<'n> NexusChannel<'n> {
     /// not the case but a side effect of using the async. As we poll
     /// threads more often depending on what core we are on etc, we might be
     /// "awaiting' while the thread is already trying to submit IO.
-    pub(crate) fn select_reader(&self) -> Option<&dyn BlockDeviceHandle> {
-        if self.readers.is_empty() {
-            None
-        } else {
-            let idx = unsafe {
-                let idx = &mut *self.previous_reader.get();
-                if *idx < self.readers.len() - 1 {
-                    *idx += 1;
-                } else {
-                    *idx = 0;
-                }
-                *idx
-            };
-            Some(self.readers[idx].as_ref())
-        }
+    /// Only readers of the given stripe set are considered.
+    pub(crate) fn select_reader(
+        &self,
+        set: usize,
+    ) -> Option<&dyn BlockDeviceHandle> {
+        let n = self.readers.len();
+        let prev = unsafe { &mut *self.previous_reader.get() };
+
+        (1 ..= n)
+            .map(|i| (*prev + i) % n)
+            .find(|&idx| self.reader_sets[idx] == set)
+            .map(|idx| {
+                *prev = idx;
+                self.readers[idx].as_ref()
+            })
     }
 
     /// Detaches a child device from this I/O channel, moving the device's
@@ -227,6 +275,7 @@ impl<'n> NexusChannel<'n> {
             .position(|c| c.get_device().device_name() == device_name)
         {
             let t = self.readers.remove(d);
+            self.reader_sets.remove(d);
             self.detached.push(t);
         }
 
@@ -236,6 +285,7 @@ impl<'n> NexusChannel<'n> {
             .position(|c| c.get_device().device_name() == device_name)
         {
             let t = self.writers.remove(d);
+            self.writer_sets.remove(d);
             self.detached.push(t);
         }
 
@@ -317,6 +367,8 @@ impl<'n> NexusChannel<'n> {
 
         let mut writers = Vec::new();
         let mut readers = Vec::new();
+        let mut writer_sets = Vec::new();
+        let mut reader_sets = Vec::new();
 
         // iterate over all our children which are in the healthy state
         self.nexus()
@@ -326,6 +378,8 @@ impl<'n> NexusChannel<'n> {
                 (Ok(w), Ok(r)) => {
                     writers.push(w);
                     readers.push(r);
+                    writer_sets.push(c.stripe_set());
+                    reader_sets.push(c.stripe_set());
 
                     debug!("{self:?}: connecting child device : {c:?}");
                 }
@@ -335,11 +389,13 @@ impl<'n> NexusChannel<'n> {
                 }
             });
 
-        // then add write-only children
+        // then add write-only children of the stripe sets that have readers
         if !readers.is_empty() {
             self.nexus()
                 .children_iter()
-                .filter(|c| c.is_rebuilding())
+                .filter(|c| {
+                    c.is_rebuilding() && reader_sets.contains(&c.stripe_set())
+                })
                 .for_each(|c| match c.get_io_handle() {
                     Ok(hdl) => {
                         debug!(
@@ -347,6 +403,7 @@ impl<'n> NexusChannel<'n> {
                                 in write-only mode: {c:?}"
                         );
                         writers.push(hdl);
+                        writer_sets.push(c.stripe_set());
                     }
                     Err(e) => {
                         c.set_faulted_state(FaultReason::CantOpen);
@@ -360,11 +417,15 @@ impl<'n> NexusChannel<'n> {
 
         self.writers = writers;
         self.readers = readers;
+        self.writer_sets = writer_sets;
+        self.reader_sets = reader_sets;
+        self.cache = self.nexus().cache_io_handle();
     }
 
     /// Reconnects all active I/O logs.
     pub(super) fn reconnect_io_logs(&mut self) {
         self.io_logs = self.nexus().io_log_channels();
+        self.cache_log = self.nexus().cache_log_channel();
     }
 
     /// Faults the child by its device, with the given fault reason.
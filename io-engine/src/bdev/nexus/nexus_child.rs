@@ -18,6 +18,7 @@ use super::{nexus_lookup_mut, DrEvent, IOLog, IOLogChannel};
 use crate::{
     bdev::{device_create, device_destroy, device_lookup},
     bdev_api::BdevError,
+    constants::TRACING_TARGET,
     core::{
         BlockDevice,
         BlockDeviceDescriptor,
@@ -28,7 +29,7 @@ use crate::{
     },
     eventing::replica_events::state_change_event_meta,
     persistent_store::PersistentStore,
-    rebuild::{NexusRebuildJob, RebuildMap},
+    rebuild::{NexusRebuildJob, RebuildCheckpoint, RebuildMap},
 };
 
 use crate::{
@@ -311,6 +312,12 @@ pub struct NexusChild<'c> {
     /// I/O log.
     #[serde(skip_serializing)]
     io_log: Mutex<Option<IOLog>>,
+    /// I/O log of a copy job reading from the child while the nexus is live.
+    #[serde(skip_serializing)]
+    copy_log: Mutex<Option<IOLog>>,
+    /// Stripe set the child belongs to. Always 0 for a mirrored nexus.
+    #[serde(skip_serializing)]
+    stripe_set: usize,
     /// TODO
     #[serde(skip_serializing)]
     _c: PhantomData<&'c ()>,
@@ -371,6 +378,11 @@ impl<'c> NexusChild<'c> {
     ///  - it's not faulted
     ///  - it's not already opened
     ///  - it's not being destroyed
+    #[tracing::instrument(
+        target = TRACING_TARGET,
+        skip_all,
+        fields(child = %self.name)
+    )]
     pub(crate) fn open(
         &mut self,
         parent_size: u64,
@@ -465,6 +477,17 @@ impl<'c> NexusChild<'c> {
         self.sync_state.load()
     }
 
+    /// Returns the stripe set the child belongs to.
+    #[inline]
+    pub fn stripe_set(&self) -> usize {
+        self.stripe_set
+    }
+
+    /// Sets the stripe set the child belongs to.
+    pub(super) fn set_stripe_set(&mut self, stripe_set: usize) {
+        self.stripe_set = stripe_set;
+    }
+
     /// Returns the sync state of the child.
     #[inline]
     pub fn set_sync_state(&self, s: ChildSyncState) {
@@ -849,6 +872,76 @@ impl<'c> NexusChild<'c> {
         self.resv_check_holder(params).await
     }
 
+    /// Acquire the NVMe reservation of the child again, after a change of the
+    /// reservation parameters of the nexus, which holds the reservation with
+    /// the previous ones. Unless told to preempt another key, the nexus only
+    /// preempts itself to change its key or reservation type.
+    /// # Warning: Ignores bdevs without NVMe reservation support.
+    pub(crate) async fn reservation_update(
+        &self,
+        params: &NexusNvmeParams,
+    ) -> Result<(), ChildError> {
+        if std::env::var("NEXUS_NVMF_RESV_ENABLE").is_err() {
+            return Ok(());
+        }
+        if !params.reservations_enabled() {
+            return Ok(());
+        }
+
+        match params.preempt_policy {
+            NexusNvmePreemption::ArgKey if params.preempt_key.is_none() => {
+                self.reservation_preempt_self(params).await?;
+            }
+            NexusNvmePreemption::ArgKey => {
+                self.reservation_acquire_argkey(params).await?;
+            }
+            NexusNvmePreemption::Holder => {
+                self.reservation_preempt_holder(params).await?;
+            }
+        }
+        self.resv_check_holder(params).await
+    }
+
+    /// Register an NVMe reservation on the child with a new key, and preempt
+    /// the reservation if we hold it, or else acquire it.
+    /// # Warning: Ignores bdevs without NVMe reservation support.
+    async fn reservation_preempt_self(
+        &self,
+        params: &NexusNvmeParams,
+    ) -> Result<(), ChildError> {
+        let hdl = self.get_io_handle_nonblock().await.context(HandleOpen {})?;
+
+        if let Err(e) = self.resv_register(&*hdl, params.resv_key).await {
+            return match e {
+                CoreError::NotSupported {
+                    ..
+                } => Ok(()),
+                _ => Err(ChildError::ResvRegisterKey {
+                    source: e,
+                }),
+            };
+        }
+
+        let my_hostid =
+            hdl.host_id().await.map_err(|e| ChildError::NvmeHostId {
+                source: e,
+            })?;
+        match self.resv_holder(&*hdl).await? {
+            Some((_, _, hostid)) if hostid == my_hostid => {
+                self.reservation_preempt_holder(params).await
+            }
+            _ => {
+                self.resv_acquire(
+                    &*hdl,
+                    params.resv_key,
+                    None,
+                    params.resv_type,
+                )
+                .await
+            }
+        }
+    }
+
     /// Register an NVMe reservation on the child and preempt any existing
     /// reservation holder automatically if necessary.
     /// Refer to the NVMe spec for more information:
@@ -1183,6 +1276,8 @@ impl<'c> NexusChild<'c> {
             faulted_at: parking_lot::Mutex::new(None),
             remove_channel: async_channel::bounded(1),
             io_log: Mutex::new(None),
+            copy_log: Mutex::new(None),
+            stripe_set: 0,
             _c: Default::default(),
         }
     }
@@ -1316,6 +1411,7 @@ impl<'c> NexusChild<'c> {
             if let Some(d) = &self.device {
                 *io_log = Some(IOLog::new(
                     &d.device_name(),
+                    self.stripe_set,
                     d.num_blocks(),
                     d.block_len(),
                 ));
@@ -1333,6 +1429,16 @@ impl<'c> NexusChild<'c> {
         self.io_log.lock().take().map(|log| log.finalize())
     }
 
+    /// Creates a rebuild map out of the given rebuild checkpoint.
+    pub(super) fn checkpoint_map(
+        &self,
+        checkpoint: &RebuildCheckpoint,
+    ) -> Option<RebuildMap> {
+        self.device.as_ref().map(|d| {
+            checkpoint.to_map(&d.device_name(), d.num_blocks(), d.block_len())
+        })
+    }
+
     /// Returns I/O log channel for the current core.
     pub(super) fn io_log_channel(&self) -> Option<IOLogChannel> {
         self.io_log.lock().as_ref().map(|log| log.current_channel())
@@ -1342,4 +1448,44 @@ impl<'c> NexusChild<'c> {
     pub(crate) fn has_io_log(&self) -> bool {
         self.io_log.lock().is_some()
     }
+
+    /// Creates a new copy log, which records the writes made to the child
+    /// while it is being copied elsewhere. Returns false if a copy log
+    /// already exists or the child has no device.
+    pub(super) fn start_copy_log(&self) -> bool {
+        let mut copy_log = self.copy_log.lock();
+
+        if copy_log.is_some() {
+            return false;
+        }
+
+        let Some(d) = &self.device else {
+            return false;
+        };
+
+        *copy_log = Some(IOLog::new(
+            &d.device_name(),
+            self.stripe_set,
+            d.num_blocks(),
+            d.block_len(),
+        ));
+
+        debug!("{self:?}: started new copy log: {log:?}", log = *copy_log);
+        true
+    }
+
+    /// Detaches the copy log from the child. The log must only be finalized
+    /// once no I/O channel refers to it anymore.
+    pub(super) fn take_copy_log(&self) -> Option<IOLog> {
+        debug!("{self:?}: stopping copy log");
+        self.copy_log.lock().take()
+    }
+
+    /// Returns copy log channel for the current core.
+    pub(super) fn copy_log_channel(&self) -> Option<IOLogChannel> {
+        self.copy_log
+            .lock()
+            .as_ref()
+            .map(|log| log.current_channel())
+    }
 }
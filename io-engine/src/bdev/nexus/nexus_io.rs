@@ -14,19 +14,29 @@ use spdk_rs::{
         spdk_io_channel,
         SPDK_NVME_SC_ABORTED_SQ_DELETION,
         SPDK_NVME_SC_CAPACITY_EXCEEDED,
+        SPDK_NVME_SC_INTERNAL_DEVICE_ERROR,
         SPDK_NVME_SC_INVALID_OPCODE,
         SPDK_NVME_SC_RESERVATION_CONFLICT,
     },
     BdevIo,
 };
 
-use super::{FaultReason, IOLogChannel, Nexus, NexusChannel, NEXUS_PRODUCT_ID};
+use super::{
+    FaultReason,
+    IOLogChannel,
+    Nexus,
+    NexusChannel,
+    NexusPiPolicy,
+    NEXUS_PRODUCT_ID,
+};
 
 use crate::core::{
     BlockDevice,
     BlockDeviceHandle,
     CoreError,
     Cores,
+    DifContext,
+    DifType,
     IoCompletionStatus,
     IoStatus,
     IoSubmissionFailure,
@@ -74,6 +84,15 @@ pub(super) struct NioCtx<'n> {
     failed: u8,
     /// Number of resubmissions. Incremented with each resubmission.
     resubmits: u8,
+    /// Set when the I/O failed on the cache device. Such I/Os are not
+    /// resubmitted, as the cache device is not retired, unless they went to
+    /// the cache only.
+    cache_failed: bool,
+    /// Set when the I/O was submitted to the cache only, in write-back mode.
+    cache_only: bool,
+    /// Set once the protection information of a write I/O is prepared for
+    /// the children, so that resubmissions don't prepare it again.
+    pi_ready: bool,
     /// Debug serial number.
     #[cfg(feature = "nexus-io-tracing")]
     serial: u64,
@@ -165,6 +184,9 @@ impl<'n> NexusBio<'n> {
         ctx.resubmits = 0;
         ctx.successful = 0;
         ctx.failed = 0;
+        ctx.cache_failed = false;
+        ctx.cache_only = false;
+        ctx.pi_ready = false;
 
         #[cfg(feature = "nexus-io-tracing")]
         {
@@ -257,8 +279,22 @@ impl<'n> NexusBio<'n> {
         debug_assert!(self.ctx().in_flight > 0);
         self.ctx_mut().in_flight -= 1;
 
+        let status = if status == IoCompletionStatus::Success
+            && self.io_type() == IoType::Read
+        {
+            self.check_read_pi(child)
+        } else {
+            status
+        };
+
         if status == IoCompletionStatus::Success {
             self.ctx_mut().successful += 1;
+        } else if self.nexus().is_cache_device(&child.device_name()) {
+            error!("{self:?}: cache I/O failed with {status:?}");
+            self.ctx_mut().status = IoStatus::Failed;
+            self.ctx_mut().failed += 1;
+            self.ctx_mut().cache_failed = true;
+            self.nexus().cache_io_failed();
         } else {
             self.ctx_mut().status = IoStatus::Failed;
             self.ctx_mut().failed += 1;
@@ -276,6 +312,13 @@ impl<'n> NexusBio<'n> {
             // No child failures, complete nexus I/O with success.
             trace_nexus_io!("Success: {self:?}");
             self.ok();
+        } else if self.ctx().cache_only {
+            // The cache is back to write-through, the I/O goes to the
+            // children as well this time.
+            self.resubmit();
+        } else if self.ctx().cache_failed {
+            error!("{self:?}: failing nexus I/O: cache I/O failed");
+            self.fail();
         } else if self.ctx().successful > 0 {
             // Having some child failures, resubmit the I/O.
             self.resubmit();
@@ -320,12 +363,14 @@ impl<'n> NexusBio<'n> {
 
         debug_assert_eq!(ctx.in_flight, 0);
         debug_assert!(ctx.failed > 0);
-        debug_assert!(ctx.successful > 0);
+        debug_assert!(ctx.successful > 0 || ctx.cache_only);
 
         ctx.status = IoStatus::Pending;
         ctx.resubmits += 1;
         ctx.successful = 0;
         ctx.failed = 0;
+        ctx.cache_failed = false;
+        ctx.cache_only = false;
 
         let bio = self.clone();
         trace_nexus_io!("New resubmit: {bio:?}");
@@ -359,19 +404,58 @@ impl<'n> NexusBio<'n> {
         self.offset() + self.data_ent_offset()
     }
 
+    /// Returns the offset and the number of blocks the I/O operation covers
+    /// on the children of the given stripe set, or None if the I/O doesn't
+    /// touch the set. For a mirrored nexus, this is the effective range of
+    /// the I/O.
+    #[inline]
+    fn child_range(&self, set: usize) -> Option<(u64, u64)> {
+        match self.nexus().stripe_geometry() {
+            None => Some((self.effective_offset(), self.num_blocks())),
+            Some(geom) => geom
+                .map_range(set, self.offset(), self.num_blocks())
+                .map(|(offset, num)| (offset + self.data_ent_offset(), num)),
+        }
+    }
+
+    /// Returns the stripe set a read operation is to be served from.
+    /// Reads never cross stripe boundaries, as the bdev layer splits them.
+    #[inline]
+    fn read_set(&self) -> usize {
+        self.nexus()
+            .stripe_geometry()
+            .map_or(0, |geom| geom.map_block(self.offset()).0)
+    }
+
+    /// Checks that every stripe set a write-like I/O touches has a writer
+    /// in this channel. Otherwise, a part of the data would not be written.
+    fn stripe_sets_writable(&self) -> bool {
+        if self.nexus().stripe_geometry().is_none()
+            || matches!(self.io_type(), IoType::Reset | IoType::Flush)
+        {
+            return true;
+        }
+
+        (0 .. self.nexus().num_sets()).all(|set| {
+            self.child_range(set).is_none() || self.channel().has_writer(set)
+        })
+    }
+
     /// submit a read operation to one of the children of this nexus
     #[inline]
     fn submit_read(
         &self,
         hdl: &dyn BlockDeviceHandle,
+        offset: u64,
+        num_blocks: u64,
     ) -> Result<(), CoreError> {
         #[cfg(feature = "fault-injection")]
         self.inject_submission_error(hdl)?;
 
         hdl.readv_blocks(
             self.iovs_mut(),
-            self.effective_offset(),
-            self.num_blocks(),
+            offset,
+            num_blocks,
             ReadOptions::None,
             Self::child_completion,
             self.as_ptr().cast(),
@@ -380,8 +464,13 @@ impl<'n> NexusBio<'n> {
 
     /// Submit a Read operation to the next available replica.
     fn __do_readv_one(&mut self) -> Result<(), CoreError> {
-        if let Some(hdl) = self.channel().select_reader() {
-            let r = self.submit_read(hdl);
+        let set = self.read_set();
+
+        if let Some(hdl) = self.channel().select_reader(set) {
+            let (offset, num_blocks) = self
+                .child_range(set)
+                .expect("read I/O must cover its stripe set");
+            let r = self.submit_read(hdl, offset, num_blocks);
 
             if r.is_err() {
                 // Such a situation can happen when there is no active I/O in
@@ -422,6 +511,10 @@ impl<'n> NexusBio<'n> {
     /// In case of submission error the requiest is transparently resubmitted
     /// to the next available replica.
     fn do_readv(&mut self) -> Result<(), CoreError> {
+        if self.channel().cache_reader().is_some() {
+            return self.do_readv_cache();
+        }
+
         match self.__do_readv_one() {
             Err(e) => {
                 match e {
@@ -433,7 +526,8 @@ impl<'n> NexusBio<'n> {
                     // Failed to submit Read I/O request to the current replica,
                     // try to resumbit request to the next available replica.
                     _ => {
-                        let mut num_readers = self.channel().num_readers();
+                        let mut num_readers =
+                            self.channel().num_readers(self.read_set());
 
                         let r = {
                             if num_readers <= 1 {
@@ -471,6 +565,25 @@ impl<'n> NexusBio<'n> {
         }
     }
 
+    /// Submit a read operation to the cache device, which holds the data of
+    /// the whole nexus.
+    fn do_readv_cache(&mut self) -> Result<(), CoreError> {
+        let hdl = self
+            .channel()
+            .cache_reader()
+            .expect("cache must be readable");
+        let r =
+            self.submit_read(hdl, self.effective_offset(), self.num_blocks());
+
+        if let Err(e) = &r {
+            error!("{self:?}: read I/O to the cache submission failed: {e:?}");
+            self.fail();
+        } else {
+            self.ctx_mut().in_flight = 1;
+        }
+        r
+    }
+
     extern "C" fn nexus_get_buf_cb(
         _ch: *mut spdk_io_channel,
         io: *mut spdk_bdev_io,
@@ -506,6 +619,8 @@ impl<'n> NexusBio<'n> {
     fn submit_write(
         &self,
         hdl: &dyn BlockDeviceHandle,
+        offset: u64,
+        num_blocks: u64,
     ) -> Result<(), CoreError> {
         trace_nexus_io!(
             "Submitting: {self:?} -> {name}",
@@ -517,8 +632,8 @@ impl<'n> NexusBio<'n> {
 
         hdl.writev_blocks(
             self.iovs(),
-            self.effective_offset(),
-            self.num_blocks(),
+            offset,
+            num_blocks,
             Self::child_completion,
             self.as_ptr().cast(),
         )
@@ -528,6 +643,8 @@ impl<'n> NexusBio<'n> {
     fn submit_unmap(
         &self,
         hdl: &dyn BlockDeviceHandle,
+        offset: u64,
+        num_blocks: u64,
     ) -> Result<(), CoreError> {
         trace_nexus_io!(
             "Submitting: {self:?} -> {name}",
@@ -535,8 +652,8 @@ impl<'n> NexusBio<'n> {
         );
 
         hdl.unmap_blocks(
-            self.effective_offset(),
-            self.num_blocks(),
+            offset,
+            num_blocks,
             Self::child_completion,
             self.as_ptr().cast(),
         )
@@ -546,6 +663,8 @@ impl<'n> NexusBio<'n> {
     fn submit_write_zeroes(
         &self,
         hdl: &dyn BlockDeviceHandle,
+        offset: u64,
+        num_blocks: u64,
     ) -> Result<(), CoreError> {
         trace_nexus_io!(
             "Submitting: {self:?} -> {name}",
@@ -556,8 +675,8 @@ impl<'n> NexusBio<'n> {
         self.inject_submission_error(hdl)?;
 
         hdl.write_zeroes(
-            self.effective_offset(),
-            self.num_blocks(),
+            offset,
+            num_blocks,
             Self::child_completion,
             self.as_ptr().cast(),
         )
@@ -589,27 +708,116 @@ impl<'n> NexusBio<'n> {
         hdl.flush_io(Self::child_completion, self.as_ptr().cast())
     }
 
+    /// Submits the I/O to the given child or cache device, covering the given
+    /// range of its blocks. Returns None if a data I/O doesn't touch the
+    /// device.
+    fn submit_device(
+        &self,
+        hdl: &dyn BlockDeviceHandle,
+        range: Option<(u64, u64)>,
+    ) -> Option<Result<(), CoreError>> {
+        let r = match (self.io_type(), range) {
+            (IoType::Reset, _) => self.submit_reset(hdl),
+            (IoType::Flush, _) => self.submit_flush(hdl),
+            // The I/O doesn't touch the stripe set of this child.
+            (_, None) => return None,
+            (IoType::Write, Some((offset, num))) => {
+                self.submit_write(hdl, offset, num)
+            }
+            (IoType::Unmap, Some((offset, num))) => {
+                self.submit_unmap(hdl, offset, num)
+            }
+            (IoType::WriteZeros, Some((offset, num))) => {
+                self.submit_write_zeroes(hdl, offset, num)
+            }
+            // we should never reach here, if we do it is a bug.
+            _ => unreachable!(),
+        };
+        Some(r)
+    }
+
+    /// Submits a write-like I/O to the cache device only, logging the written
+    /// blocks so that they get destaged to the children later.
+    fn submit_cache_only(&mut self) -> Result<(), CoreError> {
+        self.nexus().set_data_written();
+
+        let hdl = self.channel().cache().expect("nexus must have a cache");
+        let r = self
+            .submit_device(hdl, self.child_range(0))
+            .expect("data I/O must cover the cache");
+
+        match r {
+            Ok(_) => {
+                if let Some(log) = self.channel().cache_log() {
+                    self.log_io(log);
+                }
+                self.ctx_mut().in_flight = 1;
+                self.ctx_mut().cache_only = true;
+                self.ctx_mut().status = IoStatus::Success;
+            }
+            Err(ref e) => {
+                error!(
+                    "{self:?}: failing nexus I/O: cache I/O submission \
+                    failed: {e:?}"
+                );
+                self.nexus().cache_io_failed();
+                self.fail();
+            }
+        }
+        r
+    }
+
     /// Submit the IO to all underlying children, failing on the first error we
     /// find. When an IO is partially submitted -- we must wait until all
     /// the child IOs have completed before we mark the whole IO failed to
     /// avoid double frees. This function handles IO for a subset that must
     /// be submitted to all the underlying children.
+    /// With a striped layout, data I/Os are only submitted to the children
+    /// of the stripe sets they touch.
+    /// The I/O is submitted to the cache device as well, if any. In
+    /// write-back mode, data I/Os are submitted to the cache device only.
     fn submit_all(&mut self) -> Result<(), CoreError> {
+        if let Err(err) = self.prepare_write_pi() {
+            error!("{self:?}: failing nexus I/O: {err}");
+            self.fail_pi(&err);
+            return Err(err);
+        }
+
+        if self.channel().cache().is_some()
+            && self.nexus().is_cache_write_back()
+            && matches!(
+                self.io_type(),
+                IoType::Write | IoType::WriteZeros | IoType::Unmap
+            )
+        {
+            return self.submit_cache_only();
+        }
+
+        if !self.stripe_sets_writable() {
+            error!(
+                "{self:?}: failing nexus I/O: no children available \
+                for a stripe set"
+            );
+            self.fail();
+            return Err(CoreError::NoDevicesAvailable {});
+        }
+
+        if matches!(
+            self.io_type(),
+            IoType::Write | IoType::WriteZeros | IoType::Unmap
+        ) {
+            self.nexus().set_data_written();
+        }
+
         let mut inflight = 0;
         // Name of the device which experiences I/O submission failures.
         let mut failed_device = None;
 
-        let result = self.channel().for_each_writer(|h| {
-            match self.io_type() {
-                IoType::Write => self.submit_write(h),
-                IoType::Unmap => self.submit_unmap(h),
-                IoType::WriteZeros => self.submit_write_zeroes(h),
-                IoType::Reset => self.submit_reset(h),
-                IoType::Flush => self.submit_flush(h),
-                // we should never reach here, if we do it is a bug.
-                _ => unreachable!(),
-            }
-            .map(|_| {
+        let result = self.channel().for_each_writer(|h, set| {
+            let Some(r) = self.submit_device(h, self.child_range(set)) else {
+                return Ok(());
+            };
+            r.map(|_| {
                 inflight += 1;
             })
             .map_err(|err| {
@@ -657,6 +865,21 @@ impl<'n> NexusBio<'n> {
 
         self.channel().for_each_io_log(|log| self.log_io(log));
 
+        // The cache device is written along with the children, so that it
+        // keeps holding the data of the nexus.
+        if let Some(h) = self.channel().cache() {
+            match self.submit_device(h, self.child_range(0)) {
+                Some(Ok(_)) => inflight += 1,
+                Some(Err(err)) => {
+                    error!("{self:?}: cache I/O submission failed: {err:?}");
+                    self.ctx_mut().failed += 1;
+                    self.ctx_mut().cache_failed = true;
+                    self.nexus().cache_io_failed();
+                }
+                None => {}
+            }
+        }
+
         if inflight > 0 {
             // TODO: fix comment:
             // An error was experienced during submission.
@@ -678,7 +901,9 @@ impl<'n> NexusBio<'n> {
     /// Logs all write-like operation in the rebuild logs, if any exist.
     #[inline]
     fn log_io(&self, log: &IOLogChannel) {
-        log.log_io(self.io_type(), self.effective_offset(), self.num_blocks());
+        if let Some((offset, num_blocks)) = self.child_range(log.stripe_set()) {
+            log.log_io(self.io_type(), offset, num_blocks);
+        }
     }
 
     /// Initiate shutdown of the nexus associated with this BIO request.
@@ -781,6 +1006,99 @@ impl<'n> NexusBio<'n> {
         }
     }
 
+    /// Prepares the protection information of the blocks of a write I/O
+    /// for the children: generates or verifies it according to the policy
+    /// of the nexus, then remaps the reference tags from the nexus LBAs to
+    /// the children ones.
+    fn prepare_write_pi(&mut self) -> Result<(), CoreError> {
+        let pi = self.nexus().protection_info();
+        if !pi.is_enabled()
+            || self.io_type() != IoType::Write
+            || self.ctx().pi_ready
+        {
+            return Ok(());
+        }
+
+        let num_blocks = self.num_blocks();
+        let ctx =
+            DifContext::new(self.nexus().block_len(), &pi, self.offset())?;
+        match self.nexus().pi_policy() {
+            NexusPiPolicy::Passthrough => {}
+            NexusPiPolicy::Verify => ctx.verify(self.iovs(), num_blocks)?,
+            NexusPiPolicy::Generate => ctx.generate(self.iovs(), num_blocks)?,
+        }
+
+        if self.data_ent_offset() != 0 && pi.dif_type != DifType::Type3 {
+            ctx.with_remapped_ref_tag(self.effective_offset())
+                .remap_ref_tag(self.iovs(), num_blocks)?;
+        }
+
+        self.ctx_mut().pi_ready = true;
+        Ok(())
+    }
+
+    /// Remaps the reference tags of the blocks read from a child back to the
+    /// nexus LBAs, and verifies their protection information unless the
+    /// nexus passes it through. Returns the status the child read is to be
+    /// completed with.
+    fn check_read_pi(&self, child: &dyn BlockDevice) -> IoCompletionStatus {
+        let pi = self.nexus().protection_info();
+        if !pi.is_enabled() {
+            return IoCompletionStatus::Success;
+        }
+
+        let num_blocks = self.num_blocks();
+        let block_len = self.nexus().block_len();
+        let res = DifContext::new(block_len, &pi, self.effective_offset())
+            .and_then(|ctx| {
+                if self.data_ent_offset() == 0 || pi.dif_type == DifType::Type3
+                {
+                    return Ok(());
+                }
+                ctx.with_remapped_ref_tag(self.offset())
+                    .remap_ref_tag(self.iovs(), num_blocks)
+            })
+            .and_then(|_| {
+                if self.nexus().pi_policy() == NexusPiPolicy::Passthrough {
+                    return Ok(());
+                }
+                DifContext::new(block_len, &pi, self.offset())?
+                    .verify(self.iovs(), num_blocks)
+            });
+
+        match res {
+            Ok(_) => IoCompletionStatus::Success,
+            Err(CoreError::ProtectionCheckFailed {
+                status, ..
+            }) => {
+                error!(
+                    "{self:?}: protection information check of the blocks \
+                    read from '{dev}' failed: {status:?}",
+                    dev = child.device_name()
+                );
+                status
+            }
+            Err(err) => {
+                error!("{self:?}: {err}");
+                IoCompletionStatus::NvmeError(NvmeStatus::Generic(
+                    SPDK_NVME_SC_INTERNAL_DEVICE_ERROR,
+                ))
+            }
+        }
+    }
+
+    /// Fails the I/O after its protection information failed to be
+    /// prepared.
+    fn fail_pi(&self, err: &CoreError) {
+        match err {
+            CoreError::ProtectionCheckFailed {
+                status: IoCompletionStatus::NvmeError(s),
+                ..
+            } => self.fail_nvme_status(*s),
+            _ => self.0.fail(),
+        }
+    }
+
     /// Checks if an error is to be injected upon submission.
     #[cfg(feature = "fault-injection")]
     #[inline]
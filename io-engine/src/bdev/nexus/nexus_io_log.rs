@@ -21,6 +21,8 @@ pub(crate) struct IOLogChannelInner {
     core: u32,
     /// Name of the underlying block device.
     device_name: String,
+    /// Stripe set of the logged child.
+    stripe_set: usize,
     /// Map of device segments.
     segments: UnsafeCell<Option<SegmentMap>>,
 }
@@ -42,11 +44,13 @@ impl IOLogChannelInner {
     fn new(
         core: u32,
         device_name: &str,
+        stripe_set: usize,
         num_blocks: u64,
         block_len: u64,
     ) -> Self {
         Self {
             core,
+            stripe_set,
             segments: UnsafeCell::new(Some(SegmentMap::new(
                 num_blocks,
                 block_len,
@@ -76,6 +80,12 @@ impl IOLogChannelInner {
         }
     }
 
+    /// Returns the stripe set of the logged child.
+    #[inline]
+    pub(crate) fn stripe_set(&self) -> usize {
+        self.stripe_set
+    }
+
     /// Returns a reference to segments.
     #[inline]
     fn segments(&self) -> &SegmentMap {
@@ -122,12 +132,14 @@ impl IOLogChannel {
     fn new(
         core: u32,
         device_name: &str,
+        stripe_set: usize,
         num_blocks: u64,
         block_len: u64,
     ) -> Self {
         Self(Rc::new(IOLogChannelInner::new(
             core,
             device_name,
+            stripe_set,
             num_blocks,
             block_len,
         )))
@@ -157,6 +169,7 @@ impl IOLog {
     /// Creates a new I/O log instance for the given device.
     pub(crate) fn new(
         device_name: &str,
+        stripe_set: usize,
         num_blocks: u64,
         block_len: u64,
     ) -> Self {
@@ -167,7 +180,13 @@ impl IOLog {
         for i in Cores::list_cores() {
             channels.insert(
                 i,
-                IOLogChannel::new(i, device_name, num_blocks, block_len),
+                IOLogChannel::new(
+                    i,
+                    device_name,
+                    stripe_set,
+                    num_blocks,
+                    block_len,
+                ),
             );
         }
 
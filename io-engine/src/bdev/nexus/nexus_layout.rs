@@ -0,0 +1,282 @@
+//!
+//! Data layouts of a nexus.
+//!
+//! By default, a nexus mirrors all data to every child. Striped layouts
+//! spread the data across sets of children in fixed-size stripes instead:
+//! stripe N goes to set N modulo the number of sets. Children within a set
+//! mirror each other, so a striped layout with one child per set is a plain
+//! RAID-0, while a striped layout with several children per set is a RAID-10.
+
+use serde::Serialize;
+
+/// Data layout of a nexus.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Serialize)]
+pub enum NexusLayout {
+    /// Every child holds a full copy of the data.
+    #[default]
+    Mirror,
+    /// Data is striped across all children without any redundancy.
+    Striped {
+        /// Stripe size in bytes.
+        stripe_size: u64,
+    },
+    /// Data is striped across sets of mirrored children.
+    StripedMirror {
+        /// Stripe size in bytes.
+        stripe_size: u64,
+        /// Number of children in each mirror set.
+        copies: usize,
+    },
+}
+
+impl NexusLayout {
+    /// Checks if the layout stripes data across children.
+    pub fn is_striped(&self) -> bool {
+        !matches!(self, Self::Mirror)
+    }
+
+    /// Returns the stripe size in bytes, if the layout is striped.
+    pub fn stripe_size(&self) -> Option<u64> {
+        match self {
+            Self::Mirror => None,
+            Self::Striped {
+                stripe_size,
+            }
+            | Self::StripedMirror {
+                stripe_size, ..
+            } => Some(*stripe_size),
+        }
+    }
+
+    /// Returns the number of stripe sets for the given number of children.
+    pub fn num_sets(&self, num_children: usize) -> usize {
+        match self {
+            Self::Mirror => 1,
+            Self::Striped {
+                ..
+            } => num_children,
+            Self::StripedMirror {
+                copies, ..
+            } => num_children / copies,
+        }
+    }
+
+    /// Returns the stripe set of the child at the given position in the
+    /// initial list of children.
+    pub fn child_set(&self, idx: usize) -> usize {
+        match self {
+            Self::Mirror => 0,
+            Self::Striped {
+                ..
+            } => idx,
+            Self::StripedMirror {
+                copies, ..
+            } => idx / copies,
+        }
+    }
+
+    /// Returns the number of children each stripe set is made of, if the
+    /// layout restricts it.
+    pub fn copies(&self) -> Option<usize> {
+        match self {
+            Self::Mirror => None,
+            Self::Striped {
+                ..
+            } => Some(1),
+            Self::StripedMirror {
+                copies, ..
+            } => Some(*copies),
+        }
+    }
+
+    /// Validates the layout against the number of children.
+    pub fn validate(&self, num_children: usize) -> Result<(), String> {
+        if let Some(stripe_size) = self.stripe_size() {
+            if !stripe_size.is_power_of_two() {
+                return Err(format!(
+                    "stripe size {stripe_size} is not a power of two"
+                ));
+            }
+        }
+
+        match self {
+            Self::Mirror => Ok(()),
+            Self::Striped {
+                ..
+            } if num_children < 2 => {
+                Err("striped layout requires at least 2 children".to_string())
+            }
+            Self::StripedMirror {
+                copies, ..
+            } if *copies < 2 => Err(format!(
+                "striped mirror layout requires at least 2 copies, got \
+                {copies}"
+            )),
+            Self::StripedMirror {
+                copies, ..
+            } if num_children % copies != 0 || num_children / copies < 2 => {
+                Err(format!(
+                    "{num_children} children can't be split into at least \
+                    2 mirror sets of {copies} copies"
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::fmt::Display for NexusLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mirror => write!(f, "mirror"),
+            Self::Striped {
+                stripe_size,
+            } => write!(f, "striped ({stripe_size} bytes)"),
+            Self::StripedMirror {
+                stripe_size,
+                copies,
+            } => write!(f, "striped mirror ({stripe_size} bytes, {copies}x)"),
+        }
+    }
+}
+
+/// Geometry of a striped nexus. All block numbers are relative to the start
+/// of the data partition.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct StripeGeometry {
+    /// Stripe size in blocks.
+    pub stripe_blocks: u64,
+    /// Number of stripe sets.
+    pub num_sets: u64,
+}
+
+impl StripeGeometry {
+    /// Creates a new stripe geometry.
+    pub fn new(stripe_blocks: u64, num_sets: usize) -> Self {
+        assert!(stripe_blocks > 0 && num_sets > 0);
+
+        Self {
+            stripe_blocks,
+            num_sets: num_sets as u64,
+        }
+    }
+
+    /// Maps a nexus block onto its stripe set and the block on the children
+    /// of the set.
+    pub fn map_block(&self, lbn: u64) -> (usize, u64) {
+        let stripe = lbn / self.stripe_blocks;
+        let set = stripe % self.num_sets;
+        let child_lbn = (stripe / self.num_sets) * self.stripe_blocks
+            + lbn % self.stripe_blocks;
+
+        (set as usize, child_lbn)
+    }
+
+    /// Maps a range of nexus blocks onto the range of blocks it covers on the
+    /// children of the given set. Returns None if the range doesn't touch the
+    /// set at all.
+    pub fn map_range(
+        &self,
+        set: usize,
+        lbn: u64,
+        lbn_cnt: u64,
+    ) -> Option<(u64, u64)> {
+        if lbn_cnt == 0 {
+            return None;
+        }
+
+        let (s, n, set) = (self.stripe_blocks, self.num_sets, set as u64);
+        let last_lbn = lbn + lbn_cnt - 1;
+        let (first, last) = (lbn / s, last_lbn / s);
+
+        // First and last stripes of the range which belong to the set.
+        let set_first = first + (set + n - first % n) % n;
+        if set_first > last {
+            return None;
+        }
+        let set_last = last - (last % n + n - set) % n;
+
+        let start =
+            (set_first / n) * s + if set_first == first { lbn % s } else { 0 };
+        let end = (set_last / n) * s
+            + if set_last == last {
+                last_lbn % s
+            } else {
+                s - 1
+            };
+
+        Some((start, end - start + 1))
+    }
+
+    /// Returns the smallest range of nexus blocks which covers the given
+    /// range of blocks on the children of the given set.
+    pub fn nexus_range(
+        &self,
+        set: usize,
+        child_lbn: u64,
+        lbn_cnt: u64,
+    ) -> (u64, u64) {
+        let to_nexus = |lbn: u64| {
+            let stripe =
+                (lbn / self.stripe_blocks) * self.num_sets + set as u64;
+            stripe * self.stripe_blocks + lbn % self.stripe_blocks
+        };
+
+        let start = to_nexus(child_lbn);
+        let end = to_nexus(child_lbn + lbn_cnt.max(1) - 1);
+
+        (start, end - start + 1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{NexusLayout, StripeGeometry};
+
+    #[test]
+    fn test_layout_validate() {
+        let striped = NexusLayout::Striped {
+            stripe_size: 65536,
+        };
+        assert!(striped.validate(2).is_ok());
+        assert!(striped.validate(1).is_err());
+        assert!(NexusLayout::Striped {
+            stripe_size: 65535
+        }
+        .validate(2)
+        .is_err());
+
+        let raid10 = NexusLayout::StripedMirror {
+            stripe_size: 65536,
+            copies: 2,
+        };
+        assert!(raid10.validate(4).is_ok());
+        assert!(raid10.validate(3).is_err());
+        assert!(raid10.validate(2).is_err());
+        assert_eq!(raid10.num_sets(6), 3);
+        assert_eq!(raid10.child_set(3), 1);
+    }
+
+    #[test]
+    fn test_stripe_mapping() {
+        let g = StripeGeometry::new(8, 3);
+
+        assert_eq!(g.map_block(0), (0, 0));
+        assert_eq!(g.map_block(9), (1, 1));
+        assert_eq!(g.map_block(24), (0, 8));
+        assert_eq!(g.map_block(47), (2, 15));
+
+        // Range within a single stripe.
+        assert_eq!(g.map_range(1, 9, 4), Some((1, 4)));
+        assert_eq!(g.map_range(0, 9, 4), None);
+
+        // Range spanning 5 stripes: 0 (partial), 1, 2, 3, 4 (partial).
+        assert_eq!(g.map_range(0, 4, 34), Some((4, 12)));
+        assert_eq!(g.map_range(1, 4, 34), Some((0, 14)));
+        assert_eq!(g.map_range(2, 4, 34), Some((0, 8)));
+
+        // Mapping back covers all the nexus blocks of the child range.
+        assert_eq!(g.nexus_range(1, 1, 4), (9, 4));
+        assert_eq!(g.nexus_range(0, 4, 12), (4, 28));
+    }
+}
@@ -1,5 +1,18 @@
-use super::{IoMode, Nexus, NexusChild};
-use crate::{persistent_store::PersistentStore, sleep::mayastor_sleep};
+use super::{
+    nexus_lookup,
+    nexus_spares::NexusSpares,
+    IoMode,
+    Nexus,
+    NexusChild,
+};
+use crate::{
+    constants::TRACING_TARGET,
+    core::Reactors,
+    persistent_store::PersistentStore,
+    rebuild::{HistoryRecord, RebuildCheckpoint},
+    sleep::mayastor_sleep,
+    store::store_defs::StoreError,
+};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
@@ -27,6 +40,84 @@ impl PersistentNexusInfo {
     fn inner_mut(&mut self) -> &mut NexusInfo {
         &mut self.inner
     }
+
+    /// Get the key to persist the NexusInfo structure with: the key supplied
+    /// by the control plane if any, or the nexus uuid otherwise.
+    fn key(&self, nexus_uuid: &str) -> String {
+        match &self.key {
+            Some(k) => k.clone(),
+            None => nexus_uuid.to_string(),
+        }
+    }
+
+    /// Get the key to persist the rebuild checkpoint of the given child with.
+    /// Checkpoints are kept next to the NexusInfo structure, under their own
+    /// keys, as the NexusInfo is rewritten on every nexus creation.
+    fn rebuild_key(&self, nexus_uuid: &str, child_uuid: &str) -> String {
+        format!("{}{child_uuid}", self.rebuild_prefix(nexus_uuid))
+    }
+
+    /// Get the prefix of the keys of the rebuild checkpoints of all children.
+    fn rebuild_prefix(&self, nexus_uuid: &str) -> String {
+        format!("{}/rebuild/", self.key(nexus_uuid))
+    }
+
+    /// Get the key to persist the rebuild history of the nexus with.
+    /// As with the checkpoints, the history outlives the nexus so that it can
+    /// be carried over to the next nexus of the same volume.
+    fn history_key(&self, nexus_uuid: &str) -> String {
+        format!("{}/rebuild_history", self.key(nexus_uuid))
+    }
+
+    /// Get the key to persist the reservations of the nexus with.
+    /// They outlive the nexus as well, so that the next nexus of the same
+    /// volume restores them, wherever it is created.
+    fn reservations_key(&self, nexus_uuid: &str) -> String {
+        format!("{}/reservations", self.key(nexus_uuid))
+    }
+
+    /// Get the key to persist the spares of the nexus with, which are left
+    /// for the next nexus of the same volume to use.
+    fn spares_key(&self, nexus_uuid: &str) -> String {
+        format!("{}/spares", self.key(nexus_uuid))
+    }
+}
+
+/// Keys of the state a nexus keeps in the persistent store for the next nexus
+/// of the volume.
+pub(crate) struct VolumeStateKeys {
+    /// Prefix of the keys of the rebuild checkpoints.
+    rebuild_prefix: String,
+    /// Key of the rebuild history.
+    history: String,
+    /// Key of the reservations.
+    reservations: String,
+    /// Key of the spares.
+    spares: String,
+}
+
+impl VolumeStateKeys {
+    /// Deletes the state from the store, once the volume is gone.
+    /// Failures are only logged, as the state is harmless without a nexus.
+    pub(crate) async fn delete(self) {
+        if let Err(e) =
+            PersistentStore::delete_prefix(&self.rebuild_prefix).await
+        {
+            warn!(
+                "failed to delete rebuild checkpoints '{}': {e}",
+                self.rebuild_prefix
+            );
+        }
+        for key in [self.history, self.reservations, self.spares] {
+            match PersistentStore::delete(&key).await {
+                Ok(_)
+                | Err(StoreError::MissingEntry {
+                    ..
+                }) => {}
+                Err(e) => warn!("failed to delete '{key}': {e}"),
+            }
+        }
+    }
 }
 
 /// Definition of the nexus information that gets saved in the persistent
@@ -37,6 +128,10 @@ pub struct NexusInfo {
     pub clean_shutdown: bool,
     /// Information about children.
     pub children: Vec<ChildInfo>,
+    /// Set while the write-back cache of the nexus may hold blocks which are
+    /// not on the children, which are then not up to date on their own.
+    #[serde(default)]
+    pub cache_dirty: bool,
 }
 
 /// Definition of the child information that gets saved in the persistent
@@ -49,6 +144,48 @@ pub struct ChildInfo {
     pub healthy: bool,
 }
 
+/// Reservation state of the namespace of a published nexus, that gets saved
+/// in the persistent store. It has the format of the persistence through power
+/// loss (PTPL) files of the NVMe-oF target, which are read and written with it
+/// as well.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct ReservationInfo {
+    /// PTPL activated.
+    pub ptpl: bool,
+    /// Reservation type, or 0 if not reserved.
+    pub rtype: u32,
+    /// Reservation key of the holder.
+    pub crkey: u64,
+    /// UUID of the nexus bdev.
+    pub bdev_uuid: String,
+    /// Host UUID of the holder.
+    pub holder_uuid: String,
+    /// Registered hosts.
+    #[serde(default)]
+    pub registrants: Vec<RegistrantInfo>,
+}
+
+/// Registered host, as saved in the persistent store.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct RegistrantInfo {
+    /// Reservation key of the host.
+    pub rkey: u64,
+    /// Host UUID.
+    pub host_uuid: String,
+}
+
+/// Reservations of a published nexus, on their way from or to the
+/// persistent store.
+#[derive(Debug, Default)]
+pub(crate) struct NexusReservations {
+    /// Loaded from the store, for the namespace to restore once added.
+    restored: Option<ReservationInfo>,
+    /// Latest change not saved yet.
+    pending: Option<ReservationInfo>,
+    /// Set while the changes are being saved.
+    saving: bool,
+}
+
 /// Defines the type of persist operations.
 pub(crate) enum PersistOp<'a> {
     /// Create a persistent entry.
@@ -70,10 +207,17 @@ pub(crate) enum PersistOp<'a> {
     },
     /// Save the clean shutdown variable.
     Shutdown,
+    /// Save whether the cache holds blocks which are not on the children.
+    CacheDirty { dirty: bool },
 }
 
 impl<'n> Nexus<'n> {
     /// Persists nexus's information to the store.
+    #[tracing::instrument(
+        target = TRACING_TARGET,
+        skip_all,
+        fields(nexus = %self.name)
+    )]
     pub(crate) async fn persist(&self, op: PersistOp<'_>) -> Result<(), Error> {
         if !PersistentStore::enabled() {
             return Ok(());
@@ -179,6 +323,11 @@ impl<'n> Nexus<'n> {
                 // This should only be called when destroying a nexus.
                 nexus_info.clean_shutdown = true;
             }
+            PersistOp::CacheDirty {
+                dirty,
+            } => {
+                nexus_info.cache_dirty = *dirty;
+            }
         }
 
         match self.save(&persistent_nexus_info).await {
@@ -188,8 +337,12 @@ impl<'n> Nexus<'n> {
             }
             Err(e) => {
                 // If the operation was an update for shutdown, no need to
-                // shutdown in the case of an error.
-                if matches!(op, PersistOp::Shutdown) {
+                // shutdown in the case of an error. The cache stays in
+                // write-through mode if it can't be marked dirty.
+                if matches!(
+                    op,
+                    PersistOp::Shutdown | PersistOp::CacheDirty { .. }
+                ) {
                     error!("{self:?}: failed to update persistent store: {e}");
                 } else {
                     error!(
@@ -207,12 +360,7 @@ impl<'n> Nexus<'n> {
     // consistency across restarts of Mayastor. Therefore, keep retrying
     // until successful.
     async fn save(&self, info: &PersistentNexusInfo) -> Result<(), Error> {
-        // If a key has been provided, use it to store the NexusInfo; use the
-        // nexus uuid as the key otherwise.
-        let key = match &info.key {
-            Some(k) => k.clone(),
-            None => self.uuid().to_string(),
-        };
+        let key = info.key(&self.uuid().to_string());
 
         let mut retry = PersistentStore::retries();
         loop {
@@ -241,4 +389,432 @@ impl<'n> Nexus<'n> {
             }
         }
     }
+
+    /// Saves the rebuild checkpoint of a child to the store.
+    /// Checkpoints are best effort: a failure to save one is only logged, as
+    /// the rebuild can always start over.
+    pub(crate) async fn persist_rebuild_checkpoint(
+        &self,
+        checkpoint: &RebuildCheckpoint,
+    ) {
+        if !PersistentStore::enabled() {
+            return;
+        }
+
+        let Some(child_uuid) = NexusChild::uuid(&checkpoint.dst_uri) else {
+            return;
+        };
+
+        let info = self.nexus_info.lock().await;
+
+        // The job may have finished, and its checkpoint be deleted, while
+        // this one was on its way.
+        match self.rebuild_job(&checkpoint.dst_uri) {
+            Ok(job) if !job.state().done() => {}
+            _ => return,
+        }
+
+        let key = info.rebuild_key(&self.uuid().to_string(), &child_uuid);
+        if let Err(e) = PersistentStore::put(&key, checkpoint).await {
+            warn!(
+                "{self:?}: failed to save rebuild checkpoint of '{dst}': {e}",
+                dst = checkpoint.dst_uri
+            );
+        }
+    }
+
+    /// Loads the rebuild checkpoint of a child from the store, if any.
+    pub(crate) async fn load_rebuild_checkpoint(
+        &self,
+        child_uri: &str,
+    ) -> Option<RebuildCheckpoint> {
+        if !PersistentStore::enabled() {
+            return None;
+        }
+
+        let child_uuid = NexusChild::uuid(child_uri)?;
+        let key = self
+            .nexus_info
+            .lock()
+            .await
+            .rebuild_key(&self.uuid().to_string(), &child_uuid);
+
+        match PersistentStore::get(&key).await {
+            Ok(value) => serde_json::from_value(value)
+                .map_err(|e| {
+                    warn!(
+                        "{self:?}: ignoring malformed rebuild checkpoint \
+                        of '{child_uri}': {e}"
+                    );
+                })
+                .ok(),
+            Err(StoreError::MissingEntry {
+                ..
+            }) => None,
+            Err(e) => {
+                warn!(
+                    "{self:?}: failed to load rebuild checkpoint \
+                    of '{child_uri}': {e}"
+                );
+                None
+            }
+        }
+    }
+
+    /// Loads the rebuild checkpoints of all the children, including the ones
+    /// which are not part of this nexus, from the store.
+    pub(crate) async fn load_rebuild_checkpoints(
+        &self,
+    ) -> Vec<RebuildCheckpoint> {
+        if !PersistentStore::enabled() {
+            return Vec::new();
+        }
+
+        let prefix = self
+            .nexus_info
+            .lock()
+            .await
+            .rebuild_prefix(&self.uuid().to_string());
+
+        match PersistentStore::get_prefix(&prefix).await {
+            Ok(values) => values
+                .into_iter()
+                .filter_map(|value| {
+                    serde_json::from_value(value)
+                        .map_err(|e| {
+                            warn!(
+                                "{self:?}: ignoring malformed rebuild \
+                                checkpoint: {e}"
+                            );
+                        })
+                        .ok()
+                })
+                .collect(),
+            Err(e) => {
+                warn!("{self:?}: failed to load rebuild checkpoints: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Returns the keys of the state kept in the store for the next nexus of
+    /// the volume, if the store is enabled.
+    pub(crate) async fn volume_state_keys(&self) -> Option<VolumeStateKeys> {
+        if !PersistentStore::enabled() {
+            return None;
+        }
+
+        let info = self.nexus_info.lock().await;
+        let uuid = self.uuid().to_string();
+        Some(VolumeStateKeys {
+            rebuild_prefix: info.rebuild_prefix(&uuid),
+            history: info.history_key(&uuid),
+            reservations: info.reservations_key(&uuid),
+            spares: info.spares_key(&uuid),
+        })
+    }
+
+    /// Deletes the rebuild checkpoint of a child from the store, if any.
+    pub(crate) async fn delete_rebuild_checkpoint(&self, child_uri: &str) {
+        if !PersistentStore::enabled() {
+            return;
+        }
+
+        let Some(child_uuid) = NexusChild::uuid(child_uri) else {
+            return;
+        };
+
+        let info = self.nexus_info.lock().await;
+        let key = info.rebuild_key(&self.uuid().to_string(), &child_uuid);
+
+        match PersistentStore::delete(&key).await {
+            Ok(_)
+            | Err(StoreError::MissingEntry {
+                ..
+            }) => {}
+            Err(e) => {
+                warn!(
+                    "{self:?}: failed to delete rebuild checkpoint \
+                    of '{child_uri}': {e}"
+                );
+            }
+        }
+    }
+
+    /// Saves the rebuild history of the nexus to the store.
+    /// As with the checkpoints, this is best effort and failures are only
+    /// logged.
+    pub(crate) async fn persist_rebuild_history(&self) {
+        if !PersistentStore::enabled() {
+            return;
+        }
+
+        let info = self.nexus_info.lock().await;
+        let key = info.history_key(&self.uuid().to_string());
+
+        // Snapshot the history while holding the info lock, so that
+        // concurrent saves are stored in order.
+        let history = self.rebuild_history();
+        if let Err(e) = PersistentStore::put(&key, &history).await {
+            warn!("{self:?}: failed to save rebuild history: {e}");
+        }
+    }
+
+    /// Saves the spares of the nexus, and their policy, to the store.
+    pub(crate) async fn persist_spares(&self) -> Result<(), Error> {
+        if !PersistentStore::enabled() {
+            return Ok(());
+        }
+
+        let info = self.nexus_info.lock().await;
+        let key = info.spares_key(&self.uuid().to_string());
+
+        // Snapshot the spares while holding the info lock, so that
+        // concurrent saves are stored in order.
+        let spares = self.spares.lock().clone();
+        PersistentStore::put(&key, &spares).await.map_err(|source| {
+            Error::SaveStateFailed {
+                source,
+                name: self.name.clone(),
+            }
+        })
+    }
+
+    /// Loads the information saved by the previous nexus of the volume, if
+    /// any, before this nexus overwrites it.
+    pub(crate) async fn load_nexus_info(&self) -> Option<NexusInfo> {
+        if !PersistentStore::enabled() {
+            return None;
+        }
+
+        let key = self.nexus_info.lock().await.key(&self.uuid().to_string());
+
+        match PersistentStore::get(&key).await {
+            Ok(value) => serde_json::from_value(value)
+                .map_err(|e| {
+                    warn!("{self:?}: ignoring malformed nexus info: {e}");
+                })
+                .ok(),
+            Err(StoreError::MissingEntry {
+                ..
+            }) => None,
+            Err(e) => {
+                warn!("{self:?}: failed to load nexus info: {e}");
+                None
+            }
+        }
+    }
+
+    /// Loads the spares left by a previous nexus of the volume, if any.
+    pub(crate) async fn load_spares(&self) {
+        if !PersistentStore::enabled() {
+            return;
+        }
+
+        let key = self
+            .nexus_info
+            .lock()
+            .await
+            .spares_key(&self.uuid().to_string());
+
+        let spares: NexusSpares = match PersistentStore::get(&key).await {
+            Ok(value) => match serde_json::from_value(value) {
+                Ok(spares) => spares,
+                Err(e) => {
+                    warn!("{self:?}: ignoring malformed spares: {e}");
+                    return;
+                }
+            },
+            Err(StoreError::MissingEntry {
+                ..
+            }) => return,
+            Err(e) => {
+                warn!("{self:?}: failed to load spares: {e}");
+                return;
+            }
+        };
+
+        // Spares which have been added to the nexus since are used up.
+        let spares = spares.without(|uri| self.is_child_or_device(uri));
+        debug!("{self:?}: loaded spares: {spares:?}");
+        *self.spares.lock() = spares;
+    }
+
+    /// Loads the rebuild history of the nexus from the store, merging it
+    /// with the records of this nexus instance, if any.
+    pub(crate) async fn load_rebuild_history(&self) {
+        if !PersistentStore::enabled() {
+            return;
+        }
+
+        let key = self
+            .nexus_info
+            .lock()
+            .await
+            .history_key(&self.uuid().to_string());
+
+        let mut loaded: Vec<HistoryRecord> = match PersistentStore::get(&key)
+            .await
+        {
+            Ok(value) => match serde_json::from_value(value) {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    warn!("{self:?}: ignoring malformed rebuild history: {e}");
+                    return;
+                }
+            },
+            Err(StoreError::MissingEntry {
+                ..
+            }) => return,
+            Err(e) => {
+                warn!("{self:?}: failed to load rebuild history: {e}");
+                return;
+            }
+        };
+
+        let mut history = self.rebuild_history_guard();
+        loaded.append(&mut history);
+        loaded.sort_by_key(|r| r.end_time);
+        Self::apply_history_retention(&mut loaded);
+        *history = loaded;
+
+        debug!(
+            "{self:?}: loaded rebuild history; total {num} records",
+            num = history.len()
+        );
+    }
+
+    /// Loads the reservations saved by a previous nexus of the volume, for
+    /// the namespace of the nexus to restore once published.
+    pub(crate) async fn prepare_reservations(&self) {
+        let restored = self
+            .load_reservations()
+            .await
+            .filter(|resv| !resv.registrants.is_empty());
+        if let Some(resv) = &restored {
+            info!(
+                "{self:?}: restoring reservations: {num} registrants, \
+                type {rtype}",
+                num = resv.registrants.len(),
+                rtype = resv.rtype
+            );
+        }
+        self.reservations.lock().restored = restored;
+    }
+
+    /// Takes the reservations loaded for the namespace of the nexus to
+    /// restore.
+    pub(crate) fn restored_reservations(&self) -> Option<ReservationInfo> {
+        self.reservations.lock().restored.take()
+    }
+
+    /// Saves the reservations of the nexus to the store once the previous
+    /// changes are saved. Called by the NVMe-oF target whenever the
+    /// reservations of the namespace of the nexus change.
+    pub(crate) fn save_reservations(&self, resv: ReservationInfo) {
+        let mut reservations = self.reservations.lock();
+        reservations.pending = Some(resv);
+        if reservations.saving {
+            return;
+        }
+        reservations.saving = true;
+
+        let name = self.name.clone();
+        Reactors::master().send_future(async move {
+            Nexus::save_pending_reservations(name).await;
+        });
+    }
+
+    /// Saves the changes of the reservations of the nexus in order, until
+    /// none is left or the nexus is gone. Only the latest of the changes
+    /// made while saving is saved.
+    async fn save_pending_reservations(name: String) {
+        let mut retry = PersistentStore::retries();
+        loop {
+            let Some(nexus) = nexus_lookup(&name) else {
+                break;
+            };
+
+            let resv = {
+                let mut reservations = nexus.reservations.lock();
+                match reservations.pending.take() {
+                    Some(resv) => resv,
+                    None => {
+                        reservations.saving = false;
+                        break;
+                    }
+                }
+            };
+
+            if nexus.persist_reservations(&resv).await {
+                retry = PersistentStore::retries();
+                continue;
+            }
+
+            retry -= 1;
+            if retry == 0 {
+                error!("{nexus:?}: gave up saving reservations");
+                retry = PersistentStore::retries();
+                continue;
+            }
+
+            // Retry, unless changed meanwhile, once the connection to the
+            // store has had some time to be re-established.
+            nexus.reservations.lock().pending.get_or_insert(resv);
+            mayastor_sleep(Duration::from_secs(1)).await.ok();
+        }
+    }
+
+    /// Saves the reservations of the nexus to the store.
+    /// Returns true if they were saved. Failures are only logged.
+    async fn persist_reservations(&self, resv: &ReservationInfo) -> bool {
+        if !PersistentStore::enabled() {
+            return false;
+        }
+
+        let info = self.nexus_info.lock().await;
+        let key = info.reservations_key(&self.uuid().to_string());
+
+        match PersistentStore::put(&key, resv).await {
+            Ok(_) => {
+                debug!(
+                    "{self:?}: saved reservations: {num} registrants",
+                    num = resv.registrants.len()
+                );
+                true
+            }
+            Err(e) => {
+                warn!("{self:?}: failed to save reservations: {e}");
+                false
+            }
+        }
+    }
+
+    /// Loads the reservations of the nexus from the store, if any.
+    async fn load_reservations(&self) -> Option<ReservationInfo> {
+        if !PersistentStore::enabled() {
+            return None;
+        }
+
+        let key = self
+            .nexus_info
+            .lock()
+            .await
+            .reservations_key(&self.uuid().to_string());
+
+        match PersistentStore::get(&key).await {
+            Ok(value) => serde_json::from_value(value)
+                .map_err(|e| {
+                    warn!("{self:?}: ignoring malformed reservations: {e}");
+                })
+                .ok(),
+            Err(StoreError::MissingEntry {
+                ..
+            }) => None,
+            Err(e) => {
+                warn!("{self:?}: failed to load reservations: {e}");
+                None
+            }
+        }
+    }
 }
@@ -181,6 +181,9 @@ impl<'n> Nexus<'n> {
                 Ok(uri)
             }
             Protocol::Nvmf => {
+                // The namespace restores the reservations once added.
+                self.prepare_reservations().await;
+
                 let props = NvmfShareProps::new()
                     .with_range(Some((
                         self.nvme_params.min_cntlid,
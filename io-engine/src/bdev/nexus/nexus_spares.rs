@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+
+use super::{Error, Nexus, NexusOperation, NexusStatus};
+use crate::{bdev::dev::device_name, core::VerboseError};
+
+/// Policy of the use of the spares of a nexus.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum SparePolicy {
+    /// Spares are registered but never used automatically.
+    #[default]
+    Manual,
+    /// A spare is added and rebuilt in place of each retired child, which is
+    /// kept in the nexus.
+    Replace,
+    /// A spare is added and rebuilt in place of each retired child, which is
+    /// then removed from the nexus.
+    ReplaceAndRemove,
+}
+
+/// Spare replica URIs of a nexus, used in the order they were registered.
+/// They are saved in the persistent store, for the next nexus of the volume
+/// to carry on with them.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub(super) struct NexusSpares {
+    uris: Vec<String>,
+    policy: SparePolicy,
+}
+
+impl NexusSpares {
+    /// Returns the spares without the URIs matching the predicate.
+    pub(super) fn without(mut self, f: impl Fn(&str) -> bool) -> Self {
+        self.uris.retain(|uri| !f(uri));
+        self
+    }
+}
+
+impl<'n> Nexus<'n> {
+    /// Returns the spare URIs of the nexus which are yet to be used.
+    pub fn spares(&self) -> Vec<String> {
+        self.spares.lock().uris.clone()
+    }
+
+    /// Returns the policy of the use of the spares.
+    pub fn spare_policy(&self) -> SparePolicy {
+        self.spares.lock().policy
+    }
+
+    /// Replaces the spares of the nexus and their policy, and saves them.
+    /// Spares must be unique and must not be children of the nexus already.
+    pub async fn set_spares(
+        &self,
+        uris: &[String],
+        policy: SparePolicy,
+    ) -> Result<(), Error> {
+        for (i, uri) in uris.iter().enumerate() {
+            if uris[.. i].contains(uri) {
+                return Err(Error::InvalidArguments {
+                    name: self.name.clone(),
+                    args: format!("duplicate spare '{uri}'"),
+                });
+            }
+
+            if self.is_child_or_device(uri) {
+                return Err(Error::ChildAlreadyExists {
+                    child: uri.clone(),
+                    name: self.name.clone(),
+                });
+            }
+        }
+
+        info!("{self:?}: spares set to {uris:?}, policy: {policy:?}");
+
+        let prev = std::mem::replace(
+            &mut *self.spares.lock(),
+            NexusSpares {
+                uris: uris.to_vec(),
+                policy,
+            },
+        );
+        if let Err(e) = self.persist_spares().await {
+            error!("{self:?}: failed to save the spares: {e}");
+            *self.spares.lock() = prev;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Checks if the URI is the one of a child, or of the device of a child.
+    pub(super) fn is_child_or_device(&self, uri: &str) -> bool {
+        self.contains_child_uri(uri)
+            || device_name(uri)
+                .map(|name| self.contains_child_name(&name))
+                .unwrap_or(false)
+    }
+
+    /// Takes the next spare, if the spares are to be used automatically.
+    fn take_spare(&self) -> Option<String> {
+        let mut spares = self.spares.lock();
+        if spares.policy == SparePolicy::Manual || spares.uris.is_empty() {
+            return None;
+        }
+        Some(spares.uris.remove(0))
+    }
+
+    /// Puts a spare back, to be used next.
+    fn return_spare(&self, uri: String) {
+        self.spares.lock().uris.insert(0, uri);
+    }
+
+    /// Adds a spare in place of the given retired child, to its stripe set,
+    /// and starts its rebuild, as per the spare policy.
+    /// Spares which fail to be added are dropped, and the next one is tried,
+    /// unless the nexus can't take the spare in place of the child at all.
+    pub(super) async fn replace_with_spare(
+        mut self: Pin<&mut Self>,
+        retired_uri: &str,
+    ) {
+        let policy = self.spare_policy();
+        if policy == SparePolicy::Manual {
+            return;
+        }
+
+        if matches!(self.status(), NexusStatus::Faulted) {
+            warn!(
+                "{self:?}: not replacing retired child '{retired_uri}' \
+                with a spare: nexus is faulted"
+            );
+            return;
+        }
+
+        // Keep the spares if the nexus cannot take a new child at all, e.g.
+        // when it is shutting down.
+        if let Err(e) = self.check_nexus_operation(NexusOperation::ReplicaAdd) {
+            warn!(
+                "{self:?}: not replacing retired child '{retired_uri}' \
+                with a spare: {e}"
+            );
+            return;
+        }
+
+        let spare = loop {
+            let Some(spare) = self.take_spare() else {
+                warn!(
+                    "{self:?}: no spare left to replace retired child \
+                    '{retired_uri}'"
+                );
+                if let Err(e) = self.persist_spares().await {
+                    warn!("{self:?}: failed to save the spares: {e}");
+                }
+                return;
+            };
+
+            info!(
+                "{self:?}: replacing retired child '{retired_uri}' \
+                with spare '{spare}'"
+            );
+
+            match self
+                .as_mut()
+                .replace_child(retired_uri, &spare, false)
+                .await
+            {
+                Ok(_) => break spare,
+                Err(
+                    e @ (Error::ChildNotFound {
+                        ..
+                    }
+                    | Error::OperationNotAllowed {
+                        ..
+                    }),
+                ) => {
+                    warn!(
+                        "{self:?}: not replacing retired child \
+                        '{retired_uri}' with spare '{spare}': {e}",
+                        e = e.verbose()
+                    );
+                    self.return_spare(spare);
+                    return;
+                }
+                Err(e) => {
+                    error!(
+                        "{self:?}: failed to add spare '{spare}', \
+                        dropping it: {e}",
+                        e = e.verbose()
+                    );
+                }
+            }
+        };
+
+        // The spare is used up, whether the nexus keeps it or not.
+        if let Err(e) = self.persist_spares().await {
+            warn!("{self:?}: failed to save the spares: {e}");
+        }
+
+        if policy == SparePolicy::ReplaceAndRemove {
+            if let Err(e) = self.as_mut().remove_child(retired_uri).await {
+                error!(
+                    "{self:?}: spare '{spare}' added, but failed to remove \
+                    retired child '{retired_uri}': {e}",
+                    e = e.verbose()
+                );
+            }
+        }
+    }
+}
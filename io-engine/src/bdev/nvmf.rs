@@ -2,6 +2,7 @@ use std::{
     collections::HashMap,
     convert::TryFrom,
     ffi::{CStr, CString},
+    net::Ipv6Addr,
     os::raw::{c_char, c_int, c_ulong, c_void},
 };
 
@@ -20,6 +21,7 @@ use spdk_rs::{
         SPDK_NVME_IO_FLAGS_PRCHK_REFTAG,
         SPDK_NVME_TRANSPORT_TCP,
         SPDK_NVMF_ADRFAM_IPV4,
+        SPDK_NVMF_ADRFAM_IPV6,
     },
 };
 
@@ -56,10 +58,11 @@ impl TryFrom<&Url> for Nvmf {
     type Error = BdevError;
 
     fn try_from(url: &Url) -> Result<Self, Self::Error> {
-        let host = url.host_str().ok_or_else(|| BdevError::InvalidUri {
-            uri: url.to_string(),
-            message: String::from("missing host"),
-        })?;
+        let host =
+            uri::host_addr(url).ok_or_else(|| BdevError::InvalidUri {
+                uri: url.to_string(),
+                message: String::from("missing host"),
+            })?;
 
         let segments = uri::segments(url);
 
@@ -293,7 +296,11 @@ impl NvmeCreateContext {
         copy_str_with_null(&nvmf.subnqn, &mut trid.subnqn);
 
         trid.trtype = SPDK_NVME_TRANSPORT_TCP;
-        trid.adrfam = SPDK_NVMF_ADRFAM_IPV4;
+        trid.adrfam = if nvmf.host.parse::<Ipv6Addr>().is_ok() {
+            SPDK_NVMF_ADRFAM_IPV6
+        } else {
+            SPDK_NVMF_ADRFAM_IPV4
+        };
 
         NvmeCreateContext {
             trid,
@@ -7,6 +7,7 @@ use spdk_rs::{
         nvme_qpair_abort_all_queued_reqs,
         nvme_transport_qpair_abort_reqs,
         spdk_io_channel,
+        spdk_nvme_ns,
         spdk_nvme_poll_group_process_completions,
         spdk_nvme_qpair,
         spdk_nvme_qpair_set_abort_dnr,
@@ -23,9 +24,11 @@ use crate::{
 
 use super::{
     nvme_bdev_running_config,
+    path::IoPath,
     NvmeControllerState,
     PollGroup,
     QPair,
+    QPairState,
     SpdkNvmeController,
     NVME_CONTROLLERS,
 };
@@ -66,6 +69,8 @@ impl std::fmt::Debug for NvmeIoChannelInner<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("NvmeIoChannelInner")
             .field("qpair", &self.qpair)
+            .field("path", &self.path.index)
+            .field("standby paths", &self.standby.len())
             .field("pending IO", &self.num_pending_ios)
             .finish()
     }
@@ -73,6 +78,13 @@ impl std::fmt::Debug for NvmeIoChannelInner<'_> {
 
 pub struct NvmeIoChannelInner<'a> {
     qpair: Option<QPair>,
+    /// Path the active qpair is connected through.
+    path: IoPath,
+    /// Pre-connected qpairs on the standby paths of the controller, I/O is
+    /// switched over to one of them when the active path goes down.
+    standby: Vec<(IoPath, QPair)>,
+    /// Qpairs I/O has been switched away from, to be released by the poller.
+    retired: Vec<QPair>,
     poll_group: PollGroup,
     poller: Poller<'a>,
     io_stats_controller: IoStatsController,
@@ -114,6 +126,100 @@ impl NvmeIoChannelInner<'_> {
         &mut self.qpair
     }
 
+    /// Returns SPDK pointer for the namespace as seen via the active path.
+    #[inline(always)]
+    pub(crate) fn ns_ptr(&self) -> *mut spdk_nvme_ns {
+        self.path.ns
+    }
+
+    /// Returns the SPDK controller of the active path.
+    #[inline(always)]
+    pub(crate) fn ctrlr(&self) -> SpdkNvmeController {
+        self.path.ctrlr
+    }
+
+    /// Returns the index of the active path.
+    pub(crate) fn path_index(&self) -> usize {
+        self.path.index
+    }
+
+    /// Connects the qpairs of the standby paths synchronously. The ones that
+    /// fail to connect are dropped.
+    pub(crate) fn connect_standby(&mut self) {
+        self.standby.retain(|(path, qpair)| {
+            let rc = qpair.connect();
+            if rc != 0 {
+                warn!(
+                    path = path.index,
+                    rc, "failed to connect standby I/O qpair"
+                );
+            }
+            rc == 0
+        });
+    }
+
+    /// Connects the qpairs of the standby paths asynchronously. The ones that
+    /// fail to connect are dropped.
+    #[cfg(feature = "spdk-async-qpair-connect")]
+    pub(crate) async fn connect_standby_async(&mut self) {
+        let mut i = 0;
+        while i < self.standby.len() {
+            match self.standby[i].1.connect_async().await {
+                Ok(()) => i += 1,
+                Err(e) => {
+                    let (path, _) = self.standby.remove(i);
+                    warn!(
+                        path = path.index,
+                        ?e,
+                        "failed to connect standby I/O qpair"
+                    );
+                }
+            }
+        }
+    }
+
+    /// Switches I/O over to the pre-connected qpair of a standby path: the
+    /// given one, or the first connected one if none is given. The
+    /// previously active qpair is retired: its outstanding I/O is aborted by
+    /// the poller, to be resubmitted via the new one. Returns false if there
+    /// is no such standby path.
+    pub(crate) fn switch_path(&mut self, index: Option<usize>) -> bool {
+        if self.qpair.is_some() && index == Some(self.path.index) {
+            return true;
+        }
+
+        let Some(pos) = self.standby.iter().position(|(path, qpair)| {
+            index.map_or(true, |i| path.index == i)
+                && qpair.state() == QPairState::Connected
+        }) else {
+            return false;
+        };
+
+        let (path, qpair) = self.standby.remove(pos);
+        warn!(
+            from = self.path.index,
+            to = path.index,
+            "switching I/O over to standby path"
+        );
+        self.path = path;
+
+        // The switch may happen from within a completion callback of the
+        // previous qpair, so it can't be released right away.
+        if let Some(prev) = self.qpair.replace(qpair) {
+            self.retired.push(prev);
+        }
+        true
+    }
+
+    /// Releases the retired qpairs. Their outstanding I/O is aborted first,
+    /// and resubmitted via the active qpair from its completion callback.
+    fn release_retired(&mut self) {
+        for qpair in std::mem::take(&mut self.retired) {
+            qpair.abort_reqs_for_retry();
+            drop(qpair);
+        }
+    }
+
     fn remove_qpair(&mut self) -> Option<QPair> {
         if let Some(q) = &self.qpair {
             trace!(qpair = ?q.as_ptr(), "removing qpair");
@@ -123,6 +229,10 @@ impl NvmeIoChannelInner<'_> {
 
     /// Reset channel, making it unusable till reinitialize() is called.
     pub fn reset(&mut self) -> i32 {
+        // Standby qpairs are recreated along with the active one.
+        self.standby.clear();
+        self.release_retired();
+
         // Remove qpair and trigger its deallocation via drop().
         match self.remove_qpair() {
             Some(qpair) => {
@@ -179,10 +289,12 @@ impl NvmeIoChannelInner<'_> {
     }
 
     /// Reinitialize channel after reset unless the channel is shutdown.
-    pub fn reinitialize(
+    /// The first of the given paths becomes the active one, the rest are
+    /// standby paths.
+    pub(crate) fn reinitialize(
         &mut self,
         ctrlr_name: &str,
-        ctrlr_handle: SpdkNvmeController,
+        paths: &[IoPath],
     ) -> i32 {
         if self.is_shutdown {
             error!(
@@ -201,8 +313,13 @@ impl NvmeIoChannelInner<'_> {
             );
         }
 
+        let Some((&path, standby)) = paths.split_first() else {
+            error!(?ctrlr_name, "no path to reinitialize I/O channel with");
+            return -libc::ENODEV;
+        };
+
         // Create qpair for target controller.
-        let qpair = match QPair::create(ctrlr_handle, ctrlr_name) {
+        let qpair = match QPair::create(path.ctrlr, ctrlr_name) {
             Ok(qpair) => qpair,
             Err(e) => {
                 error!(?ctrlr_name, ?e, "Failed to allocate qpair,");
@@ -227,6 +344,10 @@ impl NvmeIoChannelInner<'_> {
 
         trace!("{} I/O channel successfully reinitialized", ctrlr_name);
         self.qpair = Some(qpair);
+        self.path = path;
+        self.standby =
+            create_standby_qpairs(&mut self.poll_group, ctrlr_name, standby);
+        self.connect_standby();
         0
     }
 
@@ -298,12 +419,67 @@ impl IoStatsController {
 
 pub struct NvmeControllerIoChannel(NonNull<spdk_io_channel>);
 
+/// Creates qpairs on the given standby paths and adds them to the poll group.
+/// The qpairs are connected along with the active one.
+fn create_standby_qpairs(
+    poll_group: &mut PollGroup,
+    ctrlr_name: &str,
+    paths: &[IoPath],
+) -> Vec<(IoPath, QPair)> {
+    paths
+        .iter()
+        .filter_map(|path| {
+            let qpair = match QPair::create(path.ctrlr, ctrlr_name) {
+                Ok(qpair) => qpair,
+                Err(e) => {
+                    warn!(
+                        ?ctrlr_name,
+                        path = path.index,
+                        ?e,
+                        "failed to allocate standby qpair"
+                    );
+                    return None;
+                }
+            };
+
+            if poll_group.add_qpair(&qpair) != 0 {
+                warn!(
+                    ?ctrlr_name,
+                    path = path.index,
+                    "failed to add standby qpair to poll group"
+                );
+                return None;
+            }
+            Some((*path, qpair))
+        })
+        .collect()
+}
+
 extern "C" fn disconnected_qpair_cb(
-    _qpair: *mut spdk_nvme_qpair,
+    qpair: *mut spdk_nvme_qpair,
     ctx: *mut c_void,
 ) {
     let inner = NvmeIoChannel::from_raw(ctx).inner_mut();
 
+    // Retired qpairs are released by the poller.
+    if inner.retired.iter().any(|q| q.as_ptr() == qpair) {
+        return;
+    }
+
+    // I/O can no longer be switched over to a standby path that went down.
+    if let Some(pos) =
+        inner.standby.iter().position(|(_, q)| q.as_ptr() == qpair)
+    {
+        let (path, _) = inner.standby.remove(pos);
+        warn!(path = path.index, "standby I/O qpair disconnected");
+        return;
+    }
+
+    // Switch I/O over to a standby path instead of failing it.
+    if inner.switch_path(None) {
+        return;
+    }
+
     if let Some(qpair) = inner.qpair() {
         unsafe {
             spdk_nvme_qpair_set_abort_dnr(qpair.as_ptr(), true);
@@ -328,6 +504,10 @@ extern "C" fn nvme_poll(ctx: *mut c_void) -> i32 {
         )
     };
 
+    if !inner.retired.is_empty() {
+        inner.release_retired();
+    }
+
     if num_completions > 0 {
         1
     } else {
@@ -349,7 +529,7 @@ impl NvmeControllerIoChannel {
             Some(c) => c,
         };
 
-        let (cname, controller, block_size) = {
+        let (cname, paths, block_size) = {
             let controller = carc.lock();
             // Make sure controller is available.
             if controller.get_state() != NvmeControllerState::Running {
@@ -370,11 +550,7 @@ impl NvmeControllerIoChannel {
                 .namespace()
                 .expect("No namespaces in active controller")
                 .block_len();
-            (
-                controller.get_name(),
-                controller.controller().unwrap(),
-                block_size,
-            )
+            (controller.get_name(), controller.io_paths(), block_size)
         };
 
         let nvme_channel = NvmeIoChannel::from_raw(ctx);
@@ -392,7 +568,8 @@ impl NvmeControllerIoChannel {
         };
 
         // Allocate qpair.
-        let qpair = match QPair::create(controller, &cname) {
+        let path = paths[0];
+        let qpair = match QPair::create(path.ctrlr, &cname) {
             Ok(qpair) => qpair,
             Err(e) => {
                 error!(?cname, ?e, "Failed to allocate qpair");
@@ -417,6 +594,10 @@ impl NvmeControllerIoChannel {
             return 1;
         }
 
+        // Allocate qpairs on the standby paths.
+        let standby =
+            create_standby_qpairs(&mut poll_group, &cname, &paths[1 ..]);
+
         // Create poller.
         let poller = PollerBuilder::new()
             .with_interval(Duration::from_micros(
@@ -427,6 +608,9 @@ impl NvmeControllerIoChannel {
 
         let inner = Box::new(NvmeIoChannelInner {
             qpair: Some(qpair),
+            path,
+            standby,
+            retired: Vec::new(),
             poll_group,
             poller,
             io_stats_controller: IoStatsController::new(block_size),
@@ -454,15 +638,23 @@ impl NvmeControllerIoChannel {
             let mut inner = unsafe { Box::from_raw(ch.inner) };
 
             let qpair = inner.remove_qpair();
+            let standby = std::mem::take(&mut inner.standby);
+            let retired = std::mem::take(&mut inner.retired);
 
-            // Stop the poller and do extra handling for I/O qpair, as it needs
-            // to be detached from the poller prior poller
+            // Stop the poller and do extra handling for I/O qpairs, as they
+            // need to be detached from the poller prior poller
             // destruction.
             inner.poller.stop();
 
             if let Some(qpair) = qpair {
                 inner.poll_group.remove_qpair(&qpair);
             }
+            for (_, qpair) in standby {
+                inner.poll_group.remove_qpair(&qpair);
+            }
+            for qpair in retired {
+                inner.poll_group.remove_qpair(&qpair);
+            }
         }
 
         trace!(
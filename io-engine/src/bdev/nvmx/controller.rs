@@ -22,10 +22,15 @@ use spdk_rs::{
         spdk_nvme_async_event_completion,
         spdk_nvme_cpl,
         spdk_nvme_ctrlr,
+        spdk_nvme_ctrlr_disconnect,
         spdk_nvme_ctrlr_fail,
         spdk_nvme_ctrlr_get_ns,
         spdk_nvme_ctrlr_is_active_ns,
+        spdk_nvme_ctrlr_reconnect_async,
+        spdk_nvme_ctrlr_reconnect_poll_async,
         spdk_nvme_ctrlr_register_aer_callback,
+        spdk_nvme_ctrlr_register_timeout_callback,
+        spdk_nvme_ctrlr_set_trid,
         spdk_nvme_detach,
     },
     Poller,
@@ -42,6 +47,7 @@ use crate::{
             ControllerStateMachine,
         },
         nvme_bdev_running_config,
+        path::{IoPath, StandbyPath},
         uri::NvmeControllerContext,
         utils::{
             nvme_cpl_succeeded,
@@ -77,6 +83,21 @@ struct ResetCtx {
     spdk_handle: SpdkNvmeController,
     io_device: Arc<IoDevice>,
     shutdown_in_progress: bool,
+    /// Reconnect via the next transport path instead of the current one.
+    failover: bool,
+    /// Number of alternate paths tried so far during failover.
+    paths_tried: usize,
+    /// Paths to reinitialize I/O channels with, the active one first.
+    io_paths: Vec<IoPath>,
+}
+
+/// Context for switching I/O channels over to a standby path.
+struct SwitchPathCtx {
+    name: String,
+    cb: OpCompletionCallback,
+    cb_arg: OpCompletionCallbackArg,
+    /// Index of the standby path I/O is switched over to.
+    index: usize,
 }
 
 struct ShutdownCtx {
@@ -154,6 +175,15 @@ pub struct NvmeController<'a> {
     /// so it needs to be a raw pointer. Mutable members are made atomic to
     /// eliminate lock contention between API path and callback path.
     pub(crate) timeout_config: NonNull<TimeoutConfig>,
+    /// Transport IDs of all paths to the controller, the primary one first.
+    paths: Vec<transport::NvmeTransportId>,
+    /// Index of the path the controller is currently connected through.
+    active_path: usize,
+    /// Poller driving controller reconnection during failover.
+    failover_poller: Option<Poller<'a>>,
+    /// Controllers attached via the alternate paths, I/O channels keep
+    /// pre-connected qpairs on them.
+    standby: Vec<StandbyPath<'a>>,
 }
 
 impl<'a> fmt::Debug for NvmeController<'a> {
@@ -161,6 +191,9 @@ impl<'a> fmt::Debug for NvmeController<'a> {
         f.debug_struct("NvmeController")
             .field("name", &self.name)
             .field("prchk_flags", &self.prchk_flags)
+            .field("paths", &self.paths.len())
+            .field("active_path", &self.active_path)
+            .field("standby", &self.standby)
             .field("state_machine", &self.state_machine)
             .finish()
     }
@@ -183,6 +216,10 @@ impl<'a> NvmeController<'a> {
                 TimeoutConfig::new(name),
             )))
             .expect("failed to box timeout context"),
+            paths: Vec::new(),
+            active_path: 0,
+            failover_poller: None,
+            standby: Vec::new(),
         };
 
         debug!("{}: new NVMe controller created", l.name);
@@ -208,6 +245,11 @@ impl<'a> NvmeController<'a> {
         self.id
     }
 
+    /// returns the number of transport paths known for the controller
+    pub fn num_paths(&self) -> usize {
+        self.paths.len()
+    }
+
     fn set_id(&mut self, id: u64) -> u64 {
         assert_ne!(id, 0, "Controller ID can't be zero");
         self.id = id;
@@ -234,6 +276,30 @@ impl<'a> NvmeController<'a> {
         self.inner.as_ref().map(|c| c.ctrlr)
     }
 
+    /// Returns the paths I/O qpairs are connected through: the active one
+    /// first, followed by the usable standby paths.
+    pub(crate) fn io_paths(&self) -> Vec<IoPath> {
+        let Some(ctrlr) = self.controller() else {
+            return Vec::new();
+        };
+
+        std::iter::once(IoPath::new(self.active_path, ctrlr))
+            .chain(
+                self.standby
+                    .iter()
+                    .filter(|p| p.index() != self.active_path && p.is_usable())
+                    .map(|p| p.io_path()),
+            )
+            .collect()
+    }
+
+    /// Adds a controller attached via an alternate path. Only I/O channels
+    /// created afterwards keep pre-connected qpairs on it.
+    pub(crate) fn add_standby_path(&mut self, path: StandbyPath<'a>) {
+        debug!("{}: adding standby path {}", self.name, path.index());
+        self.standby.push(path);
+    }
+
     /// we should try to avoid this
     pub fn ctrlr_as_ptr(&self) -> *mut spdk_nvme_ctrlr {
         self.inner.as_ref().map_or(std::ptr::null_mut(), |c| {
@@ -247,11 +313,13 @@ impl<'a> NvmeController<'a> {
     fn register_callbacks(&mut self) {
         let ctrlr = self.ctrlr_as_ptr();
 
+        // The controller is looked up by its ID, which remains the address
+        // of the controller it was attached with even after failover.
         unsafe {
             spdk_nvme_ctrlr_register_aer_callback(
                 ctrlr,
                 Some(aer_cb),
-                ctrlr as *mut c_void,
+                self.id as *mut c_void,
             );
         };
     }
@@ -341,9 +409,9 @@ impl<'a> NvmeController<'a> {
             self.name, failover
         );
 
-        if failover {
+        if failover && self.paths.len() < 2 {
             warn!(
-                "{} failover is not supported for controller reset",
+                "{} no alternate paths configured, failover not possible",
                 self.name
             );
         }
@@ -358,6 +426,9 @@ impl<'a> NvmeController<'a> {
                 .expect("controller is may not be NULL"),
             io_device,
             shutdown_in_progress: false,
+            failover: failover && self.paths.len() > 1,
+            paths_tried: 0,
+            io_paths: Vec::new(),
         };
 
         debug!("{}: starting reset", self.name);
@@ -372,6 +443,161 @@ impl<'a> NvmeController<'a> {
         Ok(())
     }
 
+    /// Fail the controller over to the next usable transport path.
+    /// If a standby path is usable, all I/O channels switch I/O over to
+    /// their pre-connected qpairs on it without failing any I/O, and its
+    /// controller becomes the active one.
+    /// Otherwise, all I/O channels are torn down, the controller is
+    /// reconnected via the first alternate path through which its namespace
+    /// is ANA accessible, and the I/O channels are reinitialized afterwards.
+    pub fn failover(
+        &mut self,
+        cb: OpCompletionCallback,
+        cb_arg: OpCompletionCallbackArg,
+    ) -> Result<(), CoreError> {
+        if self.paths.len() < 2 {
+            return Err(CoreError::ResetDispatch {
+                source: Errno::ENOTSUP,
+            });
+        }
+
+        let standby = self
+            .standby
+            .iter()
+            .find(|p| p.index() != self.active_path && p.is_usable())
+            .map(|p| p.index());
+
+        let index = match (standby, self.state_machine.current_state()) {
+            (Some(index), Running) => index,
+            _ => return self.reset(cb, cb_arg, true),
+        };
+
+        self.state_machine
+            .set_flag_exclusively(ControllerFlag::ResetActive)
+            .map_err(|_| {
+                error!("{} reset already in progress", self.name);
+                CoreError::ResetDispatch {
+                    source: Errno::EBUSY,
+                }
+            })?;
+
+        warn!(
+            "{}: switching I/O over to standby path {}",
+            self.name, index
+        );
+
+        let ctx = SwitchPathCtx {
+            name: self.name.clone(),
+            cb,
+            cb_arg,
+            index,
+        };
+
+        self.inner.as_ref().unwrap().io_device.traverse_io_channels(
+            NvmeController::_switch_path_channels,
+            NvmeController::_switch_path_channels_done,
+            NvmeIoChannel::inner_from_channel,
+            ctx,
+        );
+        Ok(())
+    }
+
+    fn _switch_path_channels(
+        channel: &mut NvmeIoChannelInner,
+        ctx: &mut SwitchPathCtx,
+    ) -> i32 {
+        if channel.is_shutdown() || channel.switch_path(Some(ctx.index)) {
+            return 0;
+        }
+
+        error!(
+            "{}: I/O channel has no connected qpair on path {}",
+            ctx.name, ctx.index
+        );
+        -libc::ENXIO
+    }
+
+    fn _switch_path_channels_done(status: i32, ctx: SwitchPathCtx) {
+        let Some(carc) = NVME_CONTROLLERS.lookup_by_name(&ctx.name) else {
+            (ctx.cb)(false, ctx.cb_arg);
+            return;
+        };
+        let mut controller = carc.lock();
+
+        controller
+            .state_machine
+            .clear_flag_exclusively(ControllerFlag::ResetActive)
+            .expect("Reset flag improperly cleared during failover");
+
+        if status != 0 {
+            // Some I/O channels couldn't switch over, so fall back to
+            // reconnecting the controller, which reinitializes all of them.
+            error!(
+                "{}: failed to switch I/O over to path {}, resetting",
+                ctx.name, ctx.index
+            );
+            let rc = controller.reset(ctx.cb, ctx.cb_arg, true);
+            drop(controller);
+
+            if let Err(e) = rc {
+                error!("{}: failed to initiate failover: {}", ctx.name, e);
+                (ctx.cb)(false, ctx.cb_arg);
+            }
+            return;
+        }
+
+        controller.promote_standby(ctx.index);
+        drop(controller);
+
+        (ctx.cb)(true, ctx.cb_arg);
+    }
+
+    /// Makes the controller of the standby path with the given index the
+    /// active one. The controller of the previously active path becomes a
+    /// standby one in turn.
+    fn promote_standby(&mut self, index: usize) {
+        let active_path = self.active_path;
+        let Some(standby) =
+            self.standby.iter_mut().find(|p| p.index() == index)
+        else {
+            return;
+        };
+
+        let inner = self.inner.as_mut().unwrap();
+        let prev = inner.ctrlr;
+        inner.ctrlr = standby.replace(active_path, prev);
+        self.active_path = index;
+
+        // Callbacks are only handled for the active controller.
+        unsafe {
+            spdk_nvme_ctrlr_register_aer_callback(
+                prev.as_ptr(),
+                None,
+                std::ptr::null_mut(),
+            );
+            spdk_nvme_ctrlr_register_timeout_callback(
+                prev.as_ptr(),
+                0,
+                0,
+                None,
+                std::ptr::null_mut(),
+            );
+            self.timeout_config.as_mut().set_controller(inner.ctrlr);
+        }
+
+        self.configure_timeout();
+        self.register_callbacks();
+        self.populate_namespaces();
+
+        let trid = &self.paths[index];
+        info!(
+            "{}: failed over to standby path {}:{}",
+            self.name,
+            trid.traddr(),
+            trid.svcid()
+        );
+    }
+
     fn _shutdown_channels(
         channel: &mut NvmeIoChannelInner,
         ctx: &mut ShutdownCtx,
@@ -632,6 +858,9 @@ impl<'a> NvmeController<'a> {
             spdk_handle: self.controller().expect("controller may not be NULL"),
             io_device,
             shutdown_in_progress: false,
+            failover: false,
+            paths_tried: 0,
+            io_paths: Vec::new(),
         };
 
         let inner = self.inner.as_mut().unwrap();
@@ -645,7 +874,7 @@ impl<'a> NvmeController<'a> {
         Ok(())
     }
 
-    fn _reset_destroy_channels_done(status: i32, reset_ctx: ResetCtx) {
+    fn _reset_destroy_channels_done(status: i32, mut reset_ctx: ResetCtx) {
         if status != 0 {
             error!(
                 "{}: controller reset failed with status = {}",
@@ -666,11 +895,20 @@ impl<'a> NvmeController<'a> {
             return;
         }
 
+        if reset_ctx.failover {
+            NvmeController::_failover_next_path(reset_ctx);
+            return;
+        }
+
         debug!(
             "{} controller successfully reset, reinitializing I/O channels",
             reset_ctx.name
         );
 
+        if let Some(c) = NVME_CONTROLLERS.lookup_by_name(&reset_ctx.name) {
+            reset_ctx.io_paths = c.lock().io_paths();
+        }
+
         // Once controller is successfully reset, schedule another
         //I/O channel traversal to restore all I/O channels.
         let io_device = reset_ctx.io_device.clone();
@@ -682,6 +920,154 @@ impl<'a> NvmeController<'a> {
         );
     }
 
+    /// Switch the controller over to the next untried transport path and
+    /// start reconnecting it.
+    fn _failover_next_path(mut reset_ctx: ResetCtx) {
+        let carc = match NVME_CONTROLLERS.lookup_by_name(&reset_ctx.name) {
+            Some(c) => c,
+            None => {
+                NvmeController::_complete_reset(
+                    reset_ctx,
+                    -(Errno::ENODEV as i32),
+                );
+                return;
+            }
+        };
+        let mut controller = carc.lock();
+        let num_paths = controller.paths.len();
+
+        if reset_ctx.paths_tried + 1 >= num_paths {
+            error!("{}: no usable paths left, failover failed", reset_ctx.name);
+            drop(controller);
+            NvmeController::_complete_reset(reset_ctx, -(Errno::ENXIO as i32));
+            return;
+        }
+        reset_ctx.paths_tried += 1;
+
+        let next = (controller.active_path + 1) % num_paths;
+        let trid = &controller.paths[next];
+        let path = format!("{}:{}", trid.traddr(), trid.svcid());
+        let ctrlr = reset_ctx.spdk_handle.as_ptr();
+
+        warn!("{}: failing over to path {}", reset_ctx.name, path);
+
+        // Transport ID can only be changed for a failed controller.
+        let rc = unsafe {
+            spdk_nvme_ctrlr_fail(ctrlr);
+            spdk_nvme_ctrlr_set_trid(ctrlr, trid.as_ptr() as *mut _)
+        };
+        controller.active_path = next;
+
+        if rc != 0 {
+            error!(
+                "{}: failed to switch to path {}: {}",
+                reset_ctx.name,
+                path,
+                Errno::from_i32(rc.abs())
+            );
+            drop(controller);
+            NvmeController::_failover_next_path(reset_ctx);
+            return;
+        }
+
+        let rc = unsafe { spdk_nvme_ctrlr_disconnect(ctrlr) };
+        if rc != 0 {
+            error!(
+                "{}: failed to disconnect controller: {}",
+                reset_ctx.name,
+                Errno::from_i32(rc.abs())
+            );
+            drop(controller);
+            NvmeController::_complete_reset(reset_ctx, rc);
+            return;
+        }
+
+        unsafe { spdk_nvme_ctrlr_reconnect_async(ctrlr) };
+
+        let mut reset_ctx = Some(reset_ctx);
+        controller.failover_poller = Some(
+            PollerBuilder::new()
+                .with_name("nvme_failover_poller")
+                .with_interval(Duration::from_micros(1000))
+                .with_poll_fn(move |_| {
+                    let rc =
+                        unsafe { spdk_nvme_ctrlr_reconnect_poll_async(ctrlr) };
+                    if rc == -libc::EAGAIN {
+                        return 0;
+                    }
+                    if let Some(ctx) = reset_ctx.take() {
+                        NvmeController::_failover_path_connected(ctx, rc);
+                    }
+                    1
+                })
+                .build(),
+        );
+    }
+
+    /// Complete reconnection via a new transport path: I/O channels are
+    /// reinitialized only if the namespace is ANA accessible via the path,
+    /// otherwise the next path is tried.
+    fn _failover_path_connected(mut reset_ctx: ResetCtx, status: i32) {
+        let carc = match NVME_CONTROLLERS.lookup_by_name(&reset_ctx.name) {
+            Some(c) => c,
+            None => {
+                NvmeController::_complete_reset(
+                    reset_ctx,
+                    -(Errno::ENODEV as i32),
+                );
+                return;
+            }
+        };
+        let mut controller = carc.lock();
+
+        // Reconnection is complete, so the poller is no longer needed.
+        controller.failover_poller.take();
+
+        let trid = &controller.paths[controller.active_path];
+        let path = format!("{}:{}", trid.traddr(), trid.svcid());
+
+        let usable = if status != 0 {
+            error!(
+                "{}: failed to reconnect via path {}: {}",
+                reset_ctx.name,
+                path,
+                Errno::from_i32(status.abs())
+            );
+            false
+        } else if !controller
+            .namespace()
+            .map_or(false, |ns| ns.ana_accessible())
+        {
+            warn!(
+                "{}: namespace is not accessible via path {}",
+                reset_ctx.name, path
+            );
+            false
+        } else {
+            reset_ctx.io_paths = controller.io_paths();
+            true
+        };
+        drop(controller);
+
+        if !usable {
+            NvmeController::_failover_next_path(reset_ctx);
+            return;
+        }
+
+        info!(
+            "{}: failed over to path {}, reinitializing I/O channels",
+            reset_ctx.name, path
+        );
+
+        let io_device = reset_ctx.io_device.clone();
+        io_device.traverse_io_channels(
+            NvmeController::_reset_create_channels,
+            NvmeController::_reset_create_channels_done,
+            NvmeIoChannel::inner_from_channel,
+            reset_ctx,
+        );
+    }
+
     fn _reset_create_channels(
         channel: &mut NvmeIoChannelInner,
         reset_ctx: &mut ResetCtx,
@@ -692,7 +1078,7 @@ impl<'a> NvmeController<'a> {
         }
 
         debug!("Reinitializing I/O channel");
-        let rc = channel.reinitialize(&reset_ctx.name, reset_ctx.spdk_handle);
+        let rc = channel.reinitialize(&reset_ctx.name, &reset_ctx.io_paths);
         if rc != 0 {
             error!(
                 "{} failed to reinitialize I/O channel, rc = {}",
@@ -818,6 +1204,32 @@ extern "C" fn aer_cb(ctx: *mut c_void, cpl: *const spdk_nvme_cpl) {
                 );
             }
         }
+    } else if event_type == NvmeAerType::Notice as u32
+        && event_info == NvmeAerInfoNotice::AnaChange as u32
+    {
+        let cid = ctx as u64;
+
+        let Some(c) = NVME_CONTROLLERS.lookup_by_name(cid.to_string()) else {
+            warn!("No NVMe controller exists with ID 0x{:x}", cid);
+            return;
+        };
+
+        // Release the lock before failing over, as the failover needs it.
+        let (name, accessible, mut timeout_config) = {
+            let ctrlr = c.lock();
+            (
+                ctrlr.get_name(),
+                ctrlr.namespace().map_or(true, |ns| ns.ana_accessible()),
+                ctrlr.timeout_config,
+            )
+        };
+
+        if !accessible {
+            warn!("{}: namespace became inaccessible via current path", name);
+            if !unsafe { timeout_config.as_mut() }.failover_controller() {
+                warn!("{}: no alternate path available for failover", name);
+            }
+        }
     } else if event_type == NvmeAerType::Io as u32
         && event_info == NvmeAerInfoNvmCommandSet::ReservationLogAvail as u32
     {
@@ -842,6 +1254,17 @@ pub extern "C" fn nvme_poll_adminq(ctx: *mut c_void) -> i32 {
     let result = context.process_adminq();
 
     if result < 0 {
+        // Admin queue is expected to fail while the controller is being
+        // reconnected via another path.
+        if context.failover_in_progress() {
+            return 1;
+        }
+
+        // Try another path first before giving up on the device.
+        if !context.is_destroy_in_progress() && context.failover_controller() {
+            return 1;
+        }
+
         if context.start_device_destroy() {
             error!(
                 "process adminq: {}: ctrl failed: {}, error: {}",
@@ -990,6 +1413,14 @@ pub(crate) fn connected_attached_cb(
     // set the controller as a pointer within the context of the time out config
     unsafe { controller.timeout_config.as_mut().set_controller(ctrlr) };
     controller.set_id(cid);
+    controller.paths = ctx.take_paths();
+    if controller.paths.len() > 1 {
+        info!(
+            "{}: {} paths configured for the controller",
+            ctx.name(),
+            controller.paths.len()
+        );
+    }
     controller.inner = Some(NvmeControllerInner::new(
         ctrlr,
         controller.get_name(),
@@ -1022,6 +1453,39 @@ pub(crate) fn connected_attached_cb(
         .expect("done callback receiver side disappeared");
 }
 
+/// Attaches standby controllers via the alternate paths of the controller,
+/// so that I/O channels can keep pre-connected qpairs on them. A path that
+/// fails to attach is only used by failing the controller over to it.
+pub(crate) async fn attach_standby_paths(
+    name: &str,
+    opts: &options::NvmeControllerOpts,
+) {
+    let paths = match NVME_CONTROLLERS.lookup_by_name(name) {
+        Some(c) => c.lock().paths.clone(),
+        None => return,
+    };
+
+    for (index, trid) in paths.iter().enumerate().skip(1) {
+        match StandbyPath::attach(name, index, trid, opts).await {
+            Ok(path) => {
+                let Some(c) = NVME_CONTROLLERS.lookup_by_name(name) else {
+                    return;
+                };
+                c.lock().add_standby_path(path);
+            }
+            Err(e) => {
+                warn!(
+                    "{}: failed to attach standby path {}:{}: {}",
+                    name,
+                    trid.traddr(),
+                    trid.svcid(),
+                    e
+                );
+            }
+        }
+    }
+}
+
 pub(crate) mod options {
     use spdk_rs::ffihelper::copy_str_with_null;
     use std::mem::{size_of, zeroed};
@@ -1165,13 +1629,14 @@ pub(crate) mod options {
 }
 
 pub(crate) mod transport {
-    use std::{ffi::CStr, fmt::Debug};
+    use std::{ffi::CStr, fmt::Debug, net::Ipv6Addr};
 
     use spdk_rs::{
         ffihelper::copy_str_with_null,
         libspdk::spdk_nvme_transport_id,
     };
 
+    #[derive(Clone)]
     pub struct NvmeTransportId(spdk_nvme_transport_id);
 
     impl Debug for NvmeTransportId {
@@ -1295,11 +1760,17 @@ pub(crate) mod transport {
             self
         }
 
-        /// builder for transportID currently defaults to TCP IPv4
+        /// builder for transportID currently defaults to TCP, over IPv4
+        /// unless the address is an IPv6 one
         pub fn build(self) -> NvmeTransportId {
             let trtype = String::from(TransportId::TCP);
+            let adrfam = if self.traddr.parse::<Ipv6Addr>().is_ok() {
+                AdressFamily::NvmfAdrfamIpv6
+            } else {
+                AdressFamily::NvmfAdrfamIpv4
+            };
             let mut trid = spdk_nvme_transport_id {
-                adrfam: AdressFamily::NvmfAdrfamIpv4 as u32,
+                adrfam: adrfam as u32,
                 trtype: TransportId::TCP as u32,
                 ..Default::default()
             };
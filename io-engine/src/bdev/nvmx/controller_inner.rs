@@ -75,6 +75,8 @@ pub(crate) struct TimeoutConfig {
     next_reset_time: Instant,
     destroy_in_progress: AtomicCell<bool>,
     report_failed: AtomicCell<bool>,
+    failover_in_progress: AtomicCell<bool>,
+    failover_exhausted: AtomicCell<bool>,
 }
 
 impl Drop for TimeoutConfig {
@@ -96,6 +98,8 @@ impl TimeoutConfig {
             next_reset_time: Instant::now(),
             destroy_in_progress: AtomicCell::new(false),
             report_failed: AtomicCell::new(true),
+            failover_in_progress: AtomicCell::new(false),
+            failover_exhausted: AtomicCell::new(false),
         }
     }
 
@@ -108,6 +112,12 @@ impl TimeoutConfig {
             .compare_exchange(false, true)
             .is_ok()
     }
+
+    /// Check if the device is being destroyed.
+    pub fn is_destroy_in_progress(&self) -> bool {
+        self.destroy_in_progress.load()
+    }
+
     pub fn set_controller(&mut self, ctrlr: SpdkNvmeController) {
         self.ctrlr = ctrlr;
     }
@@ -196,6 +206,81 @@ impl TimeoutConfig {
             .map(|c| c.lock().hot_remove(hot_remove_cb, self.as_ptr()));
     }
 
+    /// Check if the controller is being failed over to another path.
+    pub fn failover_in_progress(&self) -> bool {
+        self.failover_in_progress.load()
+    }
+
+    fn failover_cb(success: bool, ctx: *mut c_void) {
+        let timeout_ctx = TimeoutConfig::from_ptr(ctx as *mut TimeoutConfig);
+
+        if success {
+            info!("{} controller successfully failed over", timeout_ctx.name);
+        } else {
+            error!(
+                "{} controller failover failed, no usable paths left",
+                timeout_ctx.name
+            );
+            // Don't retry failover, let the regular failure handling take
+            // over instead.
+            timeout_ctx.failover_exhausted.store(true);
+        }
+
+        assert!(
+            timeout_ctx
+                .failover_in_progress
+                .compare_exchange(true, false)
+                .is_ok(),
+            "non-exclusive access to controller failover flag"
+        );
+    }
+
+    /// Fails the controller over to its next usable path exclusively.
+    /// Returns false if failover is not possible, i.e. the controller has
+    /// no alternate paths or all of them have already proven unusable, in
+    /// which case the caller proceeds with the regular failure handling.
+    pub(crate) fn failover_controller(&mut self) -> bool {
+        if self.failover_exhausted.load() {
+            return false;
+        }
+
+        // Failover already in progress.
+        if self
+            .failover_in_progress
+            .compare_exchange(false, true)
+            .is_err()
+        {
+            return true;
+        }
+
+        let cb_arg = self.as_ptr();
+        let started = match NVME_CONTROLLERS.lookup_by_name(&self.name) {
+            Some(c) => {
+                let mut c = c.lock();
+                if c.num_paths() < 2 {
+                    false
+                } else if let Err(e) =
+                    c.failover(TimeoutConfig::failover_cb, cb_arg)
+                {
+                    error!(
+                        "{}: failed to initiate controller failover: {}",
+                        self.name, e
+                    );
+                    false
+                } else {
+                    info!("{} controller failover initiated", self.name);
+                    true
+                }
+            }
+            None => false,
+        };
+
+        if !started {
+            self.failover_in_progress.store(false);
+        }
+        started
+    }
+
     /// Resets controller exclusively, taking into account existing active
     /// resets related to I/O timeout.
     pub(crate) fn reset_controller(&mut self) {
@@ -449,6 +534,10 @@ impl<'a> NvmeController<'a> {
                 );
             }
             DeviceTimeoutAction::HotRemove => {
+                // Try another path first before removing the device.
+                if timeout_cfg.failover_controller() {
+                    return;
+                }
                 debug!(?timeout_cfg.name, "starting hot remove");
                 timeout_cfg.hot_remove();
             }
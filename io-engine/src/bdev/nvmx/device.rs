@@ -7,7 +7,6 @@ use uuid::Uuid;
 
 use crate::{
     bdev::nvmx::{
-        controller_inner::SpdkNvmeController,
         NvmeController,
         NvmeControllerState,
         NvmeDeviceHandle,
@@ -24,6 +23,7 @@ use crate::{
         DeviceIoController,
         DeviceTimeoutAction,
         IoType,
+        ProtectionInfo,
     },
     ffihelper::{cb_arg, done_cb},
 };
@@ -38,7 +38,6 @@ pub struct NvmeBlockDevice {
 /// an NVMe controller.
 pub struct NvmeDeviceDescriptor {
     ns: Arc<NvmeNamespace>,
-    ctrlr: SpdkNvmeController,
     io_device_id: u64,
     name: String,
     prchk_flags: u32,
@@ -56,7 +55,6 @@ impl NvmeDeviceDescriptor {
                 ns,
                 io_device_id: controller.id(),
                 name: controller.get_name(),
-                ctrlr: controller.controller().unwrap(),
                 prchk_flags: controller.flags(),
             }))
         } else {
@@ -83,7 +81,6 @@ impl BlockDeviceDescriptor for NvmeDeviceDescriptor {
         Ok(Box::new(NvmeDeviceHandle::create(
             &self.name,
             self.io_device_id,
-            self.ctrlr,
             self.ns,
             self.prchk_flags,
         )?))
@@ -93,7 +90,6 @@ impl BlockDeviceDescriptor for NvmeDeviceDescriptor {
         Ok(Box::new(NvmeDeviceHandle::create(
             &self.name,
             self.io_device_id,
-            self.ctrlr,
             self.ns.clone(),
             self.prchk_flags,
         )?))
@@ -105,7 +101,6 @@ impl BlockDeviceDescriptor for NvmeDeviceDescriptor {
         let h = NvmeDeviceHandle::create_async(
             &self.name,
             self.io_device_id,
-            self.ctrlr,
             self.ns.clone(),
             self.prchk_flags,
         )
@@ -255,6 +250,19 @@ impl BlockDevice for NvmeBlockDevice {
         let controller = controller.lock();
         controller.register_device_listener(listener)
     }
+
+    fn protection_info(&self) -> ProtectionInfo {
+        ProtectionInfo {
+            md_len: self.ns.md_size() as u32,
+            md_interleave: self.ns.supports_extended_lba(),
+            dif_type: self.ns.pi_type(),
+            dif_is_head_of_md: self.ns.pi_is_head_of_md(),
+        }
+    }
+
+    fn instance_id(&self) -> Option<u64> {
+        self.ns.eui64()
+    }
 }
 
 struct NvmeDeviceIoController {
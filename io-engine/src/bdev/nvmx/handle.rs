@@ -1,5 +1,4 @@
 use std::{
-    alloc::Layout,
     mem::{zeroed, ManuallyDrop},
     os::raw::c_void,
     sync::Arc,
@@ -21,6 +20,7 @@ use spdk_rs::{
         spdk_nvme_ctrlr_cmd_admin_raw,
         spdk_nvme_ctrlr_cmd_io_raw,
         spdk_nvme_dsm_range,
+        spdk_nvme_ns,
         spdk_nvme_ns_cmd_compare,
         spdk_nvme_ns_cmd_comparev,
         spdk_nvme_ns_cmd_dataset_management,
@@ -30,6 +30,7 @@ use spdk_rs::{
         spdk_nvme_ns_cmd_write,
         spdk_nvme_ns_cmd_write_zeroes,
         spdk_nvme_ns_cmd_writev,
+        spdk_nvme_qpair,
         SPDK_NVME_SC_INTERNAL_DEVICE_ERROR,
     },
     nvme_admin_opc,
@@ -45,9 +46,12 @@ use spdk_rs::{
 use crate::{
     bdev::nvmx::{
         channel::NvmeControllerIoChannel,
-        controller_inner::SpdkNvmeController,
         utils,
-        utils::{nvme_cpl_is_pi_error, nvme_cpl_succeeded},
+        utils::{
+            nvme_cpl_is_path_error,
+            nvme_cpl_is_pi_error,
+            nvme_cpl_succeeded,
+        },
         NvmeBlockDevice,
         NvmeIoChannel,
         NvmeNamespace,
@@ -96,6 +100,10 @@ struct NvmeIoCtx {
     iov_offset: u64,
     op: IoType,
     num_blocks: u64,
+    offset_blocks: u64,
+    flags: u32,
+    /// QPair the I/O operation is submitted to.
+    qpair: *mut spdk_nvme_qpair,
     channel: *mut spdk_io_channel,
     #[cfg(feature = "fault-injection")]
     inj_op: InjectIoCtx,
@@ -120,8 +128,6 @@ const SPDK_NVME_DATASET_MANAGEMENT_RANGE_MAX_BLOCKS: u64 = 0xFFFFFFFF;
 pub struct NvmeDeviceHandle {
     /// io channel for the current thread
     io_channel: ManuallyDrop<NvmeControllerIoChannel>,
-    /// NVMe controller
-    ctrlr: SpdkNvmeController,
     /// name of the controller
     name: String,
     /// namespaces associated with this controller
@@ -144,7 +150,6 @@ impl NvmeDeviceHandle {
     fn create_handle(
         name: &str,
         id: u64,
-        ctrlr: SpdkNvmeController,
         ns: Arc<NvmeNamespace>,
         prchk_flags: u32,
     ) -> Result<NvmeDeviceHandle, CoreError> {
@@ -159,7 +164,6 @@ impl NvmeDeviceHandle {
         Ok(NvmeDeviceHandle {
             name: name.to_string(),
             io_channel: ManuallyDrop::new(io_channel),
-            ctrlr,
             block_device: Self::get_nvme_device(name, &ns),
             block_len: ns.block_len(),
             prchk_flags,
@@ -171,11 +175,10 @@ impl NvmeDeviceHandle {
     pub fn create(
         name: &str,
         id: u64,
-        ctrlr: SpdkNvmeController,
         ns: Arc<NvmeNamespace>,
         prchk_flags: u32,
     ) -> Result<NvmeDeviceHandle, CoreError> {
-        let mut handle = Self::create_handle(name, id, ctrlr, ns, prchk_flags)?;
+        let mut handle = Self::create_handle(name, id, ns, prchk_flags)?;
         handle.connect_sync();
         Ok(handle)
     }
@@ -184,11 +187,10 @@ impl NvmeDeviceHandle {
     pub async fn create_async(
         name: &str,
         id: u64,
-        ctrlr: SpdkNvmeController,
         ns: Arc<NvmeNamespace>,
         prchk_flags: u32,
     ) -> Result<NvmeDeviceHandle, CoreError> {
-        let mut handle = Self::create_handle(name, id, ctrlr, ns, prchk_flags)?;
+        let mut handle = Self::create_handle(name, id, ns, prchk_flags)?;
 
         #[cfg(feature = "spdk-async-qpair-connect")]
         handle.connect_async().await?;
@@ -209,6 +211,7 @@ impl NvmeDeviceHandle {
             }
             None => warn!("No I/O qpair in NvmeDeviceHandle, can't connect()"),
         };
+        inner.connect_standby();
     }
 
     #[cfg(feature = "spdk-async-qpair-connect")]
@@ -217,7 +220,11 @@ impl NvmeDeviceHandle {
         let inner = NvmeIoChannel::inner_from_channel(self.io_channel.as_ptr());
 
         match inner.qpair_mut() {
-            Some(q) => q.connect_async().await,
+            Some(q) => {
+                q.connect_async().await?;
+                inner.connect_standby_async().await;
+                Ok(())
+            }
             None => {
                 error!("No I/O qpair in NvmeDeviceHandle, can't connect()");
                 Err(CoreError::InvalidNvmeDeviceHandle {
@@ -340,6 +347,15 @@ fn complete_nvme_command(ctx: *mut NvmeIoCtx, cpl: *const spdk_nvme_cpl) {
     let op_succeeded = nvme_cpl_succeeded(cpl);
     let inner = NvmeIoChannel::inner_from_channel(io_ctx.channel);
 
+    // Resubmit the operation via another path in case the one it was
+    // submitted to went down.
+    if !op_succeeded
+        && nvme_cpl_is_path_error(cpl)
+        && retry_nvme_io(io_ctx, ctx, inner)
+    {
+        return;
+    }
+
     // Update I/O statistics in case the operation succeeded.
     if op_succeeded {
         let stats_controller = inner.get_io_stats_controller();
@@ -368,6 +384,106 @@ fn complete_nvme_command(ctx: *mut NvmeIoCtx, cpl: *const spdk_nvme_cpl) {
     free_nvme_io_ctx(ctx);
 }
 
+/// Checks if an I/O operation failed with a path error on the given qpair can
+/// be resubmitted via the active path of the channel, switching the channel
+/// to a standby path first if the qpair is the active one. Every switch
+/// consumes a standby path, so the number of retries is bounded.
+fn switch_path_for_retry(
+    inner: &mut NvmeIoChannelInner,
+    qpair: *mut spdk_nvme_qpair,
+) -> bool {
+    inner.qpair().is_some()
+        && (qpair != unsafe { inner.qpair_ptr() } || inner.switch_path(None))
+}
+
+/// Resubmits an I/O operation failed with a path error via the active path
+/// of the channel (see `switch_path_for_retry`).
+fn retry_nvme_io(
+    io_ctx: &mut NvmeIoCtx,
+    ctx: *mut NvmeIoCtx,
+    inner: &mut NvmeIoChannelInner,
+) -> bool {
+    if !switch_path_for_retry(inner, io_ctx.qpair) {
+        return false;
+    }
+
+    io_ctx.qpair = unsafe { inner.qpair_ptr() };
+    io_ctx.iovpos = 0;
+    io_ctx.iov_offset = 0;
+
+    let rc = unsafe {
+        match io_ctx.op {
+            IoType::Read => spdk_nvme_ns_cmd_readv(
+                inner.ns_ptr(),
+                io_ctx.qpair,
+                io_ctx.offset_blocks,
+                io_ctx.num_blocks as u32,
+                Some(nvme_io_done),
+                ctx as *mut c_void,
+                io_ctx.flags,
+                Some(nvme_queued_reset_sgl),
+                Some(nvme_queued_next_sge),
+            ),
+            IoType::Write => spdk_nvme_ns_cmd_writev(
+                inner.ns_ptr(),
+                io_ctx.qpair,
+                io_ctx.offset_blocks,
+                io_ctx.num_blocks as u32,
+                Some(nvme_writev_done),
+                ctx as *mut c_void,
+                io_ctx.flags,
+                Some(nvme_queued_reset_sgl),
+                Some(nvme_queued_next_sge),
+            ),
+            IoType::Compare => spdk_nvme_ns_cmd_comparev(
+                inner.ns_ptr(),
+                io_ctx.qpair,
+                io_ctx.offset_blocks,
+                io_ctx.num_blocks as u32,
+                Some(nvme_io_done),
+                ctx as *mut c_void,
+                io_ctx.flags,
+                Some(nvme_queued_reset_sgl),
+                Some(nvme_queued_next_sge),
+            ),
+            IoType::Flush => spdk_nvme_ns_cmd_flush(
+                inner.ns_ptr(),
+                io_ctx.qpair,
+                Some(nvme_flush_completion),
+                ctx as *mut c_void,
+            ),
+            IoType::Unmap => submit_unmap(
+                inner.ns_ptr(),
+                io_ctx.qpair,
+                io_ctx.offset_blocks,
+                io_ctx.num_blocks,
+                ctx,
+            ),
+            IoType::WriteZeros => spdk_nvme_ns_cmd_write_zeroes(
+                inner.ns_ptr(),
+                io_ctx.qpair,
+                io_ctx.offset_blocks,
+                io_ctx.num_blocks as u32,
+                Some(nvme_io_done),
+                ctx as *mut c_void,
+                io_ctx.flags,
+            ),
+            _ => return false,
+        }
+    };
+
+    if rc != 0 {
+        warn!(
+            op = ?io_ctx.op,
+            rc, "failed to resubmit I/O via another path"
+        );
+        return false;
+    }
+
+    trace!(op = ?io_ctx.op, path = inner.path_index(), "I/O resubmitted");
+    true
+}
+
 /// Completion handler for vectored write requests.
 extern "C" fn nvme_writev_done(ctx: *mut c_void, cpl: *const spdk_nvme_cpl) {
     let nvme_io_ctx = ctx as *mut NvmeIoCtx;
@@ -395,11 +511,13 @@ extern "C" fn nvme_io_done(ctx: *mut c_void, cpl: *const spdk_nvme_cpl) {
     complete_nvme_command(nvme_io_ctx, cpl);
 }
 
+/// Completion handler of the awaited I/O requests, which also tells whether
+/// the request may be resubmitted via another path.
 extern "C" fn nvme_async_io_completion(
     ctx: *mut c_void,
     cpl: *const spdk_nvme_cpl,
 ) {
-    done_cb(ctx, NvmeStatus::from(cpl));
+    done_cb(ctx, (NvmeStatus::from(cpl), nvme_cpl_is_path_error(cpl)));
 }
 
 extern "C" fn nvme_unmap_completion(
@@ -420,6 +538,51 @@ extern "C" fn nvme_flush_completion(
     complete_nvme_command(nvme_io_ctx, cpl);
 }
 
+/// Submits a dataset management command which deallocates the given blocks.
+/// The number of blocks must fit into `SPDK_NVME_DATASET_MANAGEMENT_MAX_RANGES`
+/// ranges.
+unsafe fn submit_unmap(
+    ns: *mut spdk_nvme_ns,
+    qpair: *mut spdk_nvme_qpair,
+    offset_blocks: u64,
+    num_blocks: u64,
+    ctx: *mut NvmeIoCtx,
+) -> i32 {
+    let mut dsm_ranges = Vec::new();
+    let mut remaining = num_blocks;
+    let mut offset = offset_blocks;
+
+    // Fill max-size ranges until the remaining blocks fit into one range.
+    while remaining > SPDK_NVME_DATASET_MANAGEMENT_RANGE_MAX_BLOCKS {
+        dsm_ranges.push(spdk_nvme_dsm_range {
+            attributes: zeroed(),
+            length: SPDK_NVME_DATASET_MANAGEMENT_RANGE_MAX_BLOCKS as u32,
+            starting_lba: offset,
+        });
+
+        offset += SPDK_NVME_DATASET_MANAGEMENT_RANGE_MAX_BLOCKS;
+        remaining -= SPDK_NVME_DATASET_MANAGEMENT_RANGE_MAX_BLOCKS;
+    }
+
+    // Setup range that describes the remaining blocks.
+    dsm_ranges.push(spdk_nvme_dsm_range {
+        attributes: zeroed(),
+        length: remaining as u32,
+        starting_lba: offset,
+    });
+
+    // The ranges are copied into the request.
+    spdk_nvme_ns_cmd_dataset_management(
+        ns,
+        qpair,
+        utils::NvmeDsmAttribute::Deallocate as u32,
+        dsm_ranges.as_ptr(),
+        dsm_ranges.len() as u16,
+        Some(nvme_unmap_completion),
+        ctx as *mut c_void,
+    )
+}
+
 fn check_io_args(
     op: IoType,
     iovs: &[IoVec],
@@ -605,32 +768,40 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
         // Make sure channel allows I/O.
         check_channel_for_io(IoType::Read, inner, offset_blocks, num_blocks)?;
 
-        let (s, r) = oneshot::channel::<NvmeStatus>();
+        inner.account_io();
+        let status = loop {
+            let (s, r) = oneshot::channel::<(NvmeStatus, bool)>();
+            let qpair = unsafe { inner.qpair_ptr() };
 
-        let rc = unsafe {
-            spdk_nvme_ns_cmd_read(
-                self.ns.as_ptr(),
-                inner.qpair_ptr(),
-                buffer.as_mut_ptr(),
-                offset_blocks,
-                num_blocks as u32,
-                Some(nvme_async_io_completion),
-                cb_arg(s),
-                self.prchk_flags,
-            )
-        };
+            let rc = unsafe {
+                spdk_nvme_ns_cmd_read(
+                    inner.ns_ptr(),
+                    qpair,
+                    buffer.as_mut_ptr(),
+                    offset_blocks,
+                    num_blocks as u32,
+                    Some(nvme_async_io_completion),
+                    cb_arg(s),
+                    self.prchk_flags,
+                )
+            };
 
-        if rc != 0 && rc != -libc::ENOMEM {
-            error!("{} read failed: rc = {}", self.name, rc);
-            return Err(CoreError::ReadDispatch {
-                source: Errno::from_i32(-rc),
-                offset,
-                len: buffer.len(),
-            });
-        }
+            if rc != 0 && rc != -libc::ENOMEM {
+                error!("{} read failed: rc = {}", self.name, rc);
+                inner.discard_io();
+                return Err(CoreError::ReadDispatch {
+                    source: Errno::from_i32(-rc),
+                    offset,
+                    len: buffer.len(),
+                });
+            }
 
-        inner.account_io();
-        let ret = match r.await.expect("Failed awaiting at read_at()") {
+            match r.await.expect("Failed awaiting at read_at()") {
+                (_, true) if switch_path_for_retry(inner, qpair) => {}
+                (status, _) => break status,
+            }
+        };
+        let ret = match status {
             NvmeStatus::SUCCESS => {
                 inner.get_io_stats_controller().account_block_io(
                     IoType::Read,
@@ -687,32 +858,40 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
         // Make sure channel allows I/O.
         check_channel_for_io(IoType::Write, inner, offset_blocks, num_blocks)?;
 
-        let (s, r) = oneshot::channel::<NvmeStatus>();
+        inner.account_io();
+        let status = loop {
+            let (s, r) = oneshot::channel::<(NvmeStatus, bool)>();
+            let qpair = unsafe { inner.qpair_ptr() };
 
-        let rc = unsafe {
-            spdk_nvme_ns_cmd_write(
-                self.ns.as_ptr(),
-                inner.qpair_ptr(),
-                buffer.as_ptr() as *mut _,
-                offset_blocks,
-                num_blocks as u32,
-                Some(nvme_async_io_completion),
-                cb_arg(s),
-                self.prchk_flags,
-            )
-        };
+            let rc = unsafe {
+                spdk_nvme_ns_cmd_write(
+                    inner.ns_ptr(),
+                    qpair,
+                    buffer.as_ptr() as *mut _,
+                    offset_blocks,
+                    num_blocks as u32,
+                    Some(nvme_async_io_completion),
+                    cb_arg(s),
+                    self.prchk_flags,
+                )
+            };
 
-        if rc != 0 && rc != -libc::ENOMEM {
-            error!("{} write failed: rc = {}", self.name, rc);
-            return Err(CoreError::WriteDispatch {
-                source: Errno::from_i32(-rc),
-                offset,
-                len: buffer.len(),
-            });
-        }
+            if rc != 0 && rc != -libc::ENOMEM {
+                error!("{} write failed: rc = {}", self.name, rc);
+                inner.discard_io();
+                return Err(CoreError::WriteDispatch {
+                    source: Errno::from_i32(-rc),
+                    offset,
+                    len: buffer.len(),
+                });
+            }
 
-        inner.account_io();
-        let ret = match r.await.expect("Failed awaiting at write_at()") {
+            match r.await.expect("Failed awaiting at write_at()") {
+                (_, true) if switch_path_for_retry(inner, qpair) => {}
+                (status, _) => break status,
+            }
+        };
+        let ret = match status {
             NvmeStatus::SUCCESS => {
                 inner.get_io_stats_controller().account_block_io(
                     IoType::Write,
@@ -764,6 +943,9 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
                 channel,
                 op: IoType::Read,
                 num_blocks,
+                offset_blocks,
+                flags,
+                qpair: unsafe { inner.qpair_ptr() },
                 #[cfg(feature = "fault-injection")]
                 inj_op: InjectIoCtx::with_iovs(
                     FaultDomain::BlockDevice,
@@ -784,7 +966,7 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
         let rc = if iovs.len() == 1 {
             unsafe {
                 spdk_nvme_ns_cmd_read(
-                    self.ns.as_ptr(),
+                    inner.ns_ptr(),
                     inner.qpair_ptr(),
                     iovs[0].as_mut_ptr(),
                     offset_blocks,
@@ -797,7 +979,7 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
         } else {
             unsafe {
                 spdk_nvme_ns_cmd_readv(
-                    self.ns.as_ptr(),
+                    inner.ns_ptr(),
                     inner.qpair_ptr(),
                     offset_blocks,
                     num_blocks as u32,
@@ -850,6 +1032,9 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
                 channel,
                 op: IoType::Write,
                 num_blocks,
+                offset_blocks,
+                flags: self.prchk_flags,
+                qpair: unsafe { inner.qpair_ptr() },
                 #[cfg(feature = "fault-injection")]
                 inj_op: InjectIoCtx::with_iovs(
                     FaultDomain::BlockDevice,
@@ -870,7 +1055,7 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
         let rc = if iovs.len() == 1 {
             unsafe {
                 spdk_nvme_ns_cmd_write(
-                    self.ns.as_ptr(),
+                    inner.ns_ptr(),
                     inner.qpair_ptr(),
                     iovs[0].as_ptr() as *mut _,
                     offset_blocks,
@@ -883,7 +1068,7 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
         } else {
             unsafe {
                 spdk_nvme_ns_cmd_writev(
-                    self.ns.as_ptr(),
+                    inner.ns_ptr(),
                     inner.qpair_ptr(),
                     offset_blocks,
                     num_blocks as u32,
@@ -941,6 +1126,9 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
                 channel,
                 op: IoType::Compare,
                 num_blocks,
+                offset_blocks,
+                flags: self.prchk_flags,
+                qpair: unsafe { inner.qpair_ptr() },
                 #[cfg(feature = "fault-injection")]
                 inj_op: InjectIoCtx::new(FaultDomain::BlockDevice),
             },
@@ -951,7 +1139,7 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
         let rc = if iovs.len() == 1 {
             unsafe {
                 spdk_nvme_ns_cmd_compare(
-                    self.ns.as_ptr(),
+                    inner.ns_ptr(),
                     inner.qpair_ptr(),
                     iovs[0].as_ptr() as *mut _,
                     offset_blocks,
@@ -964,7 +1152,7 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
         } else {
             unsafe {
                 spdk_nvme_ns_cmd_comparev(
-                    self.ns.as_ptr(),
+                    inner.ns_ptr(),
                     inner.qpair_ptr(),
                     offset_blocks,
                     num_blocks as u32,
@@ -1039,6 +1227,9 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
                 channel,
                 op: IoType::Flush,
                 num_blocks,
+                offset_blocks: 0,
+                flags: 0,
+                qpair: unsafe { inner.qpair_ptr() },
                 #[cfg(feature = "fault-injection")]
                 inj_op: InjectIoCtx::new(FaultDomain::BlockDevice),
             },
@@ -1049,7 +1240,7 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
         // Setup range that describes the remaining blocks and schedule unmap.
         let rc = unsafe {
             spdk_nvme_ns_cmd_flush(
-                self.ns.as_ptr(),
+                inner.ns_ptr(),
                 inner.qpair_ptr(),
                 Some(nvme_flush_completion),
                 bio as *mut c_void,
@@ -1102,6 +1293,9 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
                 channel,
                 op: IoType::Unmap,
                 num_blocks,
+                offset_blocks,
+                flags: 0,
+                qpair: unsafe { inner.qpair_ptr() },
                 #[cfg(feature = "fault-injection")]
                 inj_op: InjectIoCtx::new(FaultDomain::BlockDevice),
             },
@@ -1109,53 +1303,13 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
             num_blocks,
         )?;
 
-        let l = Layout::array::<spdk_nvme_dsm_range>(
-            SPDK_NVME_DATASET_MANAGEMENT_MAX_RANGES as usize,
-        )
-        .unwrap();
-        let dsm_ranges =
-            unsafe { std::alloc::alloc(l) as *mut spdk_nvme_dsm_range };
-
-        let mut remaining = num_blocks;
-        let mut offset = offset_blocks;
-        let mut range_id: usize = 0;
-
-        // Fill max-size ranges until the remaining blocks fit into one range.
-        while remaining > SPDK_NVME_DATASET_MANAGEMENT_RANGE_MAX_BLOCKS {
-            unsafe {
-                let range = spdk_nvme_dsm_range {
-                    attributes: zeroed(),
-                    length: SPDK_NVME_DATASET_MANAGEMENT_RANGE_MAX_BLOCKS
-                        as u32,
-                    starting_lba: offset,
-                };
-
-                *dsm_ranges.add(range_id) = range;
-            }
-
-            offset += SPDK_NVME_DATASET_MANAGEMENT_RANGE_MAX_BLOCKS;
-            remaining -= SPDK_NVME_DATASET_MANAGEMENT_RANGE_MAX_BLOCKS;
-            range_id += 1;
-        }
-
-        // Setup range that describes the remaining blocks and schedule unmap.
         let rc = unsafe {
-            let range = spdk_nvme_dsm_range {
-                attributes: zeroed(),
-                length: remaining as u32,
-                starting_lba: offset,
-            };
-
-            *dsm_ranges.add(range_id) = range;
-
-            spdk_nvme_ns_cmd_dataset_management(
-                self.ns.as_ptr(),
+            submit_unmap(
+                inner.ns_ptr(),
                 inner.qpair_ptr(),
-                utils::NvmeDsmAttribute::Deallocate as u32,
-                dsm_ranges,
-                num_ranges as u16,
-                Some(nvme_unmap_completion),
-                bio as *mut c_void,
+                offset_blocks,
+                num_blocks,
+                bio,
             )
         };
 
@@ -1201,6 +1355,9 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
                 channel,
                 op: IoType::WriteZeros,
                 num_blocks,
+                offset_blocks,
+                flags: self.prchk_flags,
+                qpair: unsafe { inner.qpair_ptr() },
                 #[cfg(feature = "fault-injection")]
                 inj_op: InjectIoCtx::new(FaultDomain::BlockDevice),
             },
@@ -1210,7 +1367,7 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
 
         let rc = unsafe {
             spdk_nvme_ns_cmd_write_zeroes(
-                self.ns.as_ptr(),
+                inner.ns_ptr(),
                 inner.qpair_ptr(),
                 offset_blocks,
                 num_blocks as u32,
@@ -1291,7 +1448,7 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
 
         unsafe {
             spdk_nvme_ctrlr_cmd_admin_raw(
-                self.ctrlr.as_ptr(),
+                inner.ctrlr().as_ptr(),
                 &mut pcmd,
                 ptr,
                 size as u32,
@@ -1456,7 +1613,10 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
                 "IO passthrough completed, succeeded={}",
                 nvme_cpl_succeeded(cpl)
             );
-            done_cb(ctx, nvme_cpl_succeeded(cpl));
+            done_cb(
+                ctx,
+                (nvme_cpl_succeeded(cpl), nvme_cpl_is_path_error(cpl)),
+            );
         }
 
         let mut pcmd = *nvme_cmd; // Make a private mutable copy of the command.
@@ -1476,27 +1636,38 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
             None => (std::ptr::null_mut(), 0),
         };
 
-        let (s, r) = oneshot::channel::<bool>();
-
-        unsafe {
-            spdk_nvme_ctrlr_cmd_io_raw(
-                self.ctrlr.as_ptr(),
-                inner.qpair_ptr(),
-                &mut pcmd,
-                ptr,
-                size as u32,
-                Some(nvme_io_passthru_done),
-                cb_arg(s),
-            )
-        }
-        .to_result(|e| CoreError::NvmeIoPassthruDispatch {
-            source: Errno::from_i32(e),
-            opcode: nvme_cmd.opc(),
-        })?;
-
         inner.account_io();
-        let ret = if r.await.expect("Failed awaiting NVMe IO passthru command")
-        {
+        let succeeded = loop {
+            let (s, r) = oneshot::channel::<(bool, bool)>();
+            let qpair = unsafe { inner.qpair_ptr() };
+
+            let rc = unsafe {
+                spdk_nvme_ctrlr_cmd_io_raw(
+                    inner.ctrlr().as_ptr(),
+                    qpair,
+                    &mut pcmd,
+                    ptr,
+                    size as u32,
+                    Some(nvme_io_passthru_done),
+                    cb_arg(s),
+                )
+            };
+            if let Err(error) =
+                rc.to_result(|e| CoreError::NvmeIoPassthruDispatch {
+                    source: Errno::from_i32(e),
+                    opcode: nvme_cmd.opc(),
+                })
+            {
+                inner.discard_io();
+                return Err(error);
+            }
+
+            match r.await.expect("Failed awaiting NVMe IO passthru command") {
+                (false, true) if switch_path_for_retry(inner, qpair) => {}
+                (succeeded, _) => break succeeded,
+            }
+        };
+        let ret = if succeeded {
             debug!("io_passthru() done");
             Ok(())
         } else {
@@ -1525,7 +1696,9 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
 
     /// Determines if the underlying controller is failed.
     fn is_ctrlr_failed(&self) -> bool {
-        self.ctrlr.is_failed
+        NvmeIoChannel::inner_from_channel(self.io_channel.as_ptr())
+            .ctrlr()
+            .is_failed
     }
 }
 
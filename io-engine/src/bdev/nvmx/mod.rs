@@ -27,6 +27,7 @@ mod controller_state;
 mod device;
 mod handle;
 mod namespace;
+mod path;
 mod poll_group;
 mod qpair;
 mod snapshot;
@@ -122,6 +123,31 @@ impl<'a> Default for NVMeCtlrList<'a> {
 pub static NVME_CONTROLLERS: Lazy<NVMeCtlrList> =
     Lazy::new(NVMeCtlrList::default);
 
-pub fn nvme_bdev_running_config() -> &'static NvmeBdevOpts {
-    &Config::get().nvme_bdev_opts
+/// NVMe bdev options currently in effect. Initially these are the options
+/// loaded from the config file, but they can be changed at runtime via a
+/// config reload.
+static NVME_BDEV_RUNNING_CONFIG: Lazy<RwLock<NvmeBdevOpts>> =
+    Lazy::new(|| RwLock::new(Config::get().nvme_bdev_opts.clone()));
+
+/// Get a copy of the NVMe bdev options currently in effect.
+pub fn nvme_bdev_running_config() -> NvmeBdevOpts {
+    NVME_BDEV_RUNNING_CONFIG.read().clone()
+}
+
+/// Replace the NVMe bdev options currently in effect and re-apply I/O
+/// timeouts to all running NVMe controllers. Connection-level options
+/// (keep-alive timeout, transport retry count, poll periods) would only
+/// affect the controllers and I/O channels created after the update.
+pub fn nvme_bdev_update_running_config(opts: NvmeBdevOpts) {
+    *NVME_BDEV_RUNNING_CONFIG.write() = opts;
+
+    for name in NVME_CONTROLLERS.controllers() {
+        let Some(ctrlr) = NVME_CONTROLLERS.lookup_by_name(&name) else {
+            continue;
+        };
+        let mut ctrlr = ctrlr.lock();
+        if ctrlr.get_state() == NvmeControllerState::Running {
+            ctrlr.configure_timeout();
+        }
+    }
 }
@@ -2,18 +2,29 @@ use std::ptr::NonNull;
 
 use spdk_rs::libspdk::{
     spdk_nvme_ns,
+    spdk_nvme_ns_get_ana_state,
+    spdk_nvme_ns_get_data,
     spdk_nvme_ns_get_extended_sector_size,
     spdk_nvme_ns_get_flags,
     spdk_nvme_ns_get_md_size,
     spdk_nvme_ns_get_num_sectors,
     spdk_nvme_ns_get_optimal_io_boundary,
+    spdk_nvme_ns_get_pi_type,
     spdk_nvme_ns_get_size,
     spdk_nvme_ns_get_uuid,
     spdk_nvme_ns_supports_compare,
+    spdk_nvme_ns_supports_extended_lba,
+    SPDK_NVME_ANA_NON_OPTIMIZED_STATE,
+    SPDK_NVME_ANA_OPTIMIZED_STATE,
+    SPDK_NVME_FMT_NVM_PROTECTION_TYPE1,
+    SPDK_NVME_FMT_NVM_PROTECTION_TYPE2,
+    SPDK_NVME_FMT_NVM_PROTECTION_TYPE3,
     SPDK_NVME_NS_DEALLOCATE_SUPPORTED,
     SPDK_NVME_NS_WRITE_ZEROES_SUPPORTED,
 };
 
+use crate::core::DifType;
+
 #[derive(Debug)]
 pub struct NvmeNamespace(NonNull<spdk_nvme_ns>);
 
@@ -41,6 +52,15 @@ impl NvmeNamespace {
         .into()
     }
 
+    /// Check whether the namespace is accessible via the current path, i.e.
+    /// its ANA state is either optimized or non-optimized.
+    pub fn ana_accessible(&self) -> bool {
+        matches!(
+            unsafe { spdk_nvme_ns_get_ana_state(self.0.as_ptr()) },
+            SPDK_NVME_ANA_OPTIMIZED_STATE | SPDK_NVME_ANA_NON_OPTIMIZED_STATE
+        )
+    }
+
     pub fn supports_compare(&self) -> bool {
         unsafe { spdk_nvme_ns_supports_compare(self.0.as_ptr()) }
     }
@@ -69,6 +89,34 @@ impl NvmeNamespace {
         unsafe { spdk_nvme_ns_get_md_size(self.0.as_ptr()) as u64 }
     }
 
+    /// Checks if the metadata is transferred along with the data of each
+    /// block, as an extended LBA.
+    pub fn supports_extended_lba(&self) -> bool {
+        unsafe { spdk_nvme_ns_supports_extended_lba(self.0.as_ptr()) }
+    }
+
+    pub fn pi_type(&self) -> DifType {
+        match unsafe { spdk_nvme_ns_get_pi_type(self.0.as_ptr()) } {
+            SPDK_NVME_FMT_NVM_PROTECTION_TYPE1 => DifType::Type1,
+            SPDK_NVME_FMT_NVM_PROTECTION_TYPE2 => DifType::Type2,
+            SPDK_NVME_FMT_NVM_PROTECTION_TYPE3 => DifType::Type3,
+            _ => DifType::Disabled,
+        }
+    }
+
+    /// Checks if the protection information is in the first bytes of the
+    /// metadata, rather than in the last ones.
+    pub fn pi_is_head_of_md(&self) -> bool {
+        unsafe { (*spdk_nvme_ns_get_data(self.0.as_ptr())).dps.md_start() != 0 }
+    }
+
+    /// Returns the IEEE extended unique identifier of the namespace, if set.
+    /// Replicas report the instance of their volume through it.
+    pub fn eui64(&self) -> Option<u64> {
+        let eui64 = unsafe { (*spdk_nvme_ns_get_data(self.0.as_ptr())).eui64 };
+        (eui64 != 0).then_some(eui64)
+    }
+
     pub fn from_ptr(ns: *mut spdk_nvme_ns) -> NvmeNamespace {
         NonNull::new(ns)
             .map(NvmeNamespace)
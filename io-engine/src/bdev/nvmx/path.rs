@@ -0,0 +1,259 @@
+//!
+//!
+//! This file contains the standby transport paths of an NVMe controller.
+//! Besides the path the controller is connected through, a separate SPDK
+//! controller is attached via every alternate path of the controller, and
+//! I/O channels keep pre-connected qpairs on them. I/O is then switched over
+//! to another path without being failed when the active path goes down.
+use std::{os::raw::c_void, ptr::NonNull, rc::Rc, time::Duration};
+
+use crossbeam::atomic::AtomicCell;
+use futures::channel::oneshot;
+use nix::errno::Errno;
+
+use spdk_rs::{
+    libspdk::{
+        spdk_nvme_connect_async,
+        spdk_nvme_ctrlr,
+        spdk_nvme_ctrlr_get_ns,
+        spdk_nvme_ctrlr_is_active_ns,
+        spdk_nvme_ctrlr_opts,
+        spdk_nvme_ctrlr_process_admin_completions,
+        spdk_nvme_detach,
+        spdk_nvme_ns,
+        spdk_nvme_probe_poll_async,
+        spdk_nvme_transport_id,
+    },
+    Poller,
+    PollerBuilder,
+};
+
+use super::{
+    controller::{options::NvmeControllerOpts, transport::NvmeTransportId},
+    controller_inner::SpdkNvmeController,
+    nvme_bdev_running_config,
+    NvmeNamespace,
+};
+
+/// Transport path an I/O qpair is connected through.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct IoPath {
+    /// Index of the path in the list of paths of the controller.
+    pub(crate) index: usize,
+    /// SPDK controller connected via the path.
+    pub(crate) ctrlr: SpdkNvmeController,
+    /// The namespace as seen via the path.
+    pub(crate) ns: *mut spdk_nvme_ns,
+}
+
+impl IoPath {
+    /// Creates a path for the only namespace supported per controller.
+    pub(crate) fn new(index: usize, ctrlr: SpdkNvmeController) -> Self {
+        let ns = unsafe { spdk_nvme_ctrlr_get_ns(ctrlr.as_ptr(), 1) };
+        Self {
+            index,
+            ctrlr,
+            ns,
+        }
+    }
+}
+
+/// State of a standby path shared with its admin queue poller.
+struct Shared {
+    index: AtomicCell<usize>,
+    ctrlr: AtomicCell<SpdkNvmeController>,
+    failed: AtomicCell<bool>,
+}
+
+/// SPDK controller attached via an alternate path of an NVMe controller.
+pub(crate) struct StandbyPath<'a> {
+    shared: Rc<Shared>,
+    adminq_poller: Poller<'a>,
+}
+
+impl std::fmt::Debug for StandbyPath<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StandbyPath")
+            .field("index", &self.index())
+            .field("ctrlr", &self.ctrlr().as_ptr())
+            .field("failed", &self.shared.failed.load())
+            .finish()
+    }
+}
+
+/// Context of a standby controller being attached.
+struct AttachCtx {
+    sender: Option<oneshot::Sender<Option<SpdkNvmeController>>>,
+}
+
+extern "C" fn attach_cb(
+    cb_ctx: *mut c_void,
+    _trid: *const spdk_nvme_transport_id,
+    ctrlr: *mut spdk_nvme_ctrlr,
+    _opts: *const spdk_nvme_ctrlr_opts,
+) {
+    let ctx = unsafe { &mut *(cb_ctx as *mut AttachCtx) };
+
+    if let Some(sender) = ctx.sender.take() {
+        sender
+            .send(SpdkNvmeController::from_ptr(ctrlr))
+            .expect("attach callback receiver side disappeared");
+    }
+}
+
+impl<'a> StandbyPath<'a> {
+    /// Attaches a controller via the alternate path with the given index.
+    pub(crate) async fn attach(
+        name: &str,
+        index: usize,
+        trid: &NvmeTransportId,
+        opts: &NvmeControllerOpts,
+    ) -> Result<StandbyPath<'a>, Errno> {
+        let mut probe_ctx = NonNull::new(unsafe {
+            spdk_nvme_connect_async(
+                trid.as_ptr(),
+                opts.as_ptr(),
+                Some(attach_cb),
+            )
+        })
+        .ok_or(Errno::ENODEV)?;
+
+        let (sender, receiver) = oneshot::channel();
+        let ctx = Box::into_raw(Box::new(AttachCtx {
+            sender: Some(sender),
+        }));
+        unsafe {
+            probe_ctx.as_mut().cb_ctx = ctx as *mut c_void;
+        }
+
+        // The probe context is freed by SPDK once the attach completes, so
+        // it must not be polled any more after that.
+        let mut done = false;
+        let probe_poller = PollerBuilder::new()
+            .with_name("nvme_standby_probe_poller")
+            .with_interval(Duration::from_micros(1000))
+            .with_poll_fn(move |_| {
+                if done {
+                    return 0;
+                }
+                let rc =
+                    unsafe { spdk_nvme_probe_poll_async(probe_ctx.as_ptr()) };
+                if rc != -libc::EAGAIN {
+                    done = true;
+                    // Attach failed if the callback hasn't been called.
+                    let ctx = unsafe { &mut *ctx };
+                    if let Some(sender) = ctx.sender.take() {
+                        sender.send(None).ok();
+                    }
+                }
+                1
+            })
+            .build();
+
+        let ctrlr = receiver.await.unwrap_or(None);
+        drop(probe_poller);
+        unsafe { drop(Box::from_raw(ctx)) };
+
+        let ctrlr = ctrlr.ok_or(Errno::ENXIO)?;
+        info!(
+            "{}: standby path {}:{} attached",
+            name,
+            trid.traddr(),
+            trid.svcid()
+        );
+
+        let shared = Rc::new(Shared {
+            index: AtomicCell::new(index),
+            ctrlr: AtomicCell::new(ctrlr),
+            failed: AtomicCell::new(false),
+        });
+
+        // Keep the admin queue of the standby controller alive, and detect
+        // the failure of the path.
+        let poll_shared = shared.clone();
+        let adminq_poller = PollerBuilder::new()
+            .with_name("nvme_standby_poll_adminq")
+            .with_interval(Duration::from_micros(
+                nvme_bdev_running_config().nvme_adminq_poll_period_us,
+            ))
+            .with_poll_fn(move |_| {
+                let rc = unsafe {
+                    spdk_nvme_ctrlr_process_admin_completions(
+                        poll_shared.ctrlr.load().as_ptr(),
+                    )
+                };
+                if rc < 0 {
+                    poll_shared.failed.store(true);
+                }
+
+                if rc > 0 {
+                    1
+                } else {
+                    0
+                }
+            })
+            .build();
+
+        Ok(Self {
+            shared,
+            adminq_poller,
+        })
+    }
+
+    /// Returns the index of the path in the list of paths of the controller.
+    pub(crate) fn index(&self) -> usize {
+        self.shared.index.load()
+    }
+
+    /// Returns the SPDK controller connected via the path.
+    pub(crate) fn ctrlr(&self) -> SpdkNvmeController {
+        self.shared.ctrlr.load()
+    }
+
+    /// Returns the path I/O qpairs are connected through.
+    pub(crate) fn io_path(&self) -> IoPath {
+        IoPath::new(self.index(), self.ctrlr())
+    }
+
+    /// Checks whether I/O can be switched over to the path: the controller
+    /// is alive and the namespace is ANA accessible via the path.
+    pub(crate) fn is_usable(&self) -> bool {
+        let ctrlr = self.ctrlr();
+        if self.shared.failed.load() || ctrlr.is_failed {
+            return false;
+        }
+
+        let active = unsafe { spdk_nvme_ctrlr_is_active_ns(ctrlr.as_ptr(), 1) };
+        active && NvmeNamespace::from_ptr(self.io_path().ns).ana_accessible()
+    }
+
+    /// Replaces the controller of the path with the one of the path the
+    /// controller is failed over from, and returns the controller of the
+    /// path.
+    pub(crate) fn replace(
+        &mut self,
+        index: usize,
+        ctrlr: SpdkNvmeController,
+    ) -> SpdkNvmeController {
+        let prev = self.shared.ctrlr.swap(ctrlr);
+        self.shared.index.store(index);
+        self.shared.failed.store(false);
+        prev
+    }
+}
+
+impl Drop for StandbyPath<'_> {
+    fn drop(&mut self) {
+        self.adminq_poller.stop();
+
+        let ctrlr = self.ctrlr();
+        let rc = unsafe { spdk_nvme_detach(ctrlr.as_ptr()) };
+        if rc != 0 {
+            error!(
+                "failed to detach standby NVMe controller {:p}: {}",
+                ctrlr.as_ptr(),
+                Errno::from_i32(rc.abs())
+            );
+        }
+    }
+}
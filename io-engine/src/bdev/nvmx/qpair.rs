@@ -152,6 +152,18 @@ impl QPair {
         self.inner.borrow_mut().state = state;
     }
 
+    /// Aborts all outstanding requests of the qpair without the DNR bit set,
+    /// so that they complete with a path error and can be retried via
+    /// another qpair.
+    pub(crate) fn abort_reqs_for_retry(&self) {
+        unsafe {
+            let qpair = self.as_ptr();
+            spdk_nvme_qpair_set_abort_dnr(qpair, false);
+            nvme_qpair_abort_all_queued_reqs(qpair);
+            nvme_transport_qpair_abort_reqs(qpair);
+        }
+    }
+
     /// Creates a qpair with default options for target NVMe controller.
     pub(super) fn create(
         ctrlr_handle: SpdkNvmeController,
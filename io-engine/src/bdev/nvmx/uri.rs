@@ -39,6 +39,7 @@ use crate::{
         nvmx::{
             controller,
             controller_inner::SpdkNvmeController,
+            nvme_bdev_running_config,
             NvmeControllerState,
             NVME_CONTROLLERS,
         },
@@ -50,7 +51,6 @@ use crate::{
     constants::NVME_NQN_PREFIX,
     core::MayastorEnvironment,
     ffihelper::ErrnoResult,
-    subsys::Config,
 };
 
 use super::controller::transport::NvmeTransportId;
@@ -113,16 +113,20 @@ pub struct NvmfDeviceTemplate {
     uuid: Option<uuid::Uuid>,
     /// The HostNqn to connect to the nvmf target with.
     hostnqn: Option<String>,
+    /// alternate paths (address and port) to the same subsystem, used to
+    /// fail the controller over when the current path becomes unusable
+    paths: Vec<(String, u16)>,
 }
 
 impl TryFrom<&Url> for NvmfDeviceTemplate {
     type Error = BdevError;
 
     fn try_from(url: &Url) -> Result<Self, Self::Error> {
-        let host = url.host_str().ok_or_else(|| BdevError::InvalidUri {
-            uri: url.to_string(),
-            message: String::from("missing host"),
-        })?;
+        let host =
+            uri::host_addr(url).ok_or_else(|| BdevError::InvalidUri {
+                uri: url.to_string(),
+                message: String::from("missing host"),
+            })?;
 
         let segments = uri::segments(url);
 
@@ -140,8 +144,21 @@ impl TryFrom<&Url> for NvmfDeviceTemplate {
             });
         }
 
+        // Alternate paths may be repeated, so collect them before the
+        // remaining parameters are folded into a map.
+        let paths = url
+            .query_pairs()
+            .filter(|(k, _)| k == "path")
+            .map(|(_, v)| parse_path(&v))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|message| BdevError::InvalidUri {
+                uri: url.to_string(),
+                message,
+            })?;
+
         let mut parameters: HashMap<String, String> =
             url.query_pairs().into_owned().collect();
+        parameters.remove("path");
 
         let mut prchk_flags: u32 = 0;
 
@@ -188,10 +205,24 @@ impl TryFrom<&Url> for NvmfDeviceTemplate {
             prchk_flags,
             uuid,
             hostnqn,
+            paths,
         })
     }
 }
 
+/// Parse an alternate path given as `host[:port]`.
+fn parse_path(path: &str) -> Result<(String, u16), String> {
+    let url = Url::parse(&format!("nvmf://{path}/"))
+        .map_err(|e| format!("invalid path '{path}': {e}"))?;
+
+    match uri::host_addr(&url) {
+        Some(host) if !host.is_empty() => {
+            Ok((host, url.port().unwrap_or(DEFAULT_NVMF_PORT)))
+        }
+        _ => Err(format!("missing host in path '{path}'")),
+    }
+}
+
 impl GetName for NvmfDeviceTemplate {
     fn get_name(&self) -> String {
         format!("{}n1", self.name)
@@ -203,6 +234,8 @@ pub(crate) struct NvmeControllerContext<'probe> {
     opts: NvmeControllerOpts,
     name: String,
     trid: NvmeTransportId,
+    /// Transport IDs of all paths to the controller, the primary one first.
+    paths: Vec<NvmeTransportId>,
     sender: Option<oneshot::Sender<Result<(), Errno>>>,
     receiver: Option<oneshot::Receiver<Result<(), Errno>>>,
     poller: Option<Poller<'probe>>,
@@ -211,22 +244,29 @@ pub(crate) struct NvmeControllerContext<'probe> {
 
 impl<'probe> NvmeControllerContext<'probe> {
     pub fn new(template: &NvmfDeviceTemplate) -> NvmeControllerContext {
-        let trid = controller::transport::Builder::new()
-            .with_subnqn(&template.subnqn)
-            .with_svcid(&template.port.to_string())
-            .with_traddr(&template.host)
-            .build();
+        let build_trid = |host: &str, port: u16| {
+            controller::transport::Builder::new()
+                .with_subnqn(&template.subnqn)
+                .with_svcid(&port.to_string())
+                .with_traddr(host)
+                .build()
+        };
+
+        let trid = build_trid(&template.host, template.port);
+        let paths = std::iter::once((template.host.as_str(), template.port))
+            .chain(template.paths.iter().map(|(h, p)| (h.as_str(), *p)))
+            .map(|(host, port)| build_trid(host, port))
+            .collect();
 
         // setting the HOSTNQN allows tracking who is connected to what. These
         // makes debugging connections easier in certain cases. If no
         // HOSTNQN is provided.
 
+        let device_defaults = nvme_bdev_running_config();
         let mut opts = controller::options::Builder::new()
-            .with_keep_alive_timeout_ms(
-                Config::get().nvme_bdev_opts.keep_alive_timeout_ms,
-            )
+            .with_keep_alive_timeout_ms(device_defaults.keep_alive_timeout_ms)
             .with_transport_retry_count(
-                Config::get().nvme_bdev_opts.transport_retry_count as u8,
+                device_defaults.transport_retry_count as u8,
             )
             .with_fabrics_connect_timeout_us(
                 crate::subsys::config::opts::try_from_env(
@@ -259,6 +299,7 @@ impl<'probe> NvmeControllerContext<'probe> {
         NvmeControllerContext {
             opts,
             trid,
+            paths,
             name: template.get_name(),
             sender: Some(sender),
             receiver: Some(receiver),
@@ -279,6 +320,11 @@ impl<'probe> NvmeControllerContext<'probe> {
     pub fn sender(&mut self) -> Sender<Result<(), Errno>> {
         self.sender.take().expect("no sender available")
     }
+
+    /// Take the transport IDs of all known paths to the controller.
+    pub(crate) fn take_paths(&mut self) -> Vec<NvmeTransportId> {
+        std::mem::take(&mut self.paths)
+    }
 }
 #[async_trait(?Send)]
 impl CreateDestroy for NvmfDeviceTemplate {
@@ -371,10 +417,9 @@ impl CreateDestroy for NvmfDeviceTemplate {
 
         let attach_status = receiver.await.unwrap();
 
-        // Drop attach context object transformed previously into a raw pointer.
-        unsafe {
-            drop(Box::from_raw(raw_ctx));
-        }
+        // Take back attach context object transformed previously into a raw
+        // pointer, its options are reused to attach the standby paths.
+        let context = unsafe { Box::from_raw(raw_ctx) };
 
         match attach_status {
             Err(e) => {
@@ -405,6 +450,9 @@ impl CreateDestroy for NvmfDeviceTemplate {
                 );
 
                 info!("{} NVMe controller successfully initialized", cname);
+                drop(controller);
+
+                controller::attach_standby_paths(&cname, &context.opts).await;
                 Ok(cname)
             }
         }
@@ -414,3 +462,52 @@ impl CreateDestroy for NvmfDeviceTemplate {
         controller::destroy_device(self.get_name()).await
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{parse_path, NvmfDeviceTemplate, DEFAULT_NVMF_PORT};
+    use std::convert::TryFrom;
+    use url::Url;
+
+    #[test]
+    fn test_multipath_uri() {
+        let url = Url::parse(
+            "nvmf://10.0.0.1:4420/nqn.2019-05.io.openebs:disk0\
+             ?path=10.0.1.1:4421&path=10.0.2.1&uuid=\
+             d0bb0c1d-4a15-4d7c-8d5b-f1e8d2a1a7d7",
+        )
+        .unwrap();
+        let template = NvmfDeviceTemplate::try_from(&url).unwrap();
+
+        assert_eq!(template.host, "10.0.0.1");
+        assert_eq!(template.port, 4420);
+        assert_eq!(
+            template.paths,
+            vec![
+                ("10.0.1.1".to_string(), 4421),
+                ("10.0.2.1".to_string(), DEFAULT_NVMF_PORT)
+            ]
+        );
+        assert!(template.uuid.is_some());
+    }
+
+    #[test]
+    fn test_ipv6_uri() {
+        let url = Url::parse(
+            "nvmf://[fd00::1]:4420/nqn.2019-05.io.openebs:disk0\
+             ?path=[fd00:1::1]:4421",
+        )
+        .unwrap();
+        let template = NvmfDeviceTemplate::try_from(&url).unwrap();
+
+        assert_eq!(template.host, "fd00::1");
+        assert_eq!(template.port, 4420);
+        assert_eq!(template.paths, vec![("fd00:1::1".to_string(), 4421)]);
+    }
+
+    #[test]
+    fn test_invalid_path() {
+        assert!(parse_path("").is_err());
+        assert!(parse_path("10.0.0.1:port").is_err());
+    }
+}
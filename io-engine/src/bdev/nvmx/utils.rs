@@ -4,6 +4,7 @@ use spdk_rs::libspdk::spdk_nvme_cpl;
 enum NvmeStatusCodeType {
     Generic = 0x0,
     MediaError = 0x2,
+    Path = 0x3,
 }
 
 #[derive(Debug, PartialEq)]
@@ -16,6 +17,7 @@ pub enum NvmeMediaErrorStatusCode {
 #[derive(Debug, PartialEq)]
 enum NvmeGenericCommandStatusCode {
     Success = 0x0,
+    AbortedSqDeletion = 0x8,
 }
 
 #[derive(Debug, PartialEq)]
@@ -31,6 +33,7 @@ pub enum NvmeAerType {
 #[derive(Debug, PartialEq)]
 pub enum NvmeAerInfoNotice {
     AttrChanged = 0x0,
+    AnaChange = 0x3,
 }
 
 #[derive(Debug, PartialEq)]
@@ -80,6 +83,31 @@ pub(crate) fn nvme_cpl_succeeded(cpl: *const spdk_nvme_cpl) -> bool {
         && sc == NvmeGenericCommandStatusCode::Success as u16
 }
 
+/// Check if the command failed because of the path it was submitted through,
+/// i.e. it may succeed if retried via another path to the same namespace:
+///   - Any path related status.
+///   - The command was aborted because its submission queue was deleted, which
+///     is how outstanding commands of a disconnected qpair complete.
+#[inline]
+pub(crate) fn nvme_cpl_is_path_error(cpl: *const spdk_nvme_cpl) -> bool {
+    let sct;
+    let sc;
+    let dnr;
+
+    unsafe {
+        let cplr = &(*cpl);
+        sct = cplr.__bindgen_anon_1.status.sct();
+        sc = cplr.__bindgen_anon_1.status.sc();
+        dnr = cplr.__bindgen_anon_1.status.dnr();
+    }
+
+    dnr == 0
+        && (sct == NvmeStatusCodeType::Path as u16
+            || (sct == NvmeStatusCodeType::Generic as u16
+                && sc
+                    == NvmeGenericCommandStatusCode::AbortedSqDeletion as u16))
+}
+
 /* Bit set of attributes for DATASET MANAGEMENT commands. */
 #[allow(dead_code)]
 pub enum NvmeDsmAttribute {
@@ -2,7 +2,16 @@
 
 use std::str::ParseBoolError;
 
-use url::Url;
+use url::{Host, Url};
+
+/// Returns the host of a URL as a transport address: IPv6 addresses are
+/// given without the brackets enclosing them in the URL.
+pub(crate) fn host_addr(url: &Url) -> Option<String> {
+    match url.host()? {
+        Host::Ipv6(addr) => Some(addr.to_string()),
+        host => Some(host.to_string()),
+    }
+}
 
 pub(crate) fn segments(url: &Url) -> Vec<&str> {
     if let Some(iter) = url.path_segments() {
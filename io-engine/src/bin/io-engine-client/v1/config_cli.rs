@@ -0,0 +1,135 @@
+//!
+//! methods to manage the io-engine configuration
+
+use super::context::Context;
+use crate::{context::OutputFormat, GrpcStatus};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use colored_json::ToColoredJson;
+use io_engine_api::v1 as v1rpc;
+use snafu::ResultExt;
+use tonic::Status;
+
+pub fn subcommands() -> Command {
+    let reload = Command::new("reload").about(
+        "Re-read the config file and apply the options which can be \
+         changed at runtime",
+    );
+
+    let reconcile = Command::new("reconcile")
+        .about(
+            "Reconcile the node with its state file (pools, replicas and \
+             nexuses) and report the differences",
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .action(ArgAction::SetTrue)
+                .help("Only report the differences, don't fix them"),
+        );
+
+    Command::new("config")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .about("Configuration management")
+        .subcommand(reload)
+        .subcommand(reconcile)
+}
+
+pub async fn handler(ctx: Context, matches: &ArgMatches) -> crate::Result<()> {
+    match matches.subcommand().unwrap() {
+        ("reload", args) => reload_config(ctx, args).await,
+        ("reconcile", args) => reconcile_node_state(ctx, args).await,
+        (cmd, _) => {
+            Err(Status::not_found(format!("command {cmd} does not exist")))
+                .context(GrpcStatus)
+        }
+    }
+}
+
+async fn reload_config(
+    mut ctx: Context,
+    _matches: &ArgMatches,
+) -> crate::Result<()> {
+    let response = ctx.v1.host.reload_config(()).await.context(GrpcStatus)?;
+
+    match ctx.output {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&response.get_ref())
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default => {
+            let reload = response.get_ref();
+            if reload.applied.is_empty() && reload.restart_required.is_empty() {
+                ctx.v1("No configuration changes found");
+                return Ok(());
+            }
+
+            let applied = reload
+                .applied
+                .iter()
+                .map(|o| vec![o.clone(), "applied".to_string()]);
+            let restart = reload
+                .restart_required
+                .iter()
+                .map(|o| vec![o.clone(), "restart required".to_string()]);
+
+            let hdr = vec!["OPTION", "STATUS"];
+            ctx.print_list(hdr, applied.chain(restart).collect());
+        }
+    }
+
+    Ok(())
+}
+
+async fn reconcile_node_state(
+    mut ctx: Context,
+    matches: &ArgMatches,
+) -> crate::Result<()> {
+    let response = ctx
+        .v1
+        .host
+        .reconcile_node_state(v1rpc::host::ReconcileNodeStateRequest {
+            dry_run: matches.get_flag("dry-run"),
+        })
+        .await
+        .context(GrpcStatus)?;
+
+    match ctx.output {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&response.get_ref())
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default => {
+            let drift = &response.get_ref().drift;
+            if drift.is_empty() {
+                ctx.v1("Node state matches the state file");
+                return Ok(());
+            }
+
+            let table = drift
+                .iter()
+                .map(|d| {
+                    vec![
+                        d.kind.clone(),
+                        d.name.clone(),
+                        d.reason.clone(),
+                        d.resolved.to_string(),
+                    ]
+                })
+                .collect();
+            ctx.print_list(vec!["KIND", "NAME", "REASON", "FIXED"], table);
+        }
+    }
+
+    Ok(())
+}
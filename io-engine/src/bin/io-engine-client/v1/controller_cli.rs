@@ -20,18 +20,34 @@ pub fn subcommands() -> Command {
                 .help("name of the controller"),
         );
 
+    let health = Command::new("health")
+        .about("Display SMART/health data of NVMe devices")
+        .arg(
+            Arg::new("name")
+                .required(false)
+                .help("name of the device, all NVMe devices if omitted"),
+        )
+        .arg(
+            Arg::new("max-errors")
+                .long("max-errors")
+                .value_parser(clap::value_parser!(u32))
+                .help("maximum number of error log entries to display"),
+        );
+
     Command::new("controller")
         .subcommand_required(true)
         .arg_required_else_help(true)
         .about("NVMe controllers")
         .subcommand(list)
         .subcommand(stats)
+        .subcommand(health)
 }
 
 pub async fn handler(ctx: Context, matches: &ArgMatches) -> crate::Result<()> {
     match matches.subcommand().unwrap() {
         ("list", args) => list_controllers(ctx, args).await,
         ("stats", args) => controller_stats(ctx, args).await,
+        ("health", args) => controller_health(ctx, args).await,
         (cmd, _) => {
             Err(Status::not_found(format!("command {cmd} does not exist")))
                 .context(GrpcStatus)
@@ -125,6 +141,77 @@ async fn controller_stats(
     Ok(())
 }
 
+async fn controller_health(
+    mut ctx: Context,
+    matches: &ArgMatches,
+) -> crate::Result<()> {
+    let response = ctx
+        .v1
+        .host
+        .get_nvme_health(v1rpc::host::GetNvmeHealthRequest {
+            name: matches.get_one::<String>("name").cloned(),
+            max_errors: matches.get_one::<u32>("max-errors").copied(),
+        })
+        .await
+        .context(GrpcStatus)?;
+
+    match ctx.output {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&response.get_ref())
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default => {
+            let devices = &response.get_ref().devices;
+            if devices.is_empty() {
+                ctx.v1("No NVMe devices found");
+                return Ok(());
+            }
+
+            let table = devices
+                .iter()
+                .map(|d| {
+                    let smart = d.smart.clone().unwrap_or_default();
+                    let warnings = if smart.critical_warnings.is_empty() {
+                        "-".to_string()
+                    } else {
+                        smart.critical_warnings.join(", ")
+                    };
+                    vec![
+                        d.name.clone(),
+                        // Reported in Kelvin.
+                        format!("{}C", smart.temperature as i64 - 273),
+                        format!("{}%", smart.available_spare),
+                        format!("{}%", smart.percentage_used),
+                        smart.media_errors.to_string(),
+                        smart.num_error_log_entries.to_string(),
+                        smart.power_on_hours.to_string(),
+                        warnings,
+                    ]
+                })
+                .collect();
+
+            let hdr = vec![
+                "NAME",
+                ">TEMP",
+                ">SPARE",
+                ">USED",
+                ">MEDIA_ERRORS",
+                ">ERROR_LOG",
+                ">POWER_ON_HOURS",
+                "CRITICAL_WARNINGS",
+            ];
+            ctx.print_list(hdr, table);
+        }
+    }
+
+    Ok(())
+}
+
 async fn list_controllers(
     mut ctx: Context,
     _matches: &ArgMatches,
@@ -1,4 +1,5 @@
 pub mod bdev_cli;
+mod config_cli;
 pub mod controller_cli;
 pub mod device_cli;
 pub mod jsonrpc_cli;
@@ -78,6 +79,7 @@ pub(super) async fn main_() -> crate::Result<()> {
         .subcommand(snapshot_cli::subcommands())
         .subcommand(jsonrpc_cli::subcommands())
         .subcommand(controller_cli::subcommands())
+        .subcommand(config_cli::subcommands())
         .subcommand(test_cli::subcommands())
         .subcommand(stats_cli::subcommands())
         .subcommand_required(true)
@@ -102,6 +104,7 @@ pub(super) async fn main_() -> crate::Result<()> {
         ("snapshot", args) => snapshot_cli::handler(ctx, args).await,
         ("stats", args) => stats_cli::handler(ctx, args).await,
         ("controller", args) => controller_cli::handler(ctx, args).await,
+        ("config", args) => config_cli::handler(ctx, args).await,
         ("jsonrpc", args) => jsonrpc_cli::json_rpc_call(ctx, args).await,
         ("test", args) => test_cli::handler(ctx, args).await,
         _ => panic!("Command not found"),
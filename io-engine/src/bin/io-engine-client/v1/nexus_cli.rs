@@ -8,7 +8,15 @@ use crate::{
 use byte_unit::Byte;
 use clap::{Arg, ArgMatches, Command};
 use colored_json::ToColoredJson;
-use io_engine_api::{v1, v1::nexus::NvmeReservation};
+use io_engine_api::{
+    v1,
+    v1::nexus::{
+        NexusCachePolicy,
+        NexusCacheSpec,
+        NexusLayout,
+        NvmeReservation,
+    },
+};
 use snafu::ResultExt;
 use std::convert::TryFrom;
 use tonic::{Code, Status};
@@ -86,6 +94,51 @@ pub fn subcommands() -> Command {
                 .default_value("")
                 .long("nexus-info-key")
                 .help("Key used to persist the NexusInfo structure to the persistent store"),
+        )
+        .arg(
+            Arg::new("layout")
+                .required(false)
+                .default_value("mirror")
+                .value_parser(["mirror", "striped", "striped-mirror"])
+                .long("layout")
+                .help("Data layout of the nexus"),
+        )
+        .arg(
+            Arg::new("stripe-size")
+                .required(false)
+                .default_value("64KiB")
+                .long("stripe-size")
+                .help("Stripe size with optional unit suffix, for striped layouts"),
+        )
+        .arg(
+            Arg::new("copies")
+                .required(false)
+                .value_parser(clap::value_parser!(u32))
+                .default_value("2")
+                .long("copies")
+                .help("Number of children in each mirror set, for the striped-mirror layout"),
+        )
+        .arg(
+            Arg::new("cache")
+                .required(false)
+                .long("cache")
+                .help("URI of a local device caching the nexus data"),
+        )
+        .arg(
+            Arg::new("cache-policy")
+                .required(false)
+                .default_value("write-back")
+                .value_parser(["write-back", "write-through"])
+                .long("cache-policy")
+                .help("Write policy of the cache"),
+        )
+        .arg(
+            Arg::new("pi-policy")
+                .required(false)
+                .default_value("verify")
+                .value_parser(["passthrough", "verify", "generate"])
+                .long("pi-policy")
+                .help("Handling of the protection information of the blocks"),
         );
 
     let destroy = Command::new("destroy")
@@ -95,6 +148,12 @@ pub fn subcommands() -> Command {
                 .required(true)
                 .index(1)
                 .help("uuid for the nexus"),
+        )
+        .arg(
+            Arg::new("purge")
+                .long("purge")
+                .action(clap::ArgAction::SetTrue)
+                .help("delete the state kept for the next nexus of the volume"),
         );
 
     let shutdown = Command::new("shutdown")
@@ -164,6 +223,12 @@ pub fn subcommands() -> Command {
                 .default_value("false")
                 .index(3)
                 .help("specify if a rebuild job runs automatically"),
+        )
+        .arg(
+            Arg::new("replace")
+                .required(false)
+                .long("replace")
+                .help("uri of the child whose stripe set the child joins"),
         );
 
     let remove = Command::new("remove")
@@ -181,6 +246,28 @@ pub fn subcommands() -> Command {
                 .help("uri of child to remove"),
         );
 
+    let spares = Command::new("spares")
+        .about("set the spare replicas used in place of retired children")
+        .arg(
+            Arg::new("uuid")
+                .required(true)
+                .index(1)
+                .help("uuid for the nexus"),
+        )
+        .arg(
+            Arg::new("policy")
+                .required(true)
+                .index(2)
+                .value_parser(["manual", "replace", "replace_and_remove"])
+                .help("policy for the use of the spares"),
+        )
+        .arg(
+            Arg::new("uris")
+                .index(3)
+                .action(clap::ArgAction::Append)
+                .help("uris of the spares, in order of use"),
+        );
+
     let list = Command::new("list").about("list all nexus devices").arg(
         Arg::new("children")
             .short('c')
@@ -216,6 +303,7 @@ pub fn subcommands() -> Command {
         .subcommand(publish)
         .subcommand(add)
         .subcommand(remove)
+        .subcommand(spares)
         .subcommand(unpublish)
         .subcommand(ana_state)
         .subcommand(list)
@@ -237,6 +325,7 @@ pub async fn handler(ctx: Context, matches: &ArgMatches) -> crate::Result<()> {
         ("ana_state", args) => nexus_nvme_ana_state(ctx, args).await,
         ("add", args) => nexus_add(ctx, args).await,
         ("remove", args) => nexus_remove(ctx, args).await,
+        ("spares", args) => nexus_spares(ctx, args).await,
         ("child", args) => nexus_child_cli::handler(ctx, args).await,
         (cmd, _) => {
             Err(Status::not_found(format!("command {cmd} does not exist")))
@@ -317,6 +406,40 @@ async fn nexus_create(
         _ => None,
     };
 
+    let layout = match matches.get_one::<String>("layout").unwrap().as_str() {
+        "striped" => NexusLayout::Striped,
+        "striped-mirror" => NexusLayout::StripedMirror,
+        _ => NexusLayout::Mirror,
+    } as i32;
+    let stripe_size =
+        parse_size(matches.get_one::<String>("stripe-size").unwrap())
+            .map_err(|s| {
+                Status::invalid_argument(format!("Bad stripe size '{s}'"))
+            })
+            .context(GrpcStatus)?
+            .get_bytes() as u64;
+    let mirror_copies = *matches.get_one::<u32>("copies").unwrap();
+    let cache = matches
+        .get_one::<String>("cache")
+        .map(|uri| NexusCacheSpec {
+            uri: uri.clone(),
+            policy: match matches
+                .get_one::<String>("cache-policy")
+                .unwrap()
+                .as_str()
+            {
+                "write-through" => NexusCachePolicy::WriteThrough,
+                _ => NexusCachePolicy::WriteBack,
+            } as i32,
+            destage_interval_ms: 0,
+        });
+    let pi_policy =
+        match matches.get_one::<String>("pi-policy").unwrap().as_str() {
+            "passthrough" => NexusPiPolicy::Passthrough,
+            "generate" => NexusPiPolicy::Generate,
+            _ => NexusPiPolicy::Verify,
+        } as i32;
+
     let response = ctx
         .v1
         .nexus
@@ -332,6 +455,11 @@ async fn nexus_create(
             nexus_info_key,
             resv_type,
             preempt_policy: 0,
+            layout,
+            stripe_size,
+            mirror_copies,
+            cache,
+            pi_policy,
         })
         .await
         .context(GrpcStatus)?;
@@ -391,12 +519,14 @@ async fn nexus_destroy(
     matches: &ArgMatches,
 ) -> crate::Result<()> {
     let uuid = matches.get_one::<String>("uuid").unwrap().to_string();
+    let purge = matches.get_flag("purge");
 
     let _response = ctx
         .v1
         .nexus
         .destroy_nexus(v1::nexus::DestroyNexusRequest {
             uuid: uuid.clone(),
+            purge,
         })
         .await
         .context(GrpcStatus)?;
@@ -809,6 +939,7 @@ async fn nexus_add(
         .unwrap()
         .parse::<bool>()
         .unwrap_or(false);
+    let replace_uri = matches.get_one::<String>("replace").cloned();
 
     let response = ctx
         .v1
@@ -817,6 +948,7 @@ async fn nexus_add(
             uuid: uuid.clone(),
             uri,
             norebuild,
+            replace_uri,
         })
         .await
         .context(GrpcStatus)?;
@@ -884,6 +1016,66 @@ async fn nexus_remove(
     Ok(())
 }
 
+async fn nexus_spares(
+    mut ctx: Context,
+    matches: &ArgMatches,
+) -> crate::Result<()> {
+    let uuid = matches
+        .get_one::<String>("uuid")
+        .ok_or_else(|| ClientError::MissingValue {
+            field: "uuid".to_string(),
+        })?
+        .to_string();
+    let policy = match matches
+        .get_one::<String>("policy")
+        .map(|s| s.as_str())
+        .unwrap_or_default()
+    {
+        "replace" => v1::nexus::SparePolicy::Replace,
+        "replace_and_remove" => v1::nexus::SparePolicy::ReplaceAndRemove,
+        _ => v1::nexus::SparePolicy::Manual,
+    };
+    let uris = matches
+        .get_many::<String>("uris")
+        .unwrap_or_default()
+        .cloned()
+        .collect();
+
+    let response = ctx
+        .v1
+        .nexus
+        .set_nexus_spares(v1::nexus::SetNexusSparesRequest {
+            uuid,
+            uris,
+            policy: policy as i32,
+        })
+        .await
+        .context(GrpcStatus)?;
+
+    match ctx.output {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(response.get_ref())
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default => {
+            let spares = response
+                .get_ref()
+                .nexus
+                .as_ref()
+                .map(|n| n.spares.clone())
+                .unwrap_or_default();
+            println!("Nexus spares: {spares:?}");
+        }
+    };
+
+    Ok(())
+}
+
 fn ana_state_idx_to_str(idx: i32) -> &'static str {
     match v1::nexus::NvmeAnaState::try_from(idx).unwrap() {
         v1::nexus::NvmeAnaState::NvmeAnaInvalidState => "invalid",
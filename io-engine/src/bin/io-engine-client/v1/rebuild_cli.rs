@@ -420,6 +420,7 @@ async fn history(mut ctx: Context, matches: &ArgMatches) -> crate::Result<()> {
                         r.is_partial.to_string(),
                         r.start_time.as_ref().unwrap().to_string(),
                         r.end_time.as_ref().unwrap().to_string(),
+                        r.error.clone(),
                     ]
                 })
                 .collect();
@@ -435,6 +436,7 @@ async fn history(mut ctx: Context, matches: &ArgMatches) -> crate::Result<()> {
                     ">PARTIAL",
                     "START",
                     "END",
+                    "ERROR",
                 ],
                 table,
             );
@@ -508,6 +510,30 @@ async fn stats(mut ctx: Context, matches: &ArgMatches) -> crate::Result<()> {
                     response.tasks_active.to_string(),
                 ]],
             );
+            if !response.sources.is_empty() {
+                ctx.print_list(
+                    vec![
+                        "SOURCE",
+                        ">BLK_READ",
+                        ">THROUGHPUT (B/s)",
+                        ">READ_FAILURES",
+                        "FAILED",
+                    ],
+                    response
+                        .sources
+                        .iter()
+                        .map(|s| {
+                            vec![
+                                s.uri.clone(),
+                                s.blocks_read.to_string(),
+                                s.throughput.to_string(),
+                                s.read_failures.to_string(),
+                                s.failed.to_string(),
+                            ]
+                        })
+                        .collect(),
+                );
+            }
         }
     };
 
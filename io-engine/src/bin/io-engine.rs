@@ -17,6 +17,7 @@ use io_engine::{
         util::uring,
     },
     core::{
+        configure_reactor_monitor,
         device_monitor_loop,
         diagnostics::process_diagnostics_cli,
         lock::{
@@ -24,7 +25,6 @@ use io_engine::{
             ResourceLockManager,
             ResourceLockManagerConfig,
         },
-        reactor_monitor_loop,
         runtime,
         MayastorCliArgs,
         MayastorEnvironment,
@@ -33,9 +33,10 @@ use io_engine::{
     },
     eventing::Event,
     grpc,
+    host::nvme_health::nvme_health_monitor_loop,
     logger,
     persistent_store::PersistentStoreBuilder,
-    subsys::Registration,
+    subsys::{reload_on_sighup, Config, Registration},
 };
 use version_info::fmt_package_info;
 
@@ -68,9 +69,15 @@ fn start_tokio_runtime(args: &MayastorCliArgs) {
     let ps_endpoint = args.ps_endpoint.clone();
     let ps_timeout = args.ps_timeout;
     let ps_retries = args.ps_retries;
+    let nvme_health_interval = args.nvme_health_interval;
 
-    let reactor_freeze_detection = args.reactor_freeze_detection;
-    let reactor_freeze_timeout = args.reactor_freeze_timeout;
+    // Reactor freeze detection can be enabled either via the command line or
+    // via the config file.
+    let freeze_opts = &Config::get().reactor_freeze_opts;
+    let reactor_freeze_detection = args.reactor_freeze_detection
+        || freeze_opts.detection.unwrap_or_default();
+    let reactor_freeze_timeout =
+        args.reactor_freeze_timeout.or(freeze_opts.timeout);
 
     // Enable partial rebuild.
     if let Ok(v) = std::env::var("NEXUS_PARTIAL_REBUILD") {
@@ -145,11 +152,15 @@ fn start_tokio_runtime(args: &MayastorCliArgs) {
             }
 
             runtime::spawn(device_monitor_loop());
+            runtime::spawn(nvme_health_monitor_loop(nvme_health_interval));
 
             // Launch reactor health monitor if diagnostics is enabled.
-            if reactor_freeze_detection {
-                runtime::spawn(reactor_monitor_loop(reactor_freeze_timeout));
-            }
+            configure_reactor_monitor(
+                reactor_freeze_detection,
+                reactor_freeze_timeout,
+            );
+
+            runtime::spawn(reload_on_sighup());
 
             futures.push(
                 grpc::MayastorGrpcServer::run(
@@ -260,9 +271,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // automatically. trace maps to debug at FFI level. If RUST_LOG is
     // passed, we will use it regardless.
     if !args.log_components.is_empty() {
-        logger::init_ex("TRACE", log_format, args.events_url.clone());
+        logger::init_ex(
+            "TRACE",
+            log_format,
+            args.events_url.clone(),
+            args.tracing_url.clone(),
+        );
     } else {
-        logger::init_ex("INFO", log_format, args.events_url.clone());
+        logger::init_ex(
+            "INFO",
+            log_format,
+            args.events_url.clone(),
+            args.tracing_url.clone(),
+        );
     }
 
     info!("{}", fmt_package_info!());
@@ -311,5 +332,6 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     ms.fini();
     ms.event(EventAction::Start).generate();
+    logger::fini();
     Ok(())
 }
@@ -12,3 +12,7 @@ pub const EVENTING_TARGET: &str = "mbus-events-target";
 
 /// Service/ source component generating events for eventing.
 pub const SERVICE_NAME: &str = "io-engine";
+
+/// Target of the spans exported via OpenTelemetry.
+/// Such spans are not printed by the regular log formatter.
+pub const TRACING_TARGET: &str = "otel-trace-target";
@@ -3,6 +3,7 @@ use super::{
     DeviceEventSink,
     IoCompletionStatus,
     IoType,
+    ProtectionInfo,
     SnapshotParams,
 };
 
@@ -13,7 +14,7 @@ use futures::channel::oneshot;
 use merge::Merge;
 use nix::errno::Errno;
 use spdk_rs::ffihelper::{cb_arg, done_cb};
-use std::os::raw::c_void;
+use std::{ops::Range, os::raw::c_void};
 use uuid::Uuid;
 
 /// Structure representing Bdev Io Stats.
@@ -101,6 +102,25 @@ pub trait BlockDevice {
         &self,
         listener: DeviceEventSink,
     ) -> Result<(), CoreError>;
+
+    /// Returns the metadata and protection information format of the device.
+    fn protection_info(&self) -> ProtectionInfo {
+        ProtectionInfo::default()
+    }
+
+    /// Returns the ranges of blocks within the given range which are
+    /// allocated on the device, or None if the device doesn't track block
+    /// allocation. Unallocated blocks read as zeroes.
+    fn allocated_ranges(&self, _range: Range<u64>) -> Option<Vec<Range<u64>>> {
+        None
+    }
+
+    /// Returns the identifier of this instance of the device, which differs
+    /// from the one of a device since recreated with the same UUID, or None
+    /// if the device doesn't have one.
+    fn instance_id(&self) -> Option<u64> {
+        None
+    }
 }
 
 /// Core trait that represents a descriptor for an opened block device.
@@ -406,6 +426,33 @@ pub trait BlockDeviceHandle {
         cb_arg: IoCompletionCallbackArg,
     ) -> Result<(), CoreError>;
 
+    /// Submits a write zeroes request to the block device.
+    ///
+    /// Operation is performed asynchronously; I/O completion status is wrapped
+    /// into `CoreError::WriteZeroesFailed` in the case of failure.
+    async fn write_zeroes_async(
+        &self,
+        offset_blocks: u64,
+        num_blocks: u64,
+    ) -> Result<(), CoreError> {
+        let (s, r) = oneshot::channel::<IoCompletionStatus>();
+
+        self.write_zeroes(
+            offset_blocks,
+            num_blocks,
+            block_device_io_completion,
+            cb_arg(s),
+        )?;
+
+        match r.await.expect("Failed awaiting at write_zeroes()") {
+            IoCompletionStatus::Success => Ok(()),
+            _ => Err(CoreError::WriteZeroesFailed {
+                offset: offset_blocks,
+                len: num_blocks,
+            }),
+        }
+    }
+
     // NVMe only.
 
     /// TODO
@@ -501,6 +548,24 @@ pub trait BlockDeviceHandle {
         cb_arg: IoCompletionCallbackArg,
     ) -> Result<(), CoreError>;
 
+    /// Flushes the io in buffer to disk.
+    ///
+    /// Operation is performed asynchronously; I/O completion status is wrapped
+    /// into `CoreError::DeviceFlush` in the case of failure.
+    async fn flush_io_async(&self) -> Result<(), CoreError> {
+        let (s, r) = oneshot::channel::<IoCompletionStatus>();
+
+        self.flush_io(block_device_io_completion, cb_arg(s))?;
+
+        match r.await.expect("Failed awaiting at flush_io()") {
+            IoCompletionStatus::Success => Ok(()),
+            _ => Err(CoreError::DeviceFlush {
+                source: Errno::EIO,
+                name: self.get_device().device_name(),
+            }),
+        }
+    }
+
     /// Determines if the underlying controller is failed.
     fn is_ctrlr_failed(&self) -> bool {
         false
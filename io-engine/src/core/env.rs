@@ -1,7 +1,7 @@
 use std::{
     env,
     ffi::CString,
-    net::Ipv4Addr,
+    net::IpAddr,
     os::raw::{c_char, c_void},
     pin::Pin,
     str::FromStr,
@@ -62,14 +62,19 @@ use crate::{
         Mthread,
     },
     eventing::{
+        event_ring::DEFAULT_EVENTS_BUFFER_SIZE,
         io_engine_events::io_engine_stop_event_meta,
         Event,
         EventWithMeta,
     },
     grpc,
-    grpc::MayastorGrpcServer,
+    grpc::{
+        audit::{DEFAULT_AUDIT_LOG_MAX_FILES, DEFAULT_AUDIT_LOG_MAX_SIZE},
+        MayastorGrpcServer,
+    },
     logger,
     persistent_store::PersistentStoreBuilder,
+    rebuild::HistoryRetention,
     subsys::{
         self,
         config::opts::TARGET_CRDT_LEN,
@@ -204,7 +209,10 @@ pub struct MayastorCliArgs {
     /// Number of entries in memory pool for NVMe controller I/O contexts
     pub nvme_ctl_io_ctx_pool_size: u64,
     #[clap(short = 'T', long = "tgt-iface", env = "NVMF_TGT_IFACE")]
-    /// NVMF target interface (ip, mac, name or subnet).
+    /// NVMF target interfaces (ip, mac, name or subnet), comma-separated.
+    /// The target listens on the matched address of the interfaces given by
+    /// ip or subnet, and on both the IPv4 and IPv6 addresses of the ones
+    /// given by mac or name.
     pub nvmf_tgt_interface: Option<String>,
     /// NVMF target Command Retry Delay in x100 ms (single integer or three
     /// comma-separated integers). First value is used for errors on nexus
@@ -249,6 +257,41 @@ pub struct MayastorCliArgs {
     /// Events message-bus endpoint url.
     #[clap(long)]
     pub events_url: Option<url::Url>,
+    /// Number of recent events kept in memory, to be replayed to the event
+    /// watchers reconnecting.
+    #[clap(long, default_value_t = DEFAULT_EVENTS_BUFFER_SIZE)]
+    pub events_buffer_size: usize,
+    /// OpenTelemetry collector endpoint url (OTLP over gRPC), e.g.
+    /// `http://collector:4317`, or `https://` for TLS.
+    /// Distributed tracing of gRPC operations is disabled if not set.
+    #[clap(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    pub tracing_url: Option<url::Url>,
+    /// PEM file with the certificate chain of the gRPC server.
+    /// The gRPC server uses TLS when set, along with its private key.
+    #[clap(long, requires = "grpc_tls_key")]
+    pub grpc_tls_cert: Option<String>,
+    /// PEM file with the private key of the gRPC server.
+    #[clap(long, requires = "grpc_tls_cert")]
+    pub grpc_tls_key: Option<String>,
+    /// PEM file with the CA certificates which must have signed the
+    /// certificates of the gRPC clients. The gRPC clients must present a
+    /// certificate when set.
+    #[clap(long, requires = "grpc_tls_cert")]
+    pub grpc_tls_client_ca: Option<String>,
+    /// YAML file giving the roles of the gRPC clients, and the gRPC methods
+    /// each role may call. All the gRPC methods may be called if not set.
+    #[clap(long)]
+    pub grpc_authz_policy: Option<String>,
+    /// File the audit log of the mutating gRPC calls is appended to. The
+    /// recent entries are kept in memory only if not set.
+    #[clap(long)]
+    pub audit_log: Option<String>,
+    /// Size in MiB beyond which the audit log file is rotated.
+    #[clap(long, default_value_t = DEFAULT_AUDIT_LOG_MAX_SIZE)]
+    pub audit_log_max_size: u64,
+    /// Number of rotated audit log files kept.
+    #[clap(long, default_value_t = DEFAULT_AUDIT_LOG_MAX_FILES)]
+    pub audit_log_max_files: usize,
     /// Enables additional nexus I/O channel debugging.
     #[clap(
         long = "enable-channel-dbg",
@@ -275,6 +318,30 @@ pub struct MayastorCliArgs {
     /// Enables globally blob store cluster release on unmap.
     #[clap(long, env = "ENABLE_BS_CLUSTER_UNMAP", hide = true)]
     pub bs_cluster_unmap: bool,
+    /// Maximum number of rebuild history records kept per nexus.
+    #[clap(
+        long,
+        env = "REBUILD_HISTORY_MAX_RECORDS",
+        default_value_t = HistoryRetention::DEFAULT_MAX_RECORDS
+    )]
+    pub rebuild_history_max_records: usize,
+    /// Maximum age of the rebuild history records, e.g. "7days".
+    /// Records are kept regardless of their age if not set.
+    #[clap(
+        long,
+        env = "REBUILD_HISTORY_MAX_AGE",
+        value_parser = humantime::parse_duration
+    )]
+    pub rebuild_history_max_age: Option<Duration>,
+    /// Interval at which the critical warnings of the NVMe devices are
+    /// evaluated, e.g. "5m". A zero interval disables the evaluation.
+    #[clap(
+        long,
+        env = "NVME_HEALTH_INTERVAL",
+        default_value = "5m",
+        value_parser = humantime::parse_duration
+    )]
+    pub nvme_health_interval: Duration,
 }
 
 fn delay_compat(s: &str) -> Result<bool, String> {
@@ -338,12 +405,24 @@ impl Default for MayastorCliArgs {
             skip_sig_handler: false,
             enable_io_all_thrd_nexus_channels: false,
             events_url: None,
+            events_buffer_size: DEFAULT_EVENTS_BUFFER_SIZE,
+            tracing_url: None,
+            grpc_tls_cert: None,
+            grpc_tls_key: None,
+            grpc_tls_client_ca: None,
+            grpc_authz_policy: None,
+            audit_log: None,
+            audit_log_max_size: DEFAULT_AUDIT_LOG_MAX_SIZE,
+            audit_log_max_files: DEFAULT_AUDIT_LOG_MAX_FILES,
             enable_nexus_channel_debug: false,
             lvm: false,
             snap_rebuild: false,
             developer_delay: false,
             rdma: false,
             bs_cluster_unmap: false,
+            rebuild_history_max_records: HistoryRetention::DEFAULT_MAX_RECORDS,
+            rebuild_history_max_age: None,
+            nvme_health_interval: Duration::from_secs(300),
         }
     }
 }
@@ -426,6 +505,24 @@ pub struct MayastorEnvironment {
     developer_delay: bool,
     rdma: bool,
     bs_cluster_unmap: bool,
+    /// Retention policy of the rebuild history of the nexuses.
+    pub rebuild_history: HistoryRetention,
+    /// Number of recent events kept in memory.
+    pub events_buffer_size: usize,
+    /// TLS certificate chain of the gRPC server.
+    pub grpc_tls_cert: Option<String>,
+    /// TLS private key of the gRPC server.
+    pub grpc_tls_key: Option<String>,
+    /// CA certificates of the gRPC clients.
+    pub grpc_tls_client_ca: Option<String>,
+    /// Authorization policy of the gRPC calls.
+    pub grpc_authz_policy: Option<String>,
+    /// Audit log file of the mutating gRPC calls.
+    pub audit_log: Option<String>,
+    /// Size in MiB beyond which the audit log file is rotated.
+    pub audit_log_max_size: u64,
+    /// Number of rotated audit log files kept.
+    pub audit_log_max_files: usize,
 }
 
 impl Default for MayastorEnvironment {
@@ -476,6 +573,15 @@ impl Default for MayastorEnvironment {
             developer_delay: false,
             rdma: false,
             bs_cluster_unmap: false,
+            rebuild_history: HistoryRetention::default(),
+            events_buffer_size: DEFAULT_EVENTS_BUFFER_SIZE,
+            grpc_tls_cert: None,
+            grpc_tls_key: None,
+            grpc_tls_client_ca: None,
+            grpc_authz_policy: None,
+            audit_log: None,
+            audit_log_max_size: DEFAULT_AUDIT_LOG_MAX_SIZE,
+            audit_log_max_files: DEFAULT_AUDIT_LOG_MAX_FILES,
         }
     }
 }
@@ -513,6 +619,8 @@ async fn do_shutdown(arg: *mut c_void) {
     if let Some(reg) = Registration::get() {
         reg.fini();
     }
+    crate::rebuild::shutdown_copy_jobs().await;
+    crate::rebuild::shutdown_replication_jobs().await;
     nexus::shutdown_nexuses().await;
     crate::rebuild::shutdown_snapshot_rebuilds().await;
     crate::lvs::Lvs::export_all().await;
@@ -619,6 +727,18 @@ impl MayastorEnvironment {
             developer_delay: args.developer_delay,
             rdma: args.rdma,
             bs_cluster_unmap: args.bs_cluster_unmap,
+            rebuild_history: HistoryRetention {
+                max_records: args.rebuild_history_max_records,
+                max_age: args.rebuild_history_max_age,
+            },
+            events_buffer_size: args.events_buffer_size,
+            grpc_tls_cert: args.grpc_tls_cert,
+            grpc_tls_key: args.grpc_tls_key,
+            grpc_tls_client_ca: args.grpc_tls_client_ca,
+            grpc_authz_policy: args.grpc_authz_policy,
+            audit_log: args.audit_log,
+            audit_log_max_size: args.audit_log_max_size,
+            audit_log_max_files: args.audit_log_max_files,
             enable_io_all_thrd_nexus_channels: args
                 .enable_io_all_thrd_nexus_channels,
             ..Default::default()
@@ -821,17 +941,19 @@ impl MayastorEnvironment {
         }
     }
 
-    /// Returns NVMF target's IP address.
-    pub(crate) fn get_nvmf_tgt_ip() -> Result<String, String> {
-        static TGT_IP: OnceCell<String> = OnceCell::new();
-        TGT_IP
+    /// Returns the IP addresses the NVMF target listens on.
+    pub(crate) fn get_nvmf_tgt_ips() -> Result<Vec<IpAddr>, String> {
+        static TGT_IPS: OnceCell<Vec<IpAddr>> = OnceCell::new();
+        TGT_IPS
             .get_or_try_init(|| {
                 match Self::global_or_default().nvmf_tgt_interface {
-                    Some(ref iface) => Self::detect_nvmf_tgt_iface_ip(iface),
-                    None => Self::detect_pod_ip(),
+                    Some(ref ifaces) => {
+                        Self::detect_nvmf_tgt_ifaces_ips(ifaces)
+                    }
+                    None => Self::detect_pod_ips(),
                 }
             })
-            .map(|s| s.clone())
+            .map(|v| v.clone())
     }
 
     /// Check if RDMA needs to be enabled for Mayastor nvmf target.
@@ -839,76 +961,52 @@ impl MayastorEnvironment {
         self.rdma
     }
 
-    /// Detects IP address for NVMF target by the interface specified in CLI
-    /// arguments.
-    fn detect_nvmf_tgt_iface_ip(iface: &str) -> Result<String, String> {
-        info!(
-            "Detecting IP address for NVMF target network interface \
-                specified as '{}' ...",
-            iface
-        );
-
-        let (cls, name) = match iface.split_once(':') {
-            Some(p) => p,
-            None => ("name", iface),
-        };
+    /// Detects the IP addresses for NVMF target of the comma-separated list
+    /// of interfaces specified in CLI arguments.
+    fn detect_nvmf_tgt_ifaces_ips(ifaces: &str) -> Result<Vec<IpAddr>, String> {
+        let mut ips = Vec::new();
 
-        let pred: Box<dyn Fn(&nic::Interface) -> bool> = match cls {
-            "name" => Box::new(|n| n.name == name),
-            "mac" => {
-                let mac = Some(name.parse::<nic::MacAddr>()?);
-                Box::new(move |n| n.mac == mac)
+        for iface in ifaces.split(',').map(str::trim) {
+            if iface.is_empty() {
+                continue;
             }
-            "ip" => {
-                let addr = Some(nic::parse_ipv4(name)?);
-                Box::new(move |n| n.inet.addr == addr)
-            }
-            "subnet" => {
-                let (subnet, mask) = nic::parse_ipv4_subnet(name)?;
-                Box::new(move |n| n.ipv4_subnet_eq(subnet, mask))
-            }
-            _ => {
-                return Err(format!(
-                    "Invalid NVMF target interface: '{iface}'",
-                ));
+            for ip in Self::detect_nvmf_tgt_iface_ips(iface)? {
+                if !ips.contains(&ip) {
+                    ips.push(ip);
+                }
             }
-        };
-
-        let mut nics: Vec<_> =
-            nic::find_all_nics().into_iter().filter(pred).collect();
-
-        if nics.is_empty() {
-            return Err(format!(
-                "Network interface matching '{iface}' not found",
-            ));
         }
 
-        if nics.len() > 1 {
-            return Err(format!(
-                "Multiple network interfaces that \
-                match '{iface}' are found",
-            ));
+        if ips.is_empty() {
+            return Err(format!("Invalid NVMF target interfaces: '{ifaces}'"));
         }
 
-        let res = nics.pop().unwrap();
+        Ok(ips)
+    }
+
+    /// Detects IP addresses for NVMF target by the interface specified in
+    /// CLI arguments: both the IPv4 and IPv6 addresses of an interface given
+    /// by name or MAC, or the one address matched otherwise.
+    fn detect_nvmf_tgt_iface_ips(iface: &str) -> Result<Vec<IpAddr>, String> {
+        info!(
+            "Detecting IP address for NVMF target network interface \
+                specified as '{}' ...",
+            iface
+        );
+
+        let (res, ips) = nic::select_interface(nic::find_all_nics(), iface)?;
 
         info!(
             "NVMF target network interface '{}' matches to {}",
             iface, res
         );
 
-        if res.inet.addr.is_none() {
-            return Err(format!(
-                "Network interface '{}' has no IPv4 address configured",
-                res.name
-            ));
-        }
-
-        Ok(res.inet.addr.unwrap().to_string())
+        Ok(ips)
     }
 
-    /// Detects pod IP address.
-    fn detect_pod_ip() -> Result<String, String> {
+    /// Detects pod IP addresses: the environment variable may hold both the
+    /// IPv4 and IPv6 addresses of a dual-stack pod, comma-separated.
+    fn detect_pod_ips() -> Result<Vec<IpAddr>, String> {
         match env::var("MY_POD_IP") {
             Ok(val) => {
                 info!(
@@ -916,16 +1014,18 @@ impl MayastorEnvironment {
                         for NVMF target network interface"
                 );
 
-                if val.parse::<Ipv4Addr>().is_ok() {
-                    Ok(val)
-                } else {
-                    Err(format!(
-                        "MY_POD_IP environment variable is set to an \
-                            invalid IPv4 address: '{val}'"
-                    ))
-                }
+                val.split(',')
+                    .map(|ip| {
+                        ip.trim().parse::<IpAddr>().map_err(|_| {
+                            format!(
+                                "MY_POD_IP environment variable is set to an \
+                                invalid IP address: '{val}'"
+                            )
+                        })
+                    })
+                    .collect()
             }
-            Err(_) => Ok("127.0.0.1".to_owned()),
+            Err(_) => Ok(vec![IpAddr::from([127, 0, 0, 1])]),
         }
     }
 
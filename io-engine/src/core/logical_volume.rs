@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use crate::{core::Protocol, pool_backend::PoolBackend};
 
 /// LogicalVolume Trait Provide all the Generic Interface for a Logical Volume
@@ -31,6 +33,10 @@ pub trait LogicalVolume: std::fmt::Debug {
     /// Return the allocated size of the Logical Volume in bytes.
     fn allocated(&self) -> u64;
 
+    /// Returns the ranges of blocks within the given range which hold data
+    /// of the Logical Volume. Blocks outside of these ranges read as zeroes.
+    fn allocated_ranges(&self, range: Range<u64>) -> Vec<Range<u64>>;
+
     /// Returns Lvol disk space usage.
     fn usage(&self) -> LvolSpaceUsage;
     /// Returns the backend type which owns this Logical Volume.
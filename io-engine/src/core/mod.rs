@@ -47,7 +47,8 @@ pub use handle::{BdevHandle, UntypedBdevHandle};
 pub use io_device::IoDevice;
 pub use logical_volume::LogicalVolume;
 pub use reactor::{
-    reactor_monitor_loop,
+    configure_reactor_monitor,
+    reactor_monitor_config,
     Reactor,
     ReactorState,
     Reactors,
@@ -62,6 +63,8 @@ pub use lock::{
     ResourceSubsystem,
 };
 
+pub(crate) use protection::DifContext;
+pub use protection::{DifType, ProtectionInfo};
 pub use runtime::spawn;
 pub(crate) use segment_map::SegmentMap;
 pub use share::{
@@ -104,6 +107,7 @@ pub mod logical_volume;
 pub mod mempool;
 mod nic;
 pub mod partition;
+mod protection;
 mod reactor;
 pub mod runtime;
 pub mod segment_map;
@@ -347,6 +351,24 @@ pub enum CoreError {
     WipeFailed {
         source: wiper::Error,
     },
+    #[snafu(display("Invalid protection information: {}", reason))]
+    ProtectionInfoInvalid {
+        reason: String,
+    },
+    #[snafu(display(
+        "Protection information check failed at block {} with status {:?}: \
+        expected {:#x}, actual {:#x}",
+        offset,
+        status,
+        expected,
+        actual
+    ))]
+    ProtectionCheckFailed {
+        status: IoCompletionStatus,
+        offset: u64,
+        expected: u64,
+        actual: u64,
+    },
 }
 
 /// Represent error as Errno value.
@@ -460,6 +482,12 @@ impl ToErrno for CoreError {
             Self::WipeFailed {
                 ..
             } => Errno::EIO,
+            Self::ProtectionInfoInvalid {
+                ..
+            } => Errno::EINVAL,
+            Self::ProtectionCheckFailed {
+                ..
+            } => Errno::EILSEQ,
         }
     }
 }
@@ -4,7 +4,7 @@ use std::{
     collections::BTreeMap,
     fmt,
     fmt::{Display, Formatter},
-    net::{Ipv4Addr, Ipv6Addr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
     str::FromStr,
 };
 
@@ -152,6 +152,43 @@ impl Interface {
 
         Ipv4Addr::from(subnet) == net_addr
     }
+
+    /// Tests if the interface belongs to the given IPv6 subnet.
+    pub fn ipv6_subnet_eq(&self, net_addr: Ipv6Addr, net_mask: u128) -> bool {
+        let (addr, mask) = match (self.inet6.addr, self.inet6.netmask) {
+            (Some(addr), Some(mask)) => (addr, mask),
+            _ => return false,
+        };
+
+        let mask = u128::from(mask);
+        if mask != net_mask {
+            return false;
+        }
+
+        Ipv6Addr::from(u128::from(addr) & mask) == net_addr
+    }
+
+    /// Returns the addresses the interface is reached at: its IPv4 address
+    /// and its IPv6 one, unless the latter is a link-local address.
+    pub fn ip_addrs(&self) -> Vec<IpAddr> {
+        self.inet
+            .addr
+            .map(IpAddr::V4)
+            .into_iter()
+            .chain(
+                self.inet6
+                    .addr
+                    .filter(|a| !is_ipv6_link_local(a))
+                    .map(IpAddr::V6),
+            )
+            .collect()
+    }
+}
+
+/// Tests if an IPv6 address is a link-local one, which can't be used without
+/// a scope.
+fn is_ipv6_link_local(addr: &Ipv6Addr) -> bool {
+    addr.segments()[0] & 0xffc0 == 0xfe80
 }
 fn ipv4addr_to_libc(addr: Ipv4Addr) -> libc::in_addr {
     let octets = addr.octets();
@@ -198,7 +235,15 @@ pub fn find_all_nics() -> Vec<Interface> {
                 nic.inet.addr = Some(sock.ip().into());
             }
             if let Some(sock) = sock.as_sockaddr_in6() {
-                nic.inet6.addr = Some(sock.ip());
+                // Global addresses take precedence over the link-local one.
+                if nic.inet6.addr.map_or(true, |a| is_ipv6_link_local(&a)) {
+                    nic.inet6.addr = Some(sock.ip());
+                    nic.inet6.netmask = addr
+                        .netmask
+                        .as_ref()
+                        .and_then(|m| m.as_sockaddr_in6())
+                        .map(|m| m.ip());
+                }
             }
             if let Some(link) = sock.as_link_addr() {
                 nic.mac = link.addr().map(MacAddr::new);
@@ -209,9 +254,6 @@ pub fn find_all_nics() -> Vec<Interface> {
             if let Some(sock) = sock.as_sockaddr_in() {
                 nic.inet.netmask = Some(sock.ip().into());
             }
-            if let Some(sock) = sock.as_sockaddr_in6() {
-                nic.inet6.netmask = Some(sock.ip());
-            }
         }
     }
 
@@ -223,6 +265,11 @@ pub fn parse_ipv4(addr: &str) -> Result<Ipv4Addr, String> {
     addr.parse::<Ipv4Addr>().map_err(|e| e.to_string())
 }
 
+/// Utility to parse an IPv4 or IPv6 address string.
+pub fn parse_ip(addr: &str) -> Result<IpAddr, String> {
+    addr.parse::<IpAddr>().map_err(|e| e.to_string())
+}
+
 /// Utility to parse an IPv4 subnet string into a nix's Ipv4Addr.
 pub fn parse_ipv4_subnet(addr_str: &str) -> Result<(Ipv4Addr, u32), String> {
     let (addr, bits) = match addr_str.split_once('/') {
@@ -246,3 +293,210 @@ pub fn parse_ipv4_subnet(addr_str: &str) -> Result<(Ipv4Addr, u32), String> {
     let subnet = addr & mask;
     Ok((Ipv4Addr::from(subnet), mask))
 }
+
+/// Utility to parse an IPv6 subnet string into an address and a mask.
+pub fn parse_ipv6_subnet(addr_str: &str) -> Result<(Ipv6Addr, u128), String> {
+    let (addr, bits) = match addr_str.split_once('/') {
+        Some(p) => p,
+        None => return Err(format!("Invalid subnet: '{addr_str}'")),
+    };
+
+    let addr = u128::from(
+        addr.parse::<Ipv6Addr>()
+            .map_err(|e| format!("Invalid subnet '{addr_str}': {e}"))?,
+    );
+
+    let bits = bits
+        .parse::<u32>()
+        .map_err(|e| format!("Invalid subnet '{addr_str}': {e}"))?;
+
+    if bits > 128 {
+        return Err(format!("Invalid subnet '{addr_str}': suffix too large"));
+    }
+
+    let mask = (!0u128).checked_shl(128 - bits).unwrap_or(0);
+
+    Ok((Ipv6Addr::from(addr & mask), mask))
+}
+
+/// Selects the interface matching the given specification (IP address, MAC
+/// address, name or subnet, optionally prefixed by its class), and returns
+/// it with the addresses to listen on: the matched address for an interface
+/// given by address or subnet, or both its IPv4 and IPv6 addresses for an
+/// interface given by name or MAC address.
+pub fn select_interface(
+    nics: Vec<Interface>,
+    iface: &str,
+) -> Result<(Interface, Vec<IpAddr>), String> {
+    let (cls, name) = match iface.split_once(':') {
+        Some(("ip", ip)) => ("ip", ip),
+        Some(("subnet", subnet)) => ("subnet", subnet),
+        Some(("mac", mac)) => ("mac", mac),
+        Some(("name", name)) => ("name", name),
+        // IPv6 addresses and MAC addresses contain colons too.
+        Some(_) if parse_ip(iface).is_ok() => ("ip", iface),
+        Some(_) => ("invalid", iface),
+        None => ("name", iface),
+    };
+
+    // The address family of the matched address, if the interface is
+    // given by address or subnet.
+    let mut ipv6 = None;
+
+    let pred: Box<dyn Fn(&Interface) -> bool> = match cls {
+        "name" => Box::new(|n| n.name == name),
+        "mac" => {
+            let mac = Some(name.parse::<MacAddr>()?);
+            Box::new(move |n| n.mac == mac)
+        }
+        "ip" => match parse_ip(name)? {
+            IpAddr::V4(addr) => {
+                ipv6 = Some(false);
+                Box::new(move |n| n.inet.addr == Some(addr))
+            }
+            IpAddr::V6(addr) => {
+                ipv6 = Some(true);
+                Box::new(move |n| n.inet6.addr == Some(addr))
+            }
+        },
+        "subnet" if name.contains(':') => {
+            let (subnet, mask) = parse_ipv6_subnet(name)?;
+            ipv6 = Some(true);
+            Box::new(move |n| n.ipv6_subnet_eq(subnet, mask))
+        }
+        "subnet" => {
+            let (subnet, mask) = parse_ipv4_subnet(name)?;
+            ipv6 = Some(false);
+            Box::new(move |n| n.ipv4_subnet_eq(subnet, mask))
+        }
+        _ => {
+            return Err(format!("Invalid NVMF target interface: '{iface}'"));
+        }
+    };
+
+    let mut nics: Vec<_> = nics.into_iter().filter(pred).collect();
+
+    if nics.is_empty() {
+        return Err(format!("Network interface matching '{iface}' not found"));
+    }
+
+    if nics.len() > 1 {
+        return Err(format!(
+            "Multiple network interfaces that match '{iface}' are found"
+        ));
+    }
+
+    let res = nics.pop().unwrap();
+
+    let addrs = match ipv6 {
+        Some(false) => res.inet.addr.map(IpAddr::V4).into_iter().collect(),
+        Some(true) => res.inet6.addr.map(IpAddr::V6).into_iter().collect(),
+        None => res.ip_addrs(),
+    };
+
+    if addrs.is_empty() {
+        return Err(format!(
+            "Network interface '{}' has no IP address configured",
+            res.name
+        ));
+    }
+
+    Ok((res, addrs))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        parse_ipv4_subnet,
+        parse_ipv6_subnet,
+        select_interface,
+        InetConfig,
+        Interface,
+        MacAddr,
+    };
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    fn nics() -> Vec<Interface> {
+        vec![
+            Interface {
+                name: "eth0".to_string(),
+                inet: InetConfig {
+                    addr: Some(Ipv4Addr::new(10, 0, 0, 1)),
+                    netmask: Some(Ipv4Addr::new(255, 255, 255, 0)),
+                },
+                inet6: InetConfig {
+                    addr: Some("fd00::1".parse().unwrap()),
+                    netmask: Some("ffff:ffff:ffff:ffff::".parse().unwrap()),
+                },
+                mac: Some(MacAddr::new([0x02, 0, 0, 0, 0, 0x01])),
+            },
+            Interface {
+                name: "eth1".to_string(),
+                inet: InetConfig::default(),
+                inet6: InetConfig {
+                    addr: Some("fd01::1".parse().unwrap()),
+                    netmask: Some("ffff:ffff:ffff:ffff::".parse().unwrap()),
+                },
+                mac: Some(MacAddr::new([0x02, 0, 0, 0, 0, 0x02])),
+            },
+            Interface {
+                name: "eth2".to_string(),
+                inet: InetConfig::default(),
+                inet6: InetConfig {
+                    addr: Some("fe80::1".parse().unwrap()),
+                    netmask: Some("ffff:ffff:ffff:ffff::".parse().unwrap()),
+                },
+                mac: None,
+            },
+        ]
+    }
+
+    fn select(iface: &str) -> Result<Vec<IpAddr>, String> {
+        select_interface(nics(), iface).map(|(_, ips)| ips)
+    }
+
+    #[test]
+    fn test_parse_ipv6_subnet() {
+        let (addr, mask) = parse_ipv6_subnet("fd00:0:0:1::5/64").unwrap();
+        assert_eq!(addr, "fd00:0:0:1::".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(mask, !0u128 << 64);
+
+        let (addr, mask) = parse_ipv6_subnet("fd00::1/0").unwrap();
+        assert_eq!(addr, Ipv6Addr::UNSPECIFIED);
+        assert_eq!(mask, 0);
+
+        let (addr, mask) = parse_ipv6_subnet("fd00::1/128").unwrap();
+        assert_eq!(addr, "fd00::1".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(mask, !0u128);
+
+        assert!(parse_ipv6_subnet("fd00::1").is_err());
+        assert!(parse_ipv6_subnet("fd00::1/129").is_err());
+        assert!(parse_ipv6_subnet("10.0.0.0/8").is_err());
+        assert!(parse_ipv4_subnet("fd00::/64").is_err());
+    }
+
+    #[test]
+    fn test_select_ipv6_interface() {
+        let fd00 = IpAddr::V6("fd00::1".parse().unwrap());
+        let fd01 = IpAddr::V6("fd01::1".parse().unwrap());
+        let ipv4 = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+        // By address or subnet: only the matched address.
+        assert_eq!(select("fd00::1").unwrap(), vec![fd00]);
+        assert_eq!(select("ip:fd01::1").unwrap(), vec![fd01]);
+        assert_eq!(select("subnet:fd01::/64").unwrap(), vec![fd01]);
+        assert_eq!(select("subnet:10.0.0.0/24").unwrap(), vec![ipv4]);
+        assert_eq!(select("ip:10.0.0.1").unwrap(), vec![ipv4]);
+
+        // By name or MAC: both address families.
+        assert_eq!(select("eth0").unwrap(), vec![ipv4, fd00]);
+        assert_eq!(select("mac:02:00:00:00:00:01").unwrap(), vec![ipv4, fd00]);
+        assert_eq!(select("name:eth1").unwrap(), vec![fd01]);
+
+        // A link-local address alone is not usable.
+        assert!(select("eth2").is_err());
+        assert!(select("subnet:fd02::/64").is_err());
+        assert!(select("eth3").is_err());
+        assert!(select("fd00::/64").is_err());
+    }
+}
@@ -0,0 +1,284 @@
+//! T10 protection information (PI / DIF) of block devices.
+//!
+//! Only the block formats with the metadata interleaved with the data, i.e.
+//! extended LBAs such as 512+8 or 4096+8, are supported: these are the ones
+//! NVMe-oF namespaces expose. The I/O buffers of such devices hold the
+//! protection information of each block right along with its data, so that
+//! copying blocks from one device to another carries it over as it is.
+
+use std::fmt::{Display, Formatter};
+
+use spdk_rs::{
+    libspdk::{
+        spdk_dif_ctx,
+        spdk_dif_ctx_init,
+        spdk_dif_ctx_init_ext_opts,
+        spdk_dif_ctx_set_remapped_init_ref_tag,
+        spdk_dif_error,
+        spdk_dif_generate,
+        spdk_dif_remap_ref_tag,
+        spdk_dif_type,
+        spdk_dif_verify,
+        SPDK_DIF_APPTAG_ERROR,
+        SPDK_DIF_DISABLE,
+        SPDK_DIF_FLAGS_GUARD_CHECK,
+        SPDK_DIF_FLAGS_REFTAG_CHECK,
+        SPDK_DIF_PI_FORMAT_16,
+        SPDK_DIF_REFTAG_ERROR,
+        SPDK_DIF_TYPE1,
+        SPDK_DIF_TYPE2,
+        SPDK_DIF_TYPE3,
+        SPDK_NVME_SC_APPLICATION_TAG_CHECK_ERROR,
+        SPDK_NVME_SC_GUARD_CHECK_ERROR,
+        SPDK_NVME_SC_REFERENCE_TAG_CHECK_ERROR,
+    },
+    IoVec,
+    NvmeStatus,
+};
+
+use super::{CoreError, IoCompletionStatus};
+
+/// Type of the protection information of a block format.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DifType {
+    /// No protection information.
+    #[default]
+    Disabled,
+    /// Guard and reference tag, the reference tag being the LBA.
+    Type1,
+    /// Guard and reference tag, the reference tag being given by the command.
+    Type2,
+    /// Guard only.
+    Type3,
+}
+
+impl From<spdk_dif_type> for DifType {
+    fn from(value: spdk_dif_type) -> Self {
+        match value {
+            SPDK_DIF_TYPE1 => Self::Type1,
+            SPDK_DIF_TYPE2 => Self::Type2,
+            SPDK_DIF_TYPE3 => Self::Type3,
+            _ => Self::Disabled,
+        }
+    }
+}
+
+impl From<DifType> for spdk_dif_type {
+    fn from(value: DifType) -> Self {
+        match value {
+            DifType::Disabled => SPDK_DIF_DISABLE,
+            DifType::Type1 => SPDK_DIF_TYPE1,
+            DifType::Type2 => SPDK_DIF_TYPE2,
+            DifType::Type3 => SPDK_DIF_TYPE3,
+        }
+    }
+}
+
+impl Display for DifType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Disabled => write!(f, "disabled"),
+            Self::Type1 => write!(f, "type 1"),
+            Self::Type2 => write!(f, "type 2"),
+            Self::Type3 => write!(f, "type 3"),
+        }
+    }
+}
+
+/// Metadata and protection information format of a block device.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ProtectionInfo {
+    /// Size of the metadata of each block, in bytes.
+    pub md_len: u32,
+    /// Set if the metadata is interleaved with the data of each block.
+    pub md_interleave: bool,
+    /// Type of the protection information.
+    pub dif_type: DifType,
+    /// Set if the protection information is at the start of the metadata,
+    /// rather than at its end.
+    pub dif_is_head_of_md: bool,
+}
+
+impl ProtectionInfo {
+    /// Checks if the blocks carry protection information.
+    pub fn is_enabled(&self) -> bool {
+        self.dif_type != DifType::Disabled
+    }
+
+    /// Checks if the metadata, if any, is interleaved with the data, which
+    /// is the only format the I/O path supports.
+    pub fn is_supported(&self) -> bool {
+        self.md_len == 0 || self.md_interleave
+    }
+
+    /// Returns the SPDK checks of the protection information: the guard is
+    /// always checked, and the reference tag unless it is not defined.
+    fn check_flags(&self) -> u32 {
+        match self.dif_type {
+            DifType::Disabled => 0,
+            DifType::Type3 => SPDK_DIF_FLAGS_GUARD_CHECK,
+            _ => SPDK_DIF_FLAGS_GUARD_CHECK | SPDK_DIF_FLAGS_REFTAG_CHECK,
+        }
+    }
+}
+
+impl Display for ProtectionInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.md_len == 0 {
+            return write!(f, "no metadata");
+        }
+        write!(
+            f,
+            "{} bytes of {} metadata, protection information {}",
+            self.md_len,
+            if self.md_interleave {
+                "interleaved"
+            } else {
+                "separate"
+            },
+            self.dif_type
+        )
+    }
+}
+
+/// Context to generate, verify or remap the protection information of a run
+/// of blocks starting at a given LBA.
+pub(crate) struct DifContext(spdk_dif_ctx);
+
+impl DifContext {
+    /// Creates a context for the blocks of the given format, the first one
+    /// having the given reference tag.
+    pub(crate) fn new(
+        block_len: u64,
+        pi: &ProtectionInfo,
+        ref_tag: u64,
+    ) -> Result<Self, CoreError> {
+        let mut ctx = spdk_dif_ctx::default();
+        let mut opts = spdk_dif_ctx_init_ext_opts {
+            size: std::mem::size_of::<spdk_dif_ctx_init_ext_opts>() as _,
+            dif_pi_format: SPDK_DIF_PI_FORMAT_16,
+        };
+
+        let rc = unsafe {
+            spdk_dif_ctx_init(
+                &mut ctx,
+                block_len as _,
+                pi.md_len as _,
+                pi.md_interleave,
+                pi.dif_is_head_of_md,
+                pi.dif_type.into(),
+                pi.check_flags(),
+                ref_tag as _,
+                0,
+                0,
+                0,
+                0,
+                &mut opts,
+            )
+        };
+
+        if rc != 0 {
+            return Err(CoreError::ProtectionInfoInvalid {
+                reason: format!("unsupported block format: {pi}"),
+            });
+        }
+
+        Ok(Self(ctx))
+    }
+
+    /// Sets the reference tag the first block gets when remapped.
+    pub(crate) fn with_remapped_ref_tag(mut self, ref_tag: u64) -> Self {
+        unsafe {
+            spdk_dif_ctx_set_remapped_init_ref_tag(&mut self.0, ref_tag as _)
+        };
+        self
+    }
+
+    /// Generates the protection information of the given blocks.
+    pub(crate) fn generate(
+        &self,
+        iovs: &[IoVec],
+        num_blocks: u64,
+    ) -> Result<(), CoreError> {
+        let rc = unsafe {
+            spdk_dif_generate(
+                iovs.as_ptr() as *mut _,
+                iovs.len() as _,
+                num_blocks as _,
+                &self.0,
+            )
+        };
+
+        if rc != 0 {
+            return Err(CoreError::ProtectionInfoInvalid {
+                reason: format!("failed to generate for {num_blocks} blocks"),
+            });
+        }
+        Ok(())
+    }
+
+    /// Verifies the protection information of the given blocks.
+    pub(crate) fn verify(
+        &self,
+        iovs: &[IoVec],
+        num_blocks: u64,
+    ) -> Result<(), CoreError> {
+        let mut err = spdk_dif_error::default();
+        let rc = unsafe {
+            spdk_dif_verify(
+                iovs.as_ptr() as *mut _,
+                iovs.len() as _,
+                num_blocks as _,
+                &self.0,
+                &mut err,
+            )
+        };
+
+        if rc != 0 {
+            return Err(Self::check_error(&err));
+        }
+        Ok(())
+    }
+
+    /// Remaps the reference tags of the given blocks, after checking them.
+    pub(crate) fn remap_ref_tag(
+        &self,
+        iovs: &[IoVec],
+        num_blocks: u64,
+    ) -> Result<(), CoreError> {
+        let mut err = spdk_dif_error::default();
+        let rc = unsafe {
+            spdk_dif_remap_ref_tag(
+                iovs.as_ptr() as *mut _,
+                iovs.len() as _,
+                num_blocks as _,
+                &self.0,
+                &mut err,
+                true,
+            )
+        };
+
+        if rc != 0 {
+            return Err(Self::check_error(&err));
+        }
+        Ok(())
+    }
+
+    /// Turns an SPDK protection information error into the NVMe status a
+    /// device would fail the I/O with.
+    fn check_error(err: &spdk_dif_error) -> CoreError {
+        let sc = match err.err_type as u32 {
+            SPDK_DIF_REFTAG_ERROR => SPDK_NVME_SC_REFERENCE_TAG_CHECK_ERROR,
+            SPDK_DIF_APPTAG_ERROR => SPDK_NVME_SC_APPLICATION_TAG_CHECK_ERROR,
+            // Guard and data errors.
+            _ => SPDK_NVME_SC_GUARD_CHECK_ERROR,
+        };
+
+        CoreError::ProtectionCheckFailed {
+            status: IoCompletionStatus::NvmeError(NvmeStatus::Media(sc)),
+            offset: err.err_offset as u64,
+            expected: err.expected as u64,
+            actual: err.actual as u64,
+        }
+    }
+}
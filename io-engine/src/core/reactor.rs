@@ -37,6 +37,7 @@ use std::{
     os::raw::c_void,
     pin::Pin,
     slice::Iter,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
     time::Duration,
 };
 
@@ -694,11 +695,42 @@ impl Future for &'static Reactor {
 /// Heartbeat timeout (in seconds) to classify a reactor as frozen.
 const REACTOR_HEARTBEAT_TIMEOUT: u64 = 3;
 
+/// Whether reactor freeze detection is currently enabled.
+static FREEZE_DETECTION: AtomicBool = AtomicBool::new(false);
+/// Heartbeat timeout (in seconds) currently used by the reactor monitor.
+static FREEZE_TIMEOUT: AtomicU64 = AtomicU64::new(REACTOR_HEARTBEAT_TIMEOUT);
+/// Whether the reactor monitor loop has already been started.
+static FREEZE_MONITOR_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable reactor freeze detection and set its timeout.
+/// The reactor monitor loop is started on the tokio runtime the first time
+/// detection is enabled; once started, it stays around and merely pauses
+/// while detection is disabled, so this can be called again at any time to
+/// change the settings of a running instance.
+pub fn configure_reactor_monitor(enabled: bool, freeze_timeout: Option<u64>) {
+    FREEZE_TIMEOUT.store(
+        freeze_timeout.unwrap_or(REACTOR_HEARTBEAT_TIMEOUT),
+        Ordering::SeqCst,
+    );
+    FREEZE_DETECTION.store(enabled, Ordering::SeqCst);
+
+    if enabled && !FREEZE_MONITOR_STARTED.swap(true, Ordering::SeqCst) {
+        crate::core::runtime::spawn(reactor_monitor_loop());
+    }
+}
+
+/// Returns the current reactor freeze detection settings: whether detection
+/// is enabled and the heartbeat timeout (in seconds).
+pub fn reactor_monitor_config() -> (bool, u64) {
+    (
+        FREEZE_DETECTION.load(Ordering::SeqCst),
+        FREEZE_TIMEOUT.load(Ordering::SeqCst),
+    )
+}
+
 /// Monitor health for all reactors: all available reactors are constantly
 /// monitored for liveness.
-pub async fn reactor_monitor_loop(freeze_timeout: Option<u64>) {
-    use std::sync::atomic::{AtomicU64, Ordering};
-
+async fn reactor_monitor_loop() {
     /// Metadata for every reactor being monitored by the reactor monitor.
     struct ReactorRecord {
         frozen: bool,
@@ -707,7 +739,7 @@ pub async fn reactor_monitor_loop(freeze_timeout: Option<u64>) {
         core: u32,
     }
 
-    let timeout = freeze_timeout.unwrap_or(REACTOR_HEARTBEAT_TIMEOUT);
+    let timeout = FREEZE_TIMEOUT.load(Ordering::SeqCst);
     let num_cores = Cores::count().id() as usize;
     let mut interval = tokio::time::interval(Duration::from_secs(1));
     let mut tick: u64 = 0;
@@ -739,6 +771,14 @@ pub async fn reactor_monitor_loop(freeze_timeout: Option<u64>) {
     }
 
     loop {
+        // Detection has been disabled at runtime: don't schedule any
+        // heartbeats and don't advance the tick, so that the heartbeat
+        // deltas remain consistent once detection is enabled again.
+        if !FREEZE_DETECTION.load(Ordering::SeqCst) {
+            interval.tick().await;
+            continue;
+        }
+
         // Schedule heartbeat futures on every reactor, ignoring reactors
         // which are already frozen.
         for (id, r) in reactor_state.iter().enumerate() {
@@ -760,6 +800,8 @@ pub async fn reactor_monitor_loop(freeze_timeout: Option<u64>) {
         interval.tick().await;
         tick += 1;
 
+        let timeout = FREEZE_TIMEOUT.load(Ordering::SeqCst);
+
         for r in &mut reactor_state {
             if r.frozen {
                 // Check if all pending heartbeat futures have resolved:
@@ -36,6 +36,12 @@ pub fn block_on(f: impl Future<Output = ()> + Send + 'static) {
     RUNTIME.block_on(f);
 }
 
+/// Enter the runtime context, which allows creating tokio resources (e.g.
+/// spawning background tasks) from threads outside of the runtime.
+pub fn enter() -> tokio::runtime::EnterGuard<'static> {
+    RUNTIME.rt.enter()
+}
+
 /// spawn a future that might block on a separate worker thread the
 /// number of threads available is determined by max_blocking_threads
 pub fn spawn_blocking<F, R>(f: F) -> JoinHandle<R>
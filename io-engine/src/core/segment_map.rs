@@ -1,5 +1,8 @@
 use bit_vec::{BitBlock, BitVec};
-use std::fmt::{Debug, Formatter};
+use std::{
+    fmt::{Debug, Formatter},
+    ops::Range,
+};
 
 // Returns ceil of an integer division.
 fn div_ceil(a: u64, b: u64) -> u64 {
@@ -58,6 +61,14 @@ impl<B: BitBlock> SegmentMap<B> {
         self
     }
 
+    /// Checks if this map and another have a dirty segment in common.
+    pub(crate) fn intersects(&self, other: &SegmentMap<B>) -> bool {
+        self.segments
+            .iter()
+            .zip(other.segments.iter())
+            .any(|(a, b)| a && b)
+    }
+
     /// Sets a segment bit corresponding to the given logical block, to the
     /// given value.
     pub fn set(&mut self, lbn: u64, lbn_cnt: u64, value: bool) {
@@ -93,6 +104,24 @@ impl<B: BitBlock> SegmentMap<B> {
         self.count_ones() * self.segment_size / self.block_len
     }
 
+    /// Returns the ranges of blocks covered by dirty segments.
+    pub(crate) fn dirty_ranges(&self) -> Vec<Range<u64>> {
+        let seg_blks = self.segment_size_blks();
+        let mut ranges: Vec<Range<u64>> = Vec::new();
+
+        for (seg, _) in self.segments.iter().enumerate().filter(|(_, v)| *v) {
+            let start = seg as u64 * seg_blks;
+            let end = (start + seg_blks).min(self.num_blocks);
+
+            match ranges.last_mut() {
+                Some(last) if last.end == start => last.end = end,
+                _ => ranges.push(start .. end),
+            }
+        }
+
+        ranges
+    }
+
     /// Get the segment size in blocks.
     pub(crate) fn segment_size_blks(&self) -> u64 {
         self.segment_size / self.block_len
@@ -0,0 +1,279 @@
+//! In-memory ring buffer of the events generated by the io-engine, which
+//! local clients can watch without the events message bus.
+//!
+//! Events are captured from the tracing events with the `EVENTING_TARGET`
+//! target, whatever the `--events-url` option: each one gets a sequence
+//! number, is kept in a bounded ring buffer and is broadcast to the current
+//! watchers. A watcher reconnecting with the last sequence number it got is
+//! first replayed the events it missed, as long as they are still buffered,
+//! and is told otherwise that some were lost.
+
+use std::{collections::VecDeque, fmt::Debug};
+
+use chrono::{DateTime, Utc};
+use events_api::event::EventMessage;
+use once_cell::sync::Lazy;
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing_core::{Event, Subscriber};
+use tracing_subscriber::{layer::Context, Layer};
+
+use crate::{constants::EVENTING_TARGET, core::MayastorEnvironment};
+
+/// Default number of events kept in the ring buffer.
+pub const DEFAULT_EVENTS_BUFFER_SIZE: usize = 1024;
+
+/// Number of events a watcher may lag behind before it misses some.
+const WATCH_CHANNEL_SIZE: usize = 256;
+
+/// An event, as recorded in the ring buffer.
+#[derive(Debug, Clone)]
+pub struct EventRecord {
+    /// Sequence number of the event, starting from 1.
+    pub sequence: u64,
+    /// Time the event was generated.
+    pub timestamp: DateTime<Utc>,
+    /// The event message.
+    pub message: EventMessage,
+}
+
+/// Filter of the events to watch. Empty lists match any event.
+#[derive(Debug, Default, Clone)]
+pub struct EventFilter {
+    /// Event categories to match.
+    pub categories: Vec<i32>,
+    /// Event actions to match.
+    pub actions: Vec<i32>,
+    /// UUIDs of the resources to match.
+    pub targets: Vec<String>,
+}
+
+impl EventFilter {
+    /// Checks if the given event matches the filter.
+    pub fn matches(&self, event: &EventRecord) -> bool {
+        let msg = &event.message;
+        (self.categories.is_empty() || self.categories.contains(&msg.category))
+            && (self.actions.is_empty() || self.actions.contains(&msg.action))
+            && (self.targets.is_empty() || self.targets.contains(&msg.target))
+    }
+}
+
+/// A watch of the events.
+#[derive(Debug)]
+pub struct EventWatch {
+    /// Buffered events following the sequence number the watch resumes
+    /// from, to be replayed.
+    pub replay: Vec<EventRecord>,
+    /// Receiver of the events to come.
+    pub events: broadcast::Receiver<EventRecord>,
+    /// Sequence number of the last event generated before the watch
+    /// started.
+    pub last_sequence: u64,
+}
+
+/// A watch resumed from a sequence number whose following events are no
+/// longer buffered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventsLost {
+    /// Sequence number of the oldest event still available.
+    pub first_available: u64,
+}
+
+/// Ring buffer state.
+struct RingInner {
+    /// Buffered events, oldest first.
+    events: VecDeque<EventRecord>,
+    /// Sequence number of the last event.
+    last_sequence: u64,
+}
+
+/// Ring buffer of the recent events, and their broadcast to the watchers.
+pub struct EventRing {
+    inner: parking_lot::Mutex<RingInner>,
+    capacity: usize,
+    sender: broadcast::Sender<EventRecord>,
+}
+
+static EVENT_RING: Lazy<EventRing> = Lazy::new(|| {
+    EventRing::new(MayastorEnvironment::global_or_default().events_buffer_size)
+});
+
+impl EventRing {
+    fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(WATCH_CHANNEL_SIZE);
+        Self {
+            inner: parking_lot::Mutex::new(RingInner {
+                events: VecDeque::with_capacity(capacity),
+                last_sequence: 0,
+            }),
+            capacity,
+            sender,
+        }
+    }
+
+    /// Returns the global event ring buffer.
+    pub fn get() -> &'static Self {
+        &EVENT_RING
+    }
+
+    /// Returns a tracing layer recording the generated events into the
+    /// global ring buffer.
+    pub fn layer() -> EventRingLayer {
+        EventRingLayer {}
+    }
+
+    /// Records an event, and sends it to the watchers.
+    fn record(&self, message: EventMessage) {
+        let mut inner = self.inner.lock();
+        inner.last_sequence += 1;
+
+        let record = EventRecord {
+            sequence: inner.last_sequence,
+            timestamp: Utc::now(),
+            message,
+        };
+
+        if self.capacity > 0 {
+            if inner.events.len() == self.capacity {
+                inner.events.pop_front();
+            }
+            inner.events.push_back(record.clone());
+        }
+
+        // Having no watcher is not an error.
+        self.sender.send(record).ok();
+    }
+
+    /// Starts watching the events. The buffered events following the given
+    /// sequence number, if any, are returned for replay, along with the
+    /// receiver of the events to come, so that none is missed in between.
+    /// Fails if some of the events following the given sequence number are
+    /// no longer buffered.
+    pub fn watch(
+        &self,
+        since: Option<u64>,
+        filter: &EventFilter,
+    ) -> Result<EventWatch, EventsLost> {
+        let inner = self.inner.lock();
+        let first_available =
+            inner.last_sequence - inner.events.len() as u64 + 1;
+
+        let replay = match since {
+            Some(since) if since + 1 < first_available => {
+                return Err(EventsLost {
+                    first_available,
+                });
+            }
+            Some(since) => inner
+                .events
+                .iter()
+                .filter(|e| e.sequence > since && filter.matches(e))
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Ok(EventWatch {
+            replay,
+            events: self.sender.subscribe(),
+            last_sequence: inner.last_sequence,
+        })
+    }
+
+    /// Returns the sequence number of the last event.
+    pub fn last_sequence(&self) -> u64 {
+        self.inner.lock().last_sequence
+    }
+}
+
+/// Tracing layer recording the events into the event ring buffer.
+pub struct EventRingLayer {}
+
+impl<S: Subscriber> Layer<S> for EventRingLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target() != EVENTING_TARGET {
+            return;
+        }
+
+        let mut visitor = EventVisitor::default();
+        event.record(&mut visitor);
+        if let Some(message) = visitor.message {
+            EventRing::get().record(message);
+        }
+    }
+}
+
+/// Extracts the event message serialized into a tracing event.
+#[derive(Default)]
+struct EventVisitor {
+    message: Option<EventMessage>,
+}
+
+impl Visit for EventVisitor {
+    fn record_str(&mut self, _field: &Field, value: &str) {
+        if self.message.is_none() {
+            self.message = serde_json::from_str(value).ok();
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, value: &dyn Debug) {
+        if self.message.is_some() {
+            return;
+        }
+        // Strings are recorded quoted by their debug representation.
+        let value = format!("{value:?}");
+        self.message = serde_json::from_str(&value).ok().or_else(|| {
+            serde_json::from_str::<String>(&value)
+                .ok()
+                .and_then(|s| serde_json::from_str(&s).ok())
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EventFilter, EventRing, EventsLost};
+    use events_api::event::EventMessage;
+
+    #[test]
+    fn test_watch_lost_events() {
+        let ring = EventRing::new(2);
+        let filter = EventFilter::default();
+
+        // Nothing is lost before the buffer wraps around.
+        ring.record(EventMessage::default());
+        ring.record(EventMessage::default());
+        let watch = ring.watch(Some(0), &filter).unwrap();
+        assert_eq!(watch.replay.len(), 2);
+        assert_eq!(watch.last_sequence, 2);
+
+        ring.record(EventMessage::default());
+        ring.record(EventMessage::default());
+        assert_eq!(
+            ring.watch(Some(1), &filter).unwrap_err(),
+            EventsLost {
+                first_available: 3
+            }
+        );
+
+        let watch = ring.watch(Some(2), &filter).unwrap();
+        let sequences: Vec<_> =
+            watch.replay.iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, vec![3, 4]);
+
+        // Starting a new watch loses nothing.
+        let watch = ring.watch(None, &filter).unwrap();
+        assert!(watch.replay.is_empty());
+        assert_eq!(watch.last_sequence, 4);
+    }
+
+    #[test]
+    fn test_watch_unbuffered() {
+        let ring = EventRing::new(0);
+        let filter = EventFilter::default();
+
+        ring.record(EventMessage::default());
+        assert!(ring.watch(Some(1), &filter).unwrap().replay.is_empty());
+        assert!(ring.watch(Some(0), &filter).is_err());
+    }
+}
@@ -11,6 +11,7 @@ use std::time::Duration;
 use crate::{
     core::{MayastorEnvironment, Reactor},
     eventing::{Event, EventWithMeta},
+    host::nvme_health::NvmeHealth,
 };
 
 pub(crate) fn io_engine_stop_event_meta(total_time: Duration) -> EventMeta {
@@ -68,3 +69,18 @@ impl Event for Reactor {
         }
     }
 }
+
+// NVMe device health event message from the device health data.
+impl Event for NvmeHealth {
+    fn event(&self, event_action: EventAction) -> EventMessage {
+        let event_source = EventSource::new(
+            MayastorEnvironment::global_or_default().node_name,
+        );
+        EventMessage {
+            category: EventCategory::IoEngineCategory as i32,
+            action: event_action as i32,
+            target: self.name.clone(),
+            metadata: Some(EventMeta::from_source(event_source)),
+        }
+    }
+}
@@ -1,4 +1,5 @@
 mod clone_events;
+pub mod event_ring;
 pub(crate) mod host_events;
 pub(crate) mod io_engine_events;
 mod nexus_child_events;
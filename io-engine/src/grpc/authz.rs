@@ -0,0 +1,312 @@
+//! Role-based authorization of the gRPC calls.
+//!
+//! The policy file maps the clients, identified by the common name of their
+//! TLS certificate, to roles, and gives the gRPC methods each role may call,
+//! for example:
+//!
+//! ```yaml
+//! roles:
+//!   monitoring:
+//!     allow: ["mayastor.v1.StatsRpc/*", "*/List*", "*/Get*"]
+//!   operator:
+//!     allow: ["*"]
+//!     deny: ["mayastor.JsonRpc/*", "mayastor.v1.JsonRpc/*"]
+//!   admin:
+//!     allow: ["*"]
+//! clients:
+//!   prometheus: monitoring
+//!   control-plane: operator
+//! # Role of the clients without a certificate, or not listed above.
+//! default_role: monitoring
+//! # Methods no one may call, e.g. in production.
+//! disabled: ["mayastor.v1.TestRpc/*"]
+//! ```
+//!
+//! Patterns match the `<package>.<service>/<method>` gRPC paths, and may
+//! contain `*` wildcards. The policy file is reloaded whenever it changes.
+
+use std::{
+    collections::HashMap,
+    fs,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::future::{ready, Either, Ready};
+use parking_lot::{Mutex, RwLock};
+use serde::Deserialize;
+use tonic::{body::BoxBody, Status};
+use tower::{Layer, Service};
+
+use super::tls::{FileWatch, TlsConnectInfo, RELOAD_INTERVAL};
+use crate::core::MayastorEnvironment;
+
+/// Methods a role may call.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RolePolicy {
+    /// Patterns of the methods the role may call.
+    allow: Vec<String>,
+    /// Patterns of the methods the role may not call, despite `allow`.
+    deny: Vec<String>,
+}
+
+/// Authorization policy of the gRPC calls.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AuthzPolicy {
+    /// Roles, by name.
+    roles: HashMap<String, RolePolicy>,
+    /// Roles of the clients, by certificate common name.
+    clients: HashMap<String, String>,
+    /// Role of the clients which are not listed.
+    default_role: Option<String>,
+    /// Patterns of the methods no one may call.
+    disabled: Vec<String>,
+}
+
+impl AuthzPolicy {
+    fn load(path: &str) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|e| e.to_string())?;
+        let policy: Self =
+            serde_yaml::from_slice(&data).map_err(|e| e.to_string())?;
+
+        for role in policy.clients.values().chain(policy.default_role.iter()) {
+            if !policy.roles.contains_key(role) {
+                return Err(format!("undefined role '{role}'"));
+            }
+        }
+        Ok(policy)
+    }
+
+    /// Checks if the given client may call the given gRPC method.
+    fn authorize(
+        &self,
+        path: &str,
+        client: Option<&str>,
+    ) -> Result<(), Status> {
+        if self.disabled.iter().any(|p| matches(p, path)) {
+            return Err(Status::permission_denied(format!(
+                "{path} is disabled"
+            )));
+        }
+
+        let role = client
+            .and_then(|c| self.clients.get(c))
+            .or(self.default_role.as_ref())
+            .ok_or_else(|| {
+                Status::permission_denied(format!(
+                    "Client {} has no role",
+                    client.unwrap_or("without certificate")
+                ))
+            })?;
+        let policy = self.roles.get(role).ok_or_else(|| {
+            Status::permission_denied(format!("Undefined role '{role}'"))
+        })?;
+
+        if policy.allow.iter().any(|p| matches(p, path))
+            && !policy.deny.iter().any(|p| matches(p, path))
+        {
+            Ok(())
+        } else {
+            Err(Status::permission_denied(format!(
+                "Role '{role}' may not call {path}"
+            )))
+        }
+    }
+}
+
+/// Matches a gRPC method path against a pattern with `*` wildcards.
+fn matches(pattern: &str, path: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(parts.next().unwrap_or_default())
+    else {
+        return false;
+    };
+    let parts = parts.collect::<Vec<_>>();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard.
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len() ..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Authorization of the gRPC calls, following the policy file given on the
+/// command line.
+pub(super) struct GrpcAuthz {
+    path: String,
+    policy: RwLock<Arc<AuthzPolicy>>,
+    watch: Mutex<FileWatch>,
+}
+
+impl GrpcAuthz {
+    /// Loads the policy file given on the command line, if any.
+    pub(super) fn from_env(
+        env: &MayastorEnvironment,
+    ) -> Result<Option<Arc<Self>>, String> {
+        let Some(path) = &env.grpc_authz_policy else {
+            return Ok(None);
+        };
+        let policy = AuthzPolicy::load(path)
+            .map_err(|e| format!("invalid policy file {path}: {e}"))?;
+        info!("gRPC authorization policy loaded from {path}");
+
+        Ok(Some(Arc::new(Self {
+            path: path.clone(),
+            policy: RwLock::new(Arc::new(policy)),
+            watch: Mutex::new(FileWatch::new(vec![path.clone()])),
+        })))
+    }
+
+    /// Periodically reloads the policy file when it changes.
+    pub(super) async fn watch(self: Arc<Self>) {
+        loop {
+            tokio::time::sleep(RELOAD_INTERVAL).await;
+            if !self.watch.lock().changed() {
+                continue;
+            }
+
+            match AuthzPolicy::load(&self.path) {
+                Ok(policy) => {
+                    *self.policy.write() = Arc::new(policy);
+                    info!("gRPC authorization policy reloaded");
+                }
+                Err(error) => {
+                    error!(
+                        "Failed to reload gRPC authorization policy {}, \
+                        keeping the current one: {error}",
+                        self.path
+                    );
+                }
+            }
+        }
+    }
+
+    /// Checks if the given request may proceed.
+    fn authorize<B>(&self, request: &http::Request<B>) -> Result<(), Status> {
+        let path = request.uri().path().trim_start_matches('/');
+        let client = request
+            .extensions()
+            .get::<TlsConnectInfo>()
+            .and_then(|info| info.common_name());
+
+        let result = self.policy.read().authorize(path, client.as_deref());
+        if let Err(status) = &result {
+            warn!("gRPC call denied: {}", status.message());
+        }
+        result
+    }
+}
+
+/// Tower layer enforcing the authorization policy, if any, on all the
+/// services of the gRPC server.
+#[derive(Clone)]
+pub(super) struct AuthzLayer(Option<Arc<GrpcAuthz>>);
+
+impl AuthzLayer {
+    pub(super) fn new(authz: Option<Arc<GrpcAuthz>>) -> Self {
+        Self(authz)
+    }
+}
+
+impl<S> Layer<S> for AuthzLayer {
+    type Service = AuthzService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthzService {
+            inner,
+            authz: self.0.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct AuthzService<S> {
+    inner: S,
+    authz: Option<Arc<GrpcAuthz>>,
+}
+
+impl<S, B> Service<http::Request<B>> for AuthzService<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<BoxBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, S::Error>>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let denied = self
+            .authz
+            .as_ref()
+            .and_then(|authz| authz.authorize(&request).err());
+
+        match denied {
+            Some(status) => Either::Right(ready(Ok(status.to_http()))),
+            None => Either::Left(self.inner.call(request)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matches, AuthzPolicy};
+
+    #[test]
+    fn authz_matches() {
+        assert!(matches("*", "mayastor.v1.PoolRpc/ListPools"));
+        assert!(matches("*/List*", "mayastor.v1.PoolRpc/ListPools"));
+        assert!(matches("mayastor.v1.*", "mayastor.v1.PoolRpc/CreatePool"));
+        assert!(matches(
+            "mayastor.v1.PoolRpc/CreatePool",
+            "mayastor.v1.PoolRpc/CreatePool"
+        ));
+        assert!(!matches("*/List*", "mayastor.v1.PoolRpc/CreatePool"));
+        assert!(!matches("mayastor.v1.PoolRpc", "mayastor.v1.PoolRpc/List"));
+        assert!(!matches("a*b*b", "ab"));
+    }
+
+    #[test]
+    fn authz_policy() {
+        let policy: AuthzPolicy = serde_yaml::from_str(
+            r#"
+roles:
+  monitoring:
+    allow: ["*/List*"]
+  operator:
+    allow: ["*"]
+    deny: ["mayastor.v1.JsonRpc/*"]
+clients:
+  control-plane: operator
+default_role: monitoring
+disabled: ["mayastor.v1.TestRpc/*"]
+"#,
+        )
+        .unwrap();
+
+        let list = "mayastor.v1.PoolRpc/ListPools";
+        let create = "mayastor.v1.PoolRpc/CreatePool";
+        let json = "mayastor.v1.JsonRpc/JsonRpcCall";
+        let wipe = "mayastor.v1.TestRpc/WipeReplica";
+
+        assert!(policy.authorize(list, None).is_ok());
+        assert!(policy.authorize(create, None).is_err());
+        assert!(policy.authorize(create, Some("unknown")).is_err());
+        assert!(policy.authorize(create, Some("control-plane")).is_ok());
+        assert!(policy.authorize(json, Some("control-plane")).is_err());
+        assert!(policy.authorize(wipe, Some("control-plane")).is_err());
+    }
+}
@@ -8,6 +8,7 @@ use std::{
 };
 use tokio::sync::RwLock;
 use tonic::{Request, Response, Status};
+use tracing::Instrument;
 
 use crate::{
     bdev_api::BdevError,
@@ -19,6 +20,7 @@ use crate::{
         ResourceSubsystem,
         VerboseError,
     },
+    subsys::NvmfError,
 };
 
 impl From<BdevError> for tonic::Status {
@@ -71,8 +73,30 @@ impl From<CoreError> for tonic::Status {
     }
 }
 
+impl From<NvmfError> for tonic::Status {
+    fn from(e: NvmfError) -> Self {
+        match e {
+            NvmfError::Referral {
+                source, ..
+            } => match source {
+                Errno::EINVAL => Status::invalid_argument(e.to_string()),
+                Errno::ENOENT => Status::not_found(e.to_string()),
+                Errno::ENODEV => Status::unavailable(e.to_string()),
+                _ => Status::internal(e.to_string()),
+            },
+            e => Status::internal(e.to_string()),
+        }
+    }
+}
+
+pub(crate) mod audit;
+mod authz;
 pub mod controller_grpc;
+pub(crate) mod operation;
+mod otel;
+mod request_cache;
 mod server;
+mod tls;
 pub mod v0 {
     pub mod bdev_grpc;
     pub mod json_grpc;
@@ -80,20 +104,41 @@ pub mod v0 {
     pub mod nexus_grpc;
 }
 pub mod v1 {
+    pub mod audit;
     pub mod bdev;
+    pub mod copy_job;
+    pub mod discovery;
+    pub mod event;
     pub mod host;
+    pub mod initiator;
     pub mod json;
     pub mod lvm;
     pub mod lvs;
     pub mod nexus;
+    pub mod operation;
     pub mod pool;
     pub mod replica;
+    pub mod replication;
     pub mod snapshot;
     pub mod snapshot_rebuild;
     pub mod stats;
     pub mod test;
 }
 
+/// Prefixes of the names of the read-only gRPC methods.
+const READ_ONLY_METHODS: [&str; 4] = ["List", "Get", "Stat", "Watch"];
+
+/// Returns the name of the gRPC method of the given path, e.g.
+/// `/mayastor.v1.PoolRpc/CreatePool`, provided it may mutate the state of the
+/// io-engine.
+pub(crate) fn mutating_method(path: &str) -> Option<&str> {
+    let (_, method) = path.rsplit_once('/')?;
+    if READ_ONLY_METHODS.iter().any(|p| method.starts_with(p)) {
+        return None;
+    }
+    Some(method)
+}
+
 /// Default timeout for gRPC calls, in seconds. Should be enforced in case
 /// no timeout is explicitly provided by the client upon gRPC method invocation.
 pub const DEFAULT_GRPC_TIMEOUT_SEC: u64 = 15;
@@ -118,7 +163,7 @@ impl GrpcClientContext {
     {
         Self {
             timeout: get_request_timeout(req),
-            args: format!("{:?}", req.get_ref()),
+            args: audit::record_args(req),
             id: fid.to_string(),
         }
     }
@@ -166,7 +211,7 @@ where
     F: Future<Output = Result<R, E>> + 'static,
     R: Send + Debug + 'static,
 {
-    Reactor::spawn_at_primary(future)
+    Reactor::spawn_at_primary(future.in_current_span())
         .map_err(|_| Status::resource_exhausted("ENOMEM"))
 }
 /// Submit rpc code to the primary reactor.
@@ -176,7 +221,7 @@ where
     F: Future<Output = R> + 'static,
     R: Send + Debug + 'static,
 {
-    Reactor::spawn_at_primary(future)
+    Reactor::spawn_at_primary(future.in_current_span())
         .map_err(|_| Status::resource_exhausted("ENOMEM"))
 }
 
@@ -190,7 +235,7 @@ where
     F: Future<Output = Result<R, tonic::Status>> + 'static,
     R: Send + Debug + 'static,
 {
-    Reactor::spawn_at_primary(future)
+    Reactor::spawn_at_primary(future.in_current_span())
         .map_err(|_| Status::resource_exhausted("ENOMEM"))
 }
 
@@ -0,0 +1,317 @@
+//! Tracking of the long-running calls of the v1 gRPC API.
+//!
+//! A client may run a long-running call asynchronously by setting the
+//! `operation-id` request metadata to an ID of its choice, usually a UUID.
+//! The call then returns at once with an empty response, and carries on in
+//! the background as an operation which the operation service reports the
+//! state, the progress and the result of, under that ID.
+//!
+//! An operation can only be cancelled while it is waiting for its turn to
+//! run, i.e. for the other calls on the same resources to complete. Once it
+//! has started running, the cancellation fails and the operation runs to
+//! completion: none of the underlying pool, replica or nexus operations can
+//! be interrupted half-way.
+
+use std::{future::Future, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use prost::Message;
+use tonic::{metadata::MetadataValue, Request, Response, Status};
+
+use crate::grpc::{GrpcClientContext, GrpcResult};
+
+/// Metadata key of the operation ID, which a client sets in a request to run
+/// it asynchronously.
+pub const OPERATION_ID_KEY: &str = "operation-id";
+
+/// Maximum number of finished operations kept for the clients to query.
+const MAX_FINISHED_OPERATIONS: usize = 256;
+
+/// State of an operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OperationState {
+    /// Waiting for its turn to run.
+    Pending,
+    /// Running, it cannot be cancelled anymore.
+    Running,
+    /// Finished successfully.
+    Succeeded,
+    /// Finished with an error.
+    Failed,
+    /// Cancelled before it started running.
+    Cancelled,
+}
+
+impl OperationState {
+    fn is_finished(&self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed | Self::Cancelled)
+    }
+}
+
+/// A long-running gRPC call running asynchronously.
+#[derive(Debug)]
+pub(crate) struct Operation {
+    /// Operation ID, as given by the client.
+    pub(crate) id: String,
+    /// Name of the gRPC method.
+    pub(crate) method: String,
+    /// Arguments of the gRPC method.
+    pub(crate) args: String,
+    /// Time the operation was created.
+    pub(crate) created: DateTime<Utc>,
+    info: Mutex<OperationInfo>,
+}
+
+/// Current state of an operation.
+#[derive(Debug, Clone)]
+pub(crate) struct OperationInfo {
+    pub(crate) state: OperationState,
+    /// Time the operation started running.
+    pub(crate) started: Option<DateTime<Utc>>,
+    /// Time the operation finished.
+    pub(crate) finished: Option<DateTime<Utc>>,
+    /// Encoded response of the gRPC method, once succeeded.
+    pub(crate) response: Vec<u8>,
+    /// Error of the operation, once failed or cancelled.
+    pub(crate) error: Option<Status>,
+    /// Progress of the operation, once running.
+    pub(crate) progress: OperationProgress,
+}
+
+/// Progress of a running operation: the stage it is at, and how far along
+/// that stage it is.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OperationProgress {
+    /// Description of the current stage.
+    pub(crate) stage: String,
+    /// Number of steps of the stage done.
+    pub(crate) done: u64,
+    /// Number of steps of the stage, or zero if unknown.
+    pub(crate) total: u64,
+}
+
+/// Operations, oldest first.
+static OPERATIONS: Lazy<Mutex<Vec<Arc<Operation>>>> =
+    Lazy::new(|| Mutex::new(Vec::new()));
+
+impl Operation {
+    /// Looks up an operation by its ID.
+    pub(crate) fn lookup(id: &str) -> Result<Arc<Self>, Status> {
+        OPERATIONS
+            .lock()
+            .iter()
+            .find(|op| op.id == id)
+            .cloned()
+            .ok_or_else(|| {
+                Status::not_found(format!("Operation '{id}' not found"))
+            })
+    }
+
+    /// Lists all the operations, oldest first.
+    pub(crate) fn list() -> Vec<Arc<Self>> {
+        OPERATIONS.lock().clone()
+    }
+
+    /// Returns a copy of the current state of the operation.
+    pub(crate) fn info(&self) -> OperationInfo {
+        self.info.lock().clone()
+    }
+
+    /// Cancels the operation, provided it has not started running yet: a
+    /// running operation cannot be cancelled.
+    pub(crate) fn cancel(&self) -> Result<(), Status> {
+        let mut info = self.info.lock();
+        match info.state {
+            OperationState::Pending => {
+                info.state = OperationState::Cancelled;
+                info.finished = Some(Utc::now());
+                info.error = Some(Status::cancelled(format!(
+                    "Operation '{}' cancelled",
+                    self.id
+                )));
+                Ok(())
+            }
+            OperationState::Cancelled => Ok(()),
+            state => Err(Status::failed_precondition(format!(
+                "Operation '{}' cannot be cancelled in state {state:?}",
+                self.id
+            ))),
+        }
+    }
+
+    /// Marks the operation as running, unless it was cancelled.
+    fn start(&self) -> Result<(), Status> {
+        let mut info = self.info.lock();
+        if info.state == OperationState::Cancelled {
+            return Err(Status::cancelled(format!(
+                "Operation '{}' cancelled",
+                self.id
+            )));
+        }
+        info.state = OperationState::Running;
+        info.started = Some(Utc::now());
+        Ok(())
+    }
+
+    /// Records the progress of the running operation.
+    fn report(&self, stage: &str, done: u64, total: u64) {
+        let mut info = self.info.lock();
+        if info.state != OperationState::Running {
+            return;
+        }
+        info.progress = OperationProgress {
+            stage: stage.to_string(),
+            done,
+            total,
+        };
+    }
+
+    /// Records the result of the operation.
+    fn finish<T: Message>(&self, result: &GrpcResult<T>) {
+        let mut info = self.info.lock();
+        if info.state == OperationState::Cancelled {
+            return;
+        }
+        info.finished = Some(Utc::now());
+        match result {
+            Ok(response) => {
+                info.state = OperationState::Succeeded;
+                info.response = response.get_ref().encode_to_vec();
+            }
+            Err(status) => {
+                info.state = OperationState::Failed;
+                info.error = Some(status.clone());
+            }
+        }
+    }
+
+    /// Adds a new operation, evicting the oldest finished ones beyond the
+    /// limit.
+    fn add(op: Arc<Self>) -> Result<(), Status> {
+        let mut ops = OPERATIONS.lock();
+        if ops.iter().any(|o| o.id == op.id) {
+            return Err(Status::already_exists(format!(
+                "Operation '{}' already exists",
+                op.id
+            )));
+        }
+
+        let finished = ops
+            .iter()
+            .filter(|o| o.info.lock().state.is_finished())
+            .count();
+        let mut evict = (finished + 1).saturating_sub(MAX_FINISHED_OPERATIONS);
+        ops.retain(|o| {
+            if evict > 0 && o.info.lock().state.is_finished() {
+                evict -= 1;
+                return false;
+            }
+            true
+        });
+
+        ops.push(op);
+        Ok(())
+    }
+}
+
+/// Handle of the operation a gRPC call runs as, if it runs asynchronously.
+#[derive(Debug, Clone)]
+pub(crate) struct OperationHandle(Option<Arc<Operation>>);
+
+impl OperationHandle {
+    /// Makes the operation handle of a gRPC call, which is asynchronous if
+    /// the request carries an operation ID.
+    pub(crate) fn new<T>(
+        request: &Request<T>,
+        ctx: &GrpcClientContext,
+    ) -> Result<Self, Status> {
+        let Some(id) = request.metadata().get(OPERATION_ID_KEY) else {
+            return Ok(Self(None));
+        };
+        let id = id
+            .to_str()
+            .ok()
+            .filter(|id| !id.is_empty())
+            .ok_or_else(|| Status::invalid_argument("Invalid operation ID"))?;
+
+        let op = Arc::new(Operation {
+            id: id.to_string(),
+            method: ctx.id.clone(),
+            args: ctx.args.clone(),
+            created: Utc::now(),
+            info: Mutex::new(OperationInfo {
+                state: OperationState::Pending,
+                started: None,
+                finished: None,
+                response: Vec::new(),
+                error: None,
+                progress: OperationProgress::default(),
+            }),
+        });
+        Operation::add(op.clone())?;
+        Ok(Self(Some(op)))
+    }
+
+    /// Returns the reporter of the progress of the operation.
+    pub(crate) fn progress(&self) -> ProgressReporter {
+        ProgressReporter(self.0.clone())
+    }
+
+    /// Wraps the part of the call which runs once the call got its turn,
+    /// after which the operation cannot be cancelled anymore.
+    pub(crate) async fn begin<T, F>(self, f: F) -> Result<T, Status>
+    where
+        F: Future<Output = Result<T, Status>>,
+    {
+        if let Some(op) = &self.0 {
+            op.start()?;
+        }
+        f.await
+    }
+
+    /// Runs the whole call: synchronously, or else in the background, in
+    /// which case an empty response is returned at once along with the
+    /// operation ID.
+    pub(crate) async fn run<T, F>(self, f: F) -> GrpcResult<T>
+    where
+        T: Message + Default + 'static,
+        F: Future<Output = GrpcResult<T>> + Send + 'static,
+    {
+        let Some(op) = self.0 else {
+            return f.await;
+        };
+
+        info!("{}: running as operation '{}'", op.method, op.id);
+        let id = MetadataValue::try_from(op.id.as_str())
+            .map_err(|_| Status::invalid_argument("Invalid operation ID"))?;
+
+        tokio::spawn(async move {
+            let result = f.await;
+            if let Err(error) = &result {
+                warn!("Operation '{}' failed: {error}", op.id);
+            }
+            op.finish(&result);
+        });
+
+        let mut response = Response::new(T::default());
+        response.metadata_mut().insert(OPERATION_ID_KEY, id);
+        Ok(response)
+    }
+}
+
+/// Reports the progress of the operation a gRPC call runs as, if it runs
+/// asynchronously.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ProgressReporter(Option<Arc<Operation>>);
+
+impl ProgressReporter {
+    /// Reports that the operation is at the given stage, with the given
+    /// number of its steps done out of the total, if known.
+    pub(crate) fn report(&self, stage: &str, done: u64, total: u64) {
+        if let Some(op) = &self.0 {
+            op.report(stage, done, total);
+        }
+    }
+}
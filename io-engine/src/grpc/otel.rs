@@ -0,0 +1,45 @@
+//! Distributed tracing of the gRPC calls.
+//! Each incoming request gets its own span, which continues the trace
+//! propagated by the client via the gRPC metadata (W3C trace context).
+
+use crate::constants::TRACING_TARGET;
+use opentelemetry::propagation::Extractor;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Extracts the trace context from the gRPC request metadata.
+struct MetadataExtractor<'a>(&'a http::HeaderMap);
+
+impl Extractor for MetadataExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+/// Creates the span covering the whole execution of a gRPC request.
+/// The span is named after the gRPC method, e.g.
+/// `mayastor.v1.NexusRpc/CreateNexus`.
+pub(super) fn request_span(request: &http::Request<()>) -> tracing::Span {
+    let path = request.uri().path().trim_start_matches('/');
+    let (service, method) = path.split_once('/').unwrap_or((path, ""));
+
+    let span = tracing::info_span!(
+        target: TRACING_TARGET,
+        "grpc",
+        otel.name = path,
+        otel.kind = "server",
+        rpc.system = "grpc",
+        rpc.service = service,
+        rpc.method = method,
+    );
+
+    let parent = opentelemetry::global::get_text_map_propagator(|p| {
+        p.extract(&MetadataExtractor(request.headers()))
+    });
+    span.set_parent(parent);
+
+    span
+}
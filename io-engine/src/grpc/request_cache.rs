@@ -0,0 +1,354 @@
+//! Idempotency of the mutating calls of the v1 gRPC API.
+//!
+//! A client may set the `request-id` request metadata to an ID of its choice,
+//! usually a UUID, and reuse it when retrying the call, for example after a
+//! timeout. The result of the first call is then kept for a while under that
+//! ID, and returned to the retries instead of running the call again. A retry
+//! which arrives while the first call is still running waits for its result.
+//!
+//! Errors worth retrying, such as a timeout acquiring a resource lock, are not
+//! kept, so that a retry runs the call again. Read-only calls, the names of
+//! which start with `List`, `Get`, `Stat` or `Watch`, are never cached.
+
+use std::{
+    collections::{hash_map::DefaultHasher, VecDeque},
+    hash::{Hash, Hasher},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use bytes::{Bytes, BytesMut};
+use futures::future::BoxFuture;
+use http::{HeaderMap, StatusCode};
+use http_body::Body;
+use parking_lot::Mutex;
+use tokio::sync::watch;
+use tonic::{body::BoxBody, Code, Status};
+use tower::{Layer, Service};
+
+use super::mutating_method;
+
+/// Metadata key of the request ID, which a client sets in a request to make
+/// its retries idempotent.
+pub const REQUEST_ID_KEY: &str = "request-id";
+
+/// Maximum number of results kept.
+const MAX_CACHED_REQUESTS: usize = 1024;
+
+/// Time a result is kept for.
+const CACHED_REQUEST_TTL: Duration = Duration::from_secs(600);
+
+/// A buffered gRPC response.
+#[derive(Debug, Clone)]
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    data: Bytes,
+    trailers: Option<HeaderMap>,
+}
+
+impl CachedResponse {
+    /// Buffers the whole response.
+    async fn collect(
+        response: http::Response<BoxBody>,
+    ) -> Result<Self, Status> {
+        let (parts, mut body) = response.into_parts();
+        let mut data = BytesMut::new();
+        while let Some(chunk) = body.data().await {
+            data.extend_from_slice(&chunk?);
+        }
+        let trailers = body.trailers().await?;
+
+        Ok(Self {
+            status: parts.status,
+            headers: parts.headers,
+            data: data.freeze(),
+            trailers,
+        })
+    }
+
+    /// Returns the gRPC status code of the response, which is found in the
+    /// headers when the response carries an error only.
+    fn code(&self) -> Code {
+        self.trailers
+            .as_ref()
+            .and_then(|trailers| trailers.get("grpc-status"))
+            .or_else(|| self.headers.get("grpc-status"))
+            .map(|code| Code::from_bytes(code.as_bytes()))
+            .unwrap_or(Code::Unknown)
+    }
+
+    /// Checks if a retry of the call is worth running again.
+    fn is_retryable(&self) -> bool {
+        matches!(
+            self.code(),
+            Code::Cancelled
+                | Code::DeadlineExceeded
+                | Code::ResourceExhausted
+                | Code::Aborted
+                | Code::Unavailable
+        )
+    }
+
+    fn to_http(&self) -> http::Response<BoxBody> {
+        let body = CachedBody {
+            data: Some(self.data.clone()).filter(|data| !data.is_empty()),
+            trailers: self.trailers.clone(),
+        };
+        let mut response = http::Response::new(tonic::body::boxed(body));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response
+    }
+}
+
+/// Body of a buffered gRPC response.
+struct CachedBody {
+    data: Option<Bytes>,
+    trailers: Option<HeaderMap>,
+}
+
+impl Body for CachedBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Poll::Ready(self.data.take().map(Ok))
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(self.trailers.take()))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.data.is_none() && self.trailers.is_none()
+    }
+}
+
+/// A call with a request ID, and its result once finished.
+struct CachedRequest {
+    id: String,
+    /// Path of the gRPC method.
+    path: String,
+    /// Digest of the request message, to tell a retry from another request
+    /// which reuses the ID.
+    digest: u64,
+    created: Instant,
+    response: watch::Receiver<Option<CachedResponse>>,
+}
+
+/// Entry of a call in the cache.
+enum CacheEntry {
+    /// The call is new, its result is to be sent over.
+    New(watch::Sender<Option<CachedResponse>>),
+    /// The call is a retry, its result is to be received.
+    Retry(watch::Receiver<Option<CachedResponse>>),
+}
+
+/// Calls with a request ID, oldest first.
+#[derive(Default)]
+struct RequestCache {
+    requests: VecDeque<CachedRequest>,
+}
+
+impl RequestCache {
+    /// Returns the entry of a call, adding it if it is new.
+    fn entry(
+        &mut self,
+        id: &str,
+        path: &str,
+        digest: u64,
+    ) -> Result<CacheEntry, Status> {
+        while self
+            .requests
+            .front()
+            .is_some_and(|r| r.created.elapsed() > CACHED_REQUEST_TTL)
+        {
+            self.requests.pop_front();
+        }
+
+        if let Some(request) = self.requests.iter().find(|r| r.id == id) {
+            if request.path != path || request.digest != digest {
+                return Err(Status::invalid_argument(format!(
+                    "Request ID '{id}' already used by another request"
+                )));
+            }
+            return Ok(CacheEntry::Retry(request.response.clone()));
+        }
+
+        if self.requests.len() >= MAX_CACHED_REQUESTS {
+            self.requests.pop_front();
+        }
+        let (tx, rx) = watch::channel(None);
+        self.requests.push_back(CachedRequest {
+            id: id.to_string(),
+            path: path.to_string(),
+            digest,
+            created: Instant::now(),
+            response: rx,
+        });
+        Ok(CacheEntry::New(tx))
+    }
+
+    /// Removes a call, so that its next retry runs it again. The call is
+    /// identified by the channel of its result as well, as it may have been
+    /// evicted and its ID reused by a newer call meanwhile.
+    fn remove(
+        &mut self,
+        id: &str,
+        response: &watch::Receiver<Option<CachedResponse>>,
+    ) {
+        self.requests
+            .retain(|r| r.id != id || !r.response.same_channel(response));
+    }
+}
+
+/// Returns the request ID of a call to a mutating v1 method, if any.
+fn request_id<B>(request: &http::Request<B>) -> Option<String> {
+    let path = request.uri().path();
+    if !path.starts_with("/mayastor.v1.") {
+        return None;
+    }
+    mutating_method(path)?;
+
+    let id = request.headers().get(REQUEST_ID_KEY)?.to_str().ok()?;
+    (!id.is_empty()).then(|| id.to_string())
+}
+
+/// Tower layer returning the kept result of the retried calls, on all the
+/// services of the gRPC server.
+#[derive(Clone, Default)]
+pub(super) struct RequestCacheLayer(Arc<Mutex<RequestCache>>);
+
+impl<S> Layer<S> for RequestCacheLayer {
+    type Service = RequestCacheService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestCacheService {
+            inner,
+            cache: self.0.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct RequestCacheService<S> {
+    inner: S,
+    cache: Arc<Mutex<RequestCache>>,
+}
+
+impl<S> Service<http::Request<hyper::Body>> for RequestCacheService<S>
+where
+    S: Service<http::Request<hyper::Body>, Response = http::Response<BoxBody>>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<hyper::Body>) -> Self::Future {
+        let Some(id) = request_id(&request) else {
+            return Box::pin(self.inner.call(request));
+        };
+
+        // Take the service which was polled ready, leaving a clone behind.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let cache = self.cache.clone();
+
+        Box::pin(async move {
+            let path = request.uri().path().to_string();
+            let (parts, body) = request.into_parts();
+            let message = match hyper::body::to_bytes(body).await {
+                Ok(message) => message,
+                Err(error) => {
+                    let status = Status::invalid_argument(format!(
+                        "Failed to read request '{id}': {error}"
+                    ));
+                    return Ok(status.to_http());
+                }
+            };
+            let mut hasher = DefaultHasher::new();
+            message.hash(&mut hasher);
+
+            let entry = cache.lock().entry(&id, &path, hasher.finish());
+            let tx = match entry {
+                Ok(CacheEntry::New(tx)) => tx,
+                Ok(CacheEntry::Retry(mut rx)) => {
+                    info!("{path}: returning the result of request '{id}'");
+                    let response = rx
+                        .wait_for(Option::is_some)
+                        .await
+                        .map(|response| response.clone());
+                    return Ok(match response {
+                        Ok(Some(response)) => response.to_http(),
+                        _ => {
+                            cache.lock().remove(&id, &rx);
+                            Status::aborted(format!(
+                                "Request '{id}' did not complete"
+                            ))
+                            .to_http()
+                        }
+                    });
+                }
+                Err(status) => return Ok(status.to_http()),
+            };
+
+            let call = inner.call(http::Request::from_parts(
+                parts,
+                hyper::Body::from(message),
+            ));
+            // Run the call apart, so that it completes and its result is kept
+            // even if the client gives up on it.
+            let rx = tx.subscribe();
+            let task = tokio::spawn(async move {
+                let response = match call.await {
+                    Ok(response) => CachedResponse::collect(response).await,
+                    Err(error) => {
+                        cache.lock().remove(&id, &rx);
+                        return Err(error);
+                    }
+                };
+                let response = match response {
+                    Ok(response) => response,
+                    Err(status) => {
+                        cache.lock().remove(&id, &rx);
+                        return Ok(status.to_http());
+                    }
+                };
+
+                if response.is_retryable() {
+                    cache.lock().remove(&id, &rx);
+                }
+                tx.send_replace(Some(response.clone()));
+                Ok(response.to_http())
+            });
+
+            match task.await {
+                Ok(result) => result,
+                Err(_) => {
+                    Ok(Status::cancelled("gRPC call cancelled").to_http())
+                }
+            }
+        })
+    }
+}
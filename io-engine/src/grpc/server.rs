@@ -1,16 +1,28 @@
 use super::{
+    audit::AuditLayer,
+    authz::{AuthzLayer, GrpcAuthz},
+    otel,
+    request_cache::RequestCacheLayer,
+    tls::GrpcTls,
     v0::{
         bdev_grpc::BdevSvc,
         json_grpc::JsonRpcSvc,
         mayastor_grpc::MayastorSvc,
     },
     v1::{
+        audit::AuditService,
         bdev::BdevService,
+        copy_job::CopyJobService,
+        discovery::DiscoveryService,
+        event::EventService,
         host::HostService,
+        initiator::InitiatorService,
         json::JsonService,
         nexus::NexusService,
+        operation::OperationService,
         pool::PoolService,
         replica::ReplicaService,
+        replication::ReplicationService,
         snapshot::SnapshotService,
         snapshot_rebuild::SnapshotRebuildService,
         stats::StatsService,
@@ -27,7 +39,10 @@ use io_engine_api::{
     v1,
 };
 
-use crate::subsys::registration::registration_grpc::ApiVersion;
+use crate::{
+    core::MayastorEnvironment,
+    subsys::registration::registration_grpc::ApiVersion,
+};
 use futures::{select, FutureExt, StreamExt};
 use once_cell::sync::OnceCell;
 use std::{borrow::Cow, time::Duration};
@@ -80,7 +95,26 @@ impl MayastorGrpcServer {
             "{:?} gRPC server configured at address {}",
             api_versions, endpoint
         );
-        let svc = Server::builder()
+
+        let env = MayastorEnvironment::global_or_default();
+        let tls = GrpcTls::from_env(&env).map_err(|error| {
+            error!("Failed to set up gRPC TLS: {error}");
+        })?;
+        let authz = GrpcAuthz::from_env(&env).map_err(|error| {
+            error!("Failed to set up gRPC authorization: {error}");
+        })?;
+        if let Some(tls) = &tls {
+            tokio::spawn(tls.clone().watch());
+        }
+        if let Some(authz) = &authz {
+            tokio::spawn(authz.clone().watch());
+        }
+
+        let router = Server::builder()
+            .trace_fn(otel::request_span)
+            .layer(AuditLayer::default())
+            .layer(AuthzLayer::new(authz))
+            .layer(RequestCacheLayer::default())
             .add_optional_service(
                 enable_v1
                     .map(|_| v1::bdev::BdevRpcServer::new(BdevService::new())),
@@ -110,6 +144,29 @@ impl MayastorGrpcServer {
                     SnapshotRebuildService::new(replica_v1.clone()),
                 )
             }))
+            .add_optional_service(enable_v1.map(|_| {
+                v1::copy_job::CopyJobRpcServer::new(CopyJobService::new())
+            }))
+            .add_optional_service(enable_v1.map(|_| {
+                v1::discovery::DiscoveryRpcServer::new(DiscoveryService::new())
+            }))
+            .add_optional_service(enable_v1.map(|_| {
+                v1::event::EventRpcServer::new(EventService::new())
+            }))
+            .add_optional_service(enable_v1.map(|_| {
+                v1::initiator::InitiatorRpcServer::new(InitiatorService::new())
+            }))
+            .add_optional_service(enable_v1.map(|_| {
+                v1::operation::OperationRpcServer::new(OperationService::new())
+            }))
+            .add_optional_service(enable_v1.map(|_| {
+                v1::audit::AuditRpcServer::new(AuditService::new())
+            }))
+            .add_optional_service(enable_v1.map(|_| {
+                v1::replication::ReplicationRpcServer::new(
+                    ReplicationService::new(),
+                )
+            }))
             .add_optional_service(enable_v1.map(|_| {
                 v1::host::HostRpcServer::new(HostService::new(
                     node_name,
@@ -140,8 +197,18 @@ impl MayastorGrpcServer {
             )
             .add_optional_service(
                 enable_v0.map(|_| BdevRpcServer::new(BdevSvc::new())),
-            )
-            .serve(endpoint);
+            );
+
+        let svc = match tls {
+            Some(tls) => {
+                let incoming =
+                    tls.incoming(endpoint).await.map_err(|error| {
+                        error!("Failed to listen on {endpoint}: {error}");
+                    })?;
+                router.serve_with_incoming(incoming).boxed()
+            }
+            None => router.serve(endpoint).boxed(),
+        };
 
         select! {
             result = svc.fuse() => {
@@ -0,0 +1,350 @@
+//! TLS of the gRPC server, with optional verification of the client
+//! certificates (mutual TLS). The certificates are reloaded whenever their
+//! files change, so that they can be renewed without restarting the server.
+
+use std::{
+    fs,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, SystemTime},
+};
+
+use parking_lot::{Mutex, RwLock};
+use rustls::{
+    server::AllowAnyAuthenticatedClient,
+    Certificate,
+    PrivateKey,
+    RootCertStore,
+    ServerConfig,
+};
+use snafu::{ResultExt, Snafu};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::{TcpListener, TcpStream},
+};
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::server::Connected;
+
+use crate::core::MayastorEnvironment;
+
+/// Interval between checks of the TLS files for changes.
+pub(super) const RELOAD_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Time given to a client to complete its TLS handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Snafu)]
+#[snafu(context(suffix(false)))]
+pub(super) enum TlsError {
+    #[snafu(display("Failed to read {path}: {source}"))]
+    ReadFile { source: io::Error, path: String },
+    #[snafu(display("No certificate found in {path}"))]
+    NoCertificate { path: String },
+    #[snafu(display("No private key found in {path}"))]
+    NoPrivateKey { path: String },
+    #[snafu(display("Invalid TLS configuration: {source}"))]
+    InvalidConfig { source: rustls::Error },
+}
+
+/// Modification times of a set of files, to detect their changes.
+pub(super) struct FileWatch {
+    paths: Vec<String>,
+    modified: Vec<Option<SystemTime>>,
+}
+
+impl FileWatch {
+    pub(super) fn new(paths: Vec<String>) -> Self {
+        let modified = Self::modified(&paths);
+        Self {
+            paths,
+            modified,
+        }
+    }
+
+    /// Checks if any of the files changed since the last check.
+    pub(super) fn changed(&mut self) -> bool {
+        let modified = Self::modified(&self.paths);
+        if modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+
+    fn modified(paths: &[String]) -> Vec<Option<SystemTime>> {
+        paths
+            .iter()
+            .map(|p| fs::metadata(p).and_then(|m| m.modified()).ok())
+            .collect()
+    }
+}
+
+/// TLS configuration of the gRPC server.
+pub(super) struct GrpcTls {
+    cert: String,
+    key: String,
+    client_ca: Option<String>,
+    config: RwLock<Arc<ServerConfig>>,
+    watch: Mutex<FileWatch>,
+}
+
+impl GrpcTls {
+    /// Loads the TLS configuration from the files given on the command line,
+    /// if any.
+    pub(super) fn from_env(
+        env: &MayastorEnvironment,
+    ) -> Result<Option<Arc<Self>>, TlsError> {
+        let (Some(cert), Some(key)) = (&env.grpc_tls_cert, &env.grpc_tls_key)
+        else {
+            return Ok(None);
+        };
+        let client_ca = env.grpc_tls_client_ca.clone();
+
+        let config = load_config(cert, key, client_ca.as_deref())?;
+        let mut paths = vec![cert.clone(), key.clone()];
+        paths.extend(client_ca.clone());
+
+        info!(
+            "gRPC TLS enabled, client certificates {}",
+            if client_ca.is_some() {
+                "required"
+            } else {
+                "not verified"
+            }
+        );
+
+        Ok(Some(Arc::new(Self {
+            cert: cert.clone(),
+            key: key.clone(),
+            client_ca,
+            config: RwLock::new(config),
+            watch: Mutex::new(FileWatch::new(paths)),
+        })))
+    }
+
+    /// Periodically reloads the TLS configuration when its files change.
+    /// The new configuration applies to the new connections.
+    pub(super) async fn watch(self: Arc<Self>) {
+        loop {
+            tokio::time::sleep(RELOAD_INTERVAL).await;
+            if !self.watch.lock().changed() {
+                continue;
+            }
+
+            match load_config(&self.cert, &self.key, self.client_ca.as_deref())
+            {
+                Ok(config) => {
+                    *self.config.write() = config;
+                    info!("gRPC TLS certificates reloaded");
+                }
+                Err(error) => {
+                    error!(
+                        "Failed to reload gRPC TLS certificates, keeping the \
+                        current ones: {error}"
+                    );
+                }
+            }
+        }
+    }
+
+    /// Listens on the given endpoint, and returns the stream of the client
+    /// connections which completed their TLS handshake.
+    pub(super) async fn incoming(
+        self: Arc<Self>,
+        endpoint: SocketAddr,
+    ) -> io::Result<ReceiverStream<io::Result<GrpcTlsStream>>> {
+        let listener = TcpListener::bind(endpoint).await?;
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+
+        tokio::spawn(async move {
+            while !tx.is_closed() {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(error) => {
+                        warn!("Failed to accept gRPC connection: {error}");
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+
+                // Handshakes run apart, so that a slow client does not hold
+                // the others back, and are timed out so that a stalled one
+                // does not hold its connection forever.
+                let acceptor = TlsAcceptor::from(self.config.read().clone());
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    let handshake = acceptor.accept(stream);
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake)
+                        .await
+                    {
+                        Ok(Ok(stream)) => {
+                            tx.send(Ok(GrpcTlsStream(stream))).await.ok();
+                        }
+                        Ok(Err(error)) => {
+                            warn!("TLS handshake with {peer} failed: {error}");
+                        }
+                        Err(_) => {
+                            warn!("TLS handshake with {peer} timed out");
+                        }
+                    }
+                });
+            }
+        });
+
+        Ok(ReceiverStream::new(rx))
+    }
+}
+
+/// Builds the TLS configuration of the server from its files.
+fn load_config(
+    cert: &str,
+    key: &str,
+    client_ca: Option<&str>,
+) -> Result<Arc<ServerConfig>, TlsError> {
+    let certs = read_certs(cert)?;
+    let key = read_key(key)?;
+
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let builder = match client_ca {
+        Some(client_ca) => {
+            let cas = read_certs(client_ca)?
+                .into_iter()
+                .map(|ca| ca.0)
+                .collect::<Vec<_>>();
+            let mut roots = RootCertStore::empty();
+            let (_, invalid) = roots.add_parsable_certificates(&cas);
+            if invalid > 0 {
+                warn!(
+                    "Ignored {invalid} invalid CA certificates in {client_ca}"
+                );
+            }
+            if roots.is_empty() {
+                return Err(TlsError::NoCertificate {
+                    path: client_ca.to_string(),
+                });
+            }
+            builder.with_client_cert_verifier(
+                AllowAnyAuthenticatedClient::new(roots).boxed(),
+            )
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut config = builder
+        .with_single_cert(certs, key)
+        .context(InvalidConfig)?;
+    config.alpn_protocols = vec![b"h2".to_vec()];
+    Ok(Arc::new(config))
+}
+
+/// Reads the certificates of a PEM file.
+fn read_certs(path: &str) -> Result<Vec<Certificate>, TlsError> {
+    let data = fs::read(path).context(ReadFile {
+        path,
+    })?;
+    let certs =
+        rustls_pemfile::certs(&mut data.as_slice()).context(ReadFile {
+            path,
+        })?;
+    if certs.is_empty() {
+        return Err(TlsError::NoCertificate {
+            path: path.to_string(),
+        });
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+/// Reads the first private key of a PEM file.
+fn read_key(path: &str) -> Result<PrivateKey, TlsError> {
+    let data = fs::read(path).context(ReadFile {
+        path,
+    })?;
+    rustls_pemfile::read_all(&mut data.as_slice())
+        .context(ReadFile {
+            path,
+        })?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| TlsError::NoPrivateKey {
+            path: path.to_string(),
+        })
+}
+
+/// Connection information of a gRPC client connected over TLS, available
+/// from the extensions of its requests.
+#[derive(Debug, Clone)]
+pub(super) struct TlsConnectInfo {
+    /// Address of the client.
+    pub(super) remote_addr: Option<SocketAddr>,
+    /// Certificate chain presented by the client, if any.
+    pub(super) certs: Option<Arc<Vec<Certificate>>>,
+}
+
+impl TlsConnectInfo {
+    /// Returns the common name of the client certificate, which identifies
+    /// the client.
+    pub(super) fn common_name(&self) -> Option<String> {
+        let cert = self.certs.as_ref()?.first()?;
+        let (_, cert) = x509_parser::parse_x509_certificate(&cert.0).ok()?;
+        let name = cert.subject().iter_common_name().next()?;
+        name.as_str().ok().map(String::from)
+    }
+}
+
+/// A client connection over TLS.
+pub(super) struct GrpcTlsStream(TlsStream<TcpStream>);
+
+impl Connected for GrpcTlsStream {
+    type ConnectInfo = TlsConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        let (io, session) = self.0.get_ref();
+        TlsConnectInfo {
+            remote_addr: io.peer_addr().ok(),
+            certs: session.peer_certificates().map(|c| Arc::new(c.to_vec())),
+        }
+    }
+}
+
+impl AsyncRead for GrpcTlsStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for GrpcTlsStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
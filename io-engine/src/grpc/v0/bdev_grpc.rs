@@ -18,7 +18,7 @@ use io_engine_api::v0::{
 use crate::{
     bdev_api::{bdev_create, bdev_destroy, BdevError},
     core::{CoreError, NvmfShareProps, Share, UntypedBdev},
-    grpc::{rpc_submit, GrpcResult},
+    grpc::{audit, rpc_submit, GrpcResult},
 };
 
 impl From<UntypedBdev> for RpcBdev {
@@ -81,6 +81,7 @@ impl BdevRpc for BdevSvc {
         &self,
         request: Request<BdevUri>,
     ) -> Result<Response<CreateReply>, Status> {
+        audit::record_args(&request);
         let uri = request.into_inner().uri;
 
         let rx = rpc_submit(async move { bdev_create(&uri).await })?;
@@ -97,6 +98,7 @@ impl BdevRpc for BdevSvc {
 
     #[instrument(level = "debug", err)]
     async fn destroy(&self, request: Request<BdevUri>) -> GrpcResult<Null> {
+        audit::record_args(&request);
         let uri = request.into_inner().uri;
 
         let rx = rpc_submit(async move { bdev_destroy(&uri).await })?;
@@ -112,6 +114,7 @@ impl BdevRpc for BdevSvc {
         &self,
         request: Request<BdevShareRequest>,
     ) -> Result<Response<BdevShareReply>, Status> {
+        audit::record_args(&request);
         let r = request.into_inner();
         let bdev_name = r.name.clone();
         let proto = &r.proto;
@@ -150,6 +153,7 @@ impl BdevRpc for BdevSvc {
 
     #[instrument(level = "debug", err)]
     async fn unshare(&self, request: Request<CreateReply>) -> GrpcResult<Null> {
+        audit::record_args(&request);
         let rx = rpc_submit::<_, _, CoreError>(async {
             let name = request.into_inner().name;
             if let Some(mut bdev) = UntypedBdev::lookup_by_name(&name) {
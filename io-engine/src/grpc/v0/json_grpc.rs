@@ -1,7 +1,7 @@
 //!
 //! gRPC method to proxy calls to (local) SPDK json-rpc service
 
-use crate::grpc::GrpcResult;
+use crate::grpc::{audit, GrpcResult};
 use io_engine_api::v0::{
     json_rpc_server::JsonRpc,
     JsonRpcReply,
@@ -34,6 +34,7 @@ impl JsonRpc for JsonRpcSvc {
         &self,
         request: Request<JsonRpcRequest>,
     ) -> GrpcResult<JsonRpcReply> {
+        audit::record_args(&request);
         let args = request.into_inner();
 
         let result = self
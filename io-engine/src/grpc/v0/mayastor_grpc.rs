@@ -296,6 +296,9 @@ impl From<LvsError> for tonic::Status {
             LvsError::InvalidBdev {
                 source, ..
             } => source.into(),
+            LvsError::UnsupportedProtectionInfo {
+                ..
+            } => Status::failed_precondition(e.to_string()),
             LvsError::SetProperty {
                 ..
             } => Status::data_loss(e.to_string()),
@@ -714,7 +717,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                     }
 
                     let p = Lvs::lookup(&args.pool).unwrap();
-                    match p
+                    let replica = match p
                         .create_lvol(
                             &args.uuid, args.size, None, args.thin, None,
                         )
@@ -761,7 +764,9 @@ impl mayastor_server::Mayastor for MayastorSvc {
                             Ok(Replica::from(lvol))
                         }
                         Err(e) => Err(e),
-                    }
+                    }?;
+                    PoolConfig::save().await;
+                    Ok(replica)
                 })?;
 
                 rx.await
@@ -808,7 +813,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                         });
                     }
 
-                    match lvs
+                    let replica = match lvs
                         .create_lvol(
                             &args.name,
                             args.size,
@@ -859,7 +864,9 @@ impl mayastor_server::Mayastor for MayastorSvc {
                             Ok(ReplicaV2::from(lvol))
                         }
                         Err(e) => Err(e),
-                    }
+                    }?;
+                    PoolConfig::save().await;
+                    Ok(replica)
                 })?;
 
                 rx.await
@@ -882,6 +889,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 if let Some(bdev) = UntypedBdev::lookup_by_name(&args.uuid) {
                     let lvol = Lvol::try_from(bdev)?;
                     lvol.destroy().await?;
+                    PoolConfig::save().await;
                 }
                 Ok(Null {})
             })?;
@@ -1015,6 +1023,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                                         ),
                                     )
                                     .await?;
+                                PoolConfig::save().await;
                                 return Ok(ShareReplicaReply {
                                     uri: lvol.bdev_share_uri().unwrap(),
                                 });
@@ -1039,6 +1048,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                                     lvol.as_mut().share_nvmf(Some(props)).await?;
                                 }
                             }
+                            PoolConfig::save().await;
 
                             Ok(ShareReplicaReply {
                                 uri: lvol.bdev_share_uri().unwrap(),
@@ -1084,6 +1094,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .await?;
                 let nexus = nexus_lookup(&uuid)?;
                 info!("Created nexus: '{}'", uuid);
+                PoolConfig::save().await;
                 Ok(nexus.to_grpc().await)
             })?;
             rx.await
@@ -1129,13 +1140,17 @@ impl mayastor_server::Mayastor for MayastorSvc {
                         },
                         resv_type,
                         preempt_policy,
+                        pi_policy: Default::default(),
                     },
+                    nexus::NexusLayout::Mirror,
                     &args.children,
                     nexus_info_key,
+                    None,
                 )
                 .await?;
                 let nexus = nexus_lookup(&args.name)?;
                 info!("Created nexus '{}'", &args.name);
+                PoolConfig::save().await;
                 Ok(nexus.to_grpc().await)
             })?;
             rx.await
@@ -1159,6 +1174,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
             let rx = rpc_submit::<_, _, nexus::Error>(async move {
                 trace!("{:?}", args);
                 nexus_destroy(&args.uuid).await?;
+                PoolConfig::save().await;
                 Ok(Null {})
             })?;
 
@@ -1264,6 +1280,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 debug!("Adding child {} to nexus {} ...", args.uri, uuid);
                 let child = nexus_add_child(args).await?;
                 info!("Added child to nexus {}", uuid);
+                PoolConfig::save().await;
                 Ok(child)
             })?;
 
@@ -1290,6 +1307,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 debug!("Removing child {} from nexus {} ...", args.uri, uuid);
                 nexus_lookup(&args.uuid)?.remove_child(&args.uri).await?;
                 info!("Removed child from nexus {}", uuid);
+                PoolConfig::save().await;
                 Ok(Null {})
             })?;
 
@@ -1371,6 +1389,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                     "Published nexus {} under {} for {:?}",
                     uuid, device_uri, args.allowed_hosts
                 );
+                PoolConfig::save().await;
                 Ok(PublishNexusReply {
                     device_uri,
                 })
@@ -1398,6 +1417,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 debug!("Unpublishing nexus {} ...", uuid);
                 nexus_lookup(&args.uuid)?.unshare_nexus().await?;
                 info!("Unpublished nexus {}", uuid);
+                PoolConfig::save().await;
                 Ok(Null {})
             })?;
 
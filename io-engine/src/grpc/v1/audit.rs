@@ -0,0 +1,68 @@
+use crate::grpc::{
+    audit::{AuditEntry, AuditLog},
+    GrpcResult,
+};
+use io_engine_api::v1::audit::{
+    self,
+    AuditRpc,
+    ListAuditEntriesRequest,
+    ListAuditEntriesResponse,
+};
+use std::time::Duration;
+use tonic::{Request, Response};
+
+#[derive(Debug, Default)]
+pub struct AuditService {
+    #[allow(unused)]
+    name: String,
+}
+
+impl AuditService {
+    pub fn new() -> Self {
+        Self {
+            name: String::from("AuditService"),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl AuditRpc for AuditService {
+    async fn list_audit_entries(
+        &self,
+        request: Request<ListAuditEntriesRequest>,
+    ) -> GrpcResult<ListAuditEntriesResponse> {
+        let args = request.into_inner();
+
+        let mut entries = AuditLog::get()
+            .recent()
+            .into_iter()
+            .filter(|e| {
+                args.method.as_ref().map_or(true, |m| e.method.contains(m))
+            })
+            .collect::<Vec<_>>();
+        // Keep the most recent entries.
+        if let Some(max_entries) = args.max_entries {
+            let skip = entries.len().saturating_sub(max_entries as usize);
+            entries.drain(.. skip);
+        }
+
+        Ok(Response::new(ListAuditEntriesResponse {
+            entries: entries.into_iter().map(Into::into).collect(),
+        }))
+    }
+}
+
+impl From<AuditEntry> for audit::AuditEntry {
+    fn from(value: AuditEntry) -> Self {
+        Self {
+            timestamp: Some(value.timestamp.into()),
+            peer: value.peer.map(|peer| peer.to_string()),
+            identity: value.identity,
+            method: value.method,
+            args: value.args,
+            duration: Duration::from_micros(value.duration_us).try_into().ok(),
+            code: value.code as i32,
+            message: value.message,
+        }
+    }
+}
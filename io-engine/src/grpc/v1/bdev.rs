@@ -2,7 +2,7 @@ use crate::{
     bdev_api::{bdev_create, bdev_destroy, BdevError},
     core,
     core::{CoreError, NvmfShareProps, Protocol, Share},
-    grpc::{rpc_submit, GrpcResult},
+    grpc::{audit, rpc_submit, GrpcResult},
 };
 use io_engine_api::v1::bdev::{
     Bdev,
@@ -92,6 +92,7 @@ impl BdevRpc for BdevService {
         &self,
         request: Request<CreateBdevRequest>,
     ) -> Result<Response<CreateBdevResponse>, Status> {
+        audit::record_args(&request);
         let uri = request.into_inner().uri;
 
         let rx = rpc_submit(async move {
@@ -118,6 +119,7 @@ impl BdevRpc for BdevService {
         &self,
         request: Request<DestroyBdevRequest>,
     ) -> GrpcResult<()> {
+        audit::record_args(&request);
         let uri = request.into_inner().uri;
 
         let rx = rpc_submit(async move { bdev_destroy(&uri).await })?;
@@ -133,6 +135,7 @@ impl BdevRpc for BdevService {
         &self,
         request: Request<BdevShareRequest>,
     ) -> Result<Response<BdevShareResponse>, Status> {
+        audit::record_args(&request);
         let r = request.into_inner();
         let bdev_name = r.name.clone();
         let protocol = r.protocol;
@@ -172,6 +175,7 @@ impl BdevRpc for BdevService {
         &self,
         request: Request<BdevUnshareRequest>,
     ) -> GrpcResult<()> {
+        audit::record_args(&request);
         let rx = rpc_submit::<_, _, CoreError>(async {
             let name = request.into_inner().name;
             if let Some(mut bdev) = core::UntypedBdev::lookup_by_name(&name) {
@@ -0,0 +1,244 @@
+use crate::{
+    core::Reactors,
+    grpc::{audit, GrpcResult},
+    rebuild::{CopyJob, CopyJobPhase, CopyJobStats, RebuildState},
+    sleep::mayastor_sleep,
+};
+use io_engine_api::v1::{
+    copy_job,
+    copy_job::{
+        CopyJobRequest,
+        CopyJobRpc,
+        ListCopyJobsRequest,
+        ListCopyJobsResponse,
+        StartCopyJobRequest,
+        WatchCopyJobRequest,
+    },
+};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+/// Default interval between progress updates of a watched copy job.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default)]
+pub struct CopyJobService {
+    #[allow(unused)]
+    name: String,
+}
+
+impl CopyJobService {
+    pub fn new() -> Self {
+        Self {
+            name: String::from("CopyJobService"),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl CopyJobRpc for CopyJobService {
+    type WatchCopyJobStream = ReceiverStream<Result<copy_job::CopyJob, Status>>;
+
+    async fn start_copy_job(
+        &self,
+        request: Request<StartCopyJobRequest>,
+    ) -> GrpcResult<copy_job::CopyJob> {
+        audit::record_args(&request);
+        let request = request.into_inner();
+
+        crate::spdk_submit!(async move {
+            info!("{:?}", request);
+
+            // A job which is over without having copied the source is
+            // replaced by a new one, so that the copy can be retried.
+            if let Ok(job) = CopyJob::lookup(&request.uuid) {
+                if !job.is_done() || job.is_completed() {
+                    return Ok(CopyJobInfo::from(job).await.into());
+                }
+            }
+            let job = CopyJob::builder()
+                .with_uuid(&request.uuid)
+                .with_verify(request.verify)
+                .with_live(request.live)
+                .build(&request.src_uri, &request.dst_uri)
+                .await?
+                .store()?;
+            if let Err(error) = job.start() {
+                CopyJob::remove(job.uuid()).ok();
+                return Err(error.into());
+            }
+            Ok(CopyJobInfo::from(job).await.into())
+        })
+    }
+
+    async fn pause_copy_job(
+        &self,
+        request: Request<CopyJobRequest>,
+    ) -> GrpcResult<copy_job::CopyJob> {
+        audit::record_args(&request);
+        crate::spdk_submit!(async move {
+            let args = request.into_inner();
+            info!("{:?}", args);
+            let job = CopyJob::lookup(&args.uuid)?;
+            job.pause()?;
+            Ok(CopyJobInfo::from(job).await.into())
+        })
+    }
+
+    async fn resume_copy_job(
+        &self,
+        request: Request<CopyJobRequest>,
+    ) -> GrpcResult<copy_job::CopyJob> {
+        audit::record_args(&request);
+        crate::spdk_submit!(async move {
+            let args = request.into_inner();
+            info!("{:?}", args);
+            let job = CopyJob::lookup(&args.uuid)?;
+            job.resume()?;
+            Ok(CopyJobInfo::from(job).await.into())
+        })
+    }
+
+    async fn cancel_copy_job(
+        &self,
+        request: Request<CopyJobRequest>,
+    ) -> GrpcResult<()> {
+        audit::record_args(&request);
+        crate::spdk_submit!(async move {
+            let args = request.into_inner();
+            info!("{:?}", args);
+            let job = CopyJob::remove(&args.uuid)?;
+            job.cancel().await;
+            info!("Copy job cancelled: {job:?}");
+            Ok(())
+        })
+    }
+
+    async fn list_copy_jobs(
+        &self,
+        request: Request<ListCopyJobsRequest>,
+    ) -> GrpcResult<ListCopyJobsResponse> {
+        crate::spdk_submit!(async move {
+            let args = request.into_inner();
+            trace!("{:?}", args);
+            let jobs = match args.uuid {
+                None => CopyJob::list(),
+                Some(uuid) => vec![CopyJob::lookup(&uuid)?],
+            };
+            let mut copy_jobs = Vec::with_capacity(jobs.len());
+            for job in jobs {
+                copy_jobs.push(CopyJobInfo::from(job).await.into());
+            }
+            Ok(ListCopyJobsResponse {
+                copy_jobs,
+            })
+        })
+    }
+
+    async fn watch_copy_job(
+        &self,
+        request: Request<WatchCopyJobRequest>,
+    ) -> Result<Response<Self::WatchCopyJobStream>, Status> {
+        let args = request.into_inner();
+        let interval = match args.interval_ms {
+            Some(ms) if ms > 0 => Duration::from_millis(ms),
+            _ => WATCH_INTERVAL,
+        };
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+
+        Reactors::master().send_future(async move {
+            let job = match CopyJob::lookup(&args.uuid) {
+                Ok(job) => job,
+                Err(error) => {
+                    tx.try_send(Err(error.into())).ok();
+                    return;
+                }
+            };
+
+            loop {
+                let info = CopyJobInfo::from(job.clone()).await;
+                let done = info.stats.phase == CopyJobPhase::Done;
+
+                // `send()` may get stuck if we send/receive from different
+                // reactors, so use try_send and skip the update if the client
+                // is lagging behind.
+                if let Err(TrySendError::Closed(_)) =
+                    tx.try_send(Ok(info.into()))
+                {
+                    break;
+                }
+                if done {
+                    break;
+                }
+                mayastor_sleep(interval).await.ok();
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// A copy job along with its progress.
+struct CopyJobInfo {
+    stats: CopyJobStats,
+    job: Arc<CopyJob>,
+}
+impl CopyJobInfo {
+    async fn from(job: Arc<CopyJob>) -> Self {
+        let stats = job.stats().await;
+        Self {
+            stats,
+            job,
+        }
+    }
+}
+
+impl From<CopyJobInfo> for copy_job::CopyJob {
+    fn from(value: CopyJobInfo) -> Self {
+        let stats = value.stats;
+        let job = value.job;
+        let phase = &stats.phase_stats;
+        Self {
+            uuid: job.uuid().to_string(),
+            src_uri: job.src_uri().to_string(),
+            dst_uri: job.dst_uri().to_string(),
+            verify: job.verify(),
+            live: job.is_live(),
+            phase: copy_job::CopyJobPhase::from(stats.phase) as i32,
+            status: copy_job::CopyJobStatus::from(stats.state) as i32,
+            phase_total: phase.blocks_total * phase.block_size,
+            phase_copied: phase.blocks_transferred * phase.block_size,
+            phase_progress: phase.progress as u32,
+            copied: stats.blocks_copied * phase.block_size,
+            start_timestamp: Some(stats.start_time.into()),
+            end_timestamp: stats.end_time.map(Into::into),
+            error: stats.error.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<CopyJobPhase> for copy_job::CopyJobPhase {
+    fn from(value: CopyJobPhase) -> Self {
+        match value {
+            CopyJobPhase::Init => Self::Init,
+            CopyJobPhase::Copy => Self::Copy,
+            CopyJobPhase::CatchUp => Self::CatchUp,
+            CopyJobPhase::Done => Self::Done,
+        }
+    }
+}
+
+impl From<RebuildState> for copy_job::CopyJobStatus {
+    fn from(value: RebuildState) -> Self {
+        match value {
+            RebuildState::Init => Self::Created,
+            RebuildState::Running => Self::Running,
+            RebuildState::Stopped => Self::Cancelled,
+            RebuildState::Paused => Self::Paused,
+            RebuildState::Failed => Self::Failed,
+            RebuildState::Completed => Self::Successful,
+        }
+    }
+}
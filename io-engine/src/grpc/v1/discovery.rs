@@ -0,0 +1,112 @@
+use crate::{
+    grpc::{audit, GrpcResult},
+    subsys::{Config, DiscoveryReferral, NvmfTarget},
+};
+use io_engine_api::v1::discovery::{
+    AddReferralRequest,
+    DiscoveryRpc,
+    ListReferralsRequest,
+    ListReferralsResponse,
+    Referral,
+    RemoveReferralRequest,
+};
+use std::net::IpAddr;
+use tonic::{Request, Status};
+
+/// Service managing the referrals of the discovery service of the NVMe-oF
+/// target. The referrals only live as long as the io-engine: the control
+/// plane is expected to register them again when it sees the io-engine
+/// restart, as it does for the other resources it doesn't import.
+#[derive(Debug, Default)]
+pub struct DiscoveryService {
+    #[allow(unused)]
+    name: String,
+}
+
+impl DiscoveryService {
+    pub fn new() -> Self {
+        Self {
+            name: String::from("DiscoveryService"),
+        }
+    }
+}
+
+/// Makes a referral to the discovery service of a peer io-engine, listening
+/// on the same port as this one unless told otherwise.
+fn referral(
+    address: &str,
+    port: Option<u32>,
+    rdma: bool,
+) -> Result<DiscoveryReferral, Status> {
+    let address = address.parse::<IpAddr>().map_err(|_| {
+        Status::invalid_argument(format!("Invalid address '{address}'"))
+    })?;
+    let port = match port {
+        Some(port) => u16::try_from(port).map_err(|_| {
+            Status::invalid_argument(format!("Invalid port {port}"))
+        })?,
+        None => Config::get().nexus_opts.nvmf_replica_port,
+    };
+
+    Ok(DiscoveryReferral {
+        address,
+        port,
+        rdma,
+    })
+}
+
+#[tonic::async_trait]
+impl DiscoveryRpc for DiscoveryService {
+    async fn add_referral(
+        &self,
+        request: Request<AddReferralRequest>,
+    ) -> GrpcResult<Referral> {
+        audit::record_args(&request);
+        let args = request.into_inner();
+        info!("{:?}", args);
+        let referral = referral(&args.address, args.port, args.rdma)?;
+
+        crate::spdk_submit!(async move {
+            NvmfTarget::add_referral(referral.clone())?;
+            Ok(referral.into())
+        })
+    }
+
+    async fn remove_referral(
+        &self,
+        request: Request<RemoveReferralRequest>,
+    ) -> GrpcResult<()> {
+        audit::record_args(&request);
+        let args = request.into_inner();
+        info!("{:?}", args);
+        let referral = referral(&args.address, args.port, args.rdma)?;
+
+        crate::spdk_submit!(async move {
+            NvmfTarget::remove_referral(&referral).map_err(Into::into)
+        })
+    }
+
+    async fn list_referrals(
+        &self,
+        _request: Request<ListReferralsRequest>,
+    ) -> GrpcResult<ListReferralsResponse> {
+        crate::spdk_submit!(async move {
+            Ok(ListReferralsResponse {
+                referrals: NvmfTarget::referrals()
+                    .into_iter()
+                    .map(Into::into)
+                    .collect(),
+            })
+        })
+    }
+}
+
+impl From<DiscoveryReferral> for Referral {
+    fn from(value: DiscoveryReferral) -> Self {
+        Self {
+            address: value.address.to_string(),
+            port: value.port as u32,
+            rdma: value.rdma,
+        }
+    }
+}
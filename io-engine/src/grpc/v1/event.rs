@@ -0,0 +1,134 @@
+use crate::eventing::event_ring::{
+    EventFilter,
+    EventRecord,
+    EventRing,
+    EventWatch,
+};
+use io_engine_api::v1::event::{EventRpc, WatchEventsRequest, WatchedEvent};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{metadata::MetadataValue, Request, Response, Status};
+
+/// Metadata key of the sequence number of the last event delivered to a
+/// watcher, which events were lost after.
+const LAST_SEQUENCE_KEY: &str = "last-sequence";
+
+/// Makes the status ending a watch which lost the events following the
+/// given sequence number: the watcher has to resync its state.
+fn events_lost(msg: String, last_sequence: u64) -> Status {
+    let mut status = Status::data_loss(msg);
+    status
+        .metadata_mut()
+        .insert(LAST_SEQUENCE_KEY, MetadataValue::from(last_sequence));
+    status
+}
+
+#[derive(Debug, Default)]
+pub struct EventService {
+    #[allow(unused)]
+    name: String,
+}
+
+impl EventService {
+    pub fn new() -> Self {
+        Self {
+            name: String::from("EventService"),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl EventRpc for EventService {
+    type WatchEventsStream = ReceiverStream<Result<WatchedEvent, Status>>;
+
+    async fn watch_events(
+        &self,
+        request: Request<WatchEventsRequest>,
+    ) -> Result<Response<Self::WatchEventsStream>, Status> {
+        let args = request.into_inner();
+        info!("{:?}", args);
+
+        let filter = EventFilter {
+            categories: args.categories,
+            actions: args.actions,
+            targets: args.targets,
+        };
+        let since = args.since_sequence;
+        let EventWatch {
+            replay,
+            mut events,
+            last_sequence,
+        } = EventRing::get().watch(since, &filter).map_err(|e| {
+            let since = since.unwrap_or_default();
+            events_lost(
+                format!(
+                    "Events following sequence {since} are no longer \
+                    buffered, the oldest one is {}",
+                    e.first_available
+                ),
+                since,
+            )
+        })?;
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+
+        tokio::spawn(async move {
+            // Sequence number of the last event the watcher is up to date
+            // with, whether it was delivered or filtered out.
+            let mut last = last_sequence;
+
+            for event in replay {
+                if tx.send(Ok(event.into())).await.is_err() {
+                    return;
+                }
+            }
+
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(missed)) => {
+                        // The client can resume from the last sequence number
+                        // it got, as long as the events are still buffered,
+                        // or has to resync otherwise.
+                        warn!("Event watcher lagging, {missed} events missed");
+                        let status = events_lost(
+                            format!(
+                                "Watcher lagged behind, {missed} events \
+                                following sequence {last} were missed"
+                            ),
+                            last,
+                        );
+                        tx.send(Err(status)).await.ok();
+                        break;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                last = event.sequence;
+                if !filter.matches(&event) {
+                    continue;
+                }
+                if tx.send(Ok(event.into())).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+impl From<EventRecord> for WatchedEvent {
+    fn from(value: EventRecord) -> Self {
+        let message = value.message;
+        Self {
+            sequence: value.sequence,
+            timestamp: Some(value.timestamp.into()),
+            category: message.category,
+            action: message.action,
+            target: message.target,
+            metadata: message
+                .metadata
+                .and_then(|meta| serde_json::to_string(&meta).ok())
+                .unwrap_or_default(),
+        }
+    }
+}
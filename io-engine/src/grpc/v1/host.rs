@@ -12,8 +12,25 @@ use crate::{
         GrpcResult,
         Serializer,
     },
-    host::{blk_device, resource},
-    subsys::{registration::registration_grpc::ApiVersion, Registration},
+    host::{
+        blk_device,
+        nvme_health::{
+            NvmeErrorLogEntry,
+            NvmeHealth,
+            NvmeSmartLog,
+            DEFAULT_ERROR_LOG_ENTRIES,
+        },
+        resource,
+    },
+    subsys::{
+        reconcile_node_state,
+        registration::registration_grpc::ApiVersion,
+        Config,
+        ConfigError,
+        ConfigReload,
+        Drift,
+        Registration,
+    },
 };
 use ::function_name::named;
 use futures::FutureExt;
@@ -232,6 +249,93 @@ impl From<BlockDeviceIoStats> for host_rpc::NvmeControllerIoStats {
     }
 }
 
+impl From<NvmeSmartLog> for host_rpc::NvmeSmartLog {
+    fn from(s: NvmeSmartLog) -> Self {
+        Self {
+            critical_warnings: s.critical_warnings(),
+            critical_warning: s.critical_warning as u32,
+            temperature: s.temperature as u32,
+            available_spare: s.available_spare as u32,
+            available_spare_threshold: s.available_spare_threshold as u32,
+            percentage_used: s.percentage_used as u32,
+            endurance_group_warning: s.endurance_group_warning as u32,
+            data_units_read: s.data_units_read,
+            data_units_written: s.data_units_written,
+            host_read_commands: s.host_read_commands,
+            host_write_commands: s.host_write_commands,
+            controller_busy_time: s.controller_busy_time,
+            power_cycles: s.power_cycles,
+            power_on_hours: s.power_on_hours,
+            unsafe_shutdowns: s.unsafe_shutdowns,
+            media_errors: s.media_errors,
+            num_error_log_entries: s.num_error_log_entries,
+            warning_temp_time: s.warning_temp_time,
+            critical_temp_time: s.critical_temp_time,
+        }
+    }
+}
+
+impl From<NvmeErrorLogEntry> for host_rpc::NvmeErrorLogEntry {
+    fn from(e: NvmeErrorLogEntry) -> Self {
+        Self {
+            error_count: e.error_count,
+            sqid: e.sqid as u32,
+            cmdid: e.cmdid as u32,
+            status: e.status as u32,
+            param_error_location: e.param_error_location as u32,
+            lba: e.lba,
+            nsid: e.nsid,
+        }
+    }
+}
+
+impl From<NvmeHealth> for host_rpc::NvmeHealth {
+    fn from(h: NvmeHealth) -> Self {
+        Self {
+            name: h.name,
+            smart: Some(h.smart.into()),
+            errors: h
+                .errors
+                .into_iter()
+                .map(host_rpc::NvmeErrorLogEntry::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<ConfigReload> for host_rpc::ReloadConfigResponse {
+    fn from(r: ConfigReload) -> Self {
+        Self {
+            applied: r.applied,
+            restart_required: r.restart_required,
+        }
+    }
+}
+
+impl From<Drift> for host_rpc::NodeStateDrift {
+    fn from(d: Drift) -> Self {
+        Self {
+            kind: d.kind.to_string(),
+            name: d.name,
+            reason: d.reason,
+            resolved: d.resolved,
+        }
+    }
+}
+
+impl From<ConfigError> for Status {
+    fn from(error: ConfigError) -> Self {
+        match error {
+            ConfigError::NoConfigFile {} | ConfigError::NoNodeStateFile {} => {
+                Self::failed_precondition(error.to_string())
+            }
+            ConfigError::LoadConfig {
+                ..
+            } => Self::invalid_argument(error.to_string()),
+        }
+    }
+}
+
 #[tonic::async_trait]
 impl host_rpc::HostRpc for HostService {
     async fn get_mayastor_info(
@@ -349,4 +453,85 @@ impl host_rpc::HostRpc for HostService {
         )
         .await
     }
+
+    #[named]
+    async fn get_nvme_health(
+        &self,
+        request: Request<host_rpc::GetNvmeHealthRequest>,
+    ) -> GrpcResult<host_rpc::GetNvmeHealthResponse> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                let max_errors =
+                    args.max_errors.unwrap_or(DEFAULT_ERROR_LOG_ENTRIES);
+                let rx = rpc_submit::<_, _, CoreError>(async move {
+                    let devices = match args.name {
+                        Some(name) => {
+                            vec![NvmeHealth::get(&name, max_errors).await?]
+                        }
+                        None => NvmeHealth::list(max_errors).await,
+                    };
+                    Ok(host_rpc::GetNvmeHealthResponse {
+                        devices: devices
+                            .into_iter()
+                            .map(host_rpc::NvmeHealth::from)
+                            .collect(),
+                    })
+                })?;
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
+    #[named]
+    async fn reload_config(
+        &self,
+        request: Request<()>,
+    ) -> GrpcResult<host_rpc::ReloadConfigResponse> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let rx = rpc_submit::<_, _, ConfigError>(async move {
+                    Config::reload().map(host_rpc::ReloadConfigResponse::from)
+                })?;
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
+    #[named]
+    async fn reconcile_node_state(
+        &self,
+        request: Request<host_rpc::ReconcileNodeStateRequest>,
+    ) -> GrpcResult<host_rpc::ReconcileNodeStateResponse> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                let rx = rpc_submit::<_, _, ConfigError>(async move {
+                    let drift = reconcile_node_state(args.dry_run).await?;
+                    Ok(host_rpc::ReconcileNodeStateResponse {
+                        drift: drift
+                            .into_iter()
+                            .map(host_rpc::NodeStateDrift::from)
+                            .collect(),
+                    })
+                })?;
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
 }
@@ -0,0 +1,161 @@
+use crate::{
+    bdev::nexus::NEXUS_MODULE_NAME,
+    grpc::{audit, GrpcResult},
+    subsys::{NvmfHostController, NvmfReservation, NvmfSubsystem, SubType},
+};
+use io_engine_api::v1::initiator::{
+    DisconnectInitiatorRequest,
+    Initiator,
+    InitiatorRpc,
+    ListInitiatorsRequest,
+    ListInitiatorsResponse,
+    Reservation,
+    SubsystemInitiators,
+    SubsystemKind,
+};
+use tonic::{Request, Status};
+
+#[derive(Debug, Default)]
+pub struct InitiatorService {
+    #[allow(unused)]
+    name: String,
+}
+
+impl InitiatorService {
+    pub fn new() -> Self {
+        Self {
+            name: String::from("InitiatorService"),
+        }
+    }
+}
+
+/// Looks up the NVMe subsystem with the given NQN.
+fn subsystem(nqn: &str) -> Result<NvmfSubsystem, Status> {
+    NvmfSubsystem::first()
+        .into_iter()
+        .flat_map(|ss| ss.into_iter())
+        .find(|ss| ss.subtype() == SubType::Nvme && ss.get_nqn() == nqn)
+        .ok_or_else(|| {
+            Status::not_found(format!("Subsystem '{nqn}' not found"))
+        })
+}
+
+/// Lists the hosts connected to a subsystem, along with their reservations.
+fn subsystem_initiators(ss: &NvmfSubsystem) -> SubsystemInitiators {
+    let (kind, name, uuid) = match ss.bdev() {
+        Some(bdev) => {
+            let kind = match bdev.driver() {
+                NEXUS_MODULE_NAME => SubsystemKind::Nexus,
+                "lvol" => SubsystemKind::Replica,
+                _ => SubsystemKind::Other,
+            };
+            (kind, bdev.name().to_string(), bdev.uuid_as_string())
+        }
+        None => (SubsystemKind::Other, String::new(), String::new()),
+    };
+    let reservation = ss.reservation();
+
+    SubsystemInitiators {
+        nqn: ss.get_nqn(),
+        kind: kind as i32,
+        name,
+        uuid,
+        initiators: ss
+            .host_controllers()
+            .into_iter()
+            .map(|ctrlr| initiator(ctrlr, reservation.as_ref()))
+            .collect(),
+        reservation: reservation.map(Into::into),
+    }
+}
+
+fn initiator(
+    ctrlr: NvmfHostController,
+    reservation: Option<&NvmfReservation>,
+) -> Initiator {
+    let registrant = reservation.and_then(|r| {
+        r.registrants
+            .iter()
+            .find(|reg| reg.host_id == ctrlr.host_id)
+    });
+
+    Initiator {
+        keep_alive_expired: ctrlr.keep_alive_expired(),
+        host_nqn: ctrlr.host_nqn,
+        host_id: ctrlr.host_id.to_string(),
+        cntlid: ctrlr.cntlid as u32,
+        address: ctrlr.address,
+        keep_alive_timeout_ms: ctrlr.keep_alive_timeout_ms,
+        keep_alive_elapsed_ms: ctrlr.keep_alive_elapsed_ms,
+        queue_count: ctrlr.queue_count,
+        reservation_key: registrant.map(|reg| reg.key),
+        reservation_holder: registrant.is_some_and(|reg| reg.holder),
+    }
+}
+
+#[tonic::async_trait]
+impl InitiatorRpc for InitiatorService {
+    async fn list_initiators(
+        &self,
+        request: Request<ListInitiatorsRequest>,
+    ) -> GrpcResult<ListInitiatorsResponse> {
+        let args = request.into_inner();
+
+        crate::spdk_submit!(async move {
+            let subsystems = match args.nqn {
+                Some(nqn) => vec![subsystem_initiators(&subsystem(&nqn)?)],
+                None => NvmfSubsystem::first()
+                    .into_iter()
+                    .flat_map(|ss| ss.into_iter())
+                    .filter(|ss| ss.subtype() == SubType::Nvme)
+                    .map(|ss| subsystem_initiators(&ss))
+                    .collect(),
+            };
+
+            Ok(ListInitiatorsResponse {
+                subsystems,
+            })
+        })
+    }
+
+    async fn disconnect_initiator(
+        &self,
+        request: Request<DisconnectInitiatorRequest>,
+    ) -> GrpcResult<()> {
+        audit::record_args(&request);
+        let args = request.into_inner();
+        info!("{:?}", args);
+
+        crate::spdk_submit!(async move {
+            let ss = subsystem(&args.nqn)?;
+            if !ss
+                .host_controllers()
+                .iter()
+                .any(|ctrlr| ctrlr.host_nqn == args.host_nqn)
+            {
+                return Err(Status::not_found(format!(
+                    "Host '{}' not connected to '{}'",
+                    args.host_nqn, args.nqn
+                )));
+            }
+
+            ss.disconnect_host(&args.host_nqn).await.map_err(|error| {
+                Status::internal(format!(
+                    "Failed to disconnect host '{}' from '{}': {error}",
+                    args.host_nqn, args.nqn
+                ))
+            })
+        })
+    }
+}
+
+impl From<NvmfReservation> for Reservation {
+    fn from(value: NvmfReservation) -> Self {
+        Self {
+            rtype: value.rtype,
+            key: value.key,
+            generation: value.generation,
+            registrants: value.registrants.len() as u32,
+        }
+    }
+}
@@ -1,7 +1,7 @@
 //!
 //! gRPC method to proxy calls to (local) SPDK json-rpc service
 
-use crate::grpc::GrpcResult;
+use crate::grpc::{audit, GrpcResult};
 use io_engine_api::v1::json::{JsonRpc, JsonRpcRequest, JsonRpcResponse};
 use jsonrpc::error::Error;
 use std::borrow::Cow;
@@ -30,6 +30,7 @@ impl JsonRpc for JsonService {
         &self,
         request: Request<JsonRpcRequest>,
     ) -> GrpcResult<JsonRpcResponse> {
+        audit::record_args(&request);
         let args = request.into_inner();
 
         let result = self
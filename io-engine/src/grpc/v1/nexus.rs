@@ -16,6 +16,7 @@ use crate::{
     },
     grpc::{rpc_submit, GrpcClientContext, GrpcResult},
     rebuild::{HistoryRecord, RebuildState, RebuildStats},
+    subsys::PoolConfig,
 };
 use futures::FutureExt;
 use std::{
@@ -26,6 +27,7 @@ use std::{
     pin::Pin,
 };
 use tonic::{Request, Response, Status};
+use tracing::Instrument;
 
 use io_engine_api::v1::nexus::*;
 
@@ -73,7 +75,9 @@ impl NexusService {
         F: core::future::Future<Output = Result<T, Status>> + Send + 'static,
     {
         let lock_manager = ResourceLockManager::get_instance();
-        let fut = AssertUnwindSafe(f).catch_unwind();
+        // Keep the future within the span of the gRPC request, as it is
+        // executed in a separate Tokio task.
+        let fut = AssertUnwindSafe(f.in_current_span()).catch_unwind();
 
         // Schedule a Tokio task to detach it from the high-level gRPC future
         // and avoid task cancellation when the top-level gRPC future is
@@ -195,6 +199,17 @@ impl From<RebuildStats> for RebuildStatsResponse {
             tasks_active: stats.tasks_active,
             is_partial: stats.is_partial,
             start_time: Some(stats.start_time.into()),
+            sources: stats
+                .sources
+                .into_iter()
+                .map(|s| RebuildSourceStats {
+                    uri: s.uri,
+                    blocks_read: s.blocks_read,
+                    throughput: s.throughput,
+                    read_failures: s.read_failures,
+                    failed: s.failed,
+                })
+                .collect(),
         }
     }
 }
@@ -227,6 +242,7 @@ impl From<HistoryRecord> for RebuildHistoryRecord {
             end_time: Some(record.end_time.into()),
             child_uri: record.child_uri.clone(),
             src_uri: record.src_uri.clone(),
+            error: record.error.clone().unwrap_or_default(),
         }
     }
 }
@@ -285,6 +301,121 @@ impl TryFrom<NvmePreemptionConv> for nexus::NexusNvmePreemption {
         }
     }
 }
+struct NexusPiPolicyConv(i32);
+impl TryFrom<NexusPiPolicyConv> for nexus::NexusPiPolicy {
+    type Error = tonic::Status;
+    fn try_from(value: NexusPiPolicyConv) -> Result<Self, Self::Error> {
+        match NexusPiPolicy::try_from(value.0) {
+            Ok(NexusPiPolicy::Passthrough) => {
+                Ok(nexus::NexusPiPolicy::Passthrough)
+            }
+            Ok(NexusPiPolicy::Verify) => Ok(nexus::NexusPiPolicy::Verify),
+            Ok(NexusPiPolicy::Generate) => Ok(nexus::NexusPiPolicy::Generate),
+            Err(_) => Err(tonic::Status::invalid_argument(format!(
+                "Invalid protection information policy {}",
+                value.0
+            ))),
+        }
+    }
+}
+struct NexusLayoutConv {
+    layout: i32,
+    stripe_size: u64,
+    copies: u32,
+}
+impl TryFrom<NexusLayoutConv> for nexus::NexusLayout {
+    type Error = tonic::Status;
+    fn try_from(value: NexusLayoutConv) -> Result<Self, Self::Error> {
+        match NexusLayout::try_from(value.layout) {
+            Ok(NexusLayout::Mirror) => Ok(Self::Mirror),
+            Ok(NexusLayout::Striped) => Ok(Self::Striped {
+                stripe_size: value.stripe_size,
+            }),
+            Ok(NexusLayout::StripedMirror) => Ok(Self::StripedMirror {
+                stripe_size: value.stripe_size,
+                copies: value.copies as usize,
+            }),
+            Err(_) => Err(tonic::Status::invalid_argument(format!(
+                "Invalid nexus layout {}",
+                value.layout
+            ))),
+        }
+    }
+}
+struct SparePolicyConv(i32);
+impl TryFrom<SparePolicyConv> for nexus::SparePolicy {
+    type Error = tonic::Status;
+    fn try_from(value: SparePolicyConv) -> Result<Self, Self::Error> {
+        match SparePolicy::try_from(value.0) {
+            Ok(SparePolicy::Manual) => Ok(Self::Manual),
+            Ok(SparePolicy::Replace) => Ok(Self::Replace),
+            Ok(SparePolicy::ReplaceAndRemove) => Ok(Self::ReplaceAndRemove),
+            Err(_) => Err(tonic::Status::invalid_argument(format!(
+                "Invalid spare policy {}",
+                value.0
+            ))),
+        }
+    }
+}
+impl From<nexus::SparePolicy> for SparePolicy {
+    fn from(value: nexus::SparePolicy) -> Self {
+        match value {
+            nexus::SparePolicy::Manual => Self::Manual,
+            nexus::SparePolicy::Replace => Self::Replace,
+            nexus::SparePolicy::ReplaceAndRemove => Self::ReplaceAndRemove,
+        }
+    }
+}
+struct CachePolicyConv(i32);
+impl TryFrom<CachePolicyConv> for nexus::CachePolicy {
+    type Error = tonic::Status;
+    fn try_from(value: CachePolicyConv) -> Result<Self, Self::Error> {
+        match NexusCachePolicy::try_from(value.0) {
+            Ok(NexusCachePolicy::WriteBack) => Ok(Self::WriteBack),
+            Ok(NexusCachePolicy::WriteThrough) => Ok(Self::WriteThrough),
+            Err(_) => Err(tonic::Status::invalid_argument(format!(
+                "Invalid cache policy {}",
+                value.0
+            ))),
+        }
+    }
+}
+impl From<nexus::CachePolicy> for NexusCachePolicy {
+    fn from(value: nexus::CachePolicy) -> Self {
+        match value {
+            nexus::CachePolicy::WriteBack => Self::WriteBack,
+            nexus::CachePolicy::WriteThrough => Self::WriteThrough,
+        }
+    }
+}
+impl TryFrom<NexusCacheSpec> for nexus::NexusCacheConfig {
+    type Error = tonic::Status;
+    fn try_from(value: NexusCacheSpec) -> Result<Self, Self::Error> {
+        let policy =
+            nexus::CachePolicy::try_from(CachePolicyConv(value.policy))?;
+        let config = Self::new(&value.uri, policy);
+        Ok(match value.destage_interval_ms {
+            0 => config,
+            ms => config
+                .with_destage_interval(std::time::Duration::from_millis(ms)),
+        })
+    }
+}
+impl From<nexus::NexusCacheStats> for NexusCache {
+    fn from(value: nexus::NexusCacheStats) -> Self {
+        Self {
+            uri: value.uri,
+            policy: NexusCachePolicy::from(value.policy) as i32,
+            state: value.state.to_string(),
+            write_back: value.write_back,
+            pending_blocks: value.pending_blocks,
+            destaged_blocks: value.destaged_blocks,
+            destages: value.destages,
+            failed_destages: value.failed_destages,
+            replayed: value.replayed,
+        }
+    }
+}
 
 /// Look up a nexus by uuid
 pub fn nexus_lookup<'n>(
@@ -300,14 +431,23 @@ pub fn nexus_lookup<'n>(
 }
 
 /// Destruction of the nexus. Returns NotFound error for invalid uuid.
-pub async fn nexus_destroy(uuid: &str) -> Result<(), nexus::Error> {
+/// With `purge`, the state kept for the next nexus of the volume is deleted
+/// as well.
+pub async fn nexus_destroy(
+    uuid: &str,
+    purge: bool,
+) -> Result<(), nexus::Error> {
     let n = nexus_lookup(uuid).map_err(|error| {
         if let Ok(uuid) = uuid::Uuid::parse_str(uuid) {
             NexusPtpl::new(uuid).destroy().ok();
         }
         error
     })?;
-    n.destroy().await
+    if purge {
+        n.destroy_purge().await
+    } else {
+        n.destroy().await
+    }
 }
 
 impl<'c> NexusChild<'c> {
@@ -359,6 +499,9 @@ impl<'n> nexus::Nexus<'n> {
             rebuilds: self.count_rebuild_jobs() as u32,
             ana_state: ana_state as i32,
             allowed_hosts: self.allowed_hosts(),
+            spares: self.spares(),
+            spare_policy: SparePolicy::from(self.spare_policy()) as i32,
+            cache: self.cache_stats().map(NexusCache::from),
         }
     }
 }
@@ -384,7 +527,14 @@ async fn nexus_add_child(
     debug!("Adding child {} to nexus {} ...", args.uri, args.uuid);
     // For that we need api to check existence of child by name (not uri that
     // contain parameters that may change).
-    n.as_mut().add_child(&args.uri, args.norebuild).await?;
+    match &args.replace_uri {
+        Some(old_uri) => {
+            n.as_mut()
+                .replace_child(old_uri, &args.uri, args.norebuild)
+                .await?
+        }
+        None => n.as_mut().add_child(&args.uri, args.norebuild).await?,
+    };
     Ok(n.into_grpc().await)
 }
 
@@ -403,6 +553,18 @@ impl NexusRpc for NexusService {
             let resv_type = NvmeReservationConv(args.resv_type).try_into()?;
             let preempt_policy =
                 NvmePreemptionConv(args.preempt_policy).try_into()?;
+            let pi_policy = NexusPiPolicyConv(args.pi_policy).try_into()?;
+            let layout = NexusLayoutConv {
+                layout: args.layout,
+                stripe_size: args.stripe_size,
+                copies: args.mirror_copies,
+            }
+            .try_into()?;
+            let cache = args
+                .cache
+                .clone()
+                .map(nexus::NexusCacheConfig::try_from)
+                .transpose()?;
             let rx = rpc_submit::<_, _, nexus::Error>(async move {
                 // check for nexus exists, uuid & name
                 if let Some(_n) = nexus::nexus_lookup(&args.name) {
@@ -439,14 +601,18 @@ impl NexusRpc for NexusService {
                         },
                         resv_type,
                         preempt_policy,
+                        pi_policy,
                     },
+                    layout,
                     &args.children,
                     nexus_info_key,
+                    cache,
                 )
                 .await?;
                 let nexus = nexus_lookup(&args.uuid)?;
                 nexus.event(EventAction::Create).generate();
                 info!("Created nexus {}/{}", &args.name, &args.uuid);
+                PoolConfig::save().await;
                 Ok(nexus.into_grpc().await)
             })?;
             rx.await
@@ -472,7 +638,8 @@ impl NexusRpc for NexusService {
         self.serialized(ctx, args.uuid.clone(), true, async move {
             let rx = rpc_submit::<_, _, nexus::Error>(async move {
                 trace!("{:?}", args);
-                nexus_destroy(&args.uuid).await?;
+                nexus_destroy(&args.uuid, args.purge).await?;
+                PoolConfig::save().await;
                 Ok(())
             })?;
 
@@ -499,6 +666,7 @@ impl NexusRpc for NexusService {
                     .resize(args.requested_size)
                     .await?;
                 info!("Nexus {} resized to {}", args.uuid, args.requested_size);
+                PoolConfig::save().await;
                 Ok(ResizeNexusResponse {
                     nexus: Some(nexus_lookup(&args.uuid)?.into_grpc().await),
                 })
@@ -595,6 +763,7 @@ impl NexusRpc for NexusService {
                 trace!("{:?}", args);
                 let nexus = nexus_add_child(&args).await?;
                 info!("Added child to nexus {}", args.uuid);
+                PoolConfig::save().await;
                 event.generate();
                 Ok(nexus)
             })?;
@@ -634,6 +803,7 @@ impl NexusRpc for NexusService {
                         "Removed child {} from nexus {}",
                         args.uri, args.uuid
                     );
+                    PoolConfig::save().await;
                     event.generate();
                 }
                 Ok(nexus_lookup(&args.uuid)?.into_grpc().await)
@@ -727,6 +897,7 @@ impl NexusRpc for NexusService {
                     "Published nexus {} under {} for {:?}",
                     args.uuid, device_uri, args.allowed_hosts
                 );
+                PoolConfig::save().await;
 
                 let nexus = nexus_lookup(&args.uuid)?.into_grpc().await;
 
@@ -757,6 +928,7 @@ impl NexusRpc for NexusService {
                 debug!("Unpublishing nexus {} ...", uuid);
                 nexus_lookup(&args.uuid)?.unshare_nexus().await?;
                 info!("Unpublished nexus {}", uuid);
+                PoolConfig::save().await;
                 Ok(nexus_lookup(&args.uuid)?.into_grpc().await)
             })?;
 
@@ -801,6 +973,81 @@ impl NexusRpc for NexusService {
         .await
     }
 
+    #[named]
+    async fn set_nexus_nvme_params(
+        &self,
+        request: Request<SetNexusNvmeParamsRequest>,
+    ) -> GrpcResult<SetNexusNvmeParamsResponse> {
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let args = request.into_inner();
+
+        self.serialized(ctx, args.uuid.clone(), false, async move {
+            info!("{args:?}");
+            let resv_type = args
+                .resv_type
+                .map(|t| NvmeReservationConv(Some(t)).try_into())
+                .transpose()?;
+            let preempt_policy = args
+                .preempt_policy
+                .map(|p| NvmePreemptionConv(p).try_into())
+                .transpose()?;
+            let cntlid = |id: Option<u32>| {
+                id.map(|id| {
+                    u16::try_from(id).map_err(|_| {
+                        Status::invalid_argument(format!(
+                            "Invalid NVMe controller ID {id}"
+                        ))
+                    })
+                })
+                .transpose()
+            };
+            let min_cntlid = cntlid(args.min_cntl_id)?;
+            let max_cntlid = cntlid(args.max_cntl_id)?;
+
+            let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                let nexus = nexus_lookup(&args.uuid)?;
+
+                // The parameters not given are kept.
+                let mut params = nexus.nvme_params.clone();
+                if let Some(min_cntlid) = min_cntlid {
+                    params.set_min_cntlid(min_cntlid);
+                }
+                if let Some(max_cntlid) = max_cntlid {
+                    params.set_max_cntlid(max_cntlid);
+                }
+                if let Some(resv_key) = args.resv_key {
+                    params.set_resv_key(resv_key);
+                }
+                if let Some(preempt_key) = args.preempt_key {
+                    params.set_preempt_key(std::num::NonZeroU64::new(
+                        preempt_key,
+                    ));
+                }
+                if let Some(resv_type) = resv_type {
+                    params.set_resv_type(resv_type);
+                }
+                if let Some(preempt_policy) = preempt_policy {
+                    params.set_preempt_policy(preempt_policy);
+                }
+
+                nexus.set_nvme_params(params).await?;
+                info!("Changed nexus {} NVMe parameters", args.uuid);
+                PoolConfig::save().await;
+                Ok(nexus_lookup(&args.uuid)?.into_grpc().await)
+            })?;
+
+            rx.await
+                .map_err(|_| Status::cancelled("cancelled"))?
+                .map_err(Status::from)
+                .map(|nexus| {
+                    Response::new(SetNexusNvmeParamsResponse {
+                        nexus: Some(nexus),
+                    })
+                })
+        })
+        .await
+    }
+
     #[named]
     async fn set_nvme_ana_state(
         &self,
@@ -834,6 +1081,37 @@ impl NexusRpc for NexusService {
         .await
     }
 
+    #[named]
+    async fn set_nexus_spares(
+        &self,
+        request: Request<SetNexusSparesRequest>,
+    ) -> GrpcResult<SetNexusSparesResponse> {
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let args = request.into_inner();
+
+        self.serialized(ctx, args.uuid.clone(), false, async move {
+            let policy =
+                nexus::SparePolicy::try_from(SparePolicyConv(args.policy))?;
+
+            let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                info!("{:?}", args);
+                let nexus = nexus_lookup(&args.uuid)?;
+                nexus.set_spares(&args.uris, policy).await?;
+                Ok(nexus.into_grpc().await)
+            })?;
+
+            rx.await
+                .map_err(|_| Status::cancelled("cancelled"))?
+                .map_err(Status::from)
+                .map(|nexus| {
+                    Response::new(SetNexusSparesResponse {
+                        nexus: Some(nexus),
+                    })
+                })
+        })
+        .await
+    }
+
     #[named]
     async fn child_operation(
         &self,
@@ -1098,6 +1376,11 @@ impl NexusRpc for NexusService {
         let end_time = args
             .since_end_time
             .and_then(|t| chrono::DateTime::<chrono::Utc>::try_from(t).ok());
+        let until_end_time = args
+            .until_end_time
+            .and_then(|t| chrono::DateTime::<chrono::Utc>::try_from(t).ok());
+        let child_uri = args.child_uri;
+        let states = args.states;
         let rx = rpc_submit::<_, _, nexus::Error>(async move {
             let mut newest_end_time = None;
             let default_end_time = chrono::Utc::now();
@@ -1120,6 +1403,18 @@ impl NexusRpc for NexusService {
                             end_time
                                 .map(|t| record.end_time > t)
                                 .unwrap_or(true)
+                                && until_end_time
+                                    .map(|t| record.end_time <= t)
+                                    .unwrap_or(true)
+                                && child_uri
+                                    .as_ref()
+                                    .map(|uri| &record.child_uri == uri)
+                                    .unwrap_or(true)
+                                && (states.is_empty()
+                                    || states.contains(
+                                        &(RebuildJobState::from(record.state)
+                                            as i32),
+                                    ))
                         })
                         .rev()
                         .take(count)
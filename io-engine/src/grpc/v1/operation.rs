@@ -0,0 +1,102 @@
+use crate::grpc::{
+    audit,
+    operation::{Operation, OperationState},
+    GrpcResult,
+};
+use io_engine_api::v1::operation::{
+    self,
+    CancelOperationRequest,
+    GetOperationRequest,
+    ListOperationsRequest,
+    ListOperationsResponse,
+    OperationError,
+    OperationRpc,
+};
+use std::sync::Arc;
+use tonic::{Request, Response};
+
+#[derive(Debug, Default)]
+pub struct OperationService {
+    #[allow(unused)]
+    name: String,
+}
+
+impl OperationService {
+    pub fn new() -> Self {
+        Self {
+            name: String::from("OperationService"),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl OperationRpc for OperationService {
+    async fn get_operation(
+        &self,
+        request: Request<GetOperationRequest>,
+    ) -> GrpcResult<operation::Operation> {
+        let args = request.into_inner();
+        let op = Operation::lookup(&args.id)?;
+        Ok(Response::new(op.into()))
+    }
+
+    async fn list_operations(
+        &self,
+        _request: Request<ListOperationsRequest>,
+    ) -> GrpcResult<ListOperationsResponse> {
+        Ok(Response::new(ListOperationsResponse {
+            operations: Operation::list().into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    /// Cancels an operation which is still waiting for its turn to run: a
+    /// running operation cannot be cancelled.
+    async fn cancel_operation(
+        &self,
+        request: Request<CancelOperationRequest>,
+    ) -> GrpcResult<operation::Operation> {
+        audit::record_args(&request);
+        let args = request.into_inner();
+        info!("{:?}", args);
+        let op = Operation::lookup(&args.id)?;
+        op.cancel()?;
+        Ok(Response::new(op.into()))
+    }
+}
+
+impl From<OperationState> for operation::OperationState {
+    fn from(value: OperationState) -> Self {
+        match value {
+            OperationState::Pending => Self::OperationPending,
+            OperationState::Running => Self::OperationRunning,
+            OperationState::Succeeded => Self::OperationSucceeded,
+            OperationState::Failed => Self::OperationFailed,
+            OperationState::Cancelled => Self::OperationCancelled,
+        }
+    }
+}
+
+impl From<Arc<Operation>> for operation::Operation {
+    fn from(value: Arc<Operation>) -> Self {
+        let info = value.info();
+        Self {
+            id: value.id.clone(),
+            method: value.method.clone(),
+            args: value.args.clone(),
+            state: operation::OperationState::from(info.state) as i32,
+            created: Some(value.created.into()),
+            started: info.started.map(Into::into),
+            finished: info.finished.map(Into::into),
+            response: info.response,
+            error: info.error.map(|status| OperationError {
+                code: status.code() as i32,
+                message: status.message().to_string(),
+            }),
+            progress: Some(operation::OperationProgress {
+                stage: info.progress.stage,
+                done: info.progress.done,
+                total: info.progress.total,
+            }),
+        }
+    }
+}
@@ -9,6 +9,7 @@ use crate::{
     },
     grpc::{
         acquire_subsystem_lock,
+        operation::{OperationHandle, ProgressReporter},
         GrpcClientContext,
         GrpcResult,
         RWLock,
@@ -25,6 +26,7 @@ use crate::{
         PoolOps,
         ReplicaArgs,
     },
+    subsys::PoolConfig,
 };
 use ::function_name::named;
 use futures::FutureExt;
@@ -331,10 +333,12 @@ impl PoolGrpc {
     }
     async fn destroy(self) -> Result<(), tonic::Status> {
         self.pool.destroy().await?;
+        PoolConfig::save().await;
         Ok(())
     }
     async fn export(self) -> Result<(), tonic::Status> {
         self.pool.export().await?;
+        PoolConfig::save().await;
         Ok(())
     }
     /// Access the `PoolOps` from this wrapper.
@@ -443,35 +447,59 @@ impl GrpcPoolFactory {
             Ok(())
         }
     }
-    async fn create(&self, args: PoolArgs) -> Result<Pool, Status> {
+    async fn create(
+        &self,
+        args: PoolArgs,
+        progress: &ProgressReporter,
+    ) -> Result<Pool, Status> {
         let pool_subsystem = ResourceLockManager::get_instance()
             .get_subsystem(ProtectedSubsystems::POOL);
         // todo: missing lock by uuid as well, need to ensure also we don't
         //  clash with a pool with != name but same uuid
+        progress.report("locking the pool", 0, 0);
         let _lock_guard =
             acquire_subsystem_lock(pool_subsystem, Some(&args.name)).await?;
 
         let finder = FindPoolArgs::from(&args);
-        for factory in Self::factories() {
-            // todo: inspect disk contents as well!
-            factory.ensure_not_found(&finder, args.backend).await?;
-        }
+        Self::ensure_not_found_all(&finder, args.backend, progress).await?;
+        progress.report("creating the pool", 0, 0);
         let pool = self.as_factory().create(args).await?;
+        PoolConfig::save().await;
         Ok(pool.into())
     }
-    async fn import(&self, args: PoolArgs) -> Result<Pool, Status> {
+    async fn import(
+        &self,
+        args: PoolArgs,
+        progress: &ProgressReporter,
+    ) -> Result<Pool, Status> {
         let pool_subsystem = ResourceLockManager::get_instance()
             .get_subsystem(ProtectedSubsystems::POOL);
+        progress.report("locking the pool", 0, 0);
         let _lock_guard =
             acquire_subsystem_lock(pool_subsystem, Some(&args.name)).await?;
 
         let finder = FindPoolArgs::from(&args);
-        for factory in Self::factories() {
-            factory.ensure_not_found(&finder, args.backend).await?;
-        }
+        Self::ensure_not_found_all(&finder, args.backend, progress).await?;
+        progress.report("importing the pool", 0, 0);
         let pool = self.as_factory().import(args).await?;
+        PoolConfig::save().await;
         Ok(pool.into())
     }
+    /// Ensures the pool is not found with any of the backends.
+    async fn ensure_not_found_all(
+        finder: &FindPoolArgs,
+        backend: PoolBackend,
+        progress: &ProgressReporter,
+    ) -> Result<(), Status> {
+        let factories = Self::factories();
+        let total = factories.len() as u64;
+        for (done, factory) in factories.iter().enumerate() {
+            // todo: inspect disk contents as well!
+            progress.report("looking up existing pools", done as u64, total);
+            factory.ensure_not_found(finder, backend).await?;
+        }
+        Ok(())
+    }
     fn as_factory(&self) -> &dyn IPoolFactory {
         self.0.as_factory()
     }
@@ -484,22 +512,28 @@ impl PoolRpc for PoolService {
         &self,
         request: Request<CreatePoolRequest>,
     ) -> GrpcResult<Pool> {
-        self.locked(
-            GrpcClientContext::new(&request, function_name!()),
-            async move {
-                crate::spdk_submit!(async move {
-                    info!("{:?}", request.get_ref());
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let op = OperationHandle::new(&request, &ctx)?;
+        let progress = op.progress();
+
+        let fut = op.clone().begin(async move {
+            crate::spdk_submit!(async move {
+                info!("{:?}", request.get_ref());
+
+                let factory = GrpcPoolFactory::new(PoolBackend::try_from(
+                    request.get_ref().pooltype,
+                )?)?;
+                factory
+                    .create(
+                        PoolArgs::try_from(request.into_inner())?,
+                        &progress,
+                    )
+                    .await
+            })
+        });
 
-                    let factory = GrpcPoolFactory::new(PoolBackend::try_from(
-                        request.get_ref().pooltype,
-                    )?)?;
-                    factory
-                        .create(PoolArgs::try_from(request.into_inner())?)
-                        .await
-                })
-            },
-        )
-        .await
+        let svc = self.clone();
+        op.run(async move { svc.locked(ctx, fut).await }).await
     }
 
     #[named]
@@ -547,22 +581,28 @@ impl PoolRpc for PoolService {
         &self,
         request: Request<ImportPoolRequest>,
     ) -> GrpcResult<Pool> {
-        self.locked(
-            GrpcClientContext::new(&request, function_name!()),
-            async move {
-                crate::spdk_submit!(async move {
-                    info!("{:?}", request.get_ref());
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let op = OperationHandle::new(&request, &ctx)?;
+        let progress = op.progress();
+
+        let fut = op.clone().begin(async move {
+            crate::spdk_submit!(async move {
+                info!("{:?}", request.get_ref());
+
+                let factory = GrpcPoolFactory::new(PoolBackend::try_from(
+                    request.get_ref().pooltype,
+                )?)?;
+                factory
+                    .import(
+                        PoolArgs::try_from(request.into_inner())?,
+                        &progress,
+                    )
+                    .await
+            })
+        });
 
-                    let factory = GrpcPoolFactory::new(PoolBackend::try_from(
-                        request.get_ref().pooltype,
-                    )?)?;
-                    factory
-                        .import(PoolArgs::try_from(request.into_inner())?)
-                        .await
-                })
-            },
-        )
-        .await
+        let svc = self.clone();
+        op.run(async move { svc.locked(ctx, fut).await }).await
     }
 
     #[named]
@@ -12,6 +12,7 @@ use crate::{
     },
     grpc::{
         acquire_subsystem_lock,
+        operation::OperationHandle,
         v1::pool::{GrpcPoolFactory, PoolGrpc, PoolIdProbe},
         GrpcClientContext,
         GrpcResult,
@@ -28,6 +29,7 @@ use crate::{
         ReplicaFactory,
         ReplicaOps,
     },
+    subsys::PoolConfig,
 };
 use ::function_name::named;
 use futures::FutureExt;
@@ -395,7 +397,9 @@ impl ReplicaRpc for ReplicaService {
                         FindPoolArgs::uuid_or_name(&args.pooluuid),
                     )
                     .await?;
-                    pool.create_replica(args).await
+                    let replica = pool.create_replica(args).await?;
+                    PoolConfig::save().await;
+                    Ok(replica)
                 })
             },
         )
@@ -407,42 +411,44 @@ impl ReplicaRpc for ReplicaService {
         &self,
         request: Request<DestroyReplicaRequest>,
     ) -> GrpcResult<()> {
-        self.locked(
-            GrpcClientContext::new(&request, function_name!()),
-            async move {
-                crate::spdk_submit!(async move {
-                    info!("{:?}", request.get_ref());
-                    let args = request.into_inner();
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let op = OperationHandle::new(&request, &ctx)?;
+
+        let fut = op.clone().begin(async move {
+            crate::spdk_submit!(async move {
+                info!("{:?}", request.get_ref());
+                let args = request.into_inner();
 
-                    let pool = match &args.pool {
-                        Some(pool) => {
-                            Some(GrpcReplicaFactory::pool_finder(pool).await?)
-                        }
-                        None => None,
-                    };
-                    let probe = FindReplicaArgs::new(&args.uuid);
-                    let replica = match GrpcReplicaFactory::finder(&probe).await
+                let pool = match &args.pool {
+                    Some(pool) => {
+                        Some(GrpcReplicaFactory::pool_finder(pool).await?)
+                    }
+                    None => None,
+                };
+                let probe = FindReplicaArgs::new(&args.uuid);
+                let replica = match GrpcReplicaFactory::finder(&probe).await {
+                    Err(mut status)
+                        if status.code() == tonic::Code::NotFound =>
                     {
-                        Err(mut status)
-                            if status.code() == tonic::Code::NotFound =>
-                        {
-                            status.metadata_mut().insert(
-                                "gtm-602",
-                                tonic::metadata::MetadataValue::from(0),
-                            );
-                            Err(status)
-                        }
-                        _else => _else,
-                    }?;
-                    if let Some(pool) = &pool {
-                        replica.verify_pool(pool)?;
+                        status.metadata_mut().insert(
+                            "gtm-602",
+                            tonic::metadata::MetadataValue::from(0),
+                        );
+                        Err(status)
                     }
-                    replica.destroy().await?;
-                    Ok(())
-                })
-            },
-        )
-        .await
+                    _else => _else,
+                }?;
+                if let Some(pool) = &pool {
+                    replica.verify_pool(pool)?;
+                }
+                replica.destroy().await?;
+                PoolConfig::save().await;
+                Ok(())
+            })
+        });
+
+        let svc = self.clone();
+        op.run(async move { svc.locked(ctx, fut).await }).await
     }
 
     #[named]
@@ -498,6 +504,7 @@ impl ReplicaRpc for ReplicaService {
                     let mut replica =
                         GrpcReplicaFactory::finder(&probe).await?;
                     replica.share(request.into_inner()).await?;
+                    PoolConfig::save().await;
                     Ok(replica.into())
                 })
             },
@@ -520,6 +527,7 @@ impl ReplicaRpc for ReplicaService {
                     let mut replica =
                         GrpcReplicaFactory::finder(&probe).await?;
                     replica.unshare().await?;
+                    PoolConfig::save().await;
                     Ok(replica.into())
                 })
             },
@@ -542,6 +550,7 @@ impl ReplicaRpc for ReplicaService {
                     let mut replica =
                         GrpcReplicaFactory::finder(&probe).await?;
                     replica.resize(request.into_inner().requested_size).await?;
+                    PoolConfig::save().await;
                     Ok(replica.into())
                 })
             },
@@ -0,0 +1,198 @@
+use crate::{
+    bdev::nexus::nexus_lookup_uuid_mut,
+    grpc::{audit, GrpcResult},
+    rebuild::{
+        RebuildError,
+        ReplicationError,
+        ReplicationJob,
+        ReplicationState,
+        ReplicationStats,
+    },
+};
+use io_engine_api::v1::{
+    replication,
+    replication::{
+        CreateReplicationRequest,
+        DestroyReplicationRequest,
+        ListReplicationsRequest,
+        ListReplicationsResponse,
+        PromoteReplicationRequest,
+        ReplicationRpc,
+    },
+};
+use std::{sync::Arc, time::Duration};
+use tonic::Request;
+
+#[derive(Debug, Default)]
+pub struct ReplicationService {
+    #[allow(unused)]
+    name: String,
+}
+
+impl ReplicationService {
+    pub fn new() -> Self {
+        Self {
+            name: String::from("ReplicationService"),
+        }
+    }
+}
+
+/// Looks up the replication of the nexus with the given uuid. The replication
+/// may outlive its nexus.
+fn lookup_replication(
+    nexus_uuid: &str,
+) -> Result<Arc<ReplicationJob>, RebuildError> {
+    ReplicationJob::list()
+        .into_iter()
+        .find(|job| job.nexus_uuid() == nexus_uuid)
+        .ok_or_else(|| RebuildError::JobNotFound {
+            job: nexus_uuid.to_string(),
+        })
+}
+
+#[tonic::async_trait]
+impl ReplicationRpc for ReplicationService {
+    async fn create_replication(
+        &self,
+        request: Request<CreateReplicationRequest>,
+    ) -> GrpcResult<replication::Replication> {
+        audit::record_args(&request);
+        let request = request.into_inner();
+
+        crate::spdk_submit!(async move {
+            info!("{:?}", request);
+
+            if let Ok(job) = lookup_replication(&request.nexus_uuid) {
+                if job.dst_uri() == request.dst_uri {
+                    return Ok(ReplicationInfo::from(job).await.into());
+                }
+            }
+
+            let Some(nexus) = nexus_lookup_uuid_mut(&request.nexus_uuid) else {
+                return Err(RebuildError::from(
+                    ReplicationError::NexusNotFound {
+                        nexus: request.nexus_uuid,
+                    },
+                )
+                .into());
+            };
+            let mut builder =
+                ReplicationJob::builder().with_verify(request.verify);
+            if let Some(rpo_ms) = request.rpo_ms {
+                builder = builder.with_rpo(Duration::from_millis(rpo_ms));
+            }
+            let job = builder
+                .build(nexus.nexus_name(), &request.dst_uri)?
+                .store()?;
+            if let Err(error) = job.start() {
+                ReplicationJob::remove(job.nexus()).ok();
+                return Err(error.into());
+            }
+            Ok(ReplicationInfo::from(job).await.into())
+        })
+    }
+
+    async fn promote_replication(
+        &self,
+        request: Request<PromoteReplicationRequest>,
+    ) -> GrpcResult<replication::Replication> {
+        audit::record_args(&request);
+        crate::spdk_submit!(async move {
+            let args = request.into_inner();
+            info!("{:?}", args);
+            let job = lookup_replication(&args.nexus_uuid)?;
+            job.promote(args.force).await?;
+            Ok(ReplicationInfo::from(job).await.into())
+        })
+    }
+
+    async fn destroy_replication(
+        &self,
+        request: Request<DestroyReplicationRequest>,
+    ) -> GrpcResult<()> {
+        audit::record_args(&request);
+        crate::spdk_submit!(async move {
+            let args = request.into_inner();
+            info!("{:?}", args);
+            let job = lookup_replication(&args.nexus_uuid)?;
+            job.stop().await;
+            ReplicationJob::remove(job.nexus())?;
+            info!("Replication destroyed: {job:?}");
+            Ok(())
+        })
+    }
+
+    async fn list_replications(
+        &self,
+        request: Request<ListReplicationsRequest>,
+    ) -> GrpcResult<ListReplicationsResponse> {
+        crate::spdk_submit!(async move {
+            let args = request.into_inner();
+            trace!("{:?}", args);
+            let jobs = match args.nexus_uuid {
+                None => ReplicationJob::list(),
+                Some(uuid) => vec![lookup_replication(&uuid)?],
+            };
+            let mut replications = Vec::with_capacity(jobs.len());
+            for job in jobs {
+                replications.push(ReplicationInfo::from(job).await.into());
+            }
+            Ok(ListReplicationsResponse {
+                replications,
+            })
+        })
+    }
+}
+
+/// A replication along with its progress and lag.
+struct ReplicationInfo {
+    stats: ReplicationStats,
+    job: Arc<ReplicationJob>,
+}
+impl ReplicationInfo {
+    async fn from(job: Arc<ReplicationJob>) -> Self {
+        let stats = job.stats().await;
+        Self {
+            stats,
+            job,
+        }
+    }
+}
+
+impl From<ReplicationInfo> for replication::Replication {
+    fn from(value: ReplicationInfo) -> Self {
+        let stats = value.stats;
+        let job = value.job;
+        Self {
+            nexus_uuid: job.nexus_uuid().to_string(),
+            dst_uri: job.dst_uri().to_string(),
+            rpo_ms: job.rpo().as_millis() as u64,
+            verify: job.verify(),
+            state: replication::ReplicationState::from(stats.state) as i32,
+            last_sync_timestamp: stats.last_sync_time.map(Into::into),
+            lag_ms: stats.lag.as_millis() as u64,
+            rpo_violated: stats.rpo_violated,
+            pending_bytes: stats.pending_blocks * stats.block_size,
+            shipped_bytes: stats.blocks_shipped * stats.block_size,
+            syncs: stats.syncs,
+            failed_syncs: stats.failed_syncs,
+            start_timestamp: Some(stats.start_time.into()),
+            end_timestamp: stats.end_time.map(Into::into),
+            error: stats.error.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<ReplicationState> for replication::ReplicationState {
+    fn from(value: ReplicationState) -> Self {
+        match value {
+            ReplicationState::Init => Self::Init,
+            ReplicationState::Syncing => Self::Syncing,
+            ReplicationState::Replicating => Self::Replicating,
+            ReplicationState::Promoting => Self::Promoting,
+            ReplicationState::Promoted => Self::Promoted,
+            ReplicationState::Stopped => Self::Stopped,
+            ReplicationState::Failed => Self::Failed,
+        }
+    }
+}
@@ -10,6 +10,7 @@ use crate::{
         UntypedBdev,
     },
     grpc::{
+        operation::OperationHandle,
         rpc_submit,
         v1::{nexus::nexus_lookup, replica::ReplicaGrpc},
         GrpcClientContext,
@@ -23,12 +24,13 @@ use futures::FutureExt;
 use io_engine_api::v1::snapshot::*;
 use std::panic::AssertUnwindSafe;
 use tonic::{Request, Response, Status};
+use tracing::Instrument;
 
 /// Support for the snapshot's consumption as source, should be marked as true
 /// once we start supporting the feature.
 const SNAPSHOT_READY_AS_SOURCE: bool = false;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct SnapshotService {
     name: String,
@@ -136,7 +138,9 @@ impl SnapshotService {
         F: core::future::Future<Output = Result<T, Status>> + Send + 'static,
     {
         let lock_manager = ResourceLockManager::get_instance();
-        let fut = AssertUnwindSafe(f).catch_unwind();
+        // Keep the future within the span of the gRPC request, as it is
+        // executed in a separate Tokio task.
+        let fut = AssertUnwindSafe(f.in_current_span()).catch_unwind();
 
         // Schedule a Tokio task to detach it from the high-level gRPC future
         // and avoid task cancellation when the top-level gRPC future is
@@ -332,9 +336,12 @@ impl SnapshotRpc for SnapshotService {
         request: Request<NexusCreateSnapshotRequest>,
     ) -> GrpcResult<NexusCreateSnapshotResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
+        let op = OperationHandle::new(&request, &ctx)?;
         let args = request.into_inner();
+        let nexus_uuid = args.nexus_uuid.clone();
+        let progress = op.progress();
 
-        self.serialized(ctx, args.nexus_uuid.clone(), false, async move {
+        let fut = op.clone().begin(async move {
             info!("{:?}", args);
             let rx = rpc_submit::<_, _, nexus::Error>(async move {
                 let snapshot = SnapshotParams::new(
@@ -355,8 +362,20 @@ impl SnapshotRpc for SnapshotService {
                     .map(NexusReplicaSnapshotDescriptor::from)
                     .collect::<Vec<_>>();
 
-                let res =
-                    nexus.as_mut().create_snapshot(snapshot, replicas).await?;
+                let res = nexus
+                    .as_mut()
+                    .create_snapshot_with_progress(
+                        snapshot,
+                        replicas,
+                        &|done, total| {
+                            progress.report(
+                                "taking the replica snapshots",
+                                done as u64,
+                                total as u64,
+                            )
+                        },
+                    )
+                    .await?;
 
                 let replicas_done = res
                     .replicas_done
@@ -378,8 +397,11 @@ impl SnapshotRpc for SnapshotService {
                 .map_err(|_| Status::cancelled("cancelled"))?
                 .map_err(Status::from)
                 .map(Response::new)
-        })
-        .await
+        });
+
+        let svc = self.clone();
+        op.run(async move { svc.serialized(ctx, nexus_uuid, false, fut).await })
+            .await
     }
     #[named]
     async fn create_replica_snapshot(
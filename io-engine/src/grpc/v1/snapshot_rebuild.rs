@@ -1,9 +1,11 @@
 use crate::{
-    grpc::GrpcResult,
+    grpc::{audit, GrpcResult},
     rebuild::{
+        CopyJobError,
         RebuildError,
         RebuildState,
         RebuildStats,
+        ReplicationError,
         SnapshotRebuildError,
         SnapshotRebuildJob,
     },
@@ -45,6 +47,7 @@ impl SnapshotRebuildRpc for SnapshotRebuildService {
         &self,
         request: Request<CreateSnapshotRebuildRequest>,
     ) -> GrpcResult<SnapshotRebuild> {
+        audit::record_args(&request);
         let request = request.into_inner();
 
         crate::spdk_submit!(async move {
@@ -104,6 +107,7 @@ impl SnapshotRebuildRpc for SnapshotRebuildService {
         &self,
         request: Request<DestroySnapshotRebuildRequest>,
     ) -> GrpcResult<()> {
+        audit::record_args(&request);
         crate::spdk_submit!(async move {
             let args = request.into_inner();
             info!("{:?}", args);
@@ -227,6 +231,49 @@ impl From<RebuildError> for tonic::Status {
                     ..
                 } => tonic::Status::not_found(message),
             },
+            RebuildError::CopyJob {
+                source,
+            } => match source {
+                CopyJobError::NotLive {
+                    ..
+                } => tonic::Status::failed_precondition(message),
+                CopyJobError::NexusNotFound {
+                    ..
+                } => tonic::Status::not_found(message),
+                CopyJobError::PhaseOp {
+                    ..
+                } => tonic::Status::failed_precondition(message),
+                _ => tonic::Status::internal(message),
+            },
+            RebuildError::Replication {
+                source,
+            } => match source {
+                ReplicationError::NexusNotFound {
+                    ..
+                } => tonic::Status::not_found(message),
+                ReplicationError::AlreadyReplicated {
+                    ..
+                } => tonic::Status::already_exists(message),
+                ReplicationError::TargetIsChild {
+                    ..
+                }
+                | ReplicationError::InvalidRpo {
+                    ..
+                } => tonic::Status::invalid_argument(message),
+                ReplicationError::NoHealthySource {
+                    ..
+                }
+                | ReplicationError::Inconsistent {
+                    ..
+                }
+                | ReplicationError::StateOp {
+                    ..
+                } => tonic::Status::failed_precondition(message),
+                ReplicationError::TargetOpen {
+                    ..
+                } => tonic::Status::unavailable(message),
+                _ => tonic::Status::internal(message),
+            },
             _ => tonic::Status::internal(message),
         }
     }
@@ -4,6 +4,7 @@ use crate::{
         VerboseError,
     },
     grpc::{
+        audit,
         v1::replica::{GrpcReplicaFactory, ReplicaGrpc},
         GrpcClientContext,
         GrpcResult,
@@ -150,6 +151,7 @@ impl TestRpc for TestService {
         &self,
         request: Request<v1::test::AddFaultInjectionRequest>,
     ) -> GrpcResult<()> {
+        audit::record_args(&request);
         let args = request.into_inner();
         trace!("{:?}", args);
 
@@ -178,6 +180,7 @@ impl TestRpc for TestService {
         &self,
         request: Request<v1::test::RemoveFaultInjectionRequest>,
     ) -> GrpcResult<()> {
+        audit::record_args(&request);
         let args = request.into_inner();
         trace!("{:?}", args);
 
@@ -1,2 +1,3 @@
 pub mod blk_device;
+pub mod nvme_health;
 pub mod resource;
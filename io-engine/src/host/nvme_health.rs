@@ -0,0 +1,373 @@
+//! Health and SMART telemetry of the NVMe devices used by this instance.
+//! The SMART/health and error log pages are retrieved through the admin
+//! queue, for both the local PCIe devices and the NVMe-oF targets.
+
+use std::{collections::HashMap, time::Duration};
+
+use events_api::event::EventAction;
+use nix::errno::Errno;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use spdk_rs::{libspdk::spdk_nvme_cmd, DmaBuf};
+
+use crate::{
+    bdev::{device_open, nvmx, NVME_CONTROLLERS},
+    core::{CoreError, Reactor, UntypedBdev, UntypedBdevHandle},
+    eventing::Event,
+};
+
+/// Get Log Page admin command opcode.
+const NVME_OPC_GET_LOG_PAGE: u8 = 0x02;
+/// Error Information log page identifier.
+const NVME_LOG_ERROR: u8 = 0x01;
+/// SMART / Health Information log page identifier.
+const NVME_LOG_HEALTH_INFORMATION: u8 = 0x02;
+/// Size of the SMART / Health Information log page.
+const HEALTH_LOG_SIZE: usize = 512;
+/// Size of each entry of the Error Information log page.
+const ERROR_LOG_ENTRY_SIZE: usize = 64;
+/// Default number of error log entries to retrieve.
+pub const DEFAULT_ERROR_LOG_ENTRIES: u32 = 16;
+/// Maximum number of error log entries to retrieve.
+const MAX_ERROR_LOG_ENTRIES: u32 = 256;
+/// Name of the SPDK bdev module used for the local PCIe NVMe devices.
+const NVME_BDEV_DRIVER: &str = "nvme";
+
+/// Critical warning bits of the SMART / Health Information log page.
+const CRITICAL_WARNINGS: [(u8, &str); 6] = [
+    (1 << 0, "available spare below threshold"),
+    (1 << 1, "temperature out of range"),
+    (1 << 2, "reliability degraded"),
+    (1 << 3, "media read-only"),
+    (1 << 4, "volatile memory backup failed"),
+    (1 << 5, "persistent memory region read-only"),
+];
+
+/// SMART / Health Information log page of an NVMe controller.
+/// 128 bit counters are saturated to 64 bits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NvmeSmartLog {
+    /// Critical warning bits.
+    pub critical_warning: u8,
+    /// Composite temperature, in Kelvin.
+    pub temperature: u16,
+    /// Remaining spare capacity, in %.
+    pub available_spare: u8,
+    /// Threshold of the available spare, in %.
+    pub available_spare_threshold: u8,
+    /// Estimate of the used life of the device, in %. May exceed 100.
+    pub percentage_used: u8,
+    /// Critical warning summary of the endurance groups.
+    pub endurance_group_warning: u8,
+    /// Number of 512 byte data units read, in thousands.
+    pub data_units_read: u64,
+    /// Number of 512 byte data units written, in thousands.
+    pub data_units_written: u64,
+    /// Number of read commands completed.
+    pub host_read_commands: u64,
+    /// Number of write commands completed.
+    pub host_write_commands: u64,
+    /// Time the controller was busy with I/O commands, in minutes.
+    pub controller_busy_time: u64,
+    /// Number of power cycles.
+    pub power_cycles: u64,
+    /// Number of power-on hours.
+    pub power_on_hours: u64,
+    /// Number of unsafe shutdowns.
+    pub unsafe_shutdowns: u64,
+    /// Number of unrecovered data integrity errors.
+    pub media_errors: u64,
+    /// Number of error log entries over the life of the controller.
+    pub num_error_log_entries: u64,
+    /// Time spent above the warning temperature threshold, in minutes.
+    pub warning_temp_time: u32,
+    /// Time spent above the critical temperature threshold, in minutes.
+    pub critical_temp_time: u32,
+}
+
+impl NvmeSmartLog {
+    /// Parses the SMART / Health Information log page.
+    fn parse(buf: &[u8]) -> Self {
+        Self {
+            critical_warning: buf[0],
+            temperature: le_u16(buf, 1),
+            available_spare: buf[3],
+            available_spare_threshold: buf[4],
+            percentage_used: buf[5],
+            endurance_group_warning: buf[6],
+            data_units_read: le_u128_sat(buf, 32),
+            data_units_written: le_u128_sat(buf, 48),
+            host_read_commands: le_u128_sat(buf, 64),
+            host_write_commands: le_u128_sat(buf, 80),
+            controller_busy_time: le_u128_sat(buf, 96),
+            power_cycles: le_u128_sat(buf, 112),
+            power_on_hours: le_u128_sat(buf, 128),
+            unsafe_shutdowns: le_u128_sat(buf, 144),
+            media_errors: le_u128_sat(buf, 160),
+            num_error_log_entries: le_u128_sat(buf, 176),
+            warning_temp_time: le_u32(buf, 192),
+            critical_temp_time: le_u32(buf, 196),
+        }
+    }
+
+    /// Returns the descriptions of the critical warnings which are set.
+    pub fn critical_warnings(&self) -> Vec<String> {
+        CRITICAL_WARNINGS
+            .iter()
+            .filter(|(bit, _)| self.critical_warning & bit != 0)
+            .map(|(_, desc)| desc.to_string())
+            .collect()
+    }
+}
+
+/// Entry of the Error Information log page of an NVMe controller.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NvmeErrorLogEntry {
+    /// Unique identifier of the error.
+    pub error_count: u64,
+    /// Submission queue of the failed command.
+    pub sqid: u16,
+    /// Identifier of the failed command.
+    pub cmdid: u16,
+    /// Status of the failed command, without the phase tag.
+    pub status: u16,
+    /// Location of the parameter which caused the error.
+    pub param_error_location: u16,
+    /// First LBA which experienced the error.
+    pub lba: u64,
+    /// Namespace which experienced the error.
+    pub nsid: u32,
+}
+
+impl NvmeErrorLogEntry {
+    /// Parses the entries of the Error Information log page, skipping the
+    /// unused ones.
+    fn parse(buf: &[u8]) -> Vec<Self> {
+        buf.chunks_exact(ERROR_LOG_ENTRY_SIZE)
+            .map(|e| Self {
+                error_count: le_u64(e, 0),
+                sqid: le_u16(e, 8),
+                cmdid: le_u16(e, 10),
+                status: le_u16(e, 12) >> 1,
+                param_error_location: le_u16(e, 14),
+                lba: le_u64(e, 16),
+                nsid: le_u32(e, 24),
+            })
+            .filter(|e| e.error_count != 0)
+            .collect()
+    }
+}
+
+/// Health of an NVMe device.
+#[derive(Debug, Clone)]
+pub struct NvmeHealth {
+    /// Name of the device.
+    pub name: String,
+    /// SMART / Health Information log page.
+    pub smart: NvmeSmartLog,
+    /// Most recent entries of the Error Information log page.
+    pub errors: Vec<NvmeErrorLogEntry>,
+}
+
+impl NvmeHealth {
+    /// Retrieves the health of the given NVMe device, along with up to
+    /// `max_errors` of its most recent error log entries.
+    pub async fn get(name: &str, max_errors: u32) -> Result<Self, CoreError> {
+        let mut buf = dma_buf(HEALTH_LOG_SIZE)?;
+        get_log_page(name, NVME_LOG_HEALTH_INFORMATION, &mut buf).await?;
+        let smart = NvmeSmartLog::parse(buf.as_slice());
+
+        let errors = match max_errors.min(MAX_ERROR_LOG_ENTRIES) {
+            0 => Vec::new(),
+            entries => {
+                let mut buf = dma_buf(entries as usize * ERROR_LOG_ENTRY_SIZE)?;
+                get_log_page(name, NVME_LOG_ERROR, &mut buf).await?;
+                NvmeErrorLogEntry::parse(buf.as_slice())
+            }
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            smart,
+            errors,
+        })
+    }
+
+    /// Retrieves the health of all the NVMe devices.
+    /// Devices which fail to report their health are logged and skipped.
+    pub async fn list(max_errors: u32) -> Vec<Self> {
+        let mut devices = Vec::new();
+        for name in nvme_devices() {
+            match Self::get(&name, max_errors).await {
+                Ok(health) => devices.push(health),
+                Err(error) => {
+                    warn!("Failed to get health of NVMe device {name}: {error}")
+                }
+            }
+        }
+        devices
+    }
+}
+
+/// Lists the names of the NVMe devices: the NVMe-oF controllers and the local
+/// PCIe NVMe bdevs.
+pub fn nvme_devices() -> Vec<String> {
+    let mut names = NVME_CONTROLLERS.controllers();
+    if let Some(bdev) = UntypedBdev::bdev_first() {
+        names.extend(
+            bdev.into_iter()
+                .filter(|b| b.driver() == NVME_BDEV_DRIVER)
+                .map(|b| b.name().to_string()),
+        );
+    }
+    names
+}
+
+/// Reads the given log page of an NVMe device into the buffer, through
+/// the admin queue of its controller.
+async fn get_log_page(
+    name: &str,
+    log_id: u8,
+    buf: &mut DmaBuf,
+) -> Result<(), CoreError> {
+    let mut cmd = spdk_nvme_cmd::default();
+    cmd.set_opc(NVME_OPC_GET_LOG_PAGE.into());
+    cmd.nsid = 0xffffffff;
+    // Number of dwords to transfer, zero based, split across cdw10 and cdw11.
+    let numd = ((buf.len() >> 2) - 1) as u32;
+    cmd.__bindgen_anon_1.cdw10 = log_id as u32 | ((numd & 0xffff) << 16);
+    cmd.__bindgen_anon_2.cdw11 = numd >> 16;
+
+    if nvmx::lookup_by_name(name).is_some() {
+        let handle = device_open(name, false)?.into_handle()?;
+        return handle.nvme_admin(&cmd, Some(buf)).await;
+    }
+
+    match UntypedBdev::lookup_by_name(name) {
+        Some(bdev) if bdev.driver() == NVME_BDEV_DRIVER => {
+            let handle = UntypedBdevHandle::open(name, false, false)?;
+            handle.nvme_admin(&cmd, Some(buf)).await
+        }
+        Some(_) => Err(CoreError::NvmeAdminDispatch {
+            source: Errno::ENXIO,
+            opcode: NVME_OPC_GET_LOG_PAGE.into(),
+        }),
+        None => Err(CoreError::BdevNotFound {
+            name: name.to_string(),
+        }),
+    }
+}
+
+fn dma_buf(size: usize) -> Result<DmaBuf, CoreError> {
+    DmaBuf::new(size as u64, 8).map_err(|_| CoreError::DmaAllocationFailed {
+        size: size as u64,
+    })
+}
+
+fn le_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(buf[offset .. offset + 2].try_into().unwrap())
+}
+
+fn le_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset .. offset + 4].try_into().unwrap())
+}
+
+fn le_u64(buf: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(buf[offset .. offset + 8].try_into().unwrap())
+}
+
+/// Reads a 128 bit counter, saturated to 64 bits.
+fn le_u128_sat(buf: &[u8], offset: usize) -> u64 {
+    let value =
+        u128::from_le_bytes(buf[offset .. offset + 16].try_into().unwrap());
+    value.min(u64::MAX as u128) as u64
+}
+
+/// Last critical warning bits reported by each NVMe device.
+static CRITICAL_WARNING_STATE: Lazy<Mutex<HashMap<String, u8>>> =
+    Lazy::new(Default::default);
+
+/// Evaluates the critical warnings of all the NVMe devices, publishing an
+/// event for each device on which a new critical warning is set.
+async fn check_critical_warnings() {
+    let devices = NvmeHealth::list(0).await;
+
+    let mut state = CRITICAL_WARNING_STATE.lock();
+    state.retain(|name, _| devices.iter().any(|d| &d.name == name));
+
+    for health in devices {
+        let warning = health.smart.critical_warning;
+        let previous = state.insert(health.name.clone(), warning);
+        if warning & !previous.unwrap_or_default() == 0 {
+            continue;
+        }
+
+        error!(
+            "NVMe device {name} reports critical warnings: {warnings:?}",
+            name = health.name,
+            warnings = health.smart.critical_warnings()
+        );
+        health.event(EventAction::NvmeCriticalWarning).generate();
+    }
+}
+
+/// Periodically evaluates the critical warnings of the NVMe devices.
+pub async fn nvme_health_monitor_loop(interval: Duration) {
+    if interval.is_zero() {
+        info!("NVMe health monitor is disabled");
+        return;
+    }
+
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        match Reactor::spawn_at_primary(check_critical_warnings()) {
+            Ok(rx) => {
+                rx.await.ok();
+            }
+            Err(error) => {
+                error!("Failed to schedule NVMe health check: {error}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{NvmeErrorLogEntry, NvmeSmartLog, ERROR_LOG_ENTRY_SIZE};
+
+    #[test]
+    fn test_smart_log() {
+        let mut buf = [0u8; 512];
+        buf[0] = 0b0000_1001;
+        buf[1 .. 3].copy_from_slice(&310u16.to_le_bytes());
+        buf[3] = 90;
+        buf[5] = 7;
+        buf[48 .. 64].copy_from_slice(&1234u128.to_le_bytes());
+        buf[160 .. 176].copy_from_slice(&u128::MAX.to_le_bytes());
+
+        let log = NvmeSmartLog::parse(&buf);
+        assert_eq!(log.temperature, 310);
+        assert_eq!(log.available_spare, 90);
+        assert_eq!(log.percentage_used, 7);
+        assert_eq!(log.data_units_written, 1234);
+        assert_eq!(log.media_errors, u64::MAX);
+        assert_eq!(
+            log.critical_warnings(),
+            vec!["available spare below threshold", "media read-only"]
+        );
+
+        let mut buf = [0u8; ERROR_LOG_ENTRY_SIZE * 3];
+        buf[0 .. 8].copy_from_slice(&5u64.to_le_bytes());
+        buf[12 .. 14].copy_from_slice(&((0x281u16 << 1) | 1).to_le_bytes());
+        buf[24 .. 28].copy_from_slice(&1u32.to_le_bytes());
+        buf[128 .. 136].copy_from_slice(&4u64.to_le_bytes());
+
+        let errors = NvmeErrorLogEntry::parse(&buf);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].error_count, 5);
+        assert_eq!(errors[0].status, 0x281);
+        assert_eq!(errors[0].nsid, 1);
+        assert_eq!(errors[1].error_count, 4);
+    }
+}
@@ -12,12 +12,20 @@ use std::{
 };
 
 use crate::{
-    constants::{EVENTING_TARGET, SERVICE_NAME},
-    core::spawn,
+    constants::{EVENTING_TARGET, SERVICE_NAME, TRACING_TARGET},
+    core::{runtime, spawn},
+    eventing::event_ring::EventRing,
 };
 use event_publisher::event_handler::EventHandle;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    trace::{self, Tracer},
+    Resource,
+};
 use tracing::field::{Field, Visit};
-use tracing_core::{event::Event, Level, Metadata};
+use tracing_core::{event::Event, Level, Metadata, Subscriber};
 use tracing_log::{LogTracer, NormalizeEvent};
 use tracing_subscriber::{
     filter::{filter_fn, Targets},
@@ -512,13 +520,60 @@ impl LogFormat {
     }
 }
 
+/// Creates an OpenTelemetry tracer which exports spans to the given OTLP
+/// (gRPC) collector endpoint. TLS is used for `https` endpoints.
+fn otel_tracer(
+    endpoint: &url::Url,
+) -> Result<Tracer, opentelemetry::trace::TraceError> {
+    opentelemetry::global::set_text_map_propagator(
+        TraceContextPropagator::new(),
+    );
+
+    // The batch span processor spawns its export task on the tokio runtime,
+    // so the runtime context must be entered while installing it.
+    let _guard = runtime::enter();
+
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint.as_str()),
+        )
+        .with_trace_config(trace::config().with_resource(Resource::new(vec![
+            KeyValue::new("service.name", SERVICE_NAME),
+        ])))
+        .install_batch(opentelemetry_sdk::runtime::Tokio)
+}
+
+/// Creates the layer which records the spans with the `TRACING_TARGET`
+/// target, along with the events within them, with the given tracer.
+fn otel_layer<S>(tracer: Tracer) -> impl Layer<S>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer()
+        .with_tracer(tracer)
+        .with_filter(filter_fn(|metadata| {
+            metadata.target() == TRACING_TARGET || metadata.is_event()
+        }))
+}
+
 /// This function configures the logging format. The loglevel is also processed
 /// here i.e `RUST_LOG=io_engine=TRACE` will print all trace!() and higher
 /// messages to the console.
 ///
 /// We might want to suppress certain messages, as some of them are redundant,
 /// in particular, the NOTICE messages as such, they are mapped to debug.
-pub fn init_ex(level: &str, format: LogFormat, events_url: Option<url::Url>) {
+///
+/// If a tracing endpoint is given, spans with the `TRACING_TARGET` target
+/// (along with the events recorded within them) are exported to it via OTLP.
+pub fn init_ex(
+    level: &str,
+    format: LogFormat,
+    events_url: Option<url::Url>,
+    tracing_url: Option<url::Url>,
+) {
     // Set up a "logger" that simply translates any "log" messages it receives
     // to trace events. This is for our custom spdk log messages, but also
     // for any other third party crates still using the logging facade.
@@ -531,8 +586,9 @@ pub fn init_ex(level: &str, format: LogFormat, events_url: Option<url::Url>) {
         .event_format(format)
         .with_filter(filter_fn(|metadata| {
             // Exclude spans or events that have the target
-            // "mbus-events-target".
+            // "mbus-events-target", as well as the distributed tracing spans.
             metadata.target() != EVENTING_TARGET
+                && metadata.target() != TRACING_TARGET
         }));
 
     let filter = tracing_filter::rust_log_filter_ext(level);
@@ -550,15 +606,76 @@ pub fn init_ex(level: &str, format: LogFormat, events_url: Option<url::Url>) {
         None => None,
     };
 
+    // Get the optional distributed tracing layer.
+    let tracer = tracing_url.as_ref().map(otel_tracer);
+    let tracing_layer = match &tracer {
+        Some(Ok(tracer)) => Some(otel_layer(tracer.clone())),
+        _ => None,
+    };
+
     let subscriber = Registry::default()
         .with(filter)
         .with(Some(builder))
-        .with(events_layer);
+        .with(events_layer)
+        .with(EventRing::layer())
+        .with(tracing_layer);
 
     tracing::subscriber::set_global_default(subscriber)
         .expect("failed to set default subscriber");
+
+    match (tracing_url, tracer) {
+        (Some(url), Some(Ok(_))) => {
+            info!("Exporting OpenTelemetry traces to {url}");
+        }
+        (Some(url), Some(Err(error))) => {
+            error!(%error, "Failed to set up OpenTelemetry tracing to {url}");
+        }
+        _ => {}
+    }
 }
 
 pub fn init(level: &str) {
-    init_ex(level, Default::default(), None)
+    init_ex(level, Default::default(), None, None)
+}
+
+/// Flushes the pending spans and shuts down the OpenTelemetry tracer, if any.
+pub fn fini() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_sdk::{
+        testing::trace::InMemorySpanExporter,
+        trace::TracerProvider,
+    };
+
+    #[tracing::instrument(target = TRACING_TARGET, skip_all)]
+    fn traced_op() {
+        tracing::info_span!("untraced_step").in_scope(|| {
+            tracing::info!("step done");
+        });
+    }
+
+    #[test]
+    fn otel_layer_exports_traced_spans() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber =
+            Registry::default().with(otel_layer(provider.tracer("test")));
+
+        tracing::subscriber::with_default(subscriber, traced_op);
+        provider.force_flush();
+
+        // Only the span with the tracing target is exported, with the events
+        // recorded within it.
+        let spans = exporter.get_finished_spans().unwrap();
+        let names = spans.iter().map(|s| s.name.as_ref()).collect::<Vec<_>>();
+        assert_eq!(names, ["traced_op"]);
+        assert_eq!(spans[0].events.len(), 1);
+    }
 }
@@ -161,6 +161,18 @@ pub enum LvsError {
         name: String,
         msg: String,
     },
+    #[snafu(display(
+        "base bdev {} of pool {} has protection information ({}), which \
+        pools do not support",
+        bdev,
+        name,
+        format
+    ))]
+    UnsupportedProtectionInfo {
+        name: String,
+        bdev: String,
+        format: String,
+    },
     #[snafu(display("lvol exists {}", name))]
     RepExists {
         source: BsError,
@@ -303,6 +315,9 @@ impl ToErrno for LvsError {
             Self::InvalidClusterSize {
                 source, ..
             } => source.to_errno(),
+            Self::UnsupportedProtectionInfo {
+                ..
+            } => Errno::EMEDIUMTYPE,
             Self::RepExists {
                 source, ..
             } => source.to_errno(),
@@ -9,6 +9,7 @@ use std::{
     convert::TryFrom,
     ffi::{c_ushort, c_void, CStr},
     fmt::{Debug, Display},
+    ops::Range,
     os::raw::c_char,
     pin::Pin,
     ptr::NonNull,
@@ -17,6 +18,8 @@ use std::{
 use spdk_rs::libspdk::{
     spdk_blob,
     spdk_blob_calc_used_clusters,
+    spdk_blob_get_next_allocated_io_unit,
+    spdk_blob_get_next_unallocated_io_unit,
     spdk_blob_get_num_clusters,
     spdk_blob_get_num_clusters_ancestors,
     spdk_blob_get_xattr_value,
@@ -358,6 +361,15 @@ impl Lvol {
         LvolPtpl::from(self)
     }
 
+    /// Returns the identifier of this instance of the lvol, which tells it
+    /// from an lvol since recreated with the same UUID. Lvols created by
+    /// older versions don't have one.
+    pub fn instance_id(&self) -> Option<u64> {
+        Lvol::get_blob_xattr(self.blob_checked(), "instance_id")
+            .and_then(|id| u64::from_str_radix(&id, 16).ok())
+            .filter(|&id| id != 0)
+    }
+
     /// Common API to get the xattr from blob.
     pub fn get_blob_xattr(blob: *mut spdk_blob, attr: &str) -> Option<String> {
         if blob.is_null() {
@@ -693,6 +705,37 @@ impl LogicalVolume for Lvol {
             unsafe { spdk_blob_calc_used_clusters(blob) };
         cluster_size * num_allocated_clusters
     }
+    /// Returns the ranges of blocks within the given range which hold data
+    /// of the Logical Volume.
+    /// Thick volumes are fully allocated. Volumes backed by a snapshot are
+    /// reported as fully allocated as well, as the data of unallocated
+    /// clusters comes from their ancestors.
+    fn allocated_ranges(&self, range: Range<u64>) -> Vec<Range<u64>> {
+        let blob = self.blob_checked();
+
+        if !self.is_thin() || unsafe { self.bs_iter_parent(blob) }.is_some() {
+            return vec![range];
+        }
+
+        let mut ranges = Vec::new();
+        let mut pos = range.start;
+
+        while pos < range.end {
+            let start =
+                unsafe { spdk_blob_get_next_allocated_io_unit(blob, pos) };
+            if start >= range.end {
+                break;
+            }
+
+            let end =
+                unsafe { spdk_blob_get_next_unallocated_io_unit(blob, start) }
+                    .min(range.end);
+            ranges.push(start .. end);
+            pos = end;
+        }
+
+        ranges
+    }
     /// Returns Lvol disk space usage.
     fn usage(&self) -> LvolSpaceUsage {
         let bs = self.lvs().blob_store();
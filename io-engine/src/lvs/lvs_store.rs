@@ -45,12 +45,14 @@ use super::{
 };
 
 use crate::{
-    bdev::{uri, PtplFileOps},
+    bdev::{device_lookup, uri, PtplFileOps},
     bdev_api::{bdev_destroy, BdevError},
+    constants::TRACING_TARGET,
     core::{
         logical_volume::LogicalVolume,
         snapshot::LvolSnapshotOps,
         Bdev,
+        BlockDevice,
         IoType,
         NvmfShareProps,
         Share,
@@ -257,6 +259,11 @@ impl Lvs {
     }
 
     /// imports a pool based on its name and base bdev name
+    #[tracing::instrument(
+        target = TRACING_TARGET,
+        skip_all,
+        fields(pool = name)
+    )]
     pub async fn import(name: &str, bdev: &str) -> Result<Lvs, LvsError> {
         let (sender, receiver) = pair::<ErrnoResult<Lvs>>();
 
@@ -440,6 +447,21 @@ impl Lvs {
                 ),
             });
         }
+        // The blobstore has no room for the metadata of the blocks, so it
+        // can't keep their protection information, which the device would
+        // check against the lvol blocks: replicas with protection information
+        // are out of scope until lvols support it. Metadata without it, e.g.
+        // a separate metadata buffer of an NVMe namespace, is left unused.
+        if let Some(pi) = device_lookup(bdev)
+            .map(|dev| dev.protection_info())
+            .filter(|pi| pi.is_enabled())
+        {
+            return Err(LvsError::UnsupportedProtectionInfo {
+                name: name.to_string(),
+                bdev: bdev.to_string(),
+                format: pi.to_string(),
+            });
+        }
         let (sender, receiver) = pair::<ErrnoResult<Lvs>>();
         unsafe {
             if let Some(uuid) = uuid {
@@ -885,6 +907,20 @@ impl Lvs {
             }
         }
 
+        let instance_id = rand::random::<u64>().max(1);
+        if let Err(error) = lvol
+            .set_blob_attr("instance_id", format!("{instance_id:x}"), true)
+            .await
+        {
+            let lvol_uuid = lvol.uuid();
+            if let Err(error) = lvol.destroy().await {
+                warn!(
+                    "uuid/{lvol_uuid}: failed to destroy lvol after failing to set instance id: {error:?}",
+                );
+            }
+            return Err(error);
+        }
+
         info!("{lvol:?}: wiping super");
 
         if let Err(error) = lvol.wipe_super().await {
@@ -5,6 +5,7 @@
 //! the etcd-client crate. This crate has a dependency on the tokio async
 //! runtime.
 use crate::{
+    constants::TRACING_TARGET,
     core,
     core::Reactor,
     store::{
@@ -26,6 +27,7 @@ use parking_lot::Mutex;
 use serde_json::Value;
 use snafu::ResultExt;
 use std::{future::Future, time::Duration};
+use tracing::Instrument;
 
 /// Persistent store builder.
 pub struct PersistentStoreBuilder {
@@ -166,6 +168,11 @@ impl PersistentStore {
     }
 
     /// Puts a key-value in the store.
+    #[tracing::instrument(
+        target = TRACING_TARGET,
+        skip_all,
+        fields(key = %key.to_string())
+    )]
     pub async fn put(
         key: &impl StoreKey,
         value: &impl StoreValue,
@@ -243,6 +250,37 @@ impl PersistentStore {
         })?
     }
 
+    /// Retrieves the values of all the keys with the given prefix from the
+    /// store.
+    pub async fn get_prefix(
+        key_prefix: &impl StoreKey,
+    ) -> Result<Vec<Value>, StoreError> {
+        let key_string = key_prefix.to_string();
+        let rx = Self::execute_store_op(async move {
+            info!("Getting keys with prefix {} from store.", key_string);
+            Self::backing_store().get_values_prefix(&key_string).await
+        });
+        rx.await.context(GetWait {
+            key: key_prefix.to_string(),
+        })?
+    }
+
+    /// Deletes all the entries in the store with the given key prefix.
+    pub async fn delete_prefix(
+        key_prefix: &impl StoreKey,
+    ) -> Result<(), StoreError> {
+        let key_string = key_prefix.to_string();
+        let rx = Self::execute_store_op(async move {
+            info!("Deleting keys with prefix {} from store.", key_string);
+            Self::backing_store()
+                .delete_values_prefix(&key_string)
+                .await
+        });
+        rx.await.context(DeleteWait {
+            key: key_prefix.to_string(),
+        })?
+    }
+
     /// Executes a future representing a store operation (i.e. put, get, delete)
     /// on the tokio runtime.
     /// A channel is returned which is signalled when the operation completes.
@@ -252,6 +290,8 @@ impl PersistentStore {
         f: impl Future<Output = Result<T, StoreError>> + Send + 'static,
     ) -> oneshot::Receiver<Result<T, StoreError>> {
         let (tx, rx) = oneshot::channel::<Result<T, StoreError>>();
+        // Keep the store operation within the span of the caller.
+        let f = f.in_current_span();
         core::runtime::spawn(async move {
             let op_timeout = Self::timeout();
             let result = match tokio::time::timeout(op_timeout, f).await {
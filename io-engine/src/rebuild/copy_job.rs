@@ -0,0 +1,610 @@
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use snafu::ResultExt;
+use std::{collections::HashMap, sync::Arc};
+
+use super::{
+    rebuild_error::{BdevInvalidUri, CopyJobError},
+    BdevRebuildJob,
+    RebuildError,
+    RebuildJobOptions,
+    RebuildState,
+    RebuildStats,
+    RebuildVerifyMode,
+};
+use crate::{
+    bdev::nexus::{nexus_iter, nexus_lookup, nexus_lookup_mut},
+    bdev_api::bdev_get_name,
+    core::{Reactors, SegmentMap},
+};
+
+/// Phase of a copy job.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum CopyJobPhase {
+    /// The job has been created but not started yet.
+    #[default]
+    Init,
+    /// The whole source is being copied to the destination.
+    Copy,
+    /// The segments written to a live source during the copy are being
+    /// copied again, with the nexus I/O paused.
+    CatchUp,
+    /// The job is over, the state tells how it went.
+    Done,
+}
+
+impl std::fmt::Display for CopyJobPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            CopyJobPhase::Init => write!(f, "init"),
+            CopyJobPhase::Copy => write!(f, "copy"),
+            CopyJobPhase::CatchUp => write!(f, "catch-up"),
+            CopyJobPhase::Done => write!(f, "done"),
+        }
+    }
+}
+
+/// Progress of a copy job.
+#[derive(Debug, Clone)]
+pub struct CopyJobStats {
+    /// Current phase of the job.
+    pub phase: CopyJobPhase,
+    /// State of the current phase, or final state of the job once done.
+    pub state: RebuildState,
+    /// Statistics of the current phase, or of the last one once done.
+    pub phase_stats: RebuildStats,
+    /// Number of blocks copied by all the phases so far.
+    pub blocks_copied: u64,
+    /// Start time of the job.
+    pub start_time: DateTime<Utc>,
+    /// End time of the job, once done.
+    pub end_time: Option<DateTime<Utc>>,
+    /// Error which failed the job, if any.
+    pub error: Option<String>,
+}
+
+/// Mutable state of a copy job.
+#[derive(Default)]
+struct CopyJobInner {
+    /// Current phase of the job.
+    phase: CopyJobPhase,
+    /// Final state of the job.
+    state: RebuildState,
+    /// Rebuild job copying the data in the current phase.
+    job: Option<Arc<BdevRebuildJob>>,
+    /// Final statistics of the last finished phase.
+    last_stats: Option<RebuildStats>,
+    /// Number of blocks copied by the finished phases.
+    blocks_copied: u64,
+    /// Set once the job is cancelled, so that no further phase is started.
+    cancelled: bool,
+    /// Error which failed the job, if any.
+    error: Option<RebuildError>,
+    /// End time of the job.
+    end_time: Option<DateTime<Utc>>,
+}
+
+/// A copy job copies a source device to a destination device, for example to
+/// move a replica between pools on the same node.
+/// The whole source is copied first. If the source is live, i.e. a child of a
+/// local nexus which keeps writing to it, the writes made during the copy are
+/// logged and a final catch-up copies the written segments again while the
+/// nexus I/O is paused, leaving the destination identical to the source. The
+/// destination then replaces the source in the nexus, before the nexus I/O
+/// is resumed.
+pub struct CopyJob {
+    /// Uuid of the job.
+    uuid: String,
+    /// Uri of the device to copy from.
+    src_uri: String,
+    /// Uri of the device to copy to.
+    dst_uri: String,
+    /// Name of the source device.
+    src_device: String,
+    /// Verify each copied segment by reading it back.
+    verify: bool,
+    /// Name of the nexus whose writes to the source are logged for the
+    /// catch-up, if the source is live.
+    live_nexus: Option<String>,
+    /// Start time of the job.
+    start_time: DateTime<Utc>,
+    /// Mutable state of the job.
+    inner: Mutex<CopyJobInner>,
+}
+
+impl std::fmt::Debug for CopyJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CopyJob")
+            .field("uuid", &self.uuid)
+            .field("src_uri", &self.src_uri)
+            .field("dst_uri", &self.dst_uri)
+            .field("live_nexus", &self.live_nexus)
+            .field("phase", &self.phase())
+            .finish()
+    }
+}
+
+/// Builder for the `CopyJob`.
+#[derive(Default)]
+pub struct CopyJobBuilder {
+    uuid: Option<String>,
+    verify: bool,
+    live: bool,
+}
+
+impl CopyJobBuilder {
+    /// Specify the job's uuid.
+    pub fn with_uuid(mut self, uuid: &str) -> Self {
+        self.uuid = Some(uuid.to_string());
+        self
+    }
+    /// Verify each copied segment by reading it back from the destination.
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+    /// Catch up with the writes made to the source while it is copied.
+    pub fn with_live(mut self, live: bool) -> Self {
+        self.live = live;
+        self
+    }
+    /// Finds the local nexus which has the given device as a child.
+    fn live_nexus(
+        src_uri: &str,
+        src_device: &str,
+    ) -> Result<String, RebuildError> {
+        nexus_iter()
+            .find(|n| n.lookup_child_by_device(src_device).is_some())
+            .map(|n| n.nexus_name().to_string())
+            .ok_or_else(|| {
+                CopyJobError::NotLive {
+                    uri: src_uri.to_string(),
+                }
+                .into()
+            })
+    }
+    /// Builds a `CopyJob` which can be started and which will then copy from
+    /// source to destination.
+    pub async fn build(
+        self,
+        src_uri: &str,
+        dst_uri: &str,
+    ) -> Result<CopyJob, RebuildError> {
+        let src_device = bdev_get_name(src_uri).context(BdevInvalidUri {
+            uri: src_uri.to_string(),
+        })?;
+        let live_nexus = match self.live {
+            true => Some(Self::live_nexus(src_uri, &src_device)?),
+            false => None,
+        };
+
+        let job = CopyJob::rebuild_job(self.verify, None)
+            .build(src_uri, dst_uri)
+            .await?;
+
+        Ok(CopyJob {
+            uuid: self
+                .uuid
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            src_uri: src_uri.to_string(),
+            dst_uri: dst_uri.to_string(),
+            src_device,
+            verify: self.verify,
+            live_nexus,
+            start_time: Utc::now(),
+            inner: Mutex::new(CopyJobInner {
+                job: Some(Arc::new(job)),
+                ..Default::default()
+            }),
+        })
+    }
+}
+
+/// List of copy jobs indexed by their uuid.
+type CopyJobInstances = HashMap<String, Arc<CopyJob>>;
+
+impl CopyJob {
+    /// Helps create a `Self` using a builder: `CopyJobBuilder`.
+    pub fn builder() -> CopyJobBuilder {
+        CopyJobBuilder::default()
+    }
+
+    /// Get the copy job instances container, we ensure that this can only
+    /// ever be called on a properly allocated thread.
+    fn get_instances<'a>() -> parking_lot::MutexGuard<'a, CopyJobInstances> {
+        assert!(
+            spdk_rs::Thread::is_spdk_thread(),
+            "not called from SPDK thread"
+        );
+
+        static COPY_JOB_INSTANCES: once_cell::sync::OnceCell<
+            Mutex<CopyJobInstances>,
+        > = once_cell::sync::OnceCell::new();
+
+        COPY_JOB_INSTANCES
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+    }
+
+    /// Stores a copy job in the copy job list.
+    /// Finished jobs with the same uuid, or copying to the same destination,
+    /// are dropped so that the copy can be retried. Fails if such a job is
+    /// still running.
+    pub fn store(self) -> Result<Arc<Self>, RebuildError> {
+        let mut jobs = Self::get_instances();
+
+        let conflicts =
+            |j: &CopyJob| j.uuid == self.uuid || j.dst_uri == self.dst_uri;
+        jobs.retain(|_, j| !(conflicts(j) && j.is_done()));
+
+        if let Some(job) = jobs.values().find(|j| conflicts(j)) {
+            return Err(RebuildError::JobAlreadyExists {
+                job: job.uuid.clone(),
+            });
+        }
+
+        let job = Arc::new(self);
+        jobs.insert(job.uuid.clone(), job.clone());
+        Ok(job)
+    }
+
+    /// Lookup a copy job by its uuid and return it.
+    pub fn lookup(uuid: &str) -> Result<Arc<Self>, RebuildError> {
+        Self::get_instances().get(uuid).cloned().ok_or_else(|| {
+            RebuildError::JobNotFound {
+                job: uuid.to_string(),
+            }
+        })
+    }
+
+    /// Lookup a copy job by its uuid then remove it.
+    pub fn remove(uuid: &str) -> Result<Arc<Self>, RebuildError> {
+        Self::get_instances().remove(uuid).ok_or_else(|| {
+            RebuildError::JobNotFound {
+                job: uuid.to_string(),
+            }
+        })
+    }
+
+    /// Get a list of all copy jobs.
+    pub fn list() -> Vec<Arc<Self>> {
+        Self::get_instances().values().cloned().collect()
+    }
+
+    /// Get the uuid of this copy job.
+    pub fn uuid(&self) -> &str {
+        &self.uuid
+    }
+
+    /// Get the uri of the copy source.
+    pub fn src_uri(&self) -> &str {
+        &self.src_uri
+    }
+
+    /// Get the uri of the copy destination.
+    pub fn dst_uri(&self) -> &str {
+        &self.dst_uri
+    }
+
+    /// Checks if each copied segment is verified.
+    pub fn verify(&self) -> bool {
+        self.verify
+    }
+
+    /// Checks if the job catches up with the writes made to the source.
+    pub fn is_live(&self) -> bool {
+        self.live_nexus.is_some()
+    }
+
+    /// Get the current phase of the job.
+    pub fn phase(&self) -> CopyJobPhase {
+        self.inner.lock().phase
+    }
+
+    /// Checks if the job is over.
+    pub fn is_done(&self) -> bool {
+        self.phase() == CopyJobPhase::Done
+    }
+
+    /// Checks if the job is over and has copied the source.
+    pub fn is_completed(&self) -> bool {
+        let inner = self.inner.lock();
+        inner.phase == CopyJobPhase::Done
+            && inner.state == RebuildState::Completed
+    }
+
+    /// Starts the job in the background.
+    pub fn start(self: &Arc<Self>) -> Result<(), RebuildError> {
+        {
+            let mut inner = self.inner.lock();
+            if inner.phase != CopyJobPhase::Init || inner.cancelled {
+                return Err(RebuildError::OpError {
+                    operation: "Start".to_string(),
+                    state: inner.phase.to_string(),
+                });
+            }
+            inner.phase = CopyJobPhase::Copy;
+        }
+
+        let job = self.clone();
+        Reactors::master().send_future(async move { job.run().await });
+        Ok(())
+    }
+
+    /// Pauses the copy. The catch-up can't be paused as it holds the nexus
+    /// I/O paused.
+    pub fn pause(&self) -> Result<(), RebuildError> {
+        self.copy_phase_job("Pause")?.pause()
+    }
+
+    /// Resumes a previously paused copy.
+    pub fn resume(&self) -> Result<(), RebuildError> {
+        self.copy_phase_job("Resume")?.resume()
+    }
+
+    /// Cancels the job and waits for the current phase to stop.
+    pub async fn cancel(&self) {
+        let job = {
+            let mut inner = self.inner.lock();
+            inner.cancelled = true;
+            inner.job.clone()
+        };
+
+        if let Some(job) = job.filter(|j| j.state() != RebuildState::Init) {
+            if let Err(error) = job.force_stop().await {
+                warn!("{self:?}: cancelled copy did not stop cleanly: {error}");
+            }
+        }
+    }
+
+    /// Get the progress of the job.
+    pub async fn stats(&self) -> CopyJobStats {
+        let (job, mut stats) = {
+            let inner = self.inner.lock();
+            let stats = CopyJobStats {
+                phase: inner.phase,
+                state: inner.state,
+                phase_stats: inner.last_stats.clone().unwrap_or_default(),
+                blocks_copied: inner.blocks_copied,
+                start_time: self.start_time,
+                end_time: inner.end_time,
+                error: inner.error.as_ref().map(ToString::to_string),
+            };
+            (inner.job.clone(), stats)
+        };
+
+        if let Some(job) = job.filter(|_| stats.phase != CopyJobPhase::Done) {
+            stats.state = job.state();
+            stats.phase_stats = job.stats().await;
+            stats.blocks_copied += stats.phase_stats.blocks_transferred;
+        }
+
+        stats
+    }
+
+    /// Returns the rebuild job of the copy phase, failing the given
+    /// operation in any other phase.
+    fn copy_phase_job(
+        &self,
+        operation: &str,
+    ) -> Result<Arc<BdevRebuildJob>, RebuildError> {
+        let inner = self.inner.lock();
+        match (inner.phase, &inner.job) {
+            (CopyJobPhase::Copy, Some(job)) => Ok(job.clone()),
+            (phase, _) => Err(CopyJobError::PhaseOp {
+                operation: operation.to_string(),
+                phase: phase.to_string(),
+            }
+            .into()),
+        }
+    }
+
+    /// Creates a rebuild job builder with the copy options.
+    fn rebuild_job(
+        verify: bool,
+        map: Option<SegmentMap>,
+    ) -> super::bdev_rebuild::BdevRebuildJobBuilder {
+        let options = RebuildJobOptions {
+            verify_mode: match verify {
+                true => RebuildVerifyMode::Fail,
+                false => RebuildVerifyMode::None,
+            },
+            ..Default::default()
+        }
+        .with_skip_unallocated(true);
+
+        let builder = BdevRebuildJob::builder().with_option(options);
+        match map {
+            Some(map) => builder.with_bitmap(map),
+            None => builder,
+        }
+    }
+
+    /// Runs all the phases of the job and records the outcome.
+    async fn run(self: Arc<Self>) {
+        info!("{self:?}: starting copy");
+
+        let result = self.run_phases().await;
+
+        // The copy log is left behind if the copy did not get to the
+        // catch-up.
+        if let Some(nexus) = &self.live_nexus {
+            if let Some(n) = nexus_lookup(nexus) {
+                n.stop_copy_log(&self.src_device).await;
+            }
+        }
+
+        let mut inner = self.inner.lock();
+        inner.phase = CopyJobPhase::Done;
+        inner.job = None;
+        inner.end_time = Some(Utc::now());
+        inner.state = match result {
+            Ok(state) => state,
+            Err(error) => {
+                error!("{self:?}: copy failed: {error}");
+                inner.error = Some(error);
+                RebuildState::Failed
+            }
+        };
+
+        info!("{self:?}: copy finished: {state}", state = inner.state);
+    }
+
+    /// Runs the copy, followed by the catch-up for a live source.
+    async fn run_phases(&self) -> Result<RebuildState, RebuildError> {
+        if let Some(nexus) = &self.live_nexus {
+            let n = nexus_lookup(nexus).ok_or_else(|| {
+                CopyJobError::NexusNotFound {
+                    nexus: nexus.clone(),
+                }
+            })?;
+            n.start_copy_log(&self.src_device).await.map_err(|error| {
+                CopyJobError::CopyLog {
+                    nexus: nexus.clone(),
+                    reason: error.to_string(),
+                }
+            })?;
+        }
+
+        let Some(job) = self.inner.lock().job.clone() else {
+            return Err(RebuildError::BackendGone);
+        };
+        let state = self.run_phase(CopyJobPhase::Copy, job).await?;
+
+        match &self.live_nexus {
+            Some(nexus) if state == RebuildState::Completed => {
+                self.catch_up(nexus).await
+            }
+            _ => Ok(state),
+        }
+    }
+
+    /// Pauses the nexus I/O, copies the segments written to the source since
+    /// the copy started, and replaces the source child with the destination.
+    async fn catch_up(
+        &self,
+        nexus: &str,
+    ) -> Result<RebuildState, RebuildError> {
+        let Some(n) = nexus_lookup_mut(nexus) else {
+            return Err(CopyJobError::NexusNotFound {
+                nexus: nexus.to_string(),
+            }
+            .into());
+        };
+        n.pause().await.map_err(|error| CopyJobError::NexusPause {
+            nexus: nexus.to_string(),
+            reason: error.to_string(),
+        })?;
+
+        let map = match nexus_lookup(nexus) {
+            Some(n) => n.stop_copy_log(&self.src_device).await,
+            None => None,
+        };
+        let result = match map {
+            Some(map) => {
+                info!(
+                    "{self:?}: catching up with {blks} blocks written during \
+                    the copy",
+                    blks = map.count_dirty_blks()
+                );
+                match Self::rebuild_job(self.verify, Some(map.into()))
+                    .build(&self.src_uri, &self.dst_uri)
+                    .await
+                {
+                    Ok(job) => {
+                        self.run_phase(CopyJobPhase::CatchUp, Arc::new(job))
+                            .await
+                    }
+                    Err(error) => Err(error),
+                }
+            }
+            None => Err(CopyJobError::CopyLog {
+                nexus: nexus.to_string(),
+                reason: "the copy log is gone".to_string(),
+            }
+            .into()),
+        };
+
+        // Swap the destination in while the source can't be written.
+        let result = match result {
+            Ok(RebuildState::Completed) => self
+                .swap_child(nexus)
+                .await
+                .map(|_| RebuildState::Completed),
+            result => result,
+        };
+
+        if let Some(n) = nexus_lookup_mut(nexus) {
+            if let Err(error) = n.resume().await {
+                error!("{self:?}: failed to resume nexus {nexus}: {error}");
+            }
+        }
+
+        result
+    }
+
+    /// Replaces the source child of the nexus with the destination.
+    async fn swap_child(&self, nexus: &str) -> Result<(), RebuildError> {
+        let error = |reason: String| CopyJobError::ChildSwap {
+            nexus: nexus.to_string(),
+            reason,
+        };
+
+        let Some(n) = nexus_lookup_mut(nexus) else {
+            return Err(CopyJobError::NexusNotFound {
+                nexus: nexus.to_string(),
+            }
+            .into());
+        };
+        let Some(src_uri) = n
+            .lookup_child_by_device(&self.src_device)
+            .map(|c| c.uri().to_string())
+        else {
+            return Err(error("the source is no longer a child".into()).into());
+        };
+
+        n.swap_child(&src_uri, &self.dst_uri)
+            .await
+            .map_err(|e| error(e.to_string()))?;
+
+        info!("{self:?}: destination replaced {src_uri} in nexus {nexus}");
+        Ok(())
+    }
+
+    /// Runs the rebuild job of the given phase to its end.
+    async fn run_phase(
+        &self,
+        phase: CopyJobPhase,
+        job: Arc<BdevRebuildJob>,
+    ) -> Result<RebuildState, RebuildError> {
+        if self.inner.lock().cancelled {
+            return Ok(RebuildState::Stopped);
+        }
+
+        let chan = job.start().await?;
+        {
+            let mut inner = self.inner.lock();
+            inner.phase = phase;
+            inner.job = Some(job.clone());
+            if inner.cancelled {
+                drop(job.force_stop());
+            }
+        }
+
+        let state = chan.await.unwrap_or(RebuildState::Failed);
+        let stats = job.stats().await;
+        {
+            let mut inner = self.inner.lock();
+            inner.blocks_copied += stats.blocks_transferred;
+            inner.last_stats = Some(stats);
+            inner.job = None;
+        }
+
+        match state {
+            RebuildState::Failed => {
+                Err(job.error().unwrap_or(RebuildError::BackendGone))
+            }
+            state => Ok(state),
+        }
+    }
+}
@@ -1,5 +1,7 @@
 mod bdev_rebuild;
+mod copy_job;
 mod nexus_rebuild;
+mod rebuild_checkpoint;
 mod rebuild_descriptor;
 mod rebuild_error;
 mod rebuild_instances;
@@ -10,12 +12,20 @@ mod rebuild_state;
 mod rebuild_stats;
 mod rebuild_task;
 mod rebuilders;
+mod replication_job;
 mod snapshot_rebuild;
 
 pub use bdev_rebuild::BdevRebuildJob;
+pub use copy_job::{CopyJob, CopyJobPhase, CopyJobStats};
 pub use nexus_rebuild::{NexusRebuildJob, NexusRebuildJobStarter};
+pub use rebuild_checkpoint::RebuildCheckpoint;
 use rebuild_descriptor::RebuildDescriptor;
-pub(crate) use rebuild_error::{RebuildError, SnapshotRebuildError};
+pub(crate) use rebuild_error::{
+    CopyJobError,
+    RebuildError,
+    ReplicationError,
+    SnapshotRebuildError,
+};
 use rebuild_job::RebuildOperation;
 pub use rebuild_job::{RebuildJob, RebuildJobOptions, RebuildVerifyMode};
 use rebuild_job_backend::{
@@ -27,8 +37,9 @@ pub use rebuild_map::RebuildMap;
 pub use rebuild_state::RebuildState;
 use rebuild_state::RebuildStates;
 pub(crate) use rebuild_stats::HistoryRecord;
-pub use rebuild_stats::RebuildStats;
+pub use rebuild_stats::{HistoryRetention, RebuildSourceStats, RebuildStats};
 use rebuild_task::{RebuildTasks, TaskResult};
+pub use replication_job::{ReplicationJob, ReplicationState, ReplicationStats};
 pub use snapshot_rebuild::SnapshotRebuildJob;
 
 /// Number of concurrent copy tasks per rebuild job
@@ -62,6 +73,20 @@ pub(crate) async fn shutdown_snapshot_rebuilds() {
     }
 }
 
+/// Cancel all pending copy jobs.
+pub(crate) async fn shutdown_copy_jobs() {
+    for job in CopyJob::list() {
+        job.cancel().await;
+    }
+}
+
+/// Stop all asynchronous replications.
+pub(crate) async fn shutdown_replication_jobs() {
+    for job in ReplicationJob::list() {
+        job.stop().await;
+    }
+}
+
 /// Parse the given url as string into a `url::Url`.
 pub fn parse_url(url: &str) -> Result<url::Url, RebuildError> {
     match url::Url::parse(url) {
@@ -4,6 +4,7 @@ use spdk_rs::LbaRange;
 use std::ops::{Deref, Range};
 
 use crate::{
+    bdev::nexus::StripeGeometry,
     core::{DescriptorGuard, UntypedBdev},
     gen_rebuild_instances,
     rebuild::{
@@ -16,6 +17,7 @@ use crate::{
             PartialSeqRebuild,
             RangeRebuilder,
         },
+        RebuildCheckpoint,
         RebuildMap,
         RebuildState,
     },
@@ -76,6 +78,10 @@ impl NexusRebuildJob {
     /// from start to end (of the data partition); notify_fn callback is called
     /// when the rebuild state is updated - with the nexus and destination
     /// URI as arguments.
+    /// The segments are read from all the given sources, the first one being
+    /// the primary source of the job.
+    /// For a striped nexus, `stripe` gives the stripe geometry and the stripe
+    /// set of the children, which is needed to lock the nexus ranges.
     /// todo: Should we use a builder? Example:
     /// NexusRebuild:
     /// Builder::new(src, srd).with_range().with_options().with_nexus().build()
@@ -83,19 +89,27 @@ impl NexusRebuildJob {
     /// Builder::new(src, srd).with_range().with_options().build()
     pub async fn new_starter(
         nexus_name: &str,
-        src_uri: &str,
+        src_uris: &[String],
         dst_uri: &str,
         range: Range<u64>,
+        stripe: Option<(StripeGeometry, usize)>,
         options: RebuildJobOptions,
         notify_fn: fn(String, String) -> (),
     ) -> Result<NexusRebuildJobStarter, RebuildError> {
-        let descriptor =
+        let Some((src_uri, extra_src_uris)) = src_uris.split_first() else {
+            return Err(RebuildError::NoSourceAvailable {});
+        };
+
+        let mut descriptor =
             RebuildDescriptor::new(src_uri, dst_uri, Some(range), options)
                 .await?;
+        for uri in extra_src_uris {
+            descriptor.add_source(uri).await?;
+        }
         let tasks = RebuildTasks::new(SEGMENT_TASKS, &descriptor)?;
 
         let backend = NexusRebuildJobBackendStarter::new(
-            nexus_name, tasks, notify_fn, descriptor,
+            nexus_name, stripe, tasks, notify_fn, descriptor,
         )
         .await?;
 
@@ -118,6 +132,15 @@ impl NexusRebuildJobStarter {
         }
         Ok(self)
     }
+    /// Specify a callback which persists the checkpoints of the rebuild
+    /// progress. Checkpoints are taken as often as the job options tell.
+    pub fn with_checkpoint_fn(
+        mut self,
+        checkpoint_fn: fn(String, RebuildCheckpoint) -> (),
+    ) -> Self {
+        self.backend.checkpoint_fn = Some(checkpoint_fn);
+        self
+    }
     /// Schedules the job to start in a future and returns a complete channel
     /// which can be waited on.
     pub async fn start(
@@ -141,6 +164,28 @@ impl NexusRebuildJobStarter {
         }
         job.start().await
     }
+    /// Schedules the job to start without storing it in the rebuild job list,
+    /// for the copies a nexus makes on its own behalf rather than to rebuild
+    /// a child. Returns the job along with its complete channel.
+    pub async fn start_detached(
+        mut self,
+        map: Option<RebuildMap>,
+    ) -> Result<
+        (
+            std::sync::Arc<NexusRebuildJob>,
+            oneshot::Receiver<RebuildState>,
+        ),
+        RebuildError,
+    > {
+        let Some(job) = self.job.take() else {
+            return Err(RebuildError::JobAlreadyExists {
+                job: self.backend.descriptor.dst_uri.clone(),
+            });
+        };
+        let job = std::sync::Arc::new(job);
+        let chan = self.start(job.clone(), map).await?;
+        Ok((job, chan))
+    }
 }
 
 gen_rebuild_instances!(NexusRebuildJob);
@@ -152,6 +197,8 @@ pub(super) struct NexusRebuildDescriptor {
     pub nexus_name: String,
     /// Nexus Descriptor so we can lock its ranges when rebuilding a segment.
     pub(super) nexus: DescriptorGuard<()>,
+    /// Stripe geometry and stripe set of the children, for a striped nexus.
+    stripe: Option<(StripeGeometry, usize)>,
     /// The generic rebuild descriptor for copying from source to target.
     pub(super) common: RebuildDescriptor,
 }
@@ -179,6 +226,8 @@ pub(super) struct NexusRebuildJobBackend<
     /// Notification callback which existing nexus uses to sync
     /// with rebuild updates.
     notify_fn: fn(String, String) -> (),
+    /// Callback which existing nexus uses to persist rebuild checkpoints.
+    checkpoint_fn: Option<fn(String, RebuildCheckpoint) -> ()>,
     /// The name of the nexus this pertains to.
     nexus_name: String,
     _p: std::marker::PhantomData<T>,
@@ -193,6 +242,8 @@ struct NexusRebuildJobBackendStarter {
     /// Notification callback which existing nexus uses to sync
     /// with rebuild updates.
     notify_fn: fn(String, String) -> (),
+    /// Callback which existing nexus uses to persist rebuild checkpoints.
+    checkpoint_fn: Option<fn(String, RebuildCheckpoint) -> ()>,
 }
 impl NexusRebuildJobBackendStarter {
     /// Creates a new RebuildJob which rebuilds from source URI to target URI
@@ -201,6 +252,7 @@ impl NexusRebuildJobBackendStarter {
     /// URI as arguments.
     pub async fn new(
         nexus_name: &str,
+        stripe: Option<(StripeGeometry, usize)>,
         task_pool: RebuildTasks,
         notify_fn: fn(String, String) -> (),
         descriptor: RebuildDescriptor,
@@ -212,6 +264,7 @@ impl NexusRebuildJobBackendStarter {
 
         let descriptor = NexusRebuildDescriptor {
             nexus: nexus_descriptor,
+            stripe,
             nexus_name: nexus_name.to_string(),
             common: descriptor,
         };
@@ -219,6 +272,7 @@ impl NexusRebuildJobBackendStarter {
             descriptor,
             task_pool,
             notify_fn,
+            checkpoint_fn: None,
         })
     }
 
@@ -232,6 +286,7 @@ impl NexusRebuildJobBackendStarter {
         NexusRebuildJobBackend {
             task_pool: self.task_pool,
             notify_fn: self.notify_fn,
+            checkpoint_fn: self.checkpoint_fn,
             nexus_name: self.descriptor.nexus_name.clone(),
             copier: PartialSeqRebuild::new(map, self.descriptor),
             _p: Default::default(),
@@ -246,6 +301,7 @@ impl NexusRebuildJobBackendStarter {
         NexusRebuildJobBackend {
             task_pool: self.task_pool,
             notify_fn: self.notify_fn,
+            checkpoint_fn: self.checkpoint_fn,
             nexus_name: self.descriptor.nexus_name.clone(),
             copier: FullRebuild::new(self.descriptor),
             _p: Default::default(),
@@ -264,6 +320,26 @@ impl<T: RebuildTaskCopier + 'static, R: RangeRebuilder<T>> RebuildBackend
         );
     }
 
+    fn checkpoint(&self) -> Option<RebuildCheckpoint> {
+        let desc = self.common_desc();
+        let remaining = self
+            .copier
+            .remaining_ranges(self.task_pool.lowest_in_flight())?;
+
+        Some(RebuildCheckpoint {
+            src_uri: desc.src_uri.clone(),
+            dst_uri: desc.dst_uri.clone(),
+            dst_instance: desc.dst_descriptor.get_device().instance_id(),
+            range: desc.range.clone(),
+            remaining,
+        })
+    }
+    fn on_checkpoint(&mut self, checkpoint: RebuildCheckpoint) {
+        if let Some(checkpoint_fn) = self.checkpoint_fn {
+            checkpoint_fn(self.nexus_name.clone(), checkpoint);
+        }
+    }
+
     fn common_desc(&self) -> &RebuildDescriptor {
         self.copier.desc()
     }
@@ -346,7 +422,17 @@ impl RebuildTaskCopier for NexusRebuildDescriptor {
         // nexus has a data partition only. Because we are locking the range on
         // the nexus, we need to calculate the offset from the start of the data
         // partition.
-        let r = LbaRange::new(blk - self.range.start, len);
+        // With a striped layout, the segment is a part of the children's
+        // stripe set, and the nexus range covering it spans the stripes of
+        // other sets as well.
+        let r = match self.stripe {
+            None => LbaRange::new(blk - self.range.start, len),
+            Some((geom, set)) => {
+                let (offset, num) =
+                    geom.nexus_range(set, blk - self.range.start, len);
+                LbaRange::new(offset, num)
+            }
+        };
 
         // Wait for LBA range to be locked.
         // This prevents other I/Os being issued to this LBA range whilst it is
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+use super::{RebuildMap, SEGMENT_SIZE};
+use crate::core::SegmentMap;
+
+/// Progress checkpoint of a rebuild job.
+/// It records the blocks of the rebuild range which are yet to be transferred,
+/// allowing an interrupted rebuild to be resumed instead of starting over.
+/// For a full rebuild this is everything past the high-water mark, and for
+/// a partial rebuild the dirty segments left in the rebuild map.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RebuildCheckpoint {
+    /// Source URI of the healthy child to rebuild from.
+    pub src_uri: String,
+    /// Target URI of the out of sync child being rebuilt.
+    pub dst_uri: String,
+    /// Instance of the target device, which tells it from a device recreated
+    /// with the same UUID since.
+    #[serde(default)]
+    pub dst_instance: Option<u64>,
+    /// The range of the entire rebuild.
+    pub range: Range<u64>,
+    /// Ranges of blocks which are yet to be rebuilt.
+    pub remaining: Vec<Range<u64>>,
+}
+
+impl RebuildCheckpoint {
+    /// Counts the total number of blocks which are yet to be rebuilt.
+    pub fn blocks_remaining(&self) -> u64 {
+        self.remaining.iter().map(|r| r.end - r.start).sum()
+    }
+
+    /// Creates a rebuild map of the given device, with the segments that are
+    /// yet to be rebuilt marked as dirty.
+    pub(crate) fn to_map(
+        &self,
+        device_name: &str,
+        num_blocks: u64,
+        block_len: u64,
+    ) -> RebuildMap {
+        let mut segments = SegmentMap::new(num_blocks, block_len, SEGMENT_SIZE);
+
+        self.remaining
+            .iter()
+            .filter(|r| r.start < r.end && r.end <= num_blocks)
+            .for_each(|r| segments.set(r.start, r.end - r.start, true));
+
+        RebuildMap::new(device_name, segments)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RebuildCheckpoint, SEGMENT_SIZE};
+
+    #[test]
+    fn test_checkpoint_map() {
+        let block_len = 512;
+        let seg_blks = SEGMENT_SIZE / block_len;
+        let num_blocks = seg_blks * 10 + 7;
+
+        let checkpoint = RebuildCheckpoint {
+            src_uri: "bdev:///src".to_string(),
+            dst_uri: "bdev:///dst".to_string(),
+            dst_instance: Some(0x1234),
+            range: 0 .. num_blocks,
+            remaining: vec![
+                seg_blks .. seg_blks * 3,
+                seg_blks * 3 .. seg_blks * 4,
+                seg_blks * 8 .. num_blocks,
+            ],
+        };
+        assert_eq!(checkpoint.blocks_remaining(), seg_blks * 5 + 7);
+
+        // Adjacent ranges are coalesced and the last segment is clipped to
+        // the device size.
+        let map = checkpoint.to_map("dst", num_blocks, block_len);
+        assert_eq!(
+            map.dirty_ranges(),
+            vec![seg_blks .. seg_blks * 4, seg_blks * 8 .. num_blocks]
+        );
+
+        let json = serde_json::to_value(&checkpoint).unwrap();
+        let restored: RebuildCheckpoint = serde_json::from_value(json).unwrap();
+        assert_eq!(restored, checkpoint);
+    }
+}
@@ -6,6 +6,11 @@ use spdk_rs::{
     IoVec,
     NvmeStatus,
 };
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
 
 use crate::{
     bdev::device_open,
@@ -15,7 +20,9 @@ use crate::{
         BlockDeviceDescriptor,
         BlockDeviceHandle,
         CoreError,
+        DifContext,
         IoCompletionStatus,
+        IoType,
         ReadOptions,
         SegmentMap,
     },
@@ -26,7 +33,72 @@ use crate::{
     },
 };
 
-use super::{RebuildError, RebuildJobOptions, RebuildVerifyMode};
+use super::{
+    RebuildError,
+    RebuildJobOptions,
+    RebuildSourceStats,
+    RebuildVerifyMode,
+};
+
+/// A source device to rebuild from, along with its statistics.
+pub(super) struct RebuildSource {
+    /// URI of the source device.
+    pub(super) uri: String,
+    /// Name of the source block device.
+    device_name: String,
+    /// Pre-opened descriptor for the source block device, released once the
+    /// source has failed.
+    descriptor: RefCell<Option<Rc<dyn BlockDeviceDescriptor>>>,
+    /// Number of blocks read from the source.
+    blocks_read: AtomicU64,
+    /// Number of reads from the source which failed.
+    read_failures: AtomicU64,
+    /// Set once the source failed, after which it is no longer used.
+    failed: AtomicBool,
+}
+
+impl RebuildSource {
+    fn new(uri: &str, descriptor: Box<dyn BlockDeviceDescriptor>) -> Self {
+        Self {
+            uri: uri.to_string(),
+            device_name: descriptor.device_name(),
+            descriptor: RefCell::new(Some(Rc::from(descriptor))),
+            blocks_read: AtomicU64::new(0),
+            read_failures: AtomicU64::new(0),
+            failed: AtomicBool::new(false),
+        }
+    }
+
+    /// Checks if the source has been dropped after a failure.
+    fn is_failed(&self) -> bool {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    /// Returns the descriptor of the source, unless the source has failed.
+    fn descriptor(&self) -> Option<Rc<dyn BlockDeviceDescriptor>> {
+        self.descriptor.borrow().clone()
+    }
+
+    /// Drops the source: it is no longer read from, and its descriptor is
+    /// released as soon as the reads in progress complete.
+    fn fail(&self) {
+        self.failed.store(true, Ordering::Relaxed);
+        self.descriptor.borrow_mut().take();
+    }
+
+    /// Collects the statistics of the source.
+    fn stats(&self, block_size: u64, elapsed_ms: u64) -> RebuildSourceStats {
+        let blocks_read = self.blocks_read.load(Ordering::Relaxed);
+
+        RebuildSourceStats {
+            uri: self.uri.clone(),
+            blocks_read,
+            throughput: blocks_read * block_size * 1000 / elapsed_ms.max(1),
+            read_failures: self.read_failures.load(Ordering::Relaxed),
+            failed: self.is_failed(),
+        }
+    }
+}
 
 /// Contains all descriptors and their associated information which allows the
 /// tasks to copy/rebuild data from source to destination.
@@ -45,9 +117,9 @@ pub(super) struct RebuildDescriptor {
     pub(super) src_uri: String,
     /// Target URI of the out of sync child to rebuild.
     pub(super) dst_uri: String,
-    /// Pre-opened descriptor for the source block device.
-    #[allow(clippy::non_send_fields_in_send_ty)]
-    pub(super) src_descriptor: Box<dyn BlockDeviceDescriptor>,
+    /// Sources to read the segments from, the first one being the source
+    /// given at creation. Segments are spread across all the sources.
+    pub(super) sources: Vec<RebuildSource>,
     /// Pre-opened descriptor for destination block device.
     #[allow(clippy::non_send_fields_in_send_ty)]
     pub(super) dst_descriptor: Box<dyn BlockDeviceDescriptor>,
@@ -62,16 +134,7 @@ impl RebuildDescriptor {
         range: Option<std::ops::Range<u64>>,
         options: RebuildJobOptions,
     ) -> Result<Self, RebuildError> {
-        let src_descriptor = device_open(
-            &bdev_get_name(src_uri).context(BdevInvalidUri {
-                uri: src_uri.to_string(),
-            })?,
-            false,
-        )
-        .map_err(|e| RebuildError::BdevNotFound {
-            source: e,
-            bdev: src_uri.to_string(),
-        })?;
+        let src_descriptor = Self::open_source(src_uri)?;
 
         let dst_descriptor = device_open(
             &bdev_get_name(dst_uri).context(BdevInvalidUri {
@@ -122,12 +185,106 @@ impl RebuildDescriptor {
             options,
             block_size,
             segment_size_blks,
-            src_descriptor,
+            sources: vec![RebuildSource::new(src_uri, src_descriptor)],
             dst_descriptor,
             start_time: Utc::now(),
         })
     }
 
+    /// Opens the source block device with the given URI.
+    fn open_source(
+        src_uri: &str,
+    ) -> Result<Box<dyn BlockDeviceDescriptor>, RebuildError> {
+        device_open(
+            &bdev_get_name(src_uri).context(BdevInvalidUri {
+                uri: src_uri.to_string(),
+            })?,
+            false,
+        )
+        .map_err(|e| RebuildError::BdevNotFound {
+            source: e,
+            bdev: src_uri.to_string(),
+        })
+    }
+
+    /// Adds another source to read the segments from.
+    pub(super) async fn add_source(
+        &mut self,
+        src_uri: &str,
+    ) -> Result<(), RebuildError> {
+        let descriptor = Self::open_source(src_uri)?;
+
+        let name = descriptor.device_name();
+        if name == self.dst_descriptor.device_name()
+            || self.sources.iter().any(|s| s.device_name == name)
+        {
+            return Err(RebuildError::SameBdev {
+                bdev: name,
+            });
+        }
+
+        let hdl = Self::io_handle(&*descriptor).await?;
+        if !Self::validate(
+            hdl.get_device(),
+            &*self.dst_descriptor.get_device(),
+            &self.range,
+        ) {
+            return Err(RebuildError::InvalidSrcDstRange {});
+        }
+
+        self.sources.push(RebuildSource::new(src_uri, descriptor));
+        Ok(())
+    }
+
+    /// Returns the URIs of all the sources.
+    pub(super) fn src_uris(&self) -> Vec<String> {
+        self.sources.iter().map(|s| s.uri.clone()).collect()
+    }
+
+    /// Marks the source with the given URI as failed, so that the segments
+    /// are read from the remaining sources. Returns false if no healthy
+    /// source remains, in which case the rebuild can't go on.
+    pub(super) fn fail_source(&self, src_uri: &str) -> bool {
+        if let Some(src) = self
+            .sources
+            .iter()
+            .find(|s| s.uri == src_uri && !s.is_failed())
+        {
+            warn!(
+                "Rebuild job '{src_uri}' -> '{dst}': dropping source",
+                dst = self.dst_uri,
+            );
+            src.fail();
+        }
+
+        self.sources.iter().any(|s| !s.is_failed())
+    }
+
+    /// Returns the sources to read the segment at the given offset from, in
+    /// order of preference. Segments are assigned to the sources round-robin,
+    /// and the sources which failed are skipped.
+    fn segment_sources(
+        &self,
+        offset_blk: u64,
+    ) -> impl Iterator<Item = &RebuildSource> {
+        let num = self.sources.len();
+        let first = ((offset_blk / self.segment_size_blks) as usize) % num;
+
+        (0 .. num)
+            .map(move |i| &self.sources[(first + i) % num])
+            .filter(|s| !s.is_failed())
+    }
+
+    /// Collects the statistics of all the sources.
+    pub(super) fn source_stats(&self) -> Vec<RebuildSourceStats> {
+        let elapsed_ms = (Utc::now() - self.start_time).num_milliseconds();
+
+        self.sources
+            .iter()
+            .map(|s| s.stats(self.block_size, elapsed_ms.max(0) as u64))
+            .collect()
+    }
+
     /// Check if the source and destination block devices are compatible for
     /// rebuild.
     fn validate(
@@ -166,19 +323,17 @@ impl RebuildDescriptor {
     /// Allocate memory from the memory pool (the mem is zeroed out)
     /// with given size and proper alignment for the bdev.
     pub(super) fn dma_malloc(&self, size: u64) -> Result<DmaBuf, RebuildError> {
-        let src_align = self.src_descriptor.get_device().alignment();
+        let src_align = self
+            .sources
+            .iter()
+            .filter_map(|s| s.descriptor())
+            .map(|d| d.get_device().alignment())
+            .max()
+            .unwrap_or_default();
         let dst_align = self.dst_descriptor.get_device().alignment();
         DmaBuf::new(size, src_align.max(dst_align)).context(NoCopyBuffer)
     }
 
-    /// Get a `BlockDeviceHandle` for the source.
-    #[inline(always)]
-    pub(super) async fn src_io_handle(
-        &self,
-    ) -> Result<Box<dyn BlockDeviceHandle>, RebuildError> {
-        Self::io_handle(&*self.src_descriptor).await
-    }
-
     /// Get a `BlockDeviceHandle` for the destination.
     #[inline(always)]
     pub(super) async fn dst_io_handle(
@@ -220,28 +375,79 @@ impl RebuildDescriptor {
         iov
     }
 
+    /// Checks if the segment at the given offset holds any data on the source
+    /// device. Devices which don't track block allocation are considered
+    /// fully allocated.
+    pub(super) fn is_src_segment_allocated(&self, offset_blk: u64) -> bool {
+        let end = offset_blk + self.get_segment_size_blks(offset_blk);
+
+        self.segment_sources(offset_blk)
+            .find_map(|s| s.descriptor())
+            .and_then(|d| d.get_device().allocated_ranges(offset_blk .. end))
+            .map_or(true, |ranges| !ranges.is_empty())
+    }
+
     /// Reads a rebuild segment at the given offset from the source replica.
     /// In the case the segment is not allocated on the source, returns false,
     /// and true otherwise.
+    /// With several sources, a source which fails a read is dropped, and the
+    /// segment is read from the next one instead.
     pub(super) async fn read_src_segment(
         &self,
         offset_blk: u64,
         iovs: &mut [IoVec],
         opts: ReadOptions,
     ) -> Result<bool, RebuildError> {
-        match self
-            .src_io_handle()
+        let mut result = Err(RebuildError::NoSourceAvailable {});
+
+        for src in self.segment_sources(offset_blk) {
+            result = self
+                .read_src_segment_from(src, offset_blk, iovs, opts)
+                .await;
+
+            let Err(err) = &result else {
+                break;
+            };
+
+            src.read_failures.fetch_add(1, Ordering::Relaxed);
+            if self.sources.len() > 1 {
+                warn!(
+                    "Rebuild job '{src_uri}' -> '{dst}': dropping source \
+                    after a failed read at segment {offset_blk}: {err}",
+                    src_uri = src.uri,
+                    dst = self.dst_uri,
+                );
+                src.fail();
+            }
+        }
+
+        result
+    }
+
+    /// Reads a rebuild segment at the given offset from the given source.
+    async fn read_src_segment_from(
+        &self,
+        src: &RebuildSource,
+        offset_blk: u64,
+        iovs: &mut [IoVec],
+        opts: ReadOptions,
+    ) -> Result<bool, RebuildError> {
+        let num_blocks = self.get_segment_size_blks(offset_blk);
+
+        let Some(descriptor) = src.descriptor() else {
+            return Err(RebuildError::NoSourceAvailable {});
+        };
+
+        match Self::io_handle(&*descriptor)
             .await?
-            .readv_blocks_async(
-                iovs,
-                offset_blk,
-                self.get_segment_size_blks(offset_blk),
-                opts,
-            )
+            .readv_blocks_async(iovs, offset_blk, num_blocks, opts)
             .await
         {
             // Read is okay, data has to be copied to the destination.
-            Ok(_) => Ok(true),
+            Ok(_) => {
+                src.blocks_read.fetch_add(num_blocks, Ordering::Relaxed);
+                Ok(true)
+            }
 
             // Read from an unallocated block occured, no need to copy it.
             Err(CoreError::ReadFailed {
@@ -257,7 +463,7 @@ impl RebuildDescriptor {
             // Read error.
             Err(err) => Err(RebuildError::ReadIoFailed {
                 source: err,
-                bdev: self.src_uri.clone(),
+                bdev: src.uri.clone(),
             }),
         }
     }
@@ -282,6 +488,35 @@ impl RebuildDescriptor {
             })
     }
 
+    /// Zeroes out the segment at the given offset on the destination replica,
+    /// unless the destination doesn't support write zeroes.
+    /// Write zeroes doesn't allocate clusters on thin volumes, so the
+    /// destination doesn't grow.
+    pub(super) async fn zero_dst_segment(
+        &self,
+        offset_blk: u64,
+    ) -> Result<(), RebuildError> {
+        if !self
+            .dst_descriptor
+            .get_device()
+            .io_type_supported(IoType::WriteZeros)
+        {
+            return Ok(());
+        }
+
+        self.dst_io_handle()
+            .await?
+            .write_zeroes_async(
+                offset_blk,
+                self.get_segment_size_blks(offset_blk),
+            )
+            .await
+            .map_err(|err| RebuildError::WriteIoFailed {
+                source: err,
+                bdev: self.dst_uri.clone(),
+            })
+    }
+
     /// Verify segment copy operation by reading destination, and comparing with
     /// the source.
     pub(super) async fn verify_segment(
@@ -290,7 +525,14 @@ impl RebuildDescriptor {
         iovs: &mut [IoVec],
     ) -> Result<(), RebuildError> {
         // Read the source again.
-        self.src_io_handle()
+        let Some(descriptor) = self
+            .segment_sources(offset_blk)
+            .find_map(|s| s.descriptor())
+        else {
+            return Err(RebuildError::NoSourceAvailable {});
+        };
+
+        Self::io_handle(&*descriptor)
             .await?
             .readv_blocks_async(
                 iovs,
@@ -304,6 +546,28 @@ impl RebuildDescriptor {
                 bdev: self.dst_uri.clone(),
             })?;
 
+        // The blocks carry their protection information, which tells if the
+        // source itself returned sound data.
+        let pi = self.dst_descriptor.get_device().protection_info();
+        if pi.is_enabled() {
+            let res = DifContext::new(self.block_size, &pi, offset_blk)
+                .and_then(|ctx| {
+                    ctx.verify(iovs, self.get_segment_size_blks(offset_blk))
+                });
+            match res {
+                Ok(_) => {}
+                Err(CoreError::ProtectionCheckFailed {
+                    ..
+                }) => return self.verify_failure(offset_blk),
+                Err(err) => {
+                    return Err(RebuildError::VerifyIoFailed {
+                        source: err,
+                        bdev: self.dst_uri.clone(),
+                    })
+                }
+            }
+        }
+
         match self
             .dst_io_handle()
             .await?
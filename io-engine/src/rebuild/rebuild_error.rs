@@ -29,6 +29,8 @@ pub enum RebuildError {
     IoFailed { source: CoreError, bdev: String },
     #[snafu(display("Read IO failed for bdev {}", bdev))]
     ReadIoFailed { source: CoreError, bdev: String },
+    #[snafu(display("All the rebuild sources have failed"))]
+    NoSourceAvailable {},
     #[snafu(display("Write IO failed for bdev {}", bdev))]
     WriteIoFailed { source: CoreError, bdev: String },
     #[snafu(display("Verify IO failed for bdev {}", bdev))]
@@ -86,6 +88,10 @@ pub enum RebuildError {
     RebuildTasksChannel { active: usize },
     #[snafu(display("Snapshot Rebuild: {source}"))]
     SnapshotRebuild { source: SnapshotRebuildError },
+    #[snafu(display("Copy Job: {source}"))]
+    CopyJob { source: CopyJobError },
+    #[snafu(display("Replication: {source}"))]
+    Replication { source: ReplicationError },
 }
 
 /// Various snapshot rebuild errors.
@@ -110,3 +116,73 @@ impl From<SnapshotRebuildError> for RebuildError {
         }
     }
 }
+
+/// Various copy job errors.
+#[derive(Debug, Snafu, Clone)]
+#[snafu(visibility(pub(crate)), context(suffix(false)))]
+#[allow(missing_docs)]
+pub enum CopyJobError {
+    #[snafu(display(
+        "Source {uri} is not a child of a local nexus, its writes can't be \
+        tracked"
+    ))]
+    NotLive { uri: String },
+    #[snafu(display("Nexus {nexus} not found"))]
+    NexusNotFound { nexus: String },
+    #[snafu(display("Failed to track the writes of nexus {nexus}: {reason}"))]
+    CopyLog { nexus: String, reason: String },
+    #[snafu(display("Failed to pause nexus {nexus}: {reason}"))]
+    NexusPause { nexus: String, reason: String },
+    #[snafu(display(
+        "Failed to replace the source with the destination in nexus \
+        {nexus}: {reason}"
+    ))]
+    ChildSwap { nexus: String, reason: String },
+    #[snafu(display("{operation} is not allowed in the {phase} phase"))]
+    PhaseOp { operation: String, phase: String },
+}
+
+impl From<CopyJobError> for RebuildError {
+    fn from(source: CopyJobError) -> Self {
+        Self::CopyJob {
+            source,
+        }
+    }
+}
+
+/// Various asynchronous replication errors.
+#[derive(Debug, Snafu, Clone)]
+#[snafu(visibility(pub(crate)), context(suffix(false)))]
+#[allow(missing_docs)]
+pub enum ReplicationError {
+    #[snafu(display("Nexus {nexus} not found"))]
+    NexusNotFound { nexus: String },
+    #[snafu(display("Nexus {nexus} is already replicated to {dst_uri}"))]
+    AlreadyReplicated { nexus: String, dst_uri: String },
+    #[snafu(display("{uri} is a child of nexus {nexus}"))]
+    TargetIsChild { nexus: String, uri: String },
+    #[snafu(display("The recovery point objective must not be zero"))]
+    InvalidRpo {},
+    #[snafu(display("Failed to track the writes of nexus {nexus}: {reason}"))]
+    ReplicationLog { nexus: String, reason: String },
+    #[snafu(display("Nexus {nexus} has no healthy child to replicate from"))]
+    NoHealthySource { nexus: String },
+    #[snafu(display("Failed to pause nexus {nexus}: {reason}"))]
+    NexusPause { nexus: String, reason: String },
+    #[snafu(display("Failed to open the replication target {uri}: {reason}"))]
+    TargetOpen { uri: String, reason: String },
+    #[snafu(display(
+        "The replication target of nexus {nexus} is not at a consistent point"
+    ))]
+    Inconsistent { nexus: String },
+    #[snafu(display("{operation} is not allowed in the {state} state"))]
+    StateOp { operation: String, state: String },
+}
+
+impl From<ReplicationError> for RebuildError {
+    fn from(source: ReplicationError) -> Self {
+        Self::Replication {
+            source,
+        }
+    }
+}
@@ -71,12 +71,13 @@ macro_rules! gen_rebuild_instances {
                 }
             }
 
-            /// Lookup all rebuilds jobs with `src_uri` as its source uri.
+            /// Lookup all rebuilds jobs with `src_uri` as one of its source
+            /// uris.
             pub fn lookup_src(src_uri: &str) -> Vec<std::sync::Arc<Self>> {
                 Self::get_instances()
                     .iter_mut()
                     .filter_map(|j| {
-                        if j.1.src_uri() == src_uri {
+                        if j.1.src_uris().iter().any(|u| u == src_uri) {
                             Some(j.1.clone())
                         } else {
                             None
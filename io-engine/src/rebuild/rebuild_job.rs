@@ -37,6 +37,12 @@ pub enum RebuildVerifyMode {
 pub struct RebuildJobOptions {
     pub verify_mode: RebuildVerifyMode,
     pub read_opts: ReadOptions,
+    /// Don't copy segments which are not allocated on the source, zero them
+    /// out on the destination instead, so that a thin destination stays thin.
+    pub skip_unallocated: bool,
+    /// Interval between checkpoints of the rebuild progress. Checkpoints are
+    /// not taken if not set.
+    pub checkpoint_interval: Option<std::time::Duration>,
 }
 impl RebuildJobOptions {
     /// Use the given `ReadOptions`.
@@ -44,6 +50,11 @@ impl RebuildJobOptions {
         self.read_opts = read_opts;
         self
     }
+    /// Skip segments which are not allocated on the source.
+    pub fn with_skip_unallocated(mut self, skip_unallocated: bool) -> Self {
+        self.skip_unallocated = skip_unallocated;
+        self
+    }
 }
 
 /// Operations used to control the state of the job.
@@ -81,6 +92,8 @@ impl std::fmt::Display for RebuildOperation {
 pub struct RebuildJob {
     /// Source URI of the healthy child to rebuild from.
     src_uri: String,
+    /// URIs of all the sources the job reads from, including `src_uri`.
+    src_uris: Vec<String>,
     /// Target URI of the out of sync child in need of a rebuild.
     pub(crate) dst_uri: String,
     /// Frontend to backend channel.
@@ -101,10 +114,12 @@ impl RebuildJob {
     ) -> Result<Self, RebuildError> {
         let desc = backend.common_desc();
         let src_uri = desc.src_uri.to_string();
+        let src_uris = desc.src_uris();
         let dst_uri = desc.dst_uri.to_string();
         let manager = RebuildJobBackendManager::new(backend);
         let frontend = Self {
             src_uri,
+            src_uris,
             dst_uri,
             states: manager.states.clone(),
             comms: RebuildFBendChan::from(&manager.info_chan),
@@ -127,6 +142,7 @@ impl RebuildJob {
     ) -> Self {
         Self {
             src_uri: desc.src_uri.to_string(),
+            src_uris: desc.src_uris(),
             dst_uri: desc.dst_uri.to_string(),
             states: manager.states.clone(),
             comms: RebuildFBendChan::from(&manager.info_chan),
@@ -185,6 +201,18 @@ impl RebuildJob {
             .unwrap_or_else(|_| oneshot::channel().1)
     }
 
+    /// Stops reading from the source with the given URI, and keeps rebuilding
+    /// from the remaining sources. Returns false if no healthy source
+    /// remains, in which case the job has to be stopped.
+    pub async fn fail_source(&self, src_uri: &str) -> bool {
+        let (s, r) = oneshot::channel::<bool>();
+        let req = RebuildJobRequest::FailSource(src_uri.to_string(), s);
+        if self.comms.send(req).await.is_err() {
+            return false;
+        }
+        r.await.unwrap_or(false)
+    }
+
     /// Get the rebuild stats.
     pub async fn stats(&self) -> RebuildStats {
         let (s, r) = oneshot::channel::<RebuildStats>();
@@ -221,6 +249,7 @@ impl RebuildJob {
             final_stats,
             state: self.state(),
             end_time: Utc::now(),
+            error: self.error().map(|error| error.verbose()),
         })
     }
 
@@ -252,6 +281,11 @@ impl RebuildJob {
         &self.src_uri
     }
 
+    /// Get the uris of all the rebuild sources.
+    pub fn src_uris(&self) -> &[String] {
+        &self.src_uris
+    }
+
     /// Get the name of this rebuild job (ie the rebuild target).
     pub fn name(&self) -> &str {
         self.dst_uri()
@@ -4,12 +4,14 @@ use std::{
         atomic::{AtomicU64, Ordering},
         Arc,
     },
+    time::Instant,
 };
 
 use crossbeam::channel::{unbounded, Receiver, Sender};
 use futures::{channel::oneshot, FutureExt, StreamExt};
 
 use super::{
+    RebuildCheckpoint,
     RebuildDescriptor,
     RebuildError,
     RebuildState,
@@ -28,6 +30,9 @@ pub(super) enum RebuildJobRequest {
     WakeUp,
     /// Get the rebuild stats from the backend.
     GetStats(oneshot::Sender<RebuildStats>),
+    /// Stop reading from the source with the given URI, replying whether any
+    /// healthy source remains.
+    FailSource(String, oneshot::Sender<bool>),
 }
 
 /// Channel to share information between frontend and backend.
@@ -72,6 +77,13 @@ pub(super) trait RebuildBackend:
     /// Callback for rebuild state change notifications.
     fn on_state_change(&mut self);
 
+    /// Get a checkpoint of the rebuild progress, if the backend supports it.
+    fn checkpoint(&self) -> Option<RebuildCheckpoint> {
+        None
+    }
+    /// Callback for rebuild checkpoint notifications.
+    fn on_checkpoint(&mut self, _checkpoint: RebuildCheckpoint) {}
+
     /// Get a reference to the common rebuild descriptor.
     fn common_desc(&self) -> &RebuildDescriptor;
 
@@ -105,6 +117,8 @@ pub(super) struct RebuildJobManager {
     pub(super) info_chan: RebuildFBendChan,
     /// Job serial number.
     serial: u64,
+    /// Time of the last checkpoint of the rebuild progress.
+    checkpoint_time: Instant,
 }
 
 /// A rebuild job is responsible for managing a rebuild (copy) which reads
@@ -165,6 +179,7 @@ impl RebuildJobManager {
             complete_chan: Default::default(),
             info_chan: RebuildFBendChan::new(),
             serial,
+            checkpoint_time: Instant::now(),
         }
     }
     pub fn into_backend(
@@ -333,6 +348,7 @@ impl RebuildJobBackendManager {
             tasks_total: self.task_pool().total as u64,
             tasks_active: self.task_pool().active as u64,
             end_time: None,
+            sources: descriptor.source_stats(),
         }
     }
 
@@ -437,11 +453,14 @@ impl RebuildJobBackendManager {
                         match state.pending {
                             None | Some(RebuildState::Running) => {
                                 self.start_task_by_id(r.id);
+                                self.checkpoint();
                             }
                             _ => {
                                 // await all active tasks as we might still have
                                 // ongoing IO. do we need a timeout?
                                 self.await_all_tasks().await;
+                                // the rebuild may be resumed from here.
+                                self.checkpoint_now();
                                 break;
                             }
                         }
@@ -467,6 +486,44 @@ impl RebuildJobBackendManager {
         }
     }
 
+    /// Takes a checkpoint of the rebuild progress, if the checkpoint interval
+    /// has elapsed since the last one.
+    fn checkpoint(&mut self) {
+        let Some(interval) =
+            self.backend.common_desc().options.checkpoint_interval
+        else {
+            return;
+        };
+
+        if self.checkpoint_time.elapsed() < interval {
+            return;
+        }
+        self.checkpoint_now();
+    }
+
+    /// Takes a checkpoint of the rebuild progress, regardless of the
+    /// checkpoint interval.
+    fn checkpoint_now(&mut self) {
+        if self
+            .backend
+            .common_desc()
+            .options
+            .checkpoint_interval
+            .is_none()
+        {
+            return;
+        }
+        self.checkpoint_time = Instant::now();
+
+        if let Some(checkpoint) = self.backend.checkpoint() {
+            trace!(
+                "{self}: checkpoint with {blks} blocks remaining",
+                blks = checkpoint.blocks_remaining()
+            );
+            self.backend.on_checkpoint(checkpoint);
+        }
+    }
+
     /// Handles a request messages replying to it if necessary.
     /// Returns false if the message was empty (ie the frontend is gone)
     async fn handle_message(
@@ -478,6 +535,10 @@ impl RebuildJobBackendManager {
             Some(RebuildJobRequest::GetStats(reply)) => {
                 self.reply_stats(reply).await.ok();
             }
+            Some(RebuildJobRequest::FailSource(src_uri, reply)) => {
+                let healthy = self.backend.common_desc().fail_source(&src_uri);
+                reply.send(healthy).ok();
+            }
             None => {
                 self.fail_with(RebuildError::FrontendGone);
                 return false;
@@ -1,5 +1,8 @@
 use bit_vec::BitVec;
-use std::fmt::{Debug, Formatter};
+use std::{
+    fmt::{Debug, Formatter},
+    ops::Range,
+};
 
 use crate::core::SegmentMap;
 
@@ -62,6 +65,20 @@ impl RebuildMap {
     pub(crate) fn count_dirty_blks(&self) -> u64 {
         self.segments.count_dirty_blks()
     }
+
+    /// Returns the ranges of dirty (to be transferred) blocks.
+    pub(crate) fn dirty_ranges(&self) -> Vec<Range<u64>> {
+        self.segments.dirty_ranges()
+    }
+
+    /// Merges (bitwise OR) this map with another one, so that every segment
+    /// which is dirty in either map is dirty in the result.
+    pub(crate) fn merge(self, other: &RebuildMap) -> Self {
+        Self {
+            device_name: self.device_name,
+            segments: self.segments.merge(&other.segments),
+        }
+    }
 }
 
 impl From<RebuildMap> for BitVec {
@@ -69,3 +86,9 @@ impl From<RebuildMap> for BitVec {
         value.segments.into()
     }
 }
+
+impl From<RebuildMap> for SegmentMap {
+    fn from(value: RebuildMap) -> Self {
+        value.segments
+    }
+}
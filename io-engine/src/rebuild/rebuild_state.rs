@@ -1,9 +1,10 @@
 use super::{RebuildError, RebuildOperation};
 use crate::rebuild::RebuildStats;
 use chrono::Utc;
+use serde::{Deserialize, Serialize};
 
 /// Allowed states for a rebuild job.
-#[derive(Default, Debug, PartialEq, Copy, Clone)]
+#[derive(Default, Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum RebuildState {
     /// Init when the job is newly created
     #[default]
@@ -1,9 +1,10 @@
 use super::RebuildState;
 use chrono::{DateTime, Utc};
-use std::ops::Deref;
+use serde::{Deserialize, Serialize};
+use std::{ops::Deref, time::Duration};
 
 /// Rebuild statistics.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RebuildStats {
     /// Total number of blocks to recover.
     pub blocks_total: u64,
@@ -29,6 +30,23 @@ pub struct RebuildStats {
     pub is_partial: bool,
     /// End time of this rebuild.
     pub end_time: Option<DateTime<Utc>>,
+    /// Statistics of each source of this rebuild.
+    pub sources: Vec<RebuildSourceStats>,
+}
+
+/// Statistics of a rebuild source.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RebuildSourceStats {
+    /// URI of the source.
+    pub uri: String,
+    /// Number of blocks read from the source.
+    pub blocks_read: u64,
+    /// Average read throughput from the source, in bytes per second.
+    pub throughput: u64,
+    /// Number of reads from the source which failed.
+    pub read_failures: u64,
+    /// Whether the source was dropped after a read failure.
+    pub failed: bool,
 }
 
 impl Default for RebuildStats {
@@ -46,13 +64,14 @@ impl Default for RebuildStats {
             start_time: Utc::now(),
             is_partial: false,
             end_time: None,
+            sources: Vec::new(),
         }
     }
 }
 
 /// A rebuild record is a lightweight extract of rebuild job that is maintained
 /// for the statistics.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct HistoryRecord {
     /// Target URI of the out of sync child in need of a rebuild.
@@ -65,6 +84,9 @@ pub struct HistoryRecord {
     pub state: RebuildState,
     /// End time of this rebuild.
     pub end_time: DateTime<Utc>,
+    /// Reason of the failure, if the rebuild failed.
+    #[serde(default)]
+    pub error: Option<String>,
 }
 
 impl Deref for HistoryRecord {
@@ -74,3 +96,98 @@ impl Deref for HistoryRecord {
         &self.final_stats
     }
 }
+
+/// Retention policy of the rebuild history of a nexus.
+/// Records older than the maximum age are dropped first, then the oldest
+/// records until no more than the maximum number of records are left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryRetention {
+    /// Maximum number of records to keep.
+    pub max_records: usize,
+    /// Maximum age of the records to keep, if any.
+    pub max_age: Option<Duration>,
+}
+
+impl Default for HistoryRetention {
+    fn default() -> Self {
+        Self {
+            max_records: Self::DEFAULT_MAX_RECORDS,
+            max_age: None,
+        }
+    }
+}
+
+impl HistoryRetention {
+    /// Default maximum number of records kept per nexus.
+    pub const DEFAULT_MAX_RECORDS: usize = 100;
+
+    /// Applies the retention policy to the given records, which are expected
+    /// to be ordered from the oldest to the newest.
+    pub(crate) fn apply(&self, records: &mut Vec<HistoryRecord>) {
+        self.apply_at(records, Utc::now());
+    }
+
+    fn apply_at(&self, records: &mut Vec<HistoryRecord>, now: DateTime<Utc>) {
+        if let Some(max_age) = self
+            .max_age
+            .and_then(|age| chrono::Duration::from_std(age).ok())
+        {
+            records.retain(|r| now - r.end_time <= max_age);
+        }
+        if records.len() > self.max_records {
+            records.drain(.. records.len() - self.max_records);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HistoryRecord, HistoryRetention, RebuildState, RebuildStats};
+    use chrono::{Duration, Utc};
+
+    fn record(child: &str, age_secs: i64) -> HistoryRecord {
+        HistoryRecord {
+            child_uri: child.to_string(),
+            src_uri: "bdev:///src".to_string(),
+            final_stats: RebuildStats::default(),
+            state: RebuildState::Completed,
+            end_time: Utc::now() - Duration::seconds(age_secs),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_history_retention() {
+        let now = Utc::now();
+        let mut records = vec![
+            record("c0", 300),
+            record("c1", 200),
+            record("c2", 100),
+            record("c3", 0),
+        ];
+
+        let retention = HistoryRetention {
+            max_records: 3,
+            max_age: None,
+        };
+        retention.apply_at(&mut records, now);
+        let children: Vec<_> =
+            records.iter().map(|r| r.child_uri.as_str()).collect();
+        assert_eq!(children, vec!["c1", "c2", "c3"]);
+
+        let retention = HistoryRetention {
+            max_records: 3,
+            max_age: Some(std::time::Duration::from_secs(150)),
+        };
+        retention.apply_at(&mut records, now);
+        let children: Vec<_> =
+            records.iter().map(|r| r.child_uri.as_str()).collect();
+        assert_eq!(children, vec!["c2", "c3"]);
+
+        let json = serde_json::to_value(&records).unwrap();
+        let restored: Vec<HistoryRecord> =
+            serde_json::from_value(json).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored[1].child_uri, "c3");
+    }
+}
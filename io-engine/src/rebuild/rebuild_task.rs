@@ -58,6 +58,14 @@ impl RebuildTask {
         offset_blk: u64,
         desc: &RebuildDescriptor,
     ) -> Result<bool, RebuildError> {
+        if desc.options.skip_unallocated
+            && !desc.is_src_segment_allocated(offset_blk)
+        {
+            // Segment holds no data in the source, no need to read it.
+            desc.zero_dst_segment(offset_blk).await?;
+            return Ok(false);
+        }
+
         let iov = desc.adjusted_iov(&self.buffer, offset_blk);
         let iovs = &mut [iov];
 
@@ -66,6 +74,9 @@ impl RebuildTask {
             .await?
         {
             // Segment is not allocated in the source, skip the write.
+            if desc.options.skip_unallocated {
+                desc.zero_dst_segment(offset_blk).await?;
+            }
             return Ok(false);
         }
         desc.write_dst_segment(offset_blk, iovs).await?;
@@ -98,6 +109,9 @@ pub(super) struct RebuildTasks {
     pub(super) segments_done: u64,
     /// How many segments have been actually transferred so far.
     pub(super) segments_transferred: u64,
+    /// Segment each task is working on, indexed by task id. A segment which
+    /// failed to be rebuilt is kept here, as it hasn't been rebuilt.
+    in_flight: Vec<Option<u64>>,
 }
 
 impl std::fmt::Debug for RebuildTasks {
@@ -135,9 +149,16 @@ impl RebuildTasks {
             active: 0,
             segments_done: 0,
             segments_transferred: 0,
+            in_flight: vec![None; task_count],
         })
     }
 
+    /// Returns the lowest block of the segments which are being rebuilt, or
+    /// which failed to be rebuilt.
+    pub(super) fn lowest_in_flight(&self) -> Option<u64> {
+        self.in_flight.iter().flatten().min().cloned()
+    }
+
     /// Check if there's at least one task still running.
     pub(super) fn running(&self) -> bool {
         self.active > 0 && !self.channel.1.is_terminated()
@@ -147,6 +168,7 @@ impl RebuildTasks {
         self.channel.1.next().await.map(|f| {
             self.active -= 1;
             if f.error.is_none() {
+                self.in_flight[f.id] = None;
                 self.segments_done += 1;
                 if f.is_transferred {
                     self.segments_transferred += 1;
@@ -165,6 +187,7 @@ impl RebuildTasks {
         copier: Rc<impl RebuildTaskCopier + 'static>,
     ) {
         let task = self.tasks[id].clone();
+        self.in_flight[id] = Some(blk);
 
         Reactors::current().send_future(async move {
             // No other thread/task will acquire the mutex at the same time.
@@ -29,6 +29,15 @@ pub(super) trait RangeRebuilder<T: RebuildTaskCopier> {
     fn desc(&self) -> &RebuildDescriptor;
     /// Get the copier which can copy a segment.
     fn copier(&self) -> Rc<T>;
+    /// Get the ranges of blocks which are yet to be rebuilt, given the lowest
+    /// block of the segments still in flight.
+    /// Returns None if the rebuilder cannot tell.
+    fn remaining_ranges(
+        &self,
+        _in_flight: Option<u64>,
+    ) -> Option<Vec<Range<u64>>> {
+        None
+    }
 }
 
 /// The range is the full range of the request, in steps of segment size.
@@ -72,6 +81,22 @@ impl<T: RebuildTaskCopier> RangeRebuilder<T> for FullRebuild<T> {
     fn copier(&self) -> Rc<T> {
         self.copier.clone()
     }
+    fn remaining_ranges(
+        &self,
+        in_flight: Option<u64>,
+    ) -> Option<Vec<Range<u64>>> {
+        // Segments are rebuilt in order, so everything below the lowest
+        // segment in flight, if any, or the next one, has been rebuilt.
+        let end = self.desc().range.end;
+        let remaining = in_flight
+            .into_iter()
+            .chain(self.peek_next())
+            .min()
+            .filter(|start| *start < end)
+            .map(|start| start .. end);
+
+        Some(remaining.into_iter().collect())
+    }
 }
 
 /// A partial rebuild range which steps through each segment but triggers
@@ -174,6 +199,25 @@ impl<T: RebuildTaskCopier> RangeRebuilder<PartialSeqCopier<T>>
     fn copier(&self) -> Rc<PartialSeqCopier<T>> {
         self.copier.clone()
     }
+    fn remaining_ranges(
+        &self,
+        _in_flight: Option<u64>,
+    ) -> Option<Vec<Range<u64>>> {
+        // Segments are marked as clean only once transferred, so the map
+        // itself tells what remains to be rebuilt.
+        let range = &self.desc().range;
+        let remaining = self
+            .copier
+            .map
+            .lock()
+            .dirty_ranges()
+            .into_iter()
+            .map(|r| r.start.max(range.start) .. r.end.min(range.end))
+            .filter(|r| r.start < r.end)
+            .collect();
+
+        Some(remaining)
+    }
 }
 /// The partial sequential rebuild copier, which uses a bitmap to determine if a
 /// particular block range must be copied.
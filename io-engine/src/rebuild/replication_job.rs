@@ -0,0 +1,867 @@
+use chrono::{DateTime, Utc};
+use futures::channel::oneshot;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use super::{
+    rebuild_error::ReplicationError,
+    BdevRebuildJob,
+    RebuildError,
+    RebuildJobOptions,
+    RebuildState,
+    RebuildVerifyMode,
+};
+use crate::{
+    bdev::{
+        dev::device_name,
+        device_create,
+        device_destroy,
+        nexus::{nexus_lookup, nexus_lookup_mut, Nexus},
+    },
+    bdev_api::BdevError,
+    core::{Reactors, SegmentMap},
+    sleep::mayastor_sleep,
+};
+
+/// Default recovery point objective of a replication.
+const DEFAULT_RPO: Duration = Duration::from_secs(60);
+
+/// Interval at which an idle replication checks for requests.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// State of an asynchronous replication.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ReplicationState {
+    /// The replication has been created but not started yet.
+    #[default]
+    Init,
+    /// The whole nexus is being copied to the target.
+    Syncing,
+    /// The writes made to the nexus are shipped to the target periodically.
+    Replicating,
+    /// The last writes are being shipped with the nexus I/O paused.
+    Promoting,
+    /// The target has been handed over and is no longer replicated to.
+    Promoted,
+    /// The replication has been stopped before being promoted.
+    Stopped,
+    /// The replication failed, see its error.
+    Failed,
+}
+
+impl std::fmt::Display for ReplicationState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            ReplicationState::Init => write!(f, "init"),
+            ReplicationState::Syncing => write!(f, "syncing"),
+            ReplicationState::Replicating => write!(f, "replicating"),
+            ReplicationState::Promoting => write!(f, "promoting"),
+            ReplicationState::Promoted => write!(f, "promoted"),
+            ReplicationState::Stopped => write!(f, "stopped"),
+            ReplicationState::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+impl ReplicationState {
+    /// Checks if the replication is over.
+    pub fn is_done(&self) -> bool {
+        matches!(self, Self::Promoted | Self::Stopped | Self::Failed)
+    }
+}
+
+/// Progress and lag of an asynchronous replication.
+#[derive(Debug, Clone)]
+pub struct ReplicationStats {
+    /// Current state of the replication.
+    pub state: ReplicationState,
+    /// Recovery point objective of the replication.
+    pub rpo: Duration,
+    /// Time of the last completed sync: all the writes acknowledged by the
+    /// nexus before this time are on the target.
+    pub last_sync_time: Option<DateTime<Utc>>,
+    /// Time at which the nexus data matches the target, if the target is
+    /// consistent, i.e. not written since its last consistent sync.
+    pub consistent_time: Option<DateTime<Utc>>,
+    /// Age of the oldest write which may be missing from the target.
+    pub lag: Duration,
+    /// Set when the lag exceeds the recovery point objective.
+    pub rpo_violated: bool,
+    /// Number of logged blocks which are yet to be shipped, not counting the
+    /// blocks written since the current sync started.
+    pub pending_blocks: u64,
+    /// Number of blocks shipped to the target so far.
+    pub blocks_shipped: u64,
+    /// Block size of the shipped blocks, once known.
+    pub block_size: u64,
+    /// Number of completed syncs.
+    pub syncs: u64,
+    /// Number of failed syncs, whose blocks are shipped again by the next one.
+    pub failed_syncs: u64,
+    /// Start time of the replication.
+    pub start_time: DateTime<Utc>,
+    /// End time of the replication, once done.
+    pub end_time: Option<DateTime<Utc>>,
+    /// Last error of the replication, if any.
+    pub error: Option<String>,
+}
+
+/// Request made to a running replication.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ReplicationRequest {
+    /// Hand over the target, shipping the last writes first unless forced.
+    Promote { force: bool },
+    /// Stop the replication, leaving the target behind as is.
+    Stop,
+}
+
+/// Mutable state of a replication.
+#[derive(Default)]
+struct ReplicationJobInner {
+    /// Current state of the replication.
+    state: ReplicationState,
+    /// Pending request, handled between two syncs.
+    request: Option<ReplicationRequest>,
+    /// Rebuild job shipping the data of the current sync.
+    job: Option<Arc<BdevRebuildJob>>,
+    /// Segments of a failed sync, to be shipped again by the next one.
+    pending: Option<SegmentMap>,
+    /// Time of the last completed sync.
+    last_sync_time: Option<DateTime<Utc>>,
+    /// Time of the nexus data held by the target, if consistent.
+    consistent_time: Option<DateTime<Utc>>,
+    /// Set while the lag exceeds the recovery point objective.
+    rpo_violated: bool,
+    /// Number of blocks shipped by the finished syncs.
+    blocks_shipped: u64,
+    /// Block size of the shipped blocks.
+    block_size: u64,
+    /// Number of completed syncs.
+    syncs: u64,
+    /// Number of failed syncs.
+    failed_syncs: u64,
+    /// Set if the target device was created by the replication.
+    created_target: bool,
+    /// Last error of the replication.
+    error: Option<RebuildError>,
+    /// End time of the replication.
+    end_time: Option<DateTime<Utc>>,
+    /// Waiters of the pending promotion.
+    promote_waiters: Vec<oneshot::Sender<Result<(), RebuildError>>>,
+    /// Waiters of the end of the replication.
+    done_waiters: Vec<oneshot::Sender<()>>,
+}
+
+/// An asynchronous replication ships the writes made to a local nexus to a
+/// remote target, typically a replica exported over NVMe-oF in another site.
+/// Unlike the nexus children, the target is not written synchronously: the
+/// writes are recorded in a log of written segments which is swapped and
+/// shipped periodically, every half of the recovery point objective, after a
+/// first full copy of the nexus.
+/// The log is swapped with the nexus I/O paused, but the blocks of a sync are
+/// read from a healthy child while the nexus stays live: a block written
+/// again during the sync may reach the target with its newer data. The target
+/// is thus only consistent, i.e. identical to the nexus data at the time of
+/// a swap, once a sync completes without any of its blocks being written
+/// meanwhile. Promoting the target ships the last writes with the nexus I/O
+/// paused, which leaves it identical to the nexus data.
+pub struct ReplicationJob {
+    /// Name of the replicated nexus.
+    nexus: String,
+    /// Uuid of the replicated nexus.
+    nexus_uuid: String,
+    /// Uri of the target device.
+    dst_uri: String,
+    /// Recovery point objective.
+    rpo: Duration,
+    /// Verify each shipped segment by reading it back.
+    verify: bool,
+    /// Start time of the replication.
+    start_time: DateTime<Utc>,
+    /// Mutable state of the replication.
+    inner: Mutex<ReplicationJobInner>,
+}
+
+impl std::fmt::Debug for ReplicationJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReplicationJob")
+            .field("nexus", &self.nexus)
+            .field("dst_uri", &self.dst_uri)
+            .field("rpo", &self.rpo)
+            .field("state", &self.state())
+            .finish()
+    }
+}
+
+/// Builder for the `ReplicationJob`.
+pub struct ReplicationJobBuilder {
+    rpo: Duration,
+    verify: bool,
+}
+
+impl Default for ReplicationJobBuilder {
+    fn default() -> Self {
+        Self {
+            rpo: DEFAULT_RPO,
+            verify: false,
+        }
+    }
+}
+
+impl ReplicationJobBuilder {
+    /// Specify the recovery point objective.
+    pub fn with_rpo(mut self, rpo: Duration) -> Self {
+        self.rpo = rpo;
+        self
+    }
+    /// Verify each shipped segment by reading it back from the target.
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+    /// Builds a `ReplicationJob` which can be started and which will then
+    /// replicate the given nexus to the target.
+    pub fn build(
+        self,
+        nexus: &str,
+        dst_uri: &str,
+    ) -> Result<ReplicationJob, RebuildError> {
+        if self.rpo.is_zero() {
+            return Err(ReplicationError::InvalidRpo {}.into());
+        }
+
+        let n = nexus_lookup(nexus).ok_or_else(|| {
+            ReplicationError::NexusNotFound {
+                nexus: nexus.to_string(),
+            }
+        })?;
+
+        let is_child = n.contains_child_uri(dst_uri)
+            || device_name(dst_uri)
+                .map(|name| n.contains_child_name(&name))
+                .unwrap_or(false);
+        if is_child {
+            return Err(ReplicationError::TargetIsChild {
+                nexus: nexus.to_string(),
+                uri: dst_uri.to_string(),
+            }
+            .into());
+        }
+
+        Ok(ReplicationJob {
+            nexus: nexus.to_string(),
+            nexus_uuid: n.uuid().to_string(),
+            dst_uri: dst_uri.to_string(),
+            rpo: self.rpo,
+            verify: self.verify,
+            start_time: Utc::now(),
+            inner: Mutex::new(ReplicationJobInner::default()),
+        })
+    }
+}
+
+/// List of replications indexed by the name of their nexus.
+type ReplicationJobInstances = HashMap<String, Arc<ReplicationJob>>;
+
+impl ReplicationJob {
+    /// Helps create a `Self` using a builder: `ReplicationJobBuilder`.
+    pub fn builder() -> ReplicationJobBuilder {
+        ReplicationJobBuilder::default()
+    }
+
+    /// Get the replication instances container, we ensure that this can only
+    /// ever be called on a properly allocated thread.
+    fn get_instances<'a>(
+    ) -> parking_lot::MutexGuard<'a, ReplicationJobInstances> {
+        assert!(
+            spdk_rs::Thread::is_spdk_thread(),
+            "not called from SPDK thread"
+        );
+
+        static REPLICATION_INSTANCES: once_cell::sync::OnceCell<
+            Mutex<ReplicationJobInstances>,
+        > = once_cell::sync::OnceCell::new();
+
+        REPLICATION_INSTANCES
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+    }
+
+    /// Stores a replication in the replication list.
+    /// Fails if the nexus is already replicated.
+    pub fn store(self) -> Result<Arc<Self>, RebuildError> {
+        let mut jobs = Self::get_instances();
+
+        if let Some(job) = jobs.get(&self.nexus) {
+            return Err(ReplicationError::AlreadyReplicated {
+                nexus: self.nexus.clone(),
+                dst_uri: job.dst_uri.clone(),
+            }
+            .into());
+        }
+
+        let job = Arc::new(self);
+        jobs.insert(job.nexus.clone(), job.clone());
+        Ok(job)
+    }
+
+    /// Lookup the replication of the given nexus and return it.
+    pub fn lookup(nexus: &str) -> Result<Arc<Self>, RebuildError> {
+        Self::get_instances().get(nexus).cloned().ok_or_else(|| {
+            RebuildError::JobNotFound {
+                job: nexus.to_string(),
+            }
+        })
+    }
+
+    /// Lookup the replication of the given nexus then remove it.
+    pub fn remove(nexus: &str) -> Result<Arc<Self>, RebuildError> {
+        Self::get_instances().remove(nexus).ok_or_else(|| {
+            RebuildError::JobNotFound {
+                job: nexus.to_string(),
+            }
+        })
+    }
+
+    /// Get a list of all replications.
+    pub fn list() -> Vec<Arc<Self>> {
+        Self::get_instances().values().cloned().collect()
+    }
+
+    /// Get the name of the replicated nexus.
+    pub fn nexus(&self) -> &str {
+        &self.nexus
+    }
+
+    /// Get the uuid of the replicated nexus.
+    pub fn nexus_uuid(&self) -> &str {
+        &self.nexus_uuid
+    }
+
+    /// Get the uri of the target.
+    pub fn dst_uri(&self) -> &str {
+        &self.dst_uri
+    }
+
+    /// Get the recovery point objective.
+    pub fn rpo(&self) -> Duration {
+        self.rpo
+    }
+
+    /// Checks if each shipped segment is verified.
+    pub fn verify(&self) -> bool {
+        self.verify
+    }
+
+    /// Get the current state of the replication.
+    pub fn state(&self) -> ReplicationState {
+        self.inner.lock().state
+    }
+
+    /// Starts the replication in the background.
+    pub fn start(self: &Arc<Self>) -> Result<(), RebuildError> {
+        {
+            let mut inner = self.inner.lock();
+            if inner.state != ReplicationState::Init {
+                return Err(ReplicationError::StateOp {
+                    operation: "Start".to_string(),
+                    state: inner.state.to_string(),
+                }
+                .into());
+            }
+            inner.state = ReplicationState::Syncing;
+        }
+
+        let job = self.clone();
+        Reactors::master().send_future(async move { job.run().await });
+        Ok(())
+    }
+
+    /// Promotes the target once the current sync is over. Unless forced, the
+    /// writes made since the last sync are shipped with the nexus I/O paused
+    /// first. A forced promotion hands over the target as of its last
+    /// consistent sync, e.g. when the nexus can't be paused anymore, and is
+    /// refused if the target has been written since.
+    pub async fn promote(&self, force: bool) -> Result<(), RebuildError> {
+        let recv = {
+            let mut inner = self.inner.lock();
+            let state = inner.state;
+            if state != ReplicationState::Replicating
+                || inner.request == Some(ReplicationRequest::Stop)
+            {
+                return Err(ReplicationError::StateOp {
+                    operation: "Promote".to_string(),
+                    state: state.to_string(),
+                }
+                .into());
+            }
+
+            if inner.request.is_none() {
+                inner.request = Some(ReplicationRequest::Promote {
+                    force,
+                });
+            }
+            let (sender, recv) = oneshot::channel();
+            inner.promote_waiters.push(sender);
+            recv
+        };
+
+        recv.await.unwrap_or(Err(RebuildError::BackendGone))
+    }
+
+    /// Stops the replication and waits for it to be over. The target is left
+    /// behind as is, and may not be consistent.
+    pub async fn stop(&self) {
+        let (job, recv) = {
+            let mut inner = self.inner.lock();
+            if inner.state == ReplicationState::Init {
+                inner.state = ReplicationState::Stopped;
+                inner.end_time = Some(Utc::now());
+                return;
+            }
+            if inner.state.is_done() {
+                return;
+            }
+
+            inner.request = Some(ReplicationRequest::Stop);
+            let (sender, recv) = oneshot::channel();
+            inner.done_waiters.push(sender);
+            (inner.job.clone(), recv)
+        };
+
+        if let Some(job) = job.filter(|j| j.state() != RebuildState::Init) {
+            job.force_stop().await.ok();
+        }
+        recv.await.ok();
+    }
+
+    /// Get the progress and the lag of the replication.
+    pub async fn stats(&self) -> ReplicationStats {
+        let (job, mut stats) = {
+            let inner = self.inner.lock();
+            let since = inner.last_sync_time.unwrap_or(self.start_time);
+            let now = inner.end_time.unwrap_or_else(Utc::now);
+            let lag = (now - since).to_std().unwrap_or_default();
+            let stats = ReplicationStats {
+                state: inner.state,
+                rpo: self.rpo,
+                last_sync_time: inner.last_sync_time,
+                consistent_time: inner.consistent_time,
+                lag,
+                rpo_violated: !inner.state.is_done() && lag > self.rpo,
+                pending_blocks: inner
+                    .pending
+                    .as_ref()
+                    .map_or(0, |map| map.count_dirty_blks()),
+                blocks_shipped: inner.blocks_shipped,
+                block_size: inner.block_size,
+                syncs: inner.syncs,
+                failed_syncs: inner.failed_syncs,
+                start_time: self.start_time,
+                end_time: inner.end_time,
+                error: inner.error.as_ref().map(ToString::to_string),
+            };
+            (inner.job.clone(), stats)
+        };
+
+        if let Some(job) = job {
+            let job_stats = job.stats().await;
+            stats.pending_blocks += job_stats.blocks_remaining;
+            stats.blocks_shipped += job_stats.blocks_transferred;
+            stats.block_size = job_stats.block_size;
+        }
+
+        stats
+    }
+
+    /// Looks up the replicated nexus.
+    fn lookup_nexus<'n>(
+        &self,
+    ) -> Result<std::pin::Pin<&'n mut Nexus<'n>>, RebuildError> {
+        nexus_lookup_mut(&self.nexus).ok_or_else(|| {
+            ReplicationError::NexusNotFound {
+                nexus: self.nexus.clone(),
+            }
+            .into()
+        })
+    }
+
+    /// Runs the replication until it is promoted, stopped or failed, and
+    /// records the outcome.
+    async fn run(self: Arc<Self>) {
+        info!("{self:?}: starting replication");
+
+        let result = self.replicate().await;
+
+        if let Some(n) = nexus_lookup(&self.nexus) {
+            n.stop_replication_log().await;
+        }
+        self.close_target().await;
+
+        if let Err(error) = &result {
+            error!("{self:?}: replication failed: {error}");
+        }
+
+        let (promote_waiters, done_waiters, state) = {
+            let mut inner = self.inner.lock();
+            inner.job = None;
+            inner.end_time = Some(Utc::now());
+            inner.state = match result {
+                Ok(state) => state,
+                Err(error) => {
+                    inner.error = Some(error);
+                    ReplicationState::Failed
+                }
+            };
+            (
+                std::mem::take(&mut inner.promote_waiters),
+                std::mem::take(&mut inner.done_waiters),
+                inner.state,
+            )
+        };
+
+        for sender in promote_waiters {
+            let result = match state {
+                ReplicationState::Promoted => Ok(()),
+                state => Err(ReplicationError::StateOp {
+                    operation: "Promote".to_string(),
+                    state: state.to_string(),
+                }
+                .into()),
+            };
+            sender.send(result).ok();
+        }
+        for sender in done_waiters {
+            sender.send(()).ok();
+        }
+
+        info!("{self:?}: replication finished: {state}");
+    }
+
+    /// Copies the whole nexus to the target, then ships the logged writes
+    /// periodically until a request ends the replication.
+    async fn replicate(&self) -> Result<ReplicationState, RebuildError> {
+        self.open_target().await?;
+
+        self.lookup_nexus()?.start_replication_log().await.map_err(
+            |error| ReplicationError::ReplicationLog {
+                nexus: self.nexus.clone(),
+                reason: error.to_string(),
+            },
+        )?;
+
+        if self.sync(None).await? != RebuildState::Completed {
+            return Ok(ReplicationState::Stopped);
+        }
+
+        let interval = self.rpo / 2;
+        let mut last_sync: Option<Instant> = None;
+
+        loop {
+            let request = self.inner.lock().request;
+            match request {
+                Some(ReplicationRequest::Stop) => {
+                    return Ok(ReplicationState::Stopped)
+                }
+                Some(ReplicationRequest::Promote {
+                    force,
+                }) => {
+                    // The waiters are answered once the target is closed.
+                    let Err(error) = self.promote_sync(force).await else {
+                        return Ok(ReplicationState::Promoted);
+                    };
+                    warn!("{self:?}: promotion failed: {error}");
+                    let waiters = {
+                        let mut inner = self.inner.lock();
+                        inner.request = None;
+                        inner.state = ReplicationState::Replicating;
+                        std::mem::take(&mut inner.promote_waiters)
+                    };
+                    for sender in waiters {
+                        sender.send(Err(error.clone())).ok();
+                    }
+                }
+                None => {}
+            }
+
+            if last_sync.map_or(true, |t| t.elapsed() >= interval) {
+                last_sync = Some(Instant::now());
+                match self.sync_log().await {
+                    Ok(true) => {
+                        let mut inner = self.inner.lock();
+                        if inner.state == ReplicationState::Syncing {
+                            inner.state = ReplicationState::Replicating;
+                        }
+                    }
+                    Ok(false) => {}
+                    Err(error) => {
+                        // Retrying is pointless once the nexus is gone.
+                        self.lookup_nexus()?;
+                        warn!("{self:?}: sync failed, will retry: {error}");
+                        let mut inner = self.inner.lock();
+                        inner.failed_syncs += 1;
+                        inner.error = Some(error);
+                    }
+                }
+            }
+
+            self.check_rpo();
+            mayastor_sleep(POLL_INTERVAL).await.ok();
+        }
+    }
+
+    /// Ships the last writes with the nexus I/O paused, unless forced.
+    async fn promote_sync(&self, force: bool) -> Result<(), RebuildError> {
+        if force {
+            let Some(consistent_time) = self.inner.lock().consistent_time
+            else {
+                return Err(ReplicationError::Inconsistent {
+                    nexus: self.nexus.clone(),
+                }
+                .into());
+            };
+            warn!(
+                "{self:?}: forced promotion, writes made after \
+                {consistent_time} are missing from the target"
+            );
+            return Ok(());
+        }
+
+        self.inner.lock().state = ReplicationState::Promoting;
+        self.lookup_nexus()?.pause().await.map_err(|error| {
+            ReplicationError::NexusPause {
+                nexus: self.nexus.clone(),
+                reason: error.to_string(),
+            }
+        })?;
+
+        let result = match self.sync_log().await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(RebuildError::BackendGone),
+            Err(error) => Err(error),
+        };
+
+        if let Ok(n) = self.lookup_nexus() {
+            if let Err(error) = n.resume().await {
+                error!("{self:?}: failed to resume nexus: {error}");
+            }
+        }
+
+        result
+    }
+
+    /// Swaps the replication log with the nexus I/O paused, so that no write
+    /// is logged across the swap, and returns the segments written since the
+    /// previous swap.
+    async fn cut_log(&self) -> Result<SegmentMap, RebuildError> {
+        let n = self.lookup_nexus()?;
+        n.pause()
+            .await
+            .map_err(|error| ReplicationError::NexusPause {
+                nexus: self.nexus.clone(),
+                reason: error.to_string(),
+            })?;
+
+        let map = match nexus_lookup(&self.nexus) {
+            Some(n) => n.swap_replication_log().await,
+            None => None,
+        };
+
+        if let Ok(n) = self.lookup_nexus() {
+            if let Err(error) = n.resume().await {
+                error!("{self:?}: failed to resume nexus: {error}");
+            }
+        }
+
+        match map {
+            Some(map) => Ok(SegmentMap::from(map)),
+            None => Err(ReplicationError::ReplicationLog {
+                nexus: self.nexus.clone(),
+                reason: "the replication log is gone".to_string(),
+            }
+            .into()),
+        }
+    }
+
+    /// Swaps the replication log and ships the segments written since the
+    /// previous swap, along with the segments of the failed syncs.
+    /// The target is consistent as of the swap once the sync is over, unless
+    /// some of the shipped segments were written during the sync; these are
+    /// shipped again by the next sync.
+    /// Returns false if the sync was stopped before completion.
+    async fn sync_log(&self) -> Result<bool, RebuildError> {
+        let sync_time = Utc::now();
+        let mut segments = self.cut_log().await?;
+        if let Some(pending) = self.inner.lock().pending.take() {
+            segments = segments.merge(&pending);
+        }
+
+        if segments.count_dirty_blks() == 0 {
+            // Neither written nor left behind by a failed sync since the
+            // target was last written.
+            let mut inner = self.inner.lock();
+            inner.consistent_time = Some(sync_time);
+            inner.last_sync_time = Some(sync_time);
+            inner.syncs += 1;
+            return Ok(true);
+        }
+
+        // The target is written from now on.
+        self.inner.lock().consistent_time = None;
+        let result = self.sync(Some(segments.clone())).await;
+
+        let result = match result {
+            Ok(RebuildState::Completed) => {
+                // Blocks written during the sync may have been shipped with
+                // their newer data, the next sync ships them again.
+                match self.cut_log().await {
+                    Ok(written) => {
+                        let consistent = !written.intersects(&segments);
+                        let mut inner = self.inner.lock();
+                        inner.pending = Some(written);
+                        if consistent {
+                            inner.consistent_time = Some(sync_time);
+                        }
+                        Ok(RebuildState::Completed)
+                    }
+                    Err(error) => Err(error),
+                }
+            }
+            result => result,
+        };
+
+        let mut inner = self.inner.lock();
+        match result {
+            Ok(RebuildState::Completed) => {
+                inner.last_sync_time = Some(sync_time);
+                inner.syncs += 1;
+                Ok(true)
+            }
+            Ok(_) => {
+                inner.pending = Some(segments);
+                Ok(false)
+            }
+            Err(error) => {
+                inner.pending = Some(segments);
+                Err(error)
+            }
+        }
+    }
+
+    /// Copies the given segments, or the whole nexus data, from a healthy
+    /// child to the target.
+    async fn sync(
+        &self,
+        map: Option<SegmentMap>,
+    ) -> Result<RebuildState, RebuildError> {
+        let (src_uri, range) = {
+            let n = self.lookup_nexus()?;
+            let Some(src) = n.children_iter().find(|c| c.is_healthy()) else {
+                return Err(ReplicationError::NoHealthySource {
+                    nexus: self.nexus.clone(),
+                }
+                .into());
+            };
+            (
+                src.uri().to_string(),
+                n.data_ent_offset .. n.data_ent_offset + n.child_data_blocks(),
+            )
+        };
+
+        let options = RebuildJobOptions {
+            verify_mode: match self.verify {
+                true => RebuildVerifyMode::Fail,
+                false => RebuildVerifyMode::None,
+            },
+            ..Default::default()
+        };
+        let mut builder = BdevRebuildJob::builder()
+            .with_range(range)
+            .with_option(options);
+        if let Some(map) = map {
+            builder = builder.with_bitmap(map);
+        }
+        let job = Arc::new(builder.build(&src_uri, &self.dst_uri).await?);
+
+        let chan = job.start().await?;
+        {
+            let mut inner = self.inner.lock();
+            inner.job = Some(job.clone());
+            if inner.request == Some(ReplicationRequest::Stop) {
+                drop(job.force_stop());
+            }
+        }
+
+        let state = chan.await.unwrap_or(RebuildState::Failed);
+        let stats = job.stats().await;
+        {
+            let mut inner = self.inner.lock();
+            inner.blocks_shipped += stats.blocks_transferred;
+            inner.block_size = stats.block_size;
+            inner.job = None;
+        }
+
+        match state {
+            RebuildState::Failed => {
+                Err(job.error().unwrap_or(RebuildError::BackendGone))
+            }
+            state => Ok(state),
+        }
+    }
+
+    /// Logs the changes of the recovery point objective compliance.
+    fn check_rpo(&self) {
+        let (lag, violated, changed) = {
+            let mut inner = self.inner.lock();
+            let since = inner.last_sync_time.unwrap_or(self.start_time);
+            let lag = (Utc::now() - since).to_std().unwrap_or_default();
+            let violated = lag > self.rpo;
+            let changed = violated != inner.rpo_violated;
+            inner.rpo_violated = violated;
+            (lag, violated, changed)
+        };
+
+        if changed {
+            match violated {
+                true => warn!(
+                    "{self:?}: lag of {lag:?} exceeds the recovery point \
+                    objective"
+                ),
+                false => info!("{self:?}: lag is back within the objective"),
+            }
+        }
+    }
+
+    /// Creates the target device, unless it exists already.
+    async fn open_target(&self) -> Result<(), RebuildError> {
+        match device_create(&self.dst_uri).await {
+            Ok(_) => {
+                self.inner.lock().created_target = true;
+                Ok(())
+            }
+            Err(BdevError::BdevExists {
+                ..
+            }) => Ok(()),
+            Err(error) => Err(ReplicationError::TargetOpen {
+                uri: self.dst_uri.clone(),
+                reason: error.to_string(),
+            }
+            .into()),
+        }
+    }
+
+    /// Destroys the target device if it was created by the replication, so
+    /// that the target can be used elsewhere.
+    async fn close_target(&self) {
+        if !std::mem::take(&mut self.inner.lock().created_target) {
+            return;
+        }
+        if let Err(error) = device_destroy(&self.dst_uri).await {
+            error!("{self:?}: failed to close the target: {error}");
+        }
+    }
+}
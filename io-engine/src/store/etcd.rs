@@ -15,7 +15,7 @@ use crate::store::store_defs::{
     ValueString,
 };
 use async_trait::async_trait;
-use etcd_client::Client;
+use etcd_client::{Client, DeleteOptions, GetOptions};
 use serde_json::Value;
 use snafu::ResultExt;
 
@@ -90,6 +90,48 @@ impl Store for Etcd {
         Ok(())
     }
 
+    /// 'Get' the values of all the keys with the given prefix from etcd.
+    async fn get_values_prefix<K: StoreKey>(
+        &mut self,
+        key_prefix: &K,
+    ) -> Result<Vec<Value>, StoreError> {
+        let resp = self
+            .0
+            .get(
+                key_prefix.to_string(),
+                Some(GetOptions::new().with_prefix()),
+            )
+            .await
+            .context(Get {
+                key: key_prefix.to_string(),
+            })?;
+        resp.kvs()
+            .iter()
+            .map(|kv| {
+                serde_json::from_slice(kv.value()).context(DeserialiseValue {
+                    value: kv.value_str().context(ValueString {})?,
+                })
+            })
+            .collect()
+    }
+
+    /// 'Delete' all the entries with the given key prefix from etcd.
+    async fn delete_values_prefix<K: StoreKey>(
+        &mut self,
+        key_prefix: &K,
+    ) -> Result<(), StoreError> {
+        self.0
+            .delete(
+                key_prefix.to_string(),
+                Some(DeleteOptions::new().with_prefix()),
+            )
+            .await
+            .context(Delete {
+                key: key_prefix.to_string(),
+            })?;
+        Ok(())
+    }
+
     async fn online(&mut self) -> bool {
         self.0.status().await.is_ok()
     }
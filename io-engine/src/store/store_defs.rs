@@ -127,6 +127,18 @@ pub trait Store: Sync + Send + Clone {
         key: &K,
     ) -> Result<(), StoreError>;
 
+    /// Get all the entries whose key starts with the given prefix.
+    async fn get_values_prefix<K: StoreKey>(
+        &mut self,
+        key_prefix: &K,
+    ) -> Result<Vec<Value>, StoreError>;
+
+    /// Delete all the entries whose key starts with the given prefix.
+    async fn delete_values_prefix<K: StoreKey>(
+        &mut self,
+        key_prefix: &K,
+    ) -> Result<(), StoreError>;
+
     /// Identify whether or not the store is online.
     async fn online(&mut self) -> bool;
 }
@@ -9,9 +9,10 @@
 use std::{fmt::Display, fs, io::Write, mem::zeroed, path::Path};
 
 use futures::FutureExt;
-use once_cell::sync::OnceCell;
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
-use snafu::Snafu;
+use snafu::{OptionExt, Snafu};
 use spdk_rs::libspdk::{
     spdk_json_write_ctx,
     spdk_json_write_val_raw,
@@ -21,20 +22,37 @@ use spdk_rs::libspdk::{
 };
 
 use crate::{
+    bdev::{nvme_bdev_running_config, nvme_bdev_update_running_config},
+    core::{configure_reactor_monitor, reactor_monitor_config, Reactor},
     jsonrpc::{jsonrpc_register, Code, RpcErrorCode},
-    subsys::config::opts::{
-        BdevOpts,
-        GetOpts,
-        IoBufOpts,
-        NexusOpts,
-        NvmeBdevOpts,
-        NvmfTgtConfig,
-        PosixSocketOpts,
+    subsys::{
+        config::opts::{
+            BdevOpts,
+            GetOpts,
+            IoBufOpts,
+            NexusOpts,
+            NvmeBdevOpts,
+            NvmfTgtConfig,
+            PosixSocketOpts,
+        },
+        nvmf::Target,
     },
 };
 
 #[derive(Debug, Clone, Snafu)]
-pub enum Error {}
+#[snafu(visibility(pub(crate)), context(suffix(false)))]
+pub enum Error {
+    #[snafu(display("No configuration file was loaded at startup"))]
+    NoConfigFile {},
+    #[snafu(display(
+        "Failed to load configuration file {}: {}",
+        file,
+        reason
+    ))]
+    LoadConfig { file: String, reason: String },
+    #[snafu(display("No node state file was loaded at startup"))]
+    NoNodeStateFile {},
+}
 
 impl RpcErrorCode for Error {
     fn rpc_error_code(&self) -> Code {
@@ -130,6 +148,56 @@ pub struct EalOpts {
     pub developer_delay: Option<bool>,
 }
 
+/// Reactor freeze detection options. Options which are not set in the
+/// config file are taken from the command line arguments.
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReactorFreezeOpts {
+    /// Enable reactor freeze detection.
+    pub detection: Option<bool>,
+    /// Timeout (in seconds) for reactor freeze detection.
+    pub timeout: Option<u64>,
+}
+
+/// Options which can be changed at runtime by reloading the config file.
+/// The I/O timeouts and the reactor freeze options apply at once to the
+/// running NVMe controllers and reactors. The connection-level NVMe options
+/// (keep-alive timeout, controller loss timeout, transport retry count, poll
+/// periods) apply to the controllers created after the reload, and the
+/// command retry delays of the target to the hosts which connect after it.
+/// Changing any other option requires a restart to take effect.
+const RELOADABLE_OPTS: [&str; 13] = [
+    "nvme_bdev_opts.action_on_timeout",
+    "nvme_bdev_opts.timeout_us",
+    "nvme_bdev_opts.timeout_admin_us",
+    "nvme_bdev_opts.keep_alive_timeout_ms",
+    "nvme_bdev_opts.transport_retry_count",
+    "nvme_bdev_opts.nvme_adminq_poll_period_us",
+    "nvme_bdev_opts.nvme_ioq_poll_period_us",
+    "nvme_bdev_opts.ctrlr_loss_timeout_sec",
+    "nvme_bdev_opts.reconnect_delay_sec",
+    "nvme_bdev_opts.fast_io_fail_timeout_sec",
+    "nvmf_tgt_conf.crdt",
+    "reactor_freeze_opts.detection",
+    "reactor_freeze_opts.timeout",
+];
+
+/// Serialized form of the configuration the instance is currently running
+/// with, as far as the config file is concerned: this is the config loaded
+/// at startup with all reloaded options applied on top of it.
+static RUNNING_CONFIG: Lazy<Mutex<Option<serde_json::Value>>> =
+    Lazy::new(|| Mutex::new(None));
+
+/// Outcome of a configuration reload.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConfigReload {
+    /// Options which have changed and have been applied to the running
+    /// instance.
+    pub applied: Vec<String>,
+    /// Options which have changed but require a restart to take effect.
+    pub restart_required: Vec<String>,
+}
+
 /// Main config structure of Mayastor. This structure can be persisted to disk.
 #[derive(Default, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(default, deny_unknown_fields)]
@@ -151,6 +219,8 @@ pub struct Config {
     pub iobuf_opts: IoBufOpts,
     /// Environment Abstraction Layer options.
     pub eal_opts: EalOpts,
+    /// Reactor freeze detection options.
+    pub reactor_freeze_opts: ReactorFreezeOpts,
 }
 
 impl Config {
@@ -215,6 +285,13 @@ impl Config {
             socket_opts: self.socket_opts.get(),
             iobuf_opts: self.iobuf_opts.get(),
             eal_opts: self.eal_opts.clone(),
+            reactor_freeze_opts: {
+                let (detection, timeout) = reactor_monitor_config();
+                ReactorFreezeOpts {
+                    detection: Some(detection),
+                    timeout: Some(timeout),
+                }
+            },
         }
     }
 
@@ -248,4 +325,173 @@ impl Config {
 
         info!("{:#?}", self);
     }
+
+    /// Re-read the config file the instance has been started with and apply
+    /// the options which can safely be changed at runtime (see
+    /// `RELOADABLE_OPTS`). Changes to any other option are reported as
+    /// requiring a restart and are not applied.
+    /// Must be called from the primary reactor thread.
+    pub fn reload() -> Result<ConfigReload, Error> {
+        let file = Config::get().source.clone().context(NoConfigFile)?;
+        info!("Reloading configuration file {}", file);
+
+        let load_err = |reason: String| Error::LoadConfig {
+            file: file.clone(),
+            reason,
+        };
+
+        let data = fs::read(&file).map_err(|e| load_err(e.to_string()))?;
+        let mut new: Config = if data.is_empty() {
+            Config::default()
+        } else {
+            serde_yaml::from_slice(&data)
+                .map_err(|e| load_err(e.to_string()))?
+        };
+        new.source = Some(file.clone());
+        let new_value =
+            serde_json::to_value(&new).map_err(|e| load_err(e.to_string()))?;
+
+        let mut running = RUNNING_CONFIG.lock();
+        let running = running.get_or_insert_with(|| {
+            serde_json::to_value(Config::get()).unwrap_or_default()
+        });
+
+        let mut changed = Vec::new();
+        changed_opts(running, &new_value, &mut Vec::new(), &mut changed);
+
+        let mut result = ConfigReload::default();
+        for opt in changed {
+            if RELOADABLE_OPTS.contains(&opt.as_str()) {
+                result.applied.push(opt);
+            } else {
+                result.restart_required.push(opt);
+            }
+        }
+
+        if result
+            .applied
+            .iter()
+            .any(|o| o.starts_with("nvme_bdev_opts."))
+        {
+            new.apply_nvme_bdev_opts();
+        }
+
+        if result.applied.iter().any(|o| o == "nvmf_tgt_conf.crdt") {
+            Target::set_crdt(new.nvmf_tgt_conf.crdt);
+        }
+
+        // Record the new values, so that the options are not reported as
+        // changed on subsequent reloads.
+        for opt in &result.applied {
+            let pointer = format!("/{}", opt.replace('.', "/"));
+            if let (Some(old), Some(new)) =
+                (running.pointer_mut(&pointer), new_value.pointer(&pointer))
+            {
+                *old = new.clone();
+            }
+        }
+
+        if result
+            .applied
+            .iter()
+            .any(|o| o.starts_with("reactor_freeze_opts."))
+        {
+            let (detection, timeout) = reactor_monitor_config();
+            configure_reactor_monitor(
+                new.reactor_freeze_opts.detection.unwrap_or(detection),
+                Some(new.reactor_freeze_opts.timeout.unwrap_or(timeout)),
+            );
+        }
+
+        info!(
+            applied = ?result.applied,
+            restart_required = ?result.restart_required,
+            "Configuration file {} reloaded",
+            file
+        );
+
+        Ok(result)
+    }
+
+    /// Apply the runtime-changeable NVMe bdev options of this config to the
+    /// running instance.
+    fn apply_nvme_bdev_opts(&self) {
+        let new = &self.nvme_bdev_opts;
+        let mut opts = nvme_bdev_running_config();
+
+        opts.action_on_timeout = new.action_on_timeout;
+        opts.timeout_us = new.timeout_us;
+        opts.timeout_admin_us = new.timeout_admin_us;
+        opts.keep_alive_timeout_ms = new.keep_alive_timeout_ms;
+        opts.transport_retry_count = new.transport_retry_count;
+        opts.nvme_adminq_poll_period_us = new.nvme_adminq_poll_period_us;
+        opts.nvme_ioq_poll_period_us = new.nvme_ioq_poll_period_us;
+        opts.ctrlr_loss_timeout_sec = new.ctrlr_loss_timeout_sec;
+        opts.reconnect_delay_sec = new.reconnect_delay_sec;
+        opts.fast_io_fail_timeout_sec = new.fast_io_fail_timeout_sec;
+
+        // SPDK refuses to change its NVMe bdev options once it has created
+        // its own controllers, our NVMe controllers use the running config
+        // regardless.
+        if !opts.set() {
+            warn!("SPDK NVMe bdev options have not been updated");
+        }
+
+        nvme_bdev_update_running_config(opts);
+    }
+}
+
+/// Collect the (dot-separated) names of all options which differ between the
+/// two serialized configurations.
+fn changed_opts(
+    old: &serde_json::Value,
+    new: &serde_json::Value,
+    path: &mut Vec<String>,
+    changed: &mut Vec<String>,
+) {
+    match (old, new) {
+        (serde_json::Value::Object(old), serde_json::Value::Object(new)) => {
+            for (name, value) in new {
+                path.push(name.clone());
+                changed_opts(
+                    old.get(name).unwrap_or(&serde_json::Value::Null),
+                    value,
+                    path,
+                    changed,
+                );
+                path.pop();
+            }
+        }
+        _ if old != new => changed.push(path.join(".")),
+        _ => {}
+    }
+}
+
+/// Reload the config file whenever a SIGHUP is received.
+pub async fn reload_on_sighup() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sighup = match signal(SignalKind::hangup()) {
+        Ok(s) => s,
+        Err(error) => {
+            error!(%error, "Failed to install SIGHUP handler");
+            return;
+        }
+    };
+
+    while sighup.recv().await.is_some() {
+        info!("Received SIGHUP, reloading configuration");
+        match Reactor::spawn_at_primary(async { Config::reload() }) {
+            Ok(r) => match r.await {
+                Ok(Err(error)) => {
+                    error!(%error, "Failed to reload configuration")
+                }
+                Ok(Ok(_)) => {}
+                Err(_) => error!("Configuration reload has been cancelled"),
+            },
+            Err(error) => {
+                error!(%error, "Failed to schedule configuration reload")
+            }
+        }
+    }
 }
@@ -120,7 +120,7 @@ pub struct NvmfTgtConfig {
     pub crdt: [u16; TARGET_CRDT_LEN],
     /// TCP transport options
     pub opts_tcp: NvmfTransportOpts,
-    /// NVMF target interface (ip, mac, name or subnet).
+    /// NVMF target interfaces (ip, mac, name or subnet), comma-separated.
     pub interface: Option<String>,
     /// Enable RDMA for NVMF target or not
     pub rdma: Option<bool>,
@@ -348,7 +348,7 @@ impl From<NvmfTransportOpts> for spdk_nvmf_transport_opts {
 }
 
 /// generic settings for the NVMe bdev (all our replicas)
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default, deny_unknown_fields)]
 pub struct NvmeBdevOpts {
     /// action take on timeout
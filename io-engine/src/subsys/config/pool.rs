@@ -1,13 +1,32 @@
 use futures::channel::oneshot;
 use once_cell::sync::{Lazy, OnceCell};
 use serde::{Deserialize, Serialize};
-use std::{fmt::Display, fs, path::Path, sync::Mutex};
-use tonic::Status;
+use snafu::OptionExt;
+use std::{
+    fmt::Display,
+    fs,
+    num::NonZeroU64,
+    path::Path,
+    pin::Pin,
+    sync::Mutex,
+    time::Duration,
+};
 
+use super::{Error, NoNodeStateFile};
 use crate::{
-    core::{runtime, Cores, Reactor, Share, VerboseError},
-    grpc::rpc_submit,
-    lvs::{Lvs, LvsBdev, LvsError},
+    bdev::nexus,
+    core::{
+        runtime,
+        Cores,
+        LogicalVolume,
+        NvmfShareProps,
+        Protocol,
+        Reactor,
+        Share,
+        UpdateProps,
+        VerboseError,
+    },
+    lvs::{Lvol, Lvs, LvsBdev},
     pool_backend::{PoolArgs, PoolBackend},
 };
 
@@ -26,10 +45,58 @@ fn get_config_file() -> Option<&'static String> {
     CONFIG_FILE.get()
 }
 
+/// Declared state of the node: the pools, with the replicas on them, and
+/// the nexuses which the node should have. The node is reconciled against it
+/// at startup and on demand.
 #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default, deny_unknown_fields)]
 pub struct PoolConfig {
     pools: Option<Vec<Pool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nexuses: Option<Vec<Nexus>>,
+}
+
+/// A difference between the declared and the actual state of the node.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Drift {
+    /// kind of the resource: "pool", "replica" or "nexus"
+    pub kind: &'static str,
+    /// name of the resource
+    pub name: String,
+    /// what differs
+    pub reason: String,
+    /// whether the difference has been corrected by the reconciliation
+    pub resolved: bool,
+}
+
+impl Drift {
+    fn new(kind: &'static str, name: &str, reason: impl Into<String>) -> Self {
+        Self {
+            kind,
+            name: name.to_string(),
+            reason: reason.into(),
+            resolved: false,
+        }
+    }
+
+    /// Mark the drift as resolved.
+    fn resolved(self) -> Self {
+        Self {
+            resolved: true,
+            ..self
+        }
+    }
+
+    /// Mark the drift as resolved if the result is Ok, or record the error.
+    fn resolve<T, E: Display>(self, result: Result<T, E>) -> Self {
+        match result {
+            Ok(_) => self.resolved(),
+            Err(error) => Self {
+                reason: format!("{}: {}", self.reason, error),
+                ..self
+            },
+        }
+    }
 }
 
 impl PoolConfig {
@@ -104,6 +171,16 @@ impl PoolConfig {
         }
     }
 
+    /// Capture the current node state and export it to the node state file,
+    /// if one has been loaded. Called whenever a replica or a nexus is
+    /// created, changed or destroyed, so that the next reconciliation
+    /// doesn't bring back what has been destroyed in the meantime.
+    pub async fn save() {
+        if get_config_file().is_some() {
+            PoolConfig::capture().export().await;
+        }
+    }
+
     /// Remove named pool from this pool configuration
     pub fn delete(&mut self, name: &str) {
         if let Some(pools) = self.pools.as_mut() {
@@ -111,46 +188,85 @@ impl PoolConfig {
         }
     }
 
-    /// Capture current pool configuration
+    /// Capture current node state: pools with their replicas, and nexuses
     pub fn capture() -> PoolConfig {
         let pools = LvsBdev::iter().map(Pool::from).collect();
+        let nexuses = nexus::nexus_iter()
+            .map(|bdev| Nexus::from(&*bdev))
+            .collect();
         PoolConfig {
             pools: Some(pools),
+            nexuses: Some(nexuses),
         }
     }
 
-    /// Create pools specified in this configuration
-    async fn create_pools(&self) -> usize {
-        let mut failures = 0;
-        if let Some(pools) = self.pools.as_ref() {
-            for pool in pools.iter() {
-                info!("creating pool {}", pool.name);
-                if let Err(error) = create_pool(pool.into()).await {
-                    error!(
-                        "failed to create pool {}: {}",
-                        pool.name,
-                        error.verbose()
-                    );
-                    failures += 1;
-                }
-            }
-        }
-        failures
-    }
-
-    /// Import pools
+    /// Import pools, and create the replicas and nexuses declared in this
+    /// configuration
     pub fn import_pools(self) {
         assert_eq!(Cores::current(), Cores::first());
         Reactor::block_on(async move {
-            let errors = self.create_pools().await;
+            let drift = self.reconcile(false).await;
+            let errors = drift.iter().filter(|d| !d.resolved).count();
+            for d in drift {
+                if d.resolved {
+                    info!("{} {}: {}, fixed", d.kind, d.name, d.reason);
+                } else {
+                    warn!("{} {}: {}", d.kind, d.name, d.reason);
+                }
+            }
             if errors != 0 {
                 warn!(
-                    "Not all pools were imported successfully ({} errors)",
+                    "Node state differs from the configuration ({} unresolved)",
                     errors
                 );
             }
         });
     }
+
+    /// Reconcile the node against this configuration. Missing pools,
+    /// replicas and nexuses are created, and the share state of replicas
+    /// and nexuses is brought in line with the declared one. Differences
+    /// which can't be corrected safely (e.g. a different replica size or
+    /// different nexus children), as well as resources which exist on the
+    /// node but are not declared, are only reported. With `dry_run` nothing
+    /// is changed. Must be called on the primary reactor.
+    pub async fn reconcile(&self, dry_run: bool) -> Vec<Drift> {
+        let mut drift = Vec::new();
+
+        for pool in self.pools.iter().flatten() {
+            drift.extend(pool.reconcile(dry_run).await);
+        }
+        for pool in LvsBdev::iter() {
+            let name = pool.name();
+            if !self.pools.iter().flatten().any(|p| p.name == name) {
+                drift.push(Drift::new("pool", &name, "not declared"));
+            }
+        }
+
+        if let Some(nexuses) = &self.nexuses {
+            for declared in nexuses {
+                drift.extend(declared.reconcile(dry_run).await);
+            }
+            for bdev in nexus::nexus_iter() {
+                if !nexuses.iter().any(|n| n.name == bdev.name) {
+                    drift.push(Drift::new("nexus", &bdev.name, "not declared"));
+                }
+            }
+        }
+
+        drift
+    }
+}
+
+/// Re-read the node state file and reconcile the node against it.
+/// Must be called on the primary reactor.
+pub async fn reconcile_node_state(dry_run: bool) -> Result<Vec<Drift>, Error> {
+    let file = get_config_file().context(NoNodeStateFile)?;
+    let config = PoolConfig::load(file).map_err(|error| Error::LoadConfig {
+        file: file.clone(),
+        reason: error.to_string(),
+    })?;
+    Ok(config.reconcile(dry_run).await)
 }
 
 #[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
@@ -161,12 +277,64 @@ struct Pool {
     name: String,
     /// bdevs to create outside of the nexus control
     disks: Vec<String>,
-    /// list of replicas (not required, informational only)
-    #[serde(skip_serializing)]
+    /// list of replicas on the pool, when not specified the replicas on
+    /// the pool are left alone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     replicas: Option<Vec<Replica>>,
     backend: PoolBackend,
 }
 
+impl Pool {
+    /// Reconcile this pool, creating or importing it if missing, and its
+    /// replicas
+    async fn reconcile(&self, dry_run: bool) -> Vec<Drift> {
+        let mut drift = Vec::new();
+
+        let lvs = match Lvs::lookup(&self.name) {
+            Some(lvs) => lvs,
+            None if self.disks.is_empty() => {
+                return vec![Drift::new(
+                    "pool",
+                    &self.name,
+                    "missing, and can't be created without devices",
+                )];
+            }
+            None => {
+                let missing = Drift::new("pool", &self.name, "missing");
+                if dry_run {
+                    return vec![missing];
+                }
+                info!("creating pool {}", self.name);
+                match Lvs::create_or_import(self.into()).await {
+                    Ok(lvs) => {
+                        drift.push(missing.resolved());
+                        lvs
+                    }
+                    Err(error) => {
+                        return vec![missing.resolve(Err(error.verbose()))]
+                    }
+                }
+            }
+        };
+        let Some(replicas) = &self.replicas else {
+            return drift;
+        };
+
+        let lvols = lvs.lvols().map(|l| l.collect()).unwrap_or_else(Vec::new);
+
+        for replica in replicas {
+            drift.extend(replica.reconcile(&lvs, &lvols, dry_run).await);
+        }
+        for lvol in lvols.iter().filter(|l| !l.is_snapshot() && !l.is_clone()) {
+            if !replicas.iter().any(|r| r.name == lvol.name()) {
+                drift.push(Drift::new("replica", &lvol.name(), "not declared"));
+            }
+        }
+
+        drift
+    }
+}
+
 /// Convert a Pool into a gRPC request payload
 impl From<&Pool> for PoolArgs {
     fn from(pool: &Pool) -> Self {
@@ -189,7 +357,16 @@ impl From<LvsBdev> for Pool {
             disks: vec![base
                 .bdev_uri_str()
                 .unwrap_or_else(|| base.name().to_string())],
-            replicas: None,
+            replicas: Lvs::lookup(&lvs_bdev.name()).map(|lvs| {
+                lvs.lvols()
+                    .into_iter()
+                    .flatten()
+                    // Snapshots and clones can't be recreated from the
+                    // state file, so only capture the plain replicas.
+                    .filter(|l| !l.is_snapshot() && !l.is_clone())
+                    .map(Replica::from)
+                    .collect()
+            }),
             backend: PoolBackend::Lvs,
         }
     }
@@ -206,24 +383,433 @@ pub enum ShareType {
 struct Replica {
     /// name of the replica
     name: String,
+    /// uuid of the replica, generated when not specified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uuid: Option<String>,
+    /// size of the replica in bytes, required to create the replica
+    #[serde(default)]
+    size: u64,
+    /// thin provisioned replica
+    #[serde(default)]
+    thin: bool,
     /// share type if shared
     #[serde(skip_serializing_if = "Option::is_none")]
     share: Option<ShareType>,
+    /// hosts allowed to connect to the shared replica, any when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    allowed_hosts: Vec<String>,
 }
 
-async fn create_pool(
-    args: PoolArgs,
-) -> Result<io_engine_api::v0::Pool, Status> {
-    if args.disks.is_empty() {
-        return Err(Status::invalid_argument("Missing devices"));
+impl From<Lvol> for Replica {
+    fn from(lvol: Lvol) -> Self {
+        Self {
+            name: lvol.name(),
+            uuid: Some(lvol.uuid()),
+            size: lvol.size(),
+            thin: lvol.is_thin(),
+            share: share_type(lvol.shared()),
+            allowed_hosts: lvol.allowed_hosts(),
+        }
     }
+}
 
-    let rx = rpc_submit::<_, _, LvsError>(async move {
-        let pool = Lvs::create_or_import(args).await?;
-        Ok(pool.into())
-    })?;
+impl Replica {
+    /// Reconcile this replica, creating it on the pool if missing
+    async fn reconcile(
+        &self,
+        lvs: &Lvs,
+        lvols: &[Lvol],
+        dry_run: bool,
+    ) -> Vec<Drift> {
+        let mut drift = Vec::new();
+
+        let mut lvol = match lvols.iter().find(|l| l.name() == self.name) {
+            Some(lvol) => lvol.clone(),
+            None if self.size == 0 => {
+                return vec![Drift::new(
+                    "replica",
+                    &self.name,
+                    "missing, and can't be created without a size",
+                )];
+            }
+            None => {
+                let missing = Drift::new("replica", &self.name, "missing");
+                if dry_run {
+                    return vec![missing];
+                }
+                let result = lvs
+                    .create_lvol(
+                        &self.name,
+                        self.size,
+                        self.uuid.as_deref(),
+                        self.thin,
+                        None,
+                    )
+                    .await;
+                match result {
+                    Ok(lvol) => {
+                        drift.push(missing.resolved());
+                        lvol
+                    }
+                    Err(error) => {
+                        return vec![missing.resolve(Err(error.verbose()))]
+                    }
+                }
+            }
+        };
+
+        if self.size != 0 && self.size != lvol.size() {
+            drift.push(Drift::new(
+                "replica",
+                &self.name,
+                format!("size is {} instead of {}", lvol.size(), self.size),
+            ));
+        }
+        if let Some(uuid) = &self.uuid {
+            if *uuid != lvol.uuid() {
+                drift.push(Drift::new(
+                    "replica",
+                    &self.name,
+                    format!("uuid is {} instead of {}", lvol.uuid(), uuid),
+                ));
+            }
+        }
+
+        let shared = share_type(lvol.shared());
+        let reason = if shared != self.share {
+            format!("share is {:?} instead of {:?}", shared, self.share)
+        } else if shared.is_some() && lvol.allowed_hosts() != self.allowed_hosts
+        {
+            "allowed hosts differ".to_string()
+        } else {
+            return drift;
+        };
+
+        let share = Drift::new("replica", &self.name, reason);
+        if dry_run {
+            drift.push(share);
+            return drift;
+        }
+
+        let result = match (shared, self.share) {
+            (_, None) => Pin::new(&mut lvol).unshare().await,
+            (None, Some(ShareType::Nvmf)) => {
+                let props = NvmfShareProps::new()
+                    .with_allowed_hosts(self.allowed_hosts.clone());
+                Pin::new(&mut lvol)
+                    .share_nvmf(Some(props))
+                    .await
+                    .map(|_| ())
+            }
+            (Some(_), Some(_)) => {
+                Pin::new(&mut lvol)
+                    .update_properties(
+                        UpdateProps::new()
+                            .with_allowed_hosts(self.allowed_hosts.clone()),
+                    )
+                    .await
+            }
+        };
+        drift.push(share.resolve(result.map_err(|e| e.verbose())));
+        drift
+    }
+}
 
-    rx.await
-        .map_err(|_| Status::cancelled("cancelled"))?
-        .map_err(Status::from)
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+/// Nexuses that we create on top of local or remote replicas
+struct Nexus {
+    /// name of the nexus
+    name: String,
+    /// uuid of the nexus
+    uuid: String,
+    /// size of the nexus in bytes
+    size: u64,
+    /// URIs of the nexus children
+    children: Vec<String>,
+    /// share type if published
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    share: Option<ShareType>,
+    /// hosts allowed to connect to the published nexus, any when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    allowed_hosts: Vec<String>,
+    /// minimum NVMe controller ID used when publishing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min_cntlid: Option<u16>,
+    /// maximum NVMe controller ID used when publishing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_cntlid: Option<u16>,
+    /// NVMe reservation key for the children
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    resv_key: Option<u64>,
+    /// NVMe preempt key for the children, none to not preempt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    preempt_key: Option<u64>,
+    /// NVMe reservation type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    resv_type: Option<u8>,
+    /// NVMe preemption policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    preempt_policy: Option<PreemptPolicy>,
+    /// protection information policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pi_policy: Option<PiPolicy>,
+    /// stripe size in bytes of a striped nexus, mirrored when not specified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stripe_size: Option<u64>,
+    /// number of children in each mirror set of a striped nexus
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    copies: Option<usize>,
+    /// cache tier of the nexus
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cache: Option<Cache>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+/// NVMe preemption policies of a nexus.
+enum PreemptPolicy {
+    ArgKey,
+    Holder,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+/// Protection information policies of a nexus.
+enum PiPolicy {
+    Passthrough,
+    Verify,
+    Generate,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+/// Write policies of the cache of a nexus.
+enum CachePolicy {
+    WriteThrough,
+    WriteBack,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+/// Cache tier of a nexus
+struct Cache {
+    /// URI of the cache device
+    uri: String,
+    /// write policy of the cache
+    policy: CachePolicy,
+    /// interval between two destages in milliseconds
+    destage_interval_ms: u64,
+}
+
+impl From<&nexus::Nexus<'_>> for Nexus {
+    fn from(nexus: &nexus::Nexus) -> Self {
+        let params = &nexus.nvme_params;
+        let layout = nexus.layout();
+        Self {
+            name: nexus.name.clone(),
+            uuid: nexus.uuid().to_string(),
+            size: nexus.req_size(),
+            children: nexus
+                .children_iter()
+                .map(|c| c.uri().to_string())
+                .collect(),
+            share: share_type(nexus.shared()),
+            allowed_hosts: nexus.allowed_hosts(),
+            min_cntlid: Some(params.min_cntlid),
+            max_cntlid: Some(params.max_cntlid),
+            resv_key: Some(params.resv_key),
+            preempt_key: params.preempt_key.map(NonZeroU64::get),
+            resv_type: Some(params.resv_type as u8),
+            preempt_policy: Some(match params.preempt_policy {
+                nexus::NexusNvmePreemption::ArgKey => PreemptPolicy::ArgKey,
+                nexus::NexusNvmePreemption::Holder => PreemptPolicy::Holder,
+            }),
+            pi_policy: Some(match params.pi_policy {
+                nexus::NexusPiPolicy::Passthrough => PiPolicy::Passthrough,
+                nexus::NexusPiPolicy::Verify => PiPolicy::Verify,
+                nexus::NexusPiPolicy::Generate => PiPolicy::Generate,
+            }),
+            stripe_size: layout.stripe_size(),
+            copies: match layout {
+                nexus::NexusLayout::StripedMirror {
+                    copies, ..
+                } => Some(copies),
+                _ => None,
+            },
+            cache: nexus.cache_config().map(|config| Cache {
+                uri: config.uri,
+                policy: match config.policy {
+                    nexus::CachePolicy::WriteThrough => {
+                        CachePolicy::WriteThrough
+                    }
+                    nexus::CachePolicy::WriteBack => CachePolicy::WriteBack,
+                },
+                destage_interval_ms: config.destage_interval.as_millis() as u64,
+            }),
+        }
+    }
+}
+
+impl Nexus {
+    /// NVMe parameters of this nexus, the defaults for those not specified
+    fn nvme_params(&self) -> Result<nexus::NexusNvmeParams, nexus::Error> {
+        let mut params = nexus::NexusNvmeParams::default();
+        if let Some(min_cntlid) = self.min_cntlid {
+            params.set_min_cntlid(min_cntlid);
+        }
+        if let Some(max_cntlid) = self.max_cntlid {
+            params.set_max_cntlid(max_cntlid);
+        }
+        if let Some(resv_key) = self.resv_key {
+            params.set_resv_key(resv_key);
+        }
+        params.set_preempt_key(self.preempt_key.and_then(NonZeroU64::new));
+        if let Some(resv_type) = self.resv_type {
+            params.set_resv_type(nexus::NvmeReservation::try_from(resv_type)?);
+        }
+        if let Some(policy) = self.preempt_policy {
+            params.set_preempt_policy(match policy {
+                PreemptPolicy::ArgKey => nexus::NexusNvmePreemption::ArgKey,
+                PreemptPolicy::Holder => nexus::NexusNvmePreemption::Holder,
+            });
+        }
+        if let Some(policy) = self.pi_policy {
+            params.set_pi_policy(match policy {
+                PiPolicy::Passthrough => nexus::NexusPiPolicy::Passthrough,
+                PiPolicy::Verify => nexus::NexusPiPolicy::Verify,
+                PiPolicy::Generate => nexus::NexusPiPolicy::Generate,
+            });
+        }
+        Ok(params)
+    }
+
+    /// Data layout of this nexus
+    fn layout(&self) -> nexus::NexusLayout {
+        match (self.stripe_size, self.copies) {
+            (None, _) => nexus::NexusLayout::Mirror,
+            (Some(stripe_size), None) => nexus::NexusLayout::Striped {
+                stripe_size,
+            },
+            (Some(stripe_size), Some(copies)) => {
+                nexus::NexusLayout::StripedMirror {
+                    stripe_size,
+                    copies,
+                }
+            }
+        }
+    }
+
+    /// Cache tier of this nexus
+    fn cache_config(&self) -> Option<nexus::NexusCacheConfig> {
+        self.cache.as_ref().map(|cache| {
+            let policy = match cache.policy {
+                CachePolicy::WriteThrough => nexus::CachePolicy::WriteThrough,
+                CachePolicy::WriteBack => nexus::CachePolicy::WriteBack,
+            };
+            nexus::NexusCacheConfig::new(&cache.uri, policy)
+                .with_destage_interval(Duration::from_millis(
+                    cache.destage_interval_ms,
+                ))
+        })
+    }
+
+    /// Reconcile this nexus, creating it if missing
+    async fn reconcile(&self, dry_run: bool) -> Vec<Drift> {
+        let mut drift = Vec::new();
+
+        if nexus::nexus_lookup(&self.name).is_none() {
+            let missing = Drift::new("nexus", &self.name, "missing");
+            if dry_run {
+                return vec![missing];
+            }
+
+            let result = match self.nvme_params() {
+                Ok(params) => {
+                    nexus::nexus_create_v2(
+                        &self.name,
+                        self.size,
+                        &self.uuid,
+                        params,
+                        self.layout(),
+                        &self.children,
+                        None,
+                        self.cache_config(),
+                    )
+                    .await
+                }
+                Err(error) => Err(error),
+            };
+            let failed = result.is_err();
+            drift.push(missing.resolve(result));
+            if failed {
+                return drift;
+            }
+        }
+
+        let Some(bdev) = nexus::nexus_lookup(&self.name) else {
+            return drift;
+        };
+
+        if bdev.layout() != self.layout() {
+            drift.push(Drift::new(
+                "nexus",
+                &self.name,
+                format!(
+                    "layout is {} instead of {}",
+                    bdev.layout(),
+                    self.layout()
+                ),
+            ));
+        }
+
+        let children = bdev
+            .children_iter()
+            .map(|c| c.uri().to_string())
+            .collect::<Vec<_>>();
+        if children != self.children {
+            drift.push(Drift::new(
+                "nexus",
+                &self.name,
+                format!(
+                    "children are {:?} instead of {:?}",
+                    children, self.children
+                ),
+            ));
+        }
+
+        let shared = share_type(bdev.shared());
+        let reason = if shared != self.share {
+            format!("share is {:?} instead of {:?}", shared, self.share)
+        } else if shared.is_some() && bdev.allowed_hosts() != self.allowed_hosts
+        {
+            "allowed hosts differ".to_string()
+        } else {
+            return drift;
+        };
+
+        let share = Drift::new("nexus", &self.name, reason);
+        if dry_run {
+            drift.push(share);
+            return drift;
+        }
+
+        let Some(bdev) = nexus::nexus_lookup_mut(&self.name) else {
+            return drift;
+        };
+        let result = match self.share {
+            None => bdev.unshare_nexus().await,
+            // Publishing an already published nexus only updates the
+            // allowed hosts.
+            Some(ShareType::Nvmf) => bdev
+                .share_ext(Protocol::Nvmf, None, self.allowed_hosts.clone())
+                .await
+                .map(|_| ()),
+        };
+        drift.push(share.resolve(result));
+        drift
+    }
+}
+
+/// Share type of a replica or nexus shared via the given protocol
+fn share_type(protocol: Option<Protocol>) -> Option<ShareType> {
+    match protocol {
+        Some(Protocol::Nvmf) => Some(ShareType::Nvmf),
+        _ => None,
+    }
 }
@@ -3,15 +3,21 @@
 
 pub use config::{
     opts::{NexusOpts, NvmeBdevOpts},
-    pool::PoolConfig,
+    pool::{reconcile_node_state, Drift, PoolConfig},
+    reload_on_sighup,
     Config,
+    ConfigReload,
     ConfigSubsystem,
+    Error as ConfigError,
 };
 pub use nvmf::{
     set_snapshot_time,
+    DiscoveryReferral,
     Error as NvmfError,
     NvmeCpl,
+    NvmfHostController,
     NvmfReq,
+    NvmfReservation,
     NvmfSubsystem,
     SubType,
     Target as NvmfTarget,
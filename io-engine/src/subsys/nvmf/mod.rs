@@ -20,8 +20,14 @@ use spdk_rs::libspdk::{
     spdk_subsystem_fini_next,
     spdk_subsystem_init_next,
 };
-pub use subsystem::{NvmfSubsystem, SubType};
-pub use target::Target;
+pub use subsystem::{
+    NvmfHostController,
+    NvmfRegistrant,
+    NvmfReservation,
+    NvmfSubsystem,
+    SubType,
+};
+pub use target::{DiscoveryReferral, Target};
 
 use crate::{
     jsonrpc::{Code, RpcErrorCode},
@@ -30,6 +36,7 @@ use crate::{
 
 mod admin_cmd;
 mod poll_groups;
+mod reservation;
 mod subsystem;
 mod target;
 mod transport;
@@ -84,6 +91,12 @@ pub enum Error {
     Listener { nqn: String, trid: String },
     #[snafu(display("Interior nul byte found for host {}", host))]
     HostCstrNul { host: String },
+    #[snafu(display("{} discovery referral {}: {}", msg, referral, source))]
+    Referral {
+        source: Errno,
+        referral: String,
+        msg: String,
+    },
 }
 
 thread_local! {
@@ -101,6 +114,9 @@ impl Nvmf {
         // set up custom NVMe Admin command handler
         admin_cmd::setup_create_snapshot_hdlr();
 
+        // persist the reservations of nexuses to the persistent store
+        reservation::setup_reservation_ops();
+
         if Config::get().nexus_opts.nvmf_enable {
             NVMF_TGT.with(|tgt| tgt.borrow_mut().next_state());
         } else {
@@ -0,0 +1,209 @@
+//! Persistence of the reservations of the namespaces of the NVMe-oF target.
+//!
+//! The target calls these operations instead of its own to load the
+//! reservations of a namespace when it is added, and to save them whenever
+//! they change. The reservations of replicas, and of nexuses without a
+//! persistent store, are kept in the PTPL file of the namespace, if any,
+//! in the format of the target. The reservations of nexuses are saved to
+//! the persistent store as well, so that the next nexus of the volume
+//! restores them, with or without a PTPL file.
+//!
+//! The target only saves the reservations on a change while PTPL is
+//! activated on the namespace, which hosts may not request. The namespaces
+//! of nexuses with a persistent store are thus loaded with PTPL activated,
+//! so that the reservations of the volume survive the nexus whatever the
+//! hosts asked for.
+
+use std::{
+    ffi::CStr,
+    os::raw::{c_char, c_int},
+    path::{Path, PathBuf},
+};
+
+use spdk_rs::libspdk::{
+    spdk_nvmf_ns,
+    spdk_nvmf_ns_get_bdev,
+    spdk_nvmf_ns_reservation_ops,
+    spdk_nvmf_reservation_info,
+    spdk_nvmf_set_custom_ns_reservation_ops,
+    SPDK_NVMF_MAX_NUM_REGISTRANTS,
+};
+
+use crate::{
+    bdev::nexus::{nexus_lookup, Nexus, RegistrantInfo, ReservationInfo},
+    core::{Bdev, UntypedBdev},
+    ffihelper::copy_str_with_null,
+    persistent_store::PersistentStore,
+};
+
+static RESERVATION_OPS: spdk_nvmf_ns_reservation_ops =
+    spdk_nvmf_ns_reservation_ops {
+        is_ptpl_capable: Some(is_ptpl_capable),
+        update: Some(update),
+        load: Some(load),
+    };
+
+/// Installs the reservation operations of the target.
+pub fn setup_reservation_ops() {
+    unsafe { spdk_nvmf_set_custom_ns_reservation_ops(&RESERVATION_OPS) };
+}
+
+/// Returns the bdev of the namespace.
+fn ns_bdev(ns: *const spdk_nvmf_ns) -> Option<UntypedBdev> {
+    Bdev::checked_from_ptr(unsafe {
+        spdk_nvmf_ns_get_bdev(ns as *mut spdk_nvmf_ns)
+    })
+}
+
+/// Returns the path of the PTPL file of the namespace, if any.
+fn ptpl_file(ns: *const spdk_nvmf_ns) -> Option<PathBuf> {
+    let file = unsafe { (*ns).ptpl_file };
+    if file.is_null() {
+        return None;
+    }
+    let file = unsafe { CStr::from_ptr(file) };
+    Some(PathBuf::from(file.to_string_lossy().into_owned()))
+}
+
+/// Returns the nexus of the namespace, if its reservations are saved to the
+/// persistent store.
+fn store_nexus<'n>(ns: *const spdk_nvmf_ns) -> Option<&'n Nexus<'n>> {
+    if !PersistentStore::enabled() {
+        return None;
+    }
+    nexus_lookup(ns_bdev(ns)?.name())
+}
+
+extern "C" fn is_ptpl_capable(ns: *const spdk_nvmf_ns) -> bool {
+    ptpl_file(ns).is_some() || store_nexus(ns).is_some()
+}
+
+extern "C" fn update(
+    ns: *const spdk_nvmf_ns,
+    info: *const spdk_nvmf_reservation_info,
+) -> c_int {
+    let resv = ReservationInfo::from(unsafe { &*info });
+
+    if let Some(nexus) = store_nexus(ns) {
+        nexus.save_reservations(resv.clone());
+    }
+
+    match ptpl_file(ns) {
+        Some(path) => match write_ptpl_file(&path, &resv) {
+            Ok(()) => 0,
+            Err(error) => {
+                error!(
+                    "failed to save reservations to '{}': {error}",
+                    path.display()
+                );
+                -error.raw_os_error().unwrap_or(libc::EIO)
+            }
+        },
+        None => 0,
+    }
+}
+
+extern "C" fn load(
+    ns: *const spdk_nvmf_ns,
+    info: *mut spdk_nvmf_reservation_info,
+) -> c_int {
+    // The store prevails over the file, which may be left over from an
+    // older nexus on this node.
+    let nexus = store_nexus(ns);
+    let restored = nexus.and_then(|n| n.restored_reservations());
+    let mut resv = match (restored, ptpl_file(ns)) {
+        (Some(resv), _) => resv,
+        (None, _) if nexus.is_some() => ReservationInfo::default(),
+        (None, Some(path)) => match read_ptpl_file(&path) {
+            Ok(Some(resv)) => resv,
+            Ok(None) => return 0,
+            Err(error) => {
+                error!(
+                    "failed to load reservations from '{}': {error}",
+                    path.display()
+                );
+                return -libc::EINVAL;
+            }
+        },
+        (None, None) => return 0,
+    };
+
+    if nexus.is_some() {
+        resv.ptpl = true;
+        if let Some(bdev) = ns_bdev(ns) {
+            resv.bdev_uuid = bdev.uuid().to_string();
+        }
+    }
+
+    resv.copy_to(unsafe { &mut *info });
+    0
+}
+
+/// Reads the reservations from the PTPL file, if it exists.
+fn read_ptpl_file(path: &Path) -> std::io::Result<Option<ReservationInfo>> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(None)
+        }
+        Err(error) => return Err(error),
+    };
+    Ok(Some(serde_json::from_slice(&data)?))
+}
+
+/// Writes the reservations to the PTPL file.
+fn write_ptpl_file(path: &Path, resv: &ReservationInfo) -> std::io::Result<()> {
+    std::fs::write(path, serde_json::to_vec_pretty(resv)?)
+}
+
+/// Returns the string held by a nul-terminated C array.
+fn c_array_str(array: &[c_char]) -> String {
+    unsafe { CStr::from_ptr(array.as_ptr()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+impl From<&spdk_nvmf_reservation_info> for ReservationInfo {
+    fn from(info: &spdk_nvmf_reservation_info) -> Self {
+        let num_regs = (info.num_regs as usize).min(info.registrants.len());
+        Self {
+            ptpl: info.ptpl_activated,
+            rtype: info.rtype,
+            crkey: info.crkey,
+            bdev_uuid: c_array_str(&info.bdev_uuid),
+            holder_uuid: c_array_str(&info.holder_uuid),
+            registrants: info.registrants[.. num_regs]
+                .iter()
+                .map(|r| RegistrantInfo {
+                    rkey: r.rkey,
+                    host_uuid: c_array_str(&r.host_uuid),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ReservationInfo {
+    /// Copies the reservations to the info the target restores them from.
+    fn copy_to(&self, info: &mut spdk_nvmf_reservation_info) {
+        info.ptpl_activated = self.ptpl;
+        info.rtype = self.rtype;
+        info.crkey = self.crkey;
+        copy_str_with_null(&self.bdev_uuid, &mut info.bdev_uuid);
+        copy_str_with_null(&self.holder_uuid, &mut info.holder_uuid);
+
+        let num_regs = self
+            .registrants
+            .len()
+            .min(SPDK_NVMF_MAX_NUM_REGISTRANTS as usize);
+        for (reg, saved) in info
+            .registrants
+            .iter_mut()
+            .zip(&self.registrants[.. num_regs])
+        {
+            reg.rkey = saved.rkey;
+            copy_str_with_null(&saved.host_uuid, &mut reg.host_uuid);
+        }
+        info.num_regs = num_regs as u32;
+    }
+}
@@ -3,6 +3,7 @@ use std::{
     ffi::{c_void, CString},
     fmt::{self, Debug, Display, Formatter},
     mem::zeroed,
+    net::IpAddr,
     ptr::{self, NonNull},
 };
 
@@ -13,9 +14,15 @@ use spdk_rs::{
     libspdk::{
         nvmf_subsystem_find_listener,
         nvmf_subsystem_set_cntlid_range,
+        spdk_bit_array_count_set,
+        spdk_get_ticks,
+        spdk_get_ticks_hz,
+        spdk_nvme_transport_id,
+        spdk_nvmf_ctrlr,
         spdk_nvmf_ctrlr_set_cpl_error_cb,
         spdk_nvmf_ns_get_bdev,
         spdk_nvmf_ns_opts,
+        spdk_nvmf_qpair_get_peer_trid,
         spdk_nvmf_request,
         spdk_nvmf_subsystem,
         spdk_nvmf_subsystem_add_host,
@@ -36,6 +43,7 @@ use spdk_rs::{
         spdk_nvmf_subsystem_listener_get_trid,
         spdk_nvmf_subsystem_pause,
         spdk_nvmf_subsystem_remove_host,
+        spdk_nvmf_subsystem_remove_listener,
         spdk_nvmf_subsystem_remove_ns,
         spdk_nvmf_subsystem_resume,
         spdk_nvmf_subsystem_set_allow_any_host,
@@ -49,6 +57,9 @@ use spdk_rs::{
         spdk_nvmf_subsystem_stop,
         spdk_nvmf_tgt,
         spdk_nvmf_tgt_get_transport,
+        spdk_nvmf_transport_stop_listen_async,
+        SPDK_NVME_RESERVE_EXCLUSIVE_ACCESS_ALL_REGS,
+        SPDK_NVME_RESERVE_WRITE_EXCLUSIVE_ALL_REGS,
         SPDK_NVME_SCT_GENERIC,
         SPDK_NVME_SC_CAPACITY_EXCEEDED,
         SPDK_NVME_SC_RESERVATION_CONFLICT,
@@ -63,7 +74,7 @@ use spdk_rs::{
 
 use crate::{
     bdev::{nexus::NEXUS_MODULE_NAME, nvmx::NVME_CONTROLLERS, Nexus},
-    constants::{NVME_CONTROLLER_MODEL_ID, NVME_NQN_PREFIX},
+    constants::{NVME_CONTROLLER_MODEL_ID, NVME_NQN_PREFIX, TRACING_TARGET},
     core::{Bdev, Reactors, UntypedBdev},
     eventing::{host_events::HostTargetMeta, EventMetaGen, EventWithMeta},
     ffihelper::{cb_arg, done_cb, AsStr, FfiResult, IntoCString},
@@ -74,6 +85,7 @@ use crate::{
         nvmf::{
             transport::{TransportId, RDMA_TRANSPORT},
             Error,
+            Target,
             NVMF_TGT,
         },
         Config,
@@ -522,11 +534,18 @@ impl NvmfSubsystem {
     where
         T: spdk_rs::BdevOps,
     {
+        // The EUI64 tells this instance of a replica from one recreated with
+        // the same UUID.
+        let instance_id = UntypedBdev::lookup_by_name(bdev.name())
+            .and_then(|b| Lvol::try_from(b).ok())
+            .and_then(|lvol| lvol.instance_id())
+            .unwrap_or_default();
+
         let opts = struct_size_init!(
             spdk_nvmf_ns_opts {
                 nsid: 0,
                 nguid: *bdev.uuid().as_bytes(),
-                eui64: unsafe { zeroed() },
+                eui64: instance_id.to_le_bytes(),
                 uuid: Default::default(),
                 reserved44: unsafe { zeroed() },
                 anagrpid: 0,
@@ -759,6 +778,54 @@ impl NvmfSubsystem {
         })
     }
 
+    /// Returns the host controllers connected to the subsystem.
+    pub fn host_controllers(&self) -> Vec<NvmfHostController> {
+        let (now, hz) = unsafe { (spdk_get_ticks(), spdk_get_ticks_hz()) };
+        let mut ctrlrs = Vec::new();
+
+        let mut ctrlr = unsafe { self.0.as_ref().ctrlrs.tqh_first };
+        while !ctrlr.is_null() {
+            ctrlrs.push(unsafe { NvmfHostController::new(ctrlr, now, hz) });
+            ctrlr = unsafe { (*ctrlr).link.tqe_next };
+        }
+
+        ctrlrs
+    }
+
+    /// Returns the reservation state of the namespace of the subsystem, if
+    /// any.
+    pub fn reservation(&self) -> Option<NvmfReservation> {
+        let ns = unsafe { spdk_nvmf_subsystem_get_first_ns(self.0.as_ptr()) };
+        if ns.is_null() {
+            return None;
+        }
+
+        let ns = unsafe { &*ns };
+        // All the registrants hold the reservations of these types.
+        let all_holders = ns.rtype
+            == SPDK_NVME_RESERVE_WRITE_EXCLUSIVE_ALL_REGS
+            || ns.rtype == SPDK_NVME_RESERVE_EXCLUSIVE_ACCESS_ALL_REGS;
+
+        let mut registrants = Vec::new();
+        let mut reg = ns.registrants.tqh_first;
+        while !reg.is_null() {
+            let r = unsafe { &*reg };
+            registrants.push(NvmfRegistrant {
+                host_id: uuid::Uuid::from_bytes(unsafe { r.hostid.u.raw }),
+                key: r.rkey,
+                holder: reg == ns.holder || all_holders,
+            });
+            reg = r.link.tqe_next;
+        }
+
+        Some(NvmfReservation {
+            rtype: ns.rtype,
+            key: ns.crkey,
+            generation: ns.gen,
+            registrants,
+        })
+    }
+
     /// enable Asymmetric Namespace Access (ANA) reporting
     pub fn set_ana_reporting(&self, enable: bool) -> Result<(), Error> {
         match std::env::var("NEXUS_NVMF_ANA_ENABLE") {
@@ -803,27 +870,63 @@ impl NvmfSubsystem {
         Ok(())
     }
 
+    /// Changes the controller ID range of a started subsystem, which is
+    /// stopped meanwhile. Its hosts get disconnected, and their new
+    /// controllers get IDs in the new range as they reconnect.
+    pub async fn change_cntlid_range(
+        &self,
+        cntlid_min: u16,
+        cntlid_max: u16,
+    ) -> Result<(), Error> {
+        self.stop().await?;
+        let res = self.set_cntlid_range(cntlid_min, cntlid_max);
+        self.change_state("start", |ss, cb, arg| unsafe {
+            spdk_nvmf_subsystem_start(ss, cb, arg)
+        })
+        .await?;
+        res
+    }
+
     // we currently allow all listeners to the subsystem
     async fn add_listener(
         &self,
         transport: NvmfTgtTransport,
     ) -> Result<(), Error> {
+        let cfg = Config::get();
+
+        // dont yet enable both ports, IOW just add one transportID now, for
+        // each of the target addresses.
+        for trid_replica in
+            TransportId::all(cfg.nexus_opts.nvmf_replica_port, transport)
+        {
+            self.add_trid_listener(&trid_replica).await?;
+        }
+        Ok(())
+    }
+
+    /// Adds a TCP listener of the subsystem on the given address, which the
+    /// target starts listening on as well.
+    pub async fn add_listener_on(&self, address: &IpAddr) -> Result<(), Error> {
+        let trid = TransportId::new(
+            address,
+            Config::get().nexus_opts.nvmf_replica_port,
+            NvmfTgtTransport::Tcp,
+        );
+        Target::listen_on(&trid)?;
+        self.add_trid_listener(&trid).await
+    }
+
+    async fn add_trid_listener(&self, trid: &TransportId) -> Result<(), Error> {
         extern "C" fn listen_cb(arg: *mut c_void, status: i32) {
             let s = unsafe { Box::from_raw(arg as *mut oneshot::Sender<i32>) };
             s.send(status).unwrap();
         }
 
-        let cfg = Config::get();
-
-        // dont yet enable both ports, IOW just add one transportID now
-        let trid_replica =
-            TransportId::new(cfg.nexus_opts.nvmf_replica_port, transport);
-
         let (s, r) = oneshot::channel::<i32>();
         unsafe {
             spdk_nvmf_subsystem_add_listener(
                 self.0.as_ptr(),
-                trid_replica.as_ptr(),
+                trid.as_ptr(),
                 Some(listen_cb),
                 cb_arg(s),
             );
@@ -832,7 +935,64 @@ impl NvmfSubsystem {
         r.await.expect("listener callback gone").to_result(|e| {
             Error::Transport {
                 source: Errno::from_i32(e),
-                msg: "Failed to add listener".to_string(),
+                msg: format!("Failed to add listener {trid}"),
+            }
+        })
+    }
+
+    /// Removes the listener of the subsystem on the given address, and
+    /// disconnects the hosts connected through it.
+    pub async fn remove_listener(&self, address: &IpAddr) -> Result<(), Error> {
+        extern "C" fn stop_listen_cb(arg: *mut c_void, status: i32) {
+            let s = unsafe { Box::from_raw(arg as *mut oneshot::Sender<i32>) };
+            s.send(status).unwrap();
+        }
+
+        let address = address.to_string();
+        let trid = self
+            .listeners_to_vec()
+            .unwrap_or_default()
+            .into_iter()
+            .find(|trid| trid.traddr.as_str() == address)
+            .ok_or_else(|| Error::Transport {
+                source: Errno::ENOENT,
+                msg: format!("No listener on {address}"),
+            })?;
+
+        self.pause().await?;
+        let rc = unsafe {
+            spdk_nvmf_subsystem_remove_listener(self.0.as_ptr(), trid.as_ptr())
+        };
+        self.resume().await?;
+
+        rc.to_result(|e| Error::Transport {
+            source: Errno::from_i32(e),
+            msg: format!("Failed to remove listener {trid}"),
+        })?;
+
+        // Disconnect the qpairs established through the listener.
+        let transport = NVMF_TGT.with(|t| unsafe {
+            spdk_nvmf_tgt_get_transport(
+                t.borrow().tgt.as_ptr(),
+                trid.trstring.as_ptr(),
+            )
+        });
+
+        let (s, r) = oneshot::channel::<i32>();
+        unsafe {
+            spdk_nvmf_transport_stop_listen_async(
+                transport,
+                trid.as_ptr(),
+                self.0.as_ptr(),
+                Some(stop_listen_cb),
+                cb_arg(s),
+            );
+        }
+
+        r.await.expect("stop listen callback gone").to_result(|e| {
+            Error::Transport {
+                source: Errno::from_i32(e),
+                msg: format!("Failed to stop listening on {trid}"),
             }
         })
     }
@@ -916,6 +1076,11 @@ impl NvmfSubsystem {
     /// start the subsystem previously created -- note that we destroy it on
     /// failure to ensure the state is not in limbo and to avoid leaking
     /// resources
+    #[tracing::instrument(
+        target = TRACING_TARGET,
+        skip_all,
+        fields(nqn = %self.get_nqn())
+    )]
     pub async fn start(self, need_rdma: bool) -> Result<String, Error> {
         self.add_listener(NvmfTgtTransport::Tcp).await?;
         // Only attempt rdma listener addition for this subsystem after making
@@ -987,10 +1152,12 @@ impl NvmfSubsystem {
     /// as today?
     pub async fn get_ana_state(&self) -> Result<u32, Error> {
         let cfg = Config::get();
-        let trid_replica = TransportId::new(
+        // All the listeners share the same ANA state.
+        let trid_replica = TransportId::all(
             cfg.nexus_opts.nvmf_replica_port,
             NvmfTgtTransport::Tcp,
-        );
+        )
+        .swap_remove(0);
         let listener = unsafe {
             nvmf_subsystem_find_listener(self.0.as_ptr(), trid_replica.as_ptr())
         };
@@ -1162,7 +1329,8 @@ impl NvmfSubsystem {
         }
     }
 
-    /// return the URI's this subsystem is listening on
+    /// return the URI's this subsystem is listening on, one for each target
+    /// address and transport
     pub fn uri_endpoints(&self) -> Option<Vec<String>> {
         if let Some(v) = self.listeners_to_vec() {
             let nqn = self.get_nqn();
@@ -1173,6 +1341,81 @@ impl NvmfSubsystem {
     }
 }
 
+/// A host controller connected to a subsystem.
+#[derive(Debug, Clone)]
+pub struct NvmfHostController {
+    /// NQN of the host.
+    pub host_nqn: String,
+    /// Identifier of the host, to which its reservations are registered.
+    pub host_id: uuid::Uuid,
+    /// Controller ID.
+    pub cntlid: u16,
+    /// Transport address of the host, if still connected.
+    pub address: Option<String>,
+    /// Keep alive timeout, in milliseconds, or 0 if disabled.
+    pub keep_alive_timeout_ms: u32,
+    /// Time elapsed since the last keep alive from the host, in milliseconds.
+    pub keep_alive_elapsed_ms: u64,
+    /// Number of queue pairs, the admin queue included.
+    pub queue_count: u32,
+}
+
+impl NvmfHostController {
+    unsafe fn new(ctrlr: *mut spdk_nvmf_ctrlr, now: u64, hz: u64) -> Self {
+        let c = &*ctrlr;
+
+        let address = if c.admin_qpair.is_null() {
+            None
+        } else {
+            let mut trid: spdk_nvme_transport_id = zeroed();
+            (spdk_nvmf_qpair_get_peer_trid(c.admin_qpair, &mut trid) == 0)
+                .then(|| TransportId(trid).to_string())
+        };
+
+        Self {
+            host_nqn: c.hostnqn.as_str().to_string(),
+            host_id: uuid::Uuid::from_bytes(c.hostid.u.raw),
+            cntlid: c.cntlid,
+            address,
+            keep_alive_timeout_ms: c.feat.keep_alive_timer.raw,
+            keep_alive_elapsed_ms: now.saturating_sub(c.last_keep_alive_tick)
+                * 1000
+                / hz.max(1),
+            queue_count: spdk_bit_array_count_set(c.qpair_mask),
+        }
+    }
+
+    /// Checks if the host missed its keep alives.
+    pub fn keep_alive_expired(&self) -> bool {
+        self.keep_alive_timeout_ms > 0
+            && self.keep_alive_elapsed_ms > self.keep_alive_timeout_ms as u64
+    }
+}
+
+/// A registrant of the reservations of the namespace of a subsystem.
+#[derive(Debug, Clone)]
+pub struct NvmfRegistrant {
+    /// Identifier of the registered host.
+    pub host_id: uuid::Uuid,
+    /// Reservation key of the host.
+    pub key: u64,
+    /// Whether the host holds the reservation.
+    pub holder: bool,
+}
+
+/// Reservation state of the namespace of a subsystem.
+#[derive(Debug, Clone)]
+pub struct NvmfReservation {
+    /// Reservation type, or 0 if not reserved.
+    pub rtype: u32,
+    /// Reservation key of the holder.
+    pub key: u64,
+    /// Generation, incremented by every preemption and registration.
+    pub generation: u32,
+    /// Registered hosts.
+    pub registrants: Vec<NvmfRegistrant>,
+}
+
 /// Makes an NQN froma UUID.
 fn make_nqn(id: &str) -> String {
     format!("{NVME_NQN_PREFIX}:{id}")
@@ -1,7 +1,9 @@
 use std::{
     cell::RefCell,
-    ffi::{c_void, CString},
-    mem::zeroed,
+    ffi::{c_void, CStr, CString},
+    fmt::{Display, Formatter},
+    mem::{size_of, zeroed},
+    net::IpAddr,
     ptr::{null, NonNull},
 };
 
@@ -12,13 +14,16 @@ use spdk_rs::libspdk::{
     spdk_nvmf_listen_opts,
     spdk_nvmf_listen_opts_init,
     spdk_nvmf_poll_group_destroy,
+    spdk_nvmf_referral_opts,
     spdk_nvmf_subsystem_create,
     spdk_nvmf_subsystem_set_mn,
     spdk_nvmf_target_opts,
     spdk_nvmf_tgt,
+    spdk_nvmf_tgt_add_referral,
     spdk_nvmf_tgt_create,
     spdk_nvmf_tgt_destroy,
     spdk_nvmf_tgt_listen_ext,
+    spdk_nvmf_tgt_remove_referral,
     spdk_nvmf_tgt_stop_listen,
     spdk_subsystem_fini_next,
     spdk_subsystem_init_next,
@@ -29,13 +34,13 @@ use spdk_rs::libspdk::{
 use crate::{
     constants::NVME_CONTROLLER_MODEL_ID,
     core::{Cores, MayastorEnvironment, Mthread, Reactors},
-    ffihelper::{AsStr, FfiResult},
+    ffihelper::{copy_str_with_null, AsStr, FfiResult},
     subsys::{
-        config::opts::NvmfTgtTransport,
+        config::opts::{NvmfTgtTransport, TARGET_CRDT_LEN},
         nvmf::{
             poll_groups::PollGroup,
             subsystem::NvmfSubsystem,
-            transport::{self, get_ipv4_address, TransportId},
+            transport::{self, listen_addresses, TransportId},
             Error,
             NVMF_PGS,
         },
@@ -59,6 +64,10 @@ pub struct Target {
     next_state: TargetState,
     /// Whether the target supports RDMA transport.
     rdma: bool,
+    /// Referrals to the discovery services of the peer io-engines. They are
+    /// kept in memory only, the control plane registers them again once the
+    /// io-engine restarts.
+    referrals: Vec<DiscoveryReferral>,
 }
 
 impl Default for Target {
@@ -105,6 +114,7 @@ impl Target {
             poll_group_count: 0,
             next_state: TargetState::Init,
             rdma: MayastorEnvironment::global_or_default().rdma(),
+            referrals: Vec::new(),
         }
     }
 
@@ -228,61 +238,7 @@ impl Target {
     /// Listen for incoming connections, by default we only listen on the
     /// replica port i.e. NVMF_PORT_REPLICA.
     fn listen(&mut self) -> Result<()> {
-        let cfg = Config::get();
-        let trid_nexus = TransportId::new(
-            cfg.nexus_opts.nvmf_nexus_port,
-            NvmfTgtTransport::Tcp,
-        );
-        let mut opts = spdk_nvmf_listen_opts {
-            opts_size: 0,
-            transport_specific: null(),
-            secure_channel: false,
-            reserved1: unsafe { zeroed() },
-            ana_state: 0,
-        };
-        unsafe {
-            spdk_nvmf_listen_opts_init(
-                &mut opts,
-                std::mem::size_of::<spdk_nvmf_listen_opts>() as u64,
-            );
-        }
-        let rc = unsafe {
-            spdk_nvmf_tgt_listen_ext(
-                self.tgt.as_ptr(),
-                trid_nexus.as_ptr(),
-                &mut opts,
-            )
-        };
-
-        if rc != 0 {
-            return Err(Error::CreateTarget {
-                msg: "failed to back target".into(),
-            });
-        }
-
-        let trid_replica = TransportId::new(
-            cfg.nexus_opts.nvmf_replica_port,
-            NvmfTgtTransport::Tcp,
-        );
-        let rc = unsafe {
-            spdk_nvmf_tgt_listen_ext(
-                self.tgt.as_ptr(),
-                trid_replica.as_ptr(),
-                &mut opts,
-            )
-        };
-
-        if rc != 0 {
-            return Err(Error::CreateTarget {
-                msg: "failed to front target".into(),
-            });
-        }
-        info!(
-            "nvmf target listening(tcp) on {}:({},{})",
-            get_ipv4_address().unwrap(),
-            trid_nexus.trsvcid.as_str(),
-            trid_replica.trsvcid.as_str(),
-        );
+        self.listen_transport(NvmfTgtTransport::Tcp)?;
 
         if self.rdma {
             // listen RDMA also.
@@ -301,61 +257,57 @@ impl Target {
     /// Listen for incoming connections, by default we only listen on the
     /// replica port i.e. NVMF_PORT_REPLICA.
     fn listen_rdma(&mut self) -> Result<()> {
+        self.listen_transport(NvmfTgtTransport::Rdma)
+    }
+
+    /// Listens on the nexus and replica ports of every target address, IPv4
+    /// or IPv6, with the given transport.
+    fn listen_transport(&self, transport: NvmfTgtTransport) -> Result<()> {
         let cfg = Config::get();
-        let trid_nexus = TransportId::new(
-            cfg.nexus_opts.nvmf_nexus_port,
-            NvmfTgtTransport::Rdma,
-        );
-        let mut opts = spdk_nvmf_listen_opts {
-            opts_size: 0,
-            transport_specific: null(),
-            secure_channel: false,
-            reserved1: unsafe { zeroed() },
-            ana_state: 0,
-        };
-        unsafe {
-            spdk_nvmf_listen_opts_init(
-                &mut opts,
-                std::mem::size_of::<spdk_nvmf_listen_opts>() as u64,
-            );
-        }
-        let rc = unsafe {
-            spdk_nvmf_tgt_listen_ext(
-                self.tgt.as_ptr(),
-                trid_nexus.as_ptr(),
-                &mut opts,
-            )
-        };
+        let mut opts = listen_opts();
+
+        for (port, what) in [
+            (cfg.nexus_opts.nvmf_nexus_port, "back"),
+            (cfg.nexus_opts.nvmf_replica_port, "front"),
+        ] {
+            for trid in TransportId::all(port, transport.clone()) {
+                let rc = unsafe {
+                    spdk_nvmf_tgt_listen_ext(
+                        self.tgt.as_ptr(),
+                        trid.as_ptr(),
+                        &mut opts,
+                    )
+                };
 
-        if rc != 0 {
-            return Err(Error::CreateTarget {
-                msg: "failed to back target".into(),
-            });
+                if rc != 0 {
+                    return Err(Error::CreateTarget {
+                        msg: format!("failed to {what} target on {trid}"),
+                    });
+                }
+                info!("nvmf target listening on {trid}");
+            }
         }
+        Ok(())
+    }
 
-        let trid_replica = TransportId::new(
-            cfg.nexus_opts.nvmf_replica_port,
-            NvmfTgtTransport::Rdma,
-        );
-        let rc = unsafe {
+    /// Listens on the given transport ID, in addition to the addresses the
+    /// target was started with.
+    pub fn listen_on(trid: &TransportId) -> Result<()> {
+        let mut opts = listen_opts();
+        let rc = NVMF_TGT.with(|t| unsafe {
             spdk_nvmf_tgt_listen_ext(
-                self.tgt.as_ptr(),
-                trid_replica.as_ptr(),
+                t.borrow().tgt.as_ptr(),
+                trid.as_ptr(),
                 &mut opts,
             )
-        };
+        });
 
         if rc != 0 {
             return Err(Error::CreateTarget {
-                msg: "failed to front target".into(),
+                msg: format!("failed to listen on {trid}"),
             });
         }
-        info!(
-            "nvmf target listening(rdma) on {}:({},{})",
-            get_ipv4_address().unwrap(),
-            trid_nexus.trsvcid.as_str(),
-            trid_replica.trsvcid.as_str(),
-        );
+        info!("nvmf target listening on {trid}");
         Ok(())
     }
 
@@ -387,6 +339,95 @@ impl Target {
         discovery
     }
 
+    /// Sets the command retry delays which the target reports to the hosts
+    /// which connect from now on.
+    pub fn set_crdt(crdt: [u16; TARGET_CRDT_LEN]) {
+        NVMF_TGT.with(|t| {
+            let tgt = t.borrow();
+            unsafe { (*tgt.tgt.as_ptr()).crdt = crdt };
+            info!("NVMF target command retry delays set to {crdt:?}");
+        })
+    }
+
+    /// Adds a referral to the discovery service of a peer io-engine, which the
+    /// discovery log page then lists along with the local subsystems.
+    /// The referral is not persisted: it is lost when the io-engine restarts.
+    pub fn add_referral(referral: DiscoveryReferral) -> Result<()> {
+        NVMF_TGT.with(|t| {
+            let mut tgt = t.borrow_mut();
+            if tgt.referrals.contains(&referral) {
+                return Ok(());
+            }
+            tgt.check_referral(&referral)?;
+
+            let opts = referral.opts();
+            unsafe { spdk_nvmf_tgt_add_referral(tgt.tgt.as_ptr(), &opts) }
+                .to_result(|e| Error::Referral {
+                    source: Errno::from_i32(e),
+                    referral: referral.to_string(),
+                    msg: "Failed to add".into(),
+                })?;
+
+            info!("Added discovery referral to {referral}");
+            tgt.referrals.push(referral);
+            Ok(())
+        })
+    }
+
+    /// Removes a referral to the discovery service of a peer io-engine.
+    pub fn remove_referral(referral: &DiscoveryReferral) -> Result<()> {
+        NVMF_TGT.with(|t| {
+            let mut tgt = t.borrow_mut();
+            if !tgt.referrals.contains(referral) {
+                return Err(Error::Referral {
+                    source: Errno::ENOENT,
+                    referral: referral.to_string(),
+                    msg: "Failed to find".into(),
+                });
+            }
+
+            let opts = referral.opts();
+            let rc = unsafe {
+                spdk_nvmf_tgt_remove_referral(tgt.tgt.as_ptr(), &opts)
+            };
+            rc.to_result(|e| Error::Referral {
+                source: Errno::from_i32(e),
+                referral: referral.to_string(),
+                msg: "Failed to remove".into(),
+            })?;
+
+            info!("Removed discovery referral to {referral}");
+            tgt.referrals.retain(|r| r != referral);
+            Ok(())
+        })
+    }
+
+    /// Returns the referrals to the discovery services of the peer
+    /// io-engines.
+    pub fn referrals() -> Vec<DiscoveryReferral> {
+        NVMF_TGT.with(|t| t.borrow().referrals.clone())
+    }
+
+    /// Checks a referral can be added: the target must be running, and the
+    /// referral must not point back to this target.
+    fn check_referral(&self, referral: &DiscoveryReferral) -> Result<()> {
+        let error = |source, msg: &str| Error::Referral {
+            source,
+            referral: referral.to_string(),
+            msg: msg.into(),
+        };
+
+        if self.next_state != TargetState::Running {
+            return Err(error(Errno::ENODEV, "Target not running, cannot add"));
+        }
+        if referral.port == Config::get().nexus_opts.nvmf_replica_port
+            && listen_addresses()?.contains(&referral.address)
+        {
+            return Err(error(Errno::EINVAL, "Cannot add self as"));
+        }
+        Ok(())
+    }
+
     /// stop all subsystems on this target we are borrowed here
     fn stop_subsystems(&self) {
         let tgt = self.tgt.as_ptr();
@@ -472,28 +513,21 @@ impl Target {
             );
         } else {
             let cfg = Config::get();
-            let trid_nexus = TransportId::new(
-                cfg.nexus_opts.nvmf_nexus_port,
-                NvmfTgtTransport::Tcp,
-            );
-            let trid_replica = TransportId::new(
-                cfg.nexus_opts.nvmf_replica_port,
-                NvmfTgtTransport::Tcp,
-            );
-            let mut trid_vec = vec![trid_nexus, trid_replica];
+            let mut transports = vec![NvmfTgtTransport::Tcp];
             // todo: handle by fetching current listeners dynamically here.
             // Since this is shutdown path we're good this way for
             // now.
             if self.rdma {
-                trid_vec.push(TransportId::new(
+                transports.push(NvmfTgtTransport::Rdma);
+            }
+            let trid_vec = transports.into_iter().flat_map(|transport| {
+                [
                     cfg.nexus_opts.nvmf_nexus_port,
-                    NvmfTgtTransport::Rdma,
-                ));
-                trid_vec.push(TransportId::new(
                     cfg.nexus_opts.nvmf_replica_port,
-                    NvmfTgtTransport::Rdma,
-                ));
-            }
+                ]
+                .into_iter()
+                .flat_map(move |port| TransportId::all(port, transport.clone()))
+            });
 
             for trid in trid_vec {
                 unsafe {
@@ -521,3 +555,64 @@ impl Target {
         });
     }
 }
+
+/// Returns the default options to listen with.
+fn listen_opts() -> spdk_nvmf_listen_opts {
+    let mut opts = spdk_nvmf_listen_opts {
+        opts_size: 0,
+        transport_specific: null(),
+        secure_channel: false,
+        reserved1: unsafe { zeroed() },
+        ana_state: 0,
+    };
+    unsafe {
+        spdk_nvmf_listen_opts_init(
+            &mut opts,
+            std::mem::size_of::<spdk_nvmf_listen_opts>() as u64,
+        );
+    }
+    opts
+}
+
+/// Referral of the discovery service to the discovery service of a peer
+/// io-engine, so that the hosts find every path to a volume from a single
+/// discovery endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveryReferral {
+    /// Address of the peer.
+    pub address: IpAddr,
+    /// Port of the discovery service of the peer.
+    pub port: u16,
+    /// Whether the peer is reached over RDMA rather than TCP.
+    pub rdma: bool,
+}
+
+impl DiscoveryReferral {
+    /// Makes the transport ID of the discovery service of the peer, whose
+    /// subsystem NQN is the well-known discovery NQN.
+    fn transport_id(&self) -> TransportId {
+        let transport = if self.rdma {
+            NvmfTgtTransport::Rdma
+        } else {
+            NvmfTgtTransport::Tcp
+        };
+        let mut trid = TransportId::new(&self.address, self.port, transport);
+        let nqn = CStr::from_bytes_with_nul(SPDK_NVMF_DISCOVERY_NQN)
+            .expect("discovery NQN is not NUL-terminated");
+        copy_str_with_null(nqn.to_str().unwrap(), &mut trid.subnqn);
+        trid
+    }
+
+    fn opts(&self) -> spdk_nvmf_referral_opts {
+        let mut opts: spdk_nvmf_referral_opts = unsafe { zeroed() };
+        opts.size = size_of::<spdk_nvmf_referral_opts>() as u64;
+        opts.trid = self.transport_id().0;
+        opts
+    }
+}
+
+impl Display for DiscoveryReferral {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.transport_id())
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
     ffi::CString,
     fmt::{Debug, Display, Formatter},
+    net::IpAddr,
     ops::{Deref, DerefMut},
 };
 
@@ -17,6 +18,7 @@ use spdk_rs::{
         SPDK_NVME_TRANSPORT_RDMA,
         SPDK_NVME_TRANSPORT_TCP,
         SPDK_NVMF_ADRFAM_IPV4,
+        SPDK_NVMF_ADRFAM_IPV6,
         SPDK_NVMF_TRSVCID_MAX_LEN,
     },
 };
@@ -122,8 +124,21 @@ impl DerefMut for TransportId {
 }
 
 impl TransportId {
-    pub fn new(port: u16, transport: NvmfTgtTransport) -> Self {
-        let address = get_ipv4_address().unwrap();
+    /// Makes the transport IDs of all the addresses the target listens on,
+    /// for the given port and transport.
+    pub fn all(port: u16, transport: NvmfTgtTransport) -> Vec<Self> {
+        listen_addresses()
+            .unwrap()
+            .iter()
+            .map(|address| Self::new(address, port, transport.clone()))
+            .collect()
+    }
+
+    pub fn new(
+        address: &IpAddr,
+        port: u16,
+        transport: NvmfTgtTransport,
+    ) -> Self {
         let (xprt_type, xprt_cstr) = match transport {
             NvmfTgtTransport::Tcp => (SPDK_NVME_TRANSPORT_TCP, &TCP_TRANSPORT),
             NvmfTgtTransport::Rdma => {
@@ -133,7 +148,10 @@ impl TransportId {
 
         let mut trid = spdk_nvme_transport_id {
             trtype: xprt_type,
-            adrfam: SPDK_NVMF_ADRFAM_IPV4,
+            adrfam: match address {
+                IpAddr::V4(_) => SPDK_NVMF_ADRFAM_IPV4,
+                IpAddr::V6(_) => SPDK_NVMF_ADRFAM_IPV6,
+            },
             ..Default::default()
         };
 
@@ -141,7 +159,7 @@ impl TransportId {
         assert!(port.len() < SPDK_NVMF_TRSVCID_MAX_LEN as usize);
 
         copy_cstr_with_null(xprt_cstr, &mut trid.trstring);
-        copy_str_with_null(&address, &mut trid.traddr);
+        copy_str_with_null(&address.to_string(), &mut trid.traddr);
         copy_str_with_null(&port, &mut trid.trsvcid);
 
         Self(trid)
@@ -162,13 +180,24 @@ impl Display for TransportId {
             _else => _else.to_lowercase(),
         };
 
-        write!(
-            f,
-            "nvmf+{}://{}:{}",
-            trstring,
-            self.0.traddr.as_str(),
-            self.0.trsvcid.as_str()
-        )
+        // IPv6 addresses are enclosed in brackets within URIs.
+        if self.0.adrfam == SPDK_NVMF_ADRFAM_IPV6 {
+            write!(
+                f,
+                "nvmf+{}://[{}]:{}",
+                trstring,
+                self.0.traddr.as_str(),
+                self.0.trsvcid.as_str()
+            )
+        } else {
+            write!(
+                f,
+                "nvmf+{}://{}:{}",
+                trstring,
+                self.0.traddr.as_str(),
+                self.0.trsvcid.as_str()
+            )
+        }
     }
 }
 
@@ -183,11 +212,42 @@ impl Debug for TransportId {
     }
 }
 
-pub(crate) fn get_ipv4_address() -> Result<String, Error> {
-    match MayastorEnvironment::get_nvmf_tgt_ip() {
+/// Returns the IP addresses the target listens on.
+pub(crate) fn listen_addresses() -> Result<Vec<IpAddr>, Error> {
+    match MayastorEnvironment::get_nvmf_tgt_ips() {
         Ok(val) => Ok(val),
         Err(msg) => Err(Error::CreateTarget {
             msg,
         }),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::TransportId;
+    use crate::subsys::config::opts::NvmfTgtTransport;
+
+    #[test]
+    fn test_transport_id_display() {
+        let trid = TransportId::new(
+            &"10.0.0.1".parse().unwrap(),
+            8420,
+            NvmfTgtTransport::Tcp,
+        );
+        assert_eq!(trid.to_string(), "nvmf+tcp://10.0.0.1:8420");
+
+        let trid = TransportId::new(
+            &"fd00::1".parse().unwrap(),
+            8420,
+            NvmfTgtTransport::Tcp,
+        );
+        assert_eq!(trid.to_string(), "nvmf+tcp://[fd00::1]:8420");
+
+        let trid = TransportId::new(
+            &"fd00::1".parse().unwrap(),
+            8420,
+            NvmfTgtTransport::Rdma,
+        );
+        assert_eq!(trid.to_string(), "nvmf+rdma+tcp://[fd00::1]:8420");
+    }
+}
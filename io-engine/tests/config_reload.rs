@@ -0,0 +1,83 @@
+use common::MayastorTest;
+use io_engine::{
+    bdev::nvme_bdev_running_config,
+    core::{reactor_monitor_config, MayastorCliArgs},
+    subsys::Config,
+};
+pub mod common;
+
+static CONFIG_FILE: &str = "/tmp/io-engine-config-reload.yaml";
+
+fn write_config(
+    timeout_us: u64,
+    keep_alive_timeout_ms: u32,
+    ctrlr_loss_timeout_sec: i32,
+    bdev_io_pool_size: u32,
+    freeze_timeout: u64,
+) {
+    std::fs::write(
+        CONFIG_FILE,
+        format!(
+            "nvme_bdev_opts:\n  timeout_us: {timeout_us}\n\
+             \x20 keep_alive_timeout_ms: {keep_alive_timeout_ms}\n\
+             \x20 ctrlr_loss_timeout_sec: {ctrlr_loss_timeout_sec}\n\
+             bdev_opts:\n  bdev_io_pool_size: {bdev_io_pool_size}\n\
+             reactor_freeze_opts:\n  timeout: {freeze_timeout}\n"
+        ),
+    )
+    .unwrap();
+}
+
+#[tokio::test]
+async fn config_reload() {
+    write_config(5_000_000, 10_000, 0, 65535, 3);
+
+    let ms = MayastorTest::new(MayastorCliArgs {
+        mayastor_config: Some(CONFIG_FILE.to_string()),
+        ..Default::default()
+    });
+
+    ms.spawn(async {
+        assert_eq!(nvme_bdev_running_config().timeout_us, 5_000_000);
+
+        // Nothing has changed yet.
+        let r = Config::reload().unwrap();
+        assert!(r.applied.is_empty());
+        assert!(r.restart_required.is_empty());
+    })
+    .await;
+
+    write_config(7_000_000, 20_000, 30, 32767, 10);
+
+    ms.spawn(async {
+        let r = Config::reload().unwrap();
+        assert_eq!(
+            r.applied,
+            vec![
+                "nvme_bdev_opts.ctrlr_loss_timeout_sec".to_string(),
+                "nvme_bdev_opts.keep_alive_timeout_ms".to_string(),
+                "nvme_bdev_opts.timeout_us".to_string(),
+                "reactor_freeze_opts.timeout".to_string(),
+            ]
+        );
+        assert_eq!(
+            r.restart_required,
+            vec!["bdev_opts.bdev_io_pool_size".to_string()]
+        );
+
+        let opts = nvme_bdev_running_config();
+        assert_eq!(opts.timeout_us, 7_000_000);
+        assert_eq!(opts.keep_alive_timeout_ms, 20_000);
+        assert_eq!(opts.ctrlr_loss_timeout_sec, 30);
+        assert_eq!(reactor_monitor_config(), (false, 10));
+
+        // Applied options are not reported again, options requiring a
+        // restart are reported until the instance is restarted.
+        let r = Config::reload().unwrap();
+        assert!(r.applied.is_empty());
+        assert_eq!(r.restart_required.len(), 1);
+    })
+    .await;
+
+    std::fs::remove_file(CONFIG_FILE).unwrap();
+}
@@ -0,0 +1,181 @@
+use once_cell::sync::OnceCell;
+use std::time::Duration;
+
+pub mod common;
+use common::{bdev_io, compose::MayastorTest};
+use io_engine::{
+    bdev::{
+        device_create,
+        device_destroy,
+        nexus::{nexus_create, nexus_lookup_mut},
+    },
+    core::{MayastorCliArgs, UntypedBdevHandle},
+    rebuild::{CopyJob, CopyJobPhase, CopyJobStats, RebuildState},
+    sleep::mayastor_sleep,
+};
+
+static MAYASTOR: OnceCell<MayastorTest> = OnceCell::new();
+
+fn get_ms() -> &'static MayastorTest<'static> {
+    MAYASTOR.get_or_init(|| MayastorTest::new(MayastorCliArgs::default()))
+}
+
+const BLOCK_SIZE: u64 = 512;
+fn mb_to_blocks(mb: u64) -> u64 {
+    (mb * 1024 * 1024) / BLOCK_SIZE
+}
+const SIZE_MB: u64 = 32;
+
+/// Waits for the copy job to be done and returns its final stats.
+async fn wait_done(job: &CopyJob) -> CopyJobStats {
+    for _ in 0 .. 100 {
+        let stats = job.stats().await;
+        if stats.phase == CopyJobPhase::Done {
+            return stats;
+        }
+        mayastor_sleep(Duration::from_millis(100)).await.ok();
+    }
+    panic!("Copy job {job:?} did not finish in time");
+}
+
+/// Checks that both devices hold the same data.
+async fn compare_devices(src: &str, dst: &str) {
+    let src = UntypedBdevHandle::open(src, false, false).unwrap();
+    let dst = UntypedBdevHandle::open(dst, false, false).unwrap();
+
+    let chunk = 1024 * 1024;
+    let mut src_buf = src.dma_malloc(chunk).unwrap();
+    let mut dst_buf = dst.dma_malloc(chunk).unwrap();
+
+    for offset in (0 .. SIZE_MB * 1024 * 1024).step_by(chunk as usize) {
+        src.read_at(offset, &mut src_buf).await.unwrap();
+        dst.read_at(offset, &mut dst_buf).await.unwrap();
+        assert!(
+            src_buf.as_slice() == dst_buf.as_slice(),
+            "Devices differ at offset {offset}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn copy_malloc_to_malloc() {
+    let ms = get_ms();
+
+    ms.spawn(async move {
+        let src_uri = format!("malloc:///c0?size_mb={SIZE_MB}");
+        let dst_uri = format!("malloc:///c1?size_mb={SIZE_MB}");
+
+        device_create(&src_uri).await.unwrap();
+        device_create(&dst_uri).await.unwrap();
+        bdev_io::write_some("c0", 4096, 16, 0xaa).await.unwrap();
+
+        let job = CopyJob::builder()
+            .with_uuid("b1e2f0a7-3c54-4d8a-9e61-0f7c2d5a8b13")
+            .with_verify(true)
+            .build(&src_uri, &dst_uri)
+            .await
+            .unwrap()
+            .store()
+            .unwrap();
+        assert!(!job.is_live());
+
+        // Only one copy job per destination.
+        let err = CopyJob::builder()
+            .build(&src_uri, &dst_uri)
+            .await
+            .unwrap()
+            .store()
+            .unwrap_err();
+        println!("expected error: {err}");
+
+        job.start().unwrap();
+        assert!(job.start().is_err());
+
+        let stats = wait_done(&job).await;
+        assert_eq!(stats.state, RebuildState::Completed, "{stats:?}");
+        assert_eq!(stats.blocks_copied, mb_to_blocks(SIZE_MB));
+        assert!(job.pause().is_err());
+
+        bdev_io::read_some("c1", 4096, 16, 0xaa).await.unwrap();
+        compare_devices("c0", "c1").await;
+
+        // A finished job doesn't prevent the copy from being retried.
+        let retry = CopyJob::builder()
+            .build(&src_uri, &dst_uri)
+            .await
+            .unwrap()
+            .store()
+            .unwrap();
+        assert!(CopyJob::lookup(job.uuid()).is_err());
+
+        CopyJob::remove(retry.uuid()).unwrap();
+        device_destroy(&src_uri).await.unwrap();
+        device_destroy(&dst_uri).await.unwrap();
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn copy_live_nexus_child() {
+    let ms = get_ms();
+
+    ms.spawn(async move {
+        let src_uri = format!("malloc:///l0?size_mb={SIZE_MB}");
+        let dst_uri = format!("malloc:///l1?size_mb={SIZE_MB}");
+
+        // Only the children of a local nexus can be copied live.
+        device_create(&dst_uri).await.unwrap();
+        device_create(&src_uri).await.unwrap();
+        let err = CopyJob::builder()
+            .with_live(true)
+            .build(&src_uri, &dst_uri)
+            .await
+            .unwrap_err();
+        println!("expected error: {err}");
+        device_destroy(&src_uri).await.unwrap();
+
+        nexus_create("copy_nexus", 16 * 1024 * 1024, None, &[src_uri.clone()])
+            .await
+            .unwrap();
+
+        let job = CopyJob::builder()
+            .with_live(true)
+            .build(&src_uri, &dst_uri)
+            .await
+            .unwrap()
+            .store()
+            .unwrap();
+        assert!(job.is_live());
+        job.start().unwrap();
+
+        // Written while the copy runs, must be caught up with.
+        bdev_io::write_some("copy_nexus", 0, 256, 0x55)
+            .await
+            .unwrap();
+
+        let stats = wait_done(&job).await;
+        assert_eq!(stats.state, RebuildState::Completed, "{stats:?}");
+        assert!(stats.blocks_copied > mb_to_blocks(SIZE_MB));
+
+        // The destination has replaced the source in the nexus, which is
+        // resumed after the catch-up.
+        let children = nexus_lookup_mut("copy_nexus")
+            .unwrap()
+            .children_iter()
+            .map(|c| c.uri().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(children, vec![dst_uri.clone()]);
+        bdev_io::read_some("copy_nexus", 0, 256, 0x55)
+            .await
+            .unwrap();
+
+        // The nexus destroys the destination along with itself.
+        CopyJob::remove(job.uuid()).unwrap();
+        nexus_lookup_mut("copy_nexus")
+            .unwrap()
+            .destroy()
+            .await
+            .unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,100 @@
+use once_cell::sync::OnceCell;
+
+pub mod common;
+use common::compose::MayastorTest;
+use events_api::event::{EventAction, EventCategory};
+use io_engine::{
+    bdev::nexus::{nexus_create, nexus_lookup_mut},
+    core::MayastorCliArgs,
+    eventing::event_ring::{EventFilter, EventRing, EventWatch},
+};
+
+static MAYASTOR: OnceCell<MayastorTest> = OnceCell::new();
+
+fn get_ms() -> &'static MayastorTest<'static> {
+    MAYASTOR.get_or_init(|| MayastorTest::new(MayastorCliArgs::default()))
+}
+
+const NEXUS_NAME: &str = "event_nexus";
+const NEXUS_UUID: &str = "3f1c2b6e-7d4a-4e8b-9c05-a2d16e8f4b73";
+
+#[tokio::test]
+async fn event_watch_replay() {
+    let ms = get_ms();
+
+    let filter = EventFilter {
+        categories: vec![EventCategory::Nexus as i32],
+        actions: vec![],
+        targets: vec![NEXUS_UUID.to_string()],
+    };
+    let since = EventRing::get().last_sequence();
+
+    ms.spawn(async move {
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            Some(NEXUS_UUID),
+            &["malloc:///event_c0?size_mb=64".to_string()],
+        )
+        .await
+        .unwrap();
+        nexus_lookup_mut(NEXUS_NAME)
+            .unwrap()
+            .destroy()
+            .await
+            .unwrap();
+    })
+    .await;
+
+    // A watcher reconnecting with the last sequence number it got is
+    // replayed the events it missed.
+    let EventWatch {
+        replay,
+        mut events,
+        ..
+    } = EventRing::get().watch(Some(since), &filter).unwrap();
+    assert!(!replay.is_empty());
+    assert!(replay.windows(2).all(|e| e[0].sequence < e[1].sequence));
+    assert!(replay.iter().all(|e| e.message.target == NEXUS_UUID));
+    assert!(replay
+        .iter()
+        .any(|e| e.message.action == EventAction::Delete as i32));
+
+    // A new watcher is not replayed anything.
+    let watch = EventRing::get().watch(None, &filter).unwrap();
+    assert!(watch.replay.is_empty());
+
+    // Filtered out events are not replayed.
+    let other = EventFilter {
+        targets: vec!["a0b1c2d3-0000-4000-8000-000000000000".to_string()],
+        ..Default::default()
+    };
+    let watch = EventRing::get().watch(Some(since), &other).unwrap();
+    assert!(watch.replay.is_empty());
+
+    // Events generated after the watch started are received.
+    ms.spawn(async move {
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            Some(NEXUS_UUID),
+            &["malloc:///event_c1?size_mb=64".to_string()],
+        )
+        .await
+        .unwrap();
+        nexus_lookup_mut(NEXUS_NAME)
+            .unwrap()
+            .destroy()
+            .await
+            .unwrap();
+    })
+    .await;
+
+    let mut received = false;
+    while let Ok(event) = events.try_recv() {
+        if filter.matches(&event) {
+            received = true;
+        }
+    }
+    assert!(received);
+}
@@ -0,0 +1,175 @@
+pub mod common;
+
+use common::compose::{
+    rpc::v1::{
+        audit::{AuditEntry, ListAuditEntriesRequest},
+        bdev::{CreateBdevRequest, DestroyBdevRequest},
+        json::JsonRpcRequest,
+        pool::{CreatePoolRequest, DestroyPoolRequest, ListPoolOptions},
+        GrpcConnect,
+        RpcHandle,
+    },
+    Builder,
+};
+use tonic::Code;
+
+const POOL_NAME: &str = "pool0";
+const POOL_UUID: &str = "40baf8b5-6256-4f29-b073-61ebf67d9b91";
+
+/// Lists the audit entries of the methods matching the given filter.
+async fn list_entries(hdl: &mut RpcHandle, method: &str) -> Vec<AuditEntry> {
+    hdl.audit
+        .list_audit_entries(ListAuditEntriesRequest {
+            max_entries: None,
+            method: Some(method.to_string()),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .entries
+}
+
+#[tokio::test]
+async fn grpc_audit() {
+    common::composer_init();
+
+    let compose = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_dbg("ms1")
+        .build()
+        .await
+        .unwrap();
+
+    let grpc = GrpcConnect::new(&compose);
+    let mut hdl = grpc.grpc_handle("ms1").await.unwrap();
+
+    hdl.pool
+        .create_pool(CreatePoolRequest {
+            name: POOL_NAME.to_string(),
+            uuid: Some(POOL_UUID.to_string()),
+            pooltype: 0,
+            disks: vec!["malloc:///disk0?size_mb=64".to_string()],
+            cluster_size: None,
+        })
+        .await
+        .unwrap();
+    hdl.pool
+        .list_pools(ListPoolOptions::default())
+        .await
+        .unwrap();
+    hdl.pool
+        .destroy_pool(DestroyPoolRequest {
+            name: POOL_NAME.to_string(),
+            uuid: Some(POOL_UUID.to_string()),
+        })
+        .await
+        .unwrap();
+    let err = hdl
+        .pool
+        .destroy_pool(DestroyPoolRequest {
+            name: POOL_NAME.to_string(),
+            uuid: Some(POOL_UUID.to_string()),
+        })
+        .await
+        .unwrap_err();
+
+    // Only the mutating calls are audited.
+    let entries = list_entries(&mut hdl, "PoolRpc").await;
+    let methods = entries
+        .iter()
+        .map(|e| e.method.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        methods,
+        vec![
+            "mayastor.v1.PoolRpc/CreatePool",
+            "mayastor.v1.PoolRpc/DestroyPool",
+            "mayastor.v1.PoolRpc/DestroyPool",
+        ]
+    );
+
+    let create = &entries[0];
+    assert_eq!(create.code, Code::Ok as i32);
+    assert!(create.args.contains(POOL_UUID));
+    assert!(create.peer.is_some());
+    assert!(create.duration.is_some());
+
+    let destroy = &entries[2];
+    assert_eq!(destroy.code, err.code() as i32);
+    assert_eq!(destroy.message, err.message());
+
+    let entries = hdl
+        .audit
+        .list_audit_entries(ListAuditEntriesRequest {
+            max_entries: Some(1),
+            method: None,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .entries;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].method, "mayastor.v1.PoolRpc/DestroyPool");
+}
+
+#[tokio::test]
+async fn grpc_audit_args() {
+    common::composer_init();
+
+    let compose = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_dbg("ms1")
+        .build()
+        .await
+        .unwrap();
+
+    let grpc = GrpcConnect::new(&compose);
+    let mut hdl = grpc.grpc_handle("ms1").await.unwrap();
+
+    // The arguments of the calls of the other services are recorded too.
+    let uri = "malloc:///disk1?size_mb=64";
+    hdl.bdev
+        .create(CreateBdevRequest {
+            uri: uri.to_string(),
+        })
+        .await
+        .unwrap();
+    hdl.bdev
+        .destroy(DestroyBdevRequest {
+            uri: uri.to_string(),
+        })
+        .await
+        .unwrap();
+
+    let entries = list_entries(&mut hdl, "BdevRpc").await;
+    let methods = entries
+        .iter()
+        .map(|e| e.method.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        methods,
+        vec!["mayastor.v1.BdevRpc/Create", "mayastor.v1.BdevRpc/Destroy"]
+    );
+    assert!(entries.iter().all(|e| e.args.contains(uri)), "{entries:?}");
+
+    // The secrets of the JSON-RPC parameters are redacted.
+    hdl.json
+        .json_rpc_call(JsonRpcRequest {
+            method: "bdev_get_bdevs".to_string(),
+            params: r#"{"name": "disk2", "dhchap_key": "key-secret"}"#
+                .to_string(),
+        })
+        .await
+        .unwrap_err();
+
+    let entries = list_entries(&mut hdl, "JsonRpc").await;
+    assert_eq!(entries.len(), 1, "{entries:?}");
+    let args = &entries[0].args;
+    assert!(args.contains("bdev_get_bdevs"), "{args}");
+    assert!(args.contains("disk2"), "{args}");
+    assert!(!args.contains("key-secret"), "{args}");
+}
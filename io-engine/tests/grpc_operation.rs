@@ -0,0 +1,125 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::compose::{
+    rpc::v1::{
+        operation::{
+            CancelOperationRequest,
+            GetOperationRequest,
+            Operation,
+            OperationState,
+        },
+        pool::{CreatePoolRequest, Pool},
+        GrpcConnect,
+        RpcHandle,
+    },
+    Builder,
+};
+use prost::Message;
+use tonic::{Code, Request};
+
+const POOL_NAME: &str = "pool0";
+const POOL_UUID: &str = "40baf8b5-6256-4f29-b073-61ebf67d9b91";
+
+fn create_pool_request(id: &str, disk: &str) -> Request<CreatePoolRequest> {
+    let mut request = Request::new(CreatePoolRequest {
+        name: POOL_NAME.to_string(),
+        uuid: Some(POOL_UUID.to_string()),
+        pooltype: 0,
+        disks: vec![disk.to_string()],
+        cluster_size: None,
+    });
+    request
+        .metadata_mut()
+        .insert("operation-id", id.parse().unwrap());
+    request
+}
+
+async fn wait_operation(hdl: &mut RpcHandle, id: &str) -> Operation {
+    for _ in 0 .. 100 {
+        let op = hdl
+            .operation
+            .get_operation(GetOperationRequest {
+                id: id.to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        if op.finished.is_some() {
+            return op;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Operation '{id}' did not finish");
+}
+
+#[tokio::test]
+async fn grpc_operation_create_pool() {
+    common::composer_init();
+
+    let compose = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_dbg("ms1")
+        .build()
+        .await
+        .unwrap();
+
+    let grpc = GrpcConnect::new(&compose);
+    let mut hdl = grpc.grpc_handle("ms1").await.unwrap();
+
+    // The call returns at once, along with the operation ID.
+    let response = hdl
+        .pool
+        .create_pool(create_pool_request("op-1", "malloc:///disk0?size_mb=64"))
+        .await
+        .unwrap();
+    assert_eq!(response.metadata().get("operation-id").unwrap(), "op-1");
+
+    let op = wait_operation(&mut hdl, "op-1").await;
+    assert_eq!(op.state, OperationState::OperationSucceeded as i32);
+    assert_eq!(op.method, "create_pool");
+    let pool = Pool::decode(op.response.as_slice()).unwrap();
+    assert_eq!(pool.name, POOL_NAME);
+    assert_eq!(pool.uuid, POOL_UUID);
+    // The progress is left at the last stage the operation got to.
+    assert_eq!(op.progress.unwrap().stage, "creating the pool");
+
+    // Operation IDs are unique.
+    let err = hdl
+        .pool
+        .create_pool(create_pool_request("op-1", "malloc:///disk0?size_mb=64"))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::AlreadyExists);
+
+    // A failed operation reports its error.
+    hdl.pool
+        .create_pool(create_pool_request("op-2", "bogus:///disk1"))
+        .await
+        .unwrap();
+    let op = wait_operation(&mut hdl, "op-2").await;
+    assert_eq!(op.state, OperationState::OperationFailed as i32);
+    assert!(op.error.is_some());
+
+    // A finished operation cannot be cancelled.
+    let err = hdl
+        .operation
+        .cancel_operation(CancelOperationRequest {
+            id: "op-1".to_string(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::FailedPrecondition);
+
+    let err = hdl
+        .operation
+        .get_operation(GetOperationRequest {
+            id: "op-3".to_string(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+}
@@ -0,0 +1,95 @@
+pub mod common;
+
+use common::compose::{
+    rpc::v1::{
+        pool::{CreatePoolRequest, DestroyPoolRequest},
+        GrpcConnect,
+    },
+    Builder,
+};
+use tonic::{Code, Request};
+
+const POOL_UUID: &str = "40baf8b5-6256-4f29-b073-61ebf67d9b91";
+
+fn with_request_id<T>(message: T, id: &str) -> Request<T> {
+    let mut request = Request::new(message);
+    request
+        .metadata_mut()
+        .insert("request-id", id.parse().unwrap());
+    request
+}
+
+fn create_pool(name: &str) -> CreatePoolRequest {
+    CreatePoolRequest {
+        name: name.to_string(),
+        uuid: Some(POOL_UUID.to_string()),
+        pooltype: 0,
+        disks: vec!["malloc:///disk0?size_mb=64".to_string()],
+        cluster_size: None,
+    }
+}
+
+#[tokio::test]
+async fn grpc_request_cache() {
+    common::composer_init();
+
+    let compose = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_dbg("ms1")
+        .build()
+        .await
+        .unwrap();
+
+    let grpc = GrpcConnect::new(&compose);
+    let mut hdl = grpc.grpc_handle("ms1").await.unwrap();
+
+    let pool = hdl
+        .pool
+        .create_pool(with_request_id(create_pool("pool0"), "req-1"))
+        .await
+        .unwrap()
+        .into_inner();
+
+    // A retry returns the original result, instead of creating the pool
+    // again.
+    let retry = hdl
+        .pool
+        .create_pool(with_request_id(create_pool("pool0"), "req-1"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(retry, pool);
+
+    // Without a request ID, the call runs again.
+    let err = hdl
+        .pool
+        .create_pool(create_pool("pool0"))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::AlreadyExists);
+
+    // A request ID cannot be reused by another request.
+    let err = hdl
+        .pool
+        .create_pool(with_request_id(create_pool("pool1"), "req-1"))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+
+    let destroy = DestroyPoolRequest {
+        name: "pool0".to_string(),
+        uuid: Some(POOL_UUID.to_string()),
+    };
+    hdl.pool
+        .destroy_pool(with_request_id(destroy.clone(), "req-2"))
+        .await
+        .unwrap();
+    hdl.pool
+        .destroy_pool(with_request_id(destroy.clone(), "req-2"))
+        .await
+        .unwrap();
+    let err = hdl.pool.destroy_pool(destroy).await.unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+}
@@ -0,0 +1,212 @@
+use once_cell::sync::OnceCell;
+use std::time::Duration;
+
+pub mod common;
+use common::{bdev_io, compose::MayastorTest};
+use io_engine::{
+    bdev::nexus::{
+        nexus_create_v2,
+        nexus_lookup_mut,
+        CachePolicy,
+        CacheState,
+        NexusCacheConfig,
+        NexusCacheStats,
+        NexusLayout,
+        NexusNvmeParams,
+    },
+    core::MayastorCliArgs,
+    sleep::mayastor_sleep,
+};
+
+static MAYASTOR: OnceCell<MayastorTest> = OnceCell::new();
+
+fn get_ms() -> &'static MayastorTest<'static> {
+    MAYASTOR.get_or_init(|| MayastorTest::new(MayastorCliArgs::default()))
+}
+
+const NEXUS_SIZE: u64 = 16 * 1024 * 1024;
+
+fn malloc_uri(name: &str) -> String {
+    format!("malloc:///{name}?size_mb=32")
+}
+
+/// Creates a file backing a persistent cache device, and returns its uri.
+fn aio_uri(path: &str) -> String {
+    common::delete_file(&[path.to_string()]);
+    common::truncate_file_bytes(path, 32 * 1024 * 1024);
+    format!("aio://{path}?blk_size=512")
+}
+
+async fn create_cached_nexus(
+    name: &str,
+    uuid: &str,
+    children: &[String],
+    cache: NexusCacheConfig,
+) -> Result<(), io_engine::bdev::nexus::Error> {
+    nexus_create_v2(
+        name,
+        NEXUS_SIZE,
+        uuid,
+        NexusNvmeParams::default(),
+        NexusLayout::Mirror,
+        children,
+        None,
+        Some(cache.with_destage_interval(Duration::from_millis(100))),
+    )
+    .await
+}
+
+/// Waits for the cache stats of the nexus to satisfy the given condition.
+async fn wait_cache<F>(name: &str, cond: F) -> NexusCacheStats
+where
+    F: Fn(&NexusCacheStats) -> bool,
+{
+    for _ in 0 .. 100 {
+        let stats = nexus_lookup_mut(name).unwrap().cache_stats().unwrap();
+        if cond(&stats) {
+            return stats;
+        }
+        mayastor_sleep(Duration::from_millis(100)).await.ok();
+    }
+    panic!("Cache of nexus '{name}' did not get there in time");
+}
+
+#[tokio::test]
+async fn nexus_cache_write_back() {
+    let ms = get_ms();
+
+    ms.spawn(async move {
+        let children = vec![malloc_uri("wb_c0"), malloc_uri("wb_c1")];
+        let cache = aio_uri("/tmp/wb_cache.img");
+
+        create_cached_nexus(
+            "wb_nexus",
+            "a6e21b5a-6cf5-4e0c-a2a1-5fe3ac18a0e1",
+            &children,
+            NexusCacheConfig::new(&cache, CachePolicy::WriteBack),
+        )
+        .await
+        .unwrap();
+        bdev_io::write_some("wb_nexus", 0, 16, 0xaa).await.unwrap();
+
+        // Filled from the children, then switched to write-back.
+        let stats = wait_cache("wb_nexus", |s| s.write_back).await;
+        assert_eq!(stats.state, CacheState::Ready, "{stats:?}");
+        assert!(!stats.replayed, "{stats:?}");
+        bdev_io::read_some("wb_nexus", 0, 16, 0xaa).await.unwrap();
+
+        // Acknowledged from the cache, then destaged to the children.
+        bdev_io::write_some("wb_nexus", 1024 * 1024, 16, 0x55)
+            .await
+            .unwrap();
+        bdev_io::read_some("wb_nexus", 1024 * 1024, 16, 0x55)
+            .await
+            .unwrap();
+        let stats = wait_cache("wb_nexus", |s| s.destaged_blocks > 0).await;
+        assert_eq!(stats.pending_blocks, 0, "{stats:?}");
+        assert_eq!(stats.failed_destages, 0, "{stats:?}");
+
+        let nex = nexus_lookup_mut("wb_nexus").unwrap();
+        nex.destroy().await.unwrap();
+        common::delete_file(&["/tmp/wb_cache.img".to_string()]);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn nexus_cache_write_through() {
+    let ms = get_ms();
+
+    ms.spawn(async move {
+        let children = vec![malloc_uri("wt_c0")];
+        let cache = malloc_uri("wt_cache");
+
+        create_cached_nexus(
+            "wt_nexus",
+            "0b6c2a45-6f4e-45ce-97b8-2c1cb4e4f2f6",
+            &children,
+            NexusCacheConfig::new(&cache, CachePolicy::WriteThrough),
+        )
+        .await
+        .unwrap();
+        bdev_io::write_some("wt_nexus", 0, 16, 0xaa).await.unwrap();
+
+        let stats =
+            wait_cache("wt_nexus", |s| s.state == CacheState::Ready).await;
+        assert!(!stats.write_back, "{stats:?}");
+
+        // Served from the cache, which got the writes made while filling.
+        bdev_io::write_some("wt_nexus", 1024 * 1024, 16, 0x55)
+            .await
+            .unwrap();
+        bdev_io::read_some("wt_nexus", 0, 16, 0xaa).await.unwrap();
+        bdev_io::read_some("wt_nexus", 1024 * 1024, 16, 0x55)
+            .await
+            .unwrap();
+        assert_eq!(
+            nexus_lookup_mut("wt_nexus")
+                .unwrap()
+                .cache_stats()
+                .unwrap()
+                .destages,
+            0
+        );
+
+        nexus_lookup_mut("wt_nexus")
+            .unwrap()
+            .destroy()
+            .await
+            .unwrap();
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn nexus_cache_invalid() {
+    let ms = get_ms();
+
+    ms.spawn(async move {
+        let children = vec![malloc_uri("iv_c0"), malloc_uri("iv_c1")];
+
+        // A child can't cache the nexus.
+        let err = create_cached_nexus(
+            "iv_nexus",
+            "53c4d9c4-7f59-4a5e-9e5c-0a7ad0b6a3a2",
+            &children,
+            NexusCacheConfig::new(&children[1], CachePolicy::WriteBack),
+        )
+        .await
+        .unwrap_err();
+        println!("expected error: {err}");
+
+        // A write-back cache must be persistent.
+        let err = create_cached_nexus(
+            "iv_nexus",
+            "53c4d9c4-7f59-4a5e-9e5c-0a7ad0b6a3a2",
+            &children,
+            NexusCacheConfig::new(
+                &malloc_uri("iv_cache"),
+                CachePolicy::WriteBack,
+            ),
+        )
+        .await
+        .unwrap_err();
+        println!("expected error: {err}");
+
+        // The cache must hold the whole nexus.
+        let err = create_cached_nexus(
+            "iv_nexus",
+            "53c4d9c4-7f59-4a5e-9e5c-0a7ad0b6a3a2",
+            &children,
+            NexusCacheConfig::new(
+                "malloc:///iv_cache?size_mb=8",
+                CachePolicy::WriteThrough,
+            ),
+        )
+        .await
+        .unwrap_err();
+        println!("expected error: {err}");
+        assert!(nexus_lookup_mut("iv_nexus").is_none());
+    })
+    .await;
+}
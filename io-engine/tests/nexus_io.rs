@@ -6,6 +6,7 @@ use io_engine::{
         nexus_create_v2,
         nexus_lookup,
         nexus_lookup_mut,
+        NexusLayout,
         NexusNvmeParams,
         NexusPauseState,
         NvmeAnaState,
@@ -392,8 +393,10 @@ async fn nexus_io_resv_acquire() {
                 32 * 1024 * 1024,
                 NEXUS_UUID,
                 nvme_params,
+                NexusLayout::Mirror,
                 &[format!("nvmf://{ip0}:8420/{HOSTNQN}:{REPL_UUID}")],
                 None,
+                None,
             )
             .await
             .unwrap();
@@ -588,8 +591,10 @@ async fn nexus_io_resv_preempt() {
                 32 * 1024 * 1024,
                 NEXUS_UUID,
                 nvme_params,
+                NexusLayout::Mirror,
                 &[format!("nvmf://{ip0}:8420/{HOSTNQN}:{REPL_UUID}")],
                 None,
+                None,
             )
             .await
             .unwrap();
@@ -864,8 +869,10 @@ async fn nexus_io_resv_preempt_tabled() {
                         32 * 1024 * 1024,
                         NEXUS_UUID,
                         nvme_params,
+                        NexusLayout::Mirror,
                         &[format!("nvmf://{ip0}:8420/{HOSTNQN}:{REPL_UUID}")],
                         None,
+                        None,
                     )
                     .await
                     .unwrap();
@@ -0,0 +1,175 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            initiator::ListInitiatorsRequest,
+            nexus::{NvmeReservation, SetNexusNvmeParamsRequest},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::Code;
+
+const POOL_SIZE: u64 = 60;
+const REPL_SIZE: u64 = 40;
+const RESV_KEY_1: u64 = 0xabcd_0001;
+const RESV_KEY_2: u64 = 0xabcd_0002;
+
+/// Returns the reservation key of the host of the replica, whether it holds
+/// the reservation, and the reservation type.
+async fn replica_reservation(
+    rpc: &SharedRpcHandle,
+) -> (Option<u64>, bool, i32) {
+    let subsystems = rpc
+        .lock()
+        .await
+        .initiator
+        .list_initiators(ListInitiatorsRequest {
+            nqn: None,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .subsystems;
+    assert_eq!(subsystems.len(), 1);
+    let ss = &subsystems[0];
+    assert_eq!(ss.initiators.len(), 1);
+
+    let host = &ss.initiators[0];
+    (
+        host.reservation_key,
+        host.reservation_holder,
+        ss.reservation.as_ref().unwrap().rtype,
+    )
+}
+
+#[tokio::test]
+async fn nexus_nvme_params() {
+    common::composer_init();
+
+    std::env::set_var("NEXUS_NVMF_RESV_ENABLE", "1");
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine")
+                .with_env("NEXUS_NVMF_RESV_ENABLE", "1"),
+        )
+        .add_container_bin(
+            "ms_nex",
+            Binary::from_dbg("io-engine")
+                .with_env("NEXUS_NVMF_RESV_ENABLE", "1"),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+    let ms_nex = conn.grpc_handle_shared("ms_nex").await.unwrap();
+
+    let mut pool_0 = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+
+    let mut repl_0 = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool_0)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+
+    pool_0.create().await.unwrap();
+    repl_0.create().await.unwrap();
+    repl_0.share().await.unwrap();
+
+    let mut nex_0 = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_resv_key(RESV_KEY_1)
+        .with_resv_type(NvmeReservation::ExclusiveAccess)
+        .with_replica(&repl_0);
+
+    nex_0.create().await.unwrap();
+    nex_0.publish().await.unwrap();
+
+    let (key, holder, rtype) = replica_reservation(&ms_0).await;
+    assert_eq!(key, Some(RESV_KEY_1));
+    assert!(holder);
+    assert_eq!(rtype, NvmeReservation::ExclusiveAccess as i32);
+
+    // Change the key and the type of the reservation of the live nexus.
+    nex_0
+        .set_nvme_params(SetNexusNvmeParamsRequest {
+            resv_key: Some(RESV_KEY_2),
+            resv_type: Some(NvmeReservation::WriteExclusive as i32),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let (key, holder, rtype) = replica_reservation(&ms_0).await;
+    assert_eq!(key, Some(RESV_KEY_2));
+    assert!(holder);
+    assert_eq!(rtype, NvmeReservation::WriteExclusive as i32);
+
+    // Change the controller ID range of the published nexus.
+    nex_0
+        .set_nvme_params(SetNexusNvmeParamsRequest {
+            min_cntl_id: Some(100),
+            max_cntl_id: Some(200),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    // An invalid controller ID range is rejected, and the reservation kept.
+    let err = nex_0
+        .set_nvme_params(SetNexusNvmeParamsRequest {
+            min_cntl_id: Some(200),
+            max_cntl_id: Some(100),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+
+    // So is a controller ID which doesn't fit in 16 bits, rather than being
+    // truncated into a valid one.
+    let err = nex_0
+        .set_nvme_params(SetNexusNvmeParamsRequest {
+            min_cntl_id: Some(0x1_0064),
+            max_cntl_id: Some(200),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+
+    let (key, holder, _) = replica_reservation(&ms_0).await;
+    assert_eq!(key, Some(RESV_KEY_2));
+    assert!(holder);
+
+    let err = NexusBuilder::new(ms_nex.clone())
+        .with_new_uuid()
+        .set_nvme_params(SetNexusNvmeParamsRequest {
+            resv_key: Some(RESV_KEY_1),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+}
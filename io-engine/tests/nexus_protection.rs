@@ -0,0 +1,164 @@
+use once_cell::sync::OnceCell;
+
+pub mod common;
+use common::{bdev_io, compose::MayastorTest};
+use io_engine::{
+    bdev::nexus::{
+        nexus_create_v2,
+        nexus_lookup_mut,
+        NexusLayout,
+        NexusNvmeParams,
+        NexusPiPolicy,
+    },
+    core::{DifType, MayastorCliArgs},
+    lvs::{Lvs, LvsError},
+    pool_backend::PoolArgs,
+};
+
+static MAYASTOR: OnceCell<MayastorTest> = OnceCell::new();
+
+fn get_ms() -> &'static MayastorTest<'static> {
+    MAYASTOR.get_or_init(|| MayastorTest::new(MayastorCliArgs::default()))
+}
+
+const NEXUS_SIZE: u64 = 16 * 1024 * 1024;
+
+fn malloc_pi_uri(name: &str) -> String {
+    format!("malloc:///{name}?size_mb=32&md_size=8&dif_type=1")
+}
+
+async fn create_pi_nexus(
+    name: &str,
+    uuid: &str,
+    children: &[String],
+    pi_policy: NexusPiPolicy,
+) -> Result<(), io_engine::bdev::nexus::Error> {
+    let mut nvme_params = NexusNvmeParams::default();
+    nvme_params.set_pi_policy(pi_policy);
+
+    nexus_create_v2(
+        name,
+        NEXUS_SIZE,
+        uuid,
+        nvme_params,
+        NexusLayout::Mirror,
+        children,
+        None,
+        None,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn nexus_protection_generate() {
+    let ms = get_ms();
+
+    ms.spawn(async move {
+        let children = vec![malloc_pi_uri("gen_c0"), malloc_pi_uri("gen_c1")];
+
+        create_pi_nexus(
+            "gen_nexus",
+            "5d3c0f62-2a8e-4f0f-9d4b-8d7b5f0c2a11",
+            &children,
+            NexusPiPolicy::Generate,
+        )
+        .await
+        .unwrap();
+
+        let nex = nexus_lookup_mut("gen_nexus").unwrap();
+        let pi = nex.protection_info();
+        assert!(pi.is_enabled(), "{pi}");
+        assert_eq!(pi.dif_type, DifType::Type1);
+        assert_eq!(pi.md_len, 8);
+
+        // The children check the reference tags of the blocks written at
+        // their own LBAs, past the nexus metadata.
+        bdev_io::write_some("gen_nexus", 0, 8, 0xaa).await.unwrap();
+        bdev_io::read_some("gen_nexus", 0, 8, 0xaa).await.unwrap();
+
+        nexus_lookup_mut("gen_nexus")
+            .unwrap()
+            .destroy()
+            .await
+            .unwrap();
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn nexus_protection_verify() {
+    let ms = get_ms();
+
+    ms.spawn(async move {
+        let children = vec![malloc_pi_uri("ver_c0")];
+
+        create_pi_nexus(
+            "ver_nexus",
+            "0f7e5a2c-9b61-4c1e-8a3d-6e2f4b9c7d05",
+            &children,
+            NexusPiPolicy::Verify,
+        )
+        .await
+        .unwrap();
+
+        // The blocks are written without valid protection information.
+        let err = bdev_io::write_some("ver_nexus", 0, 8, 0xaa)
+            .await
+            .unwrap_err();
+        println!("expected error: {err}");
+
+        nexus_lookup_mut("ver_nexus")
+            .unwrap()
+            .destroy()
+            .await
+            .unwrap();
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn nexus_protection_mixed() {
+    let ms = get_ms();
+
+    ms.spawn(async move {
+        let children = vec![
+            malloc_pi_uri("mix_c0"),
+            "malloc:///mix_c1?size_mb=32&md_size=8".to_string(),
+        ];
+
+        let err = create_pi_nexus(
+            "mix_nexus",
+            "8b2d6e41-3f0a-47c9-b5e8-1c9a0d3f6e27",
+            &children,
+            NexusPiPolicy::Generate,
+        )
+        .await
+        .unwrap_err();
+        println!("expected error: {err}");
+        assert!(nexus_lookup_mut("mix_nexus").is_none());
+    })
+    .await;
+}
+
+/// Pools have no room for the protection information of the blocks, so
+/// replicas with protection information are out of scope.
+#[tokio::test]
+async fn pool_with_block_metadata() {
+    let ms = get_ms();
+
+    ms.spawn(async move {
+        let err = Lvs::create_or_import(PoolArgs {
+            name: "md_pool".to_string(),
+            disks: vec![malloc_pi_uri("md_disk")],
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(err, LvsError::UnsupportedProtectionInfo { .. }),
+            "{err}"
+        );
+        assert!(Lvs::lookup("md_pool").is_none());
+    })
+    .await;
+}
@@ -242,24 +242,24 @@ async fn rebuild_replica() {
             NexusRebuildJob::lookup(&get_dev(child))
                 .expect_err("rebuild job not created yet");
         }
-        let src = NexusRebuildJob::lookup(&get_dev(NUM_CHILDREN))
-            .expect("now the job should exist")
-            .src_uri()
-            .to_string();
+        let job = NexusRebuildJob::lookup(&get_dev(NUM_CHILDREN))
+            .expect("now the job should exist");
+        let src = job.src_uri().to_string();
 
+        // The job reads from all the healthy children.
+        assert_eq!(job.src_uris().len() as u64, NUM_CHILDREN);
         for child in 0 .. NUM_CHILDREN {
-            if get_dev(child) != src {
-                NexusRebuildJob::lookup_src(&get_dev(child))
-                    .iter()
-                    .filter(|s| s.dst_uri() != get_dev(child))
-                    .inspect(|&job| {
-                        error!(
-                            "Job {:?} should be associated with src child {}",
-                            job, child
-                        );
-                    })
-                    .any(|_| panic!("Should not have found any jobs!"));
-            }
+            NexusRebuildJob::lookup_src(&get_dev(child))
+                .iter()
+                .filter(|s| s.dst_uri() != get_dev(NUM_CHILDREN))
+                .inspect(|&job| {
+                    error!(
+                        "Job {:?} should not be associated with src child {}",
+                        job, child
+                    );
+                })
+                .any(|_| panic!("Should not have found any other jobs!"));
+            assert_eq!(NexusRebuildJob::lookup_src(&get_dev(child)).len(), 1);
         }
 
         assert_eq!(
@@ -325,6 +325,74 @@ async fn rebuild_replica() {
     .await;
 }
 
+/// Losing one of the sources of a multi-source rebuild leaves the rebuild
+/// going on from the remaining sources.
+#[tokio::test]
+async fn rebuild_replica_lost_source() {
+    const NUM_CHILDREN: u64 = 3;
+
+    test_ini("rebuild_replica_lost_source");
+
+    let ms = get_ms();
+
+    ms.spawn(async move {
+        nexus_create(NEXUS_SIZE, NUM_CHILDREN, true).await;
+        let mut nexus = nexus_lookup_mut(nexus_name()).unwrap();
+        nexus
+            .as_mut()
+            .add_child(&get_dev(NUM_CHILDREN), true)
+            .await
+            .unwrap();
+        let _ = nexus.start_rebuild(&get_dev(NUM_CHILDREN)).await;
+
+        wait_for_rebuild(
+            get_dev(NUM_CHILDREN),
+            RebuildState::Running,
+            Duration::from_secs(1),
+        )
+        .await;
+        nexus
+            .as_mut()
+            .pause_rebuild(&get_dev(NUM_CHILDREN))
+            .await
+            .unwrap();
+
+        nexus.as_mut().remove_child(&get_dev(1)).await.unwrap();
+
+        // The job is neither stopped nor restarted, and only the removed
+        // child is marked as failed.
+        let job = NexusRebuildJob::lookup(&get_dev(NUM_CHILDREN))
+            .expect("the job should still exist");
+        assert_eq!(job.state(), RebuildState::Paused);
+        let stats = job.stats().await;
+        for source in &stats.sources {
+            assert_eq!(source.failed, source.uri == get_dev(1));
+        }
+        assert_eq!(NexusRebuildJob::lookup_src(&get_dev(0)).len(), 1);
+
+        nexus
+            .as_mut()
+            .resume_rebuild(&get_dev(NUM_CHILDREN))
+            .await
+            .unwrap();
+    })
+    .await;
+
+    wait_for_replica_rebuild(&get_dev(0), &get_dev(NUM_CHILDREN)).await;
+
+    ms.spawn(async move {
+        let mut nexus = nexus_lookup_mut(nexus_name()).unwrap();
+        nexus
+            .as_mut()
+            .remove_child(&get_dev(NUM_CHILDREN))
+            .await
+            .unwrap();
+        nexus.destroy().await.unwrap();
+        test_fini();
+    })
+    .await;
+}
+
 #[tokio::test]
 async fn rebuild_bdev() {
     test_ini("rebuild_bdev");
@@ -0,0 +1,235 @@
+pub mod common;
+
+use io_engine_tests::{
+    compose::{
+        rpc::v1::{
+            nexus::{PauseRebuildRequest, RebuildJobState},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+        ComposeTest,
+    },
+    file_io::DataSize,
+    nexus::{test_write_to_nexus, NexusBuilder},
+    pool::PoolBuilder,
+    replica::{validate_replicas, ReplicaBuilder},
+};
+use std::time::Duration;
+
+const ETCD_IP: &str = "10.1.0.2";
+const ETCD_PORT: &str = "2379";
+const ETCD_PORT_2: &str = "2380";
+
+const DISK_NAME_0: &str = "/tmp/disk0.img";
+const DISK_NAME_1: &str = "/tmp/disk1.img";
+const DISK_SIZE: u64 = 2048;
+const REPL_SIZE: u64 = 1024;
+
+const NEXUS_NAME: &str = "nexus_0";
+const NEXUS_UUID: &str = "5c8e2ab9-1f93-4f30-8c0e-0a26ce3f84a1";
+
+/// Test cluster: two storage nodes and a nexus node, which saves its state to
+/// etcd.
+struct TestCluster {
+    test: Box<ComposeTest>,
+    etcd: etcd_client::Client,
+    repl_0: ReplicaBuilder,
+    repl_1: ReplicaBuilder,
+}
+
+impl TestCluster {
+    async fn create() -> Self {
+        for disk in [DISK_NAME_0, DISK_NAME_1] {
+            common::delete_file(&[disk.to_string()]);
+            common::truncate_file_bytes(disk, DISK_SIZE * 1024 * 1024);
+        }
+
+        let etcd_endpoint = format!("http://{ETCD_IP}:{ETCD_PORT}");
+
+        let test = Box::new(
+            Builder::new()
+                .name("cargo-test")
+                .network("10.1.0.0/16")
+                .unwrap()
+                .add_container_spec(
+                    common::compose::ContainerSpec::from_binary(
+                        "etcd",
+                        Binary::from_path(env!("ETCD_BIN")).with_args(vec![
+                            "--data-dir",
+                            "/tmp/etcd-data",
+                            "--advertise-client-urls",
+                            &etcd_endpoint,
+                            "--listen-client-urls",
+                            &etcd_endpoint,
+                        ]),
+                    )
+                    .with_portmap(ETCD_PORT, ETCD_PORT)
+                    .with_portmap(ETCD_PORT_2, ETCD_PORT_2),
+                )
+                .add_container_bin(
+                    "ms_0",
+                    Binary::from_dbg("io-engine")
+                        .with_direct_bind(DISK_NAME_0)
+                        .with_args(vec!["-l", "1", "-Fcolor,nodate,host"]),
+                )
+                .add_container_bin(
+                    "ms_1",
+                    Binary::from_dbg("io-engine")
+                        .with_direct_bind(DISK_NAME_1)
+                        .with_args(vec!["-l", "2", "-Fcolor,nodate,host"]),
+                )
+                .add_container_bin(
+                    "ms_nex",
+                    Binary::from_dbg("io-engine").with_args(vec![
+                        "-l",
+                        "3,4",
+                        "-Fcolor,nodate,host",
+                        "-p",
+                        &etcd_endpoint,
+                    ]),
+                )
+                .with_clean(true)
+                .with_logs(true)
+                .build()
+                .await
+                .unwrap(),
+        );
+
+        let etcd = etcd_client::Client::connect([&etcd_endpoint], None)
+            .await
+            .unwrap();
+
+        let mut repls = Vec::new();
+        for (idx, disk) in [DISK_NAME_0, DISK_NAME_1].iter().enumerate() {
+            let ms = GrpcConnect::new(&test)
+                .grpc_handle_shared(&format!("ms_{idx}"))
+                .await
+                .unwrap();
+
+            let mut pool = PoolBuilder::new(ms.clone())
+                .with_name(&format!("pool_{idx}"))
+                .with_new_uuid()
+                .with_bdev(&format!("aio://{disk}?blk_size=512"));
+
+            let mut repl = ReplicaBuilder::new(ms)
+                .with_pool(&pool)
+                .with_name(&format!("repl_{idx}"))
+                .with_new_uuid()
+                .with_thin(false)
+                .with_size_mb(REPL_SIZE);
+
+            pool.create().await.unwrap();
+            repl.create().await.unwrap();
+            repl.share().await.unwrap();
+            repls.push(repl);
+        }
+        let repl_1 = repls.pop().unwrap();
+        let repl_0 = repls.pop().unwrap();
+
+        Self {
+            test,
+            etcd,
+            repl_0,
+            repl_1,
+        }
+    }
+
+    async fn ms_nex(&self) -> SharedRpcHandle {
+        GrpcConnect::new(&self.test)
+            .grpc_handle_shared("ms_nex")
+            .await
+            .unwrap()
+    }
+
+    /// Creates and publishes the nexus with the first replica only.
+    async fn create_nexus(&self) -> NexusBuilder {
+        let mut nex = NexusBuilder::new(self.ms_nex().await)
+            .with_name(NEXUS_NAME)
+            .with_uuid(NEXUS_UUID)
+            .with_size_mb(REPL_SIZE)
+            .with_replica(&self.repl_0);
+
+        nex.create().await.unwrap();
+        nex.publish().await.unwrap();
+        nex
+    }
+
+    /// Returns the number of rebuild checkpoints saved for the nexus.
+    async fn count_checkpoints(&mut self) -> usize {
+        let opts = etcd_client::GetOptions::new().with_prefix();
+        self.etcd
+            .get(format!("{NEXUS_UUID}/rebuild/"), Some(opts))
+            .await
+            .unwrap()
+            .kvs()
+            .len()
+    }
+}
+
+#[tokio::test]
+/// Tests that a rebuild interrupted by a restart of the nexus node is resumed
+/// from its checkpoint by the next nexus of the volume, including the writes
+/// made to that nexus before the child is added back.
+///
+/// 1. Create a nexus with 1 replica, and write data.
+/// 2. Add the 2nd replica, and pause its rebuild.
+/// 3. Restart the nexus's io-engine.
+/// 4. Recreate the nexus with 1 replica, and write data again.
+/// 5. Add the 2nd replica back, and wait for its rebuild to complete.
+/// 6. Verify that the rebuild was resumed and that the replicas match.
+async fn nexus_rebuild_resume() {
+    common::composer_init();
+
+    let mut cluster = TestCluster::create().await;
+
+    let nex = cluster.create_nexus().await;
+    test_write_to_nexus(&nex, DataSize::from_mb(0), 64, DataSize::from_mb(1))
+        .await
+        .unwrap();
+
+    // Pause the rebuild right away, which checkpoints its progress.
+    nex.add_replica(&cluster.repl_1, false).await.unwrap();
+    nex.rpc()
+        .lock()
+        .await
+        .nexus
+        .pause_rebuild(PauseRebuildRequest {
+            nexus_uuid: nex.uuid(),
+            uri: nex.replica_uri(&cluster.repl_1),
+        })
+        .await
+        .unwrap();
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(cluster.count_checkpoints().await, 1);
+
+    // Restart the io-engine of the nexus.
+    cluster.test.kill("ms_nex").await.unwrap();
+    cluster.test.start("ms_nex").await.unwrap();
+
+    // Write to blocks which were rebuilt before the restart.
+    let nex = cluster.create_nexus().await;
+    test_write_to_nexus(&nex, DataSize::from_mb(0), 4, DataSize::from_mb(1))
+        .await
+        .unwrap();
+
+    nex.add_replica(&cluster.repl_1, false).await.unwrap();
+    nex.wait_children_online(Duration::from_secs(60))
+        .await
+        .unwrap();
+
+    let hist = nex.get_rebuild_history().await.unwrap();
+    let rec = hist
+        .iter()
+        .rev()
+        .find(|r| r.child_uri == nex.replica_uri(&cluster.repl_1))
+        .unwrap();
+    assert_eq!(rec.state, RebuildJobState::Completed as i32);
+    assert!(rec.blocks_transferred < rec.blocks_total);
+
+    assert_eq!(cluster.count_checkpoints().await, 0);
+
+    validate_replicas(&[cluster.repl_0.clone(), cluster.repl_1.clone()]).await;
+}
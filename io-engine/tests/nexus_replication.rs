@@ -0,0 +1,180 @@
+use once_cell::sync::OnceCell;
+use std::time::Duration;
+
+pub mod common;
+use common::{bdev_io, compose::MayastorTest};
+use io_engine::{
+    bdev::{
+        device_create,
+        device_destroy,
+        nexus::{nexus_create, nexus_lookup_mut},
+    },
+    core::MayastorCliArgs,
+    rebuild::{ReplicationJob, ReplicationState, ReplicationStats},
+    sleep::mayastor_sleep,
+};
+
+static MAYASTOR: OnceCell<MayastorTest> = OnceCell::new();
+
+fn get_ms() -> &'static MayastorTest<'static> {
+    MAYASTOR.get_or_init(|| MayastorTest::new(MayastorCliArgs::default()))
+}
+
+const NEXUS_SIZE: u64 = 16 * 1024 * 1024;
+
+fn malloc_uri(name: &str) -> String {
+    format!("malloc:///{name}?size_mb=32")
+}
+
+/// Waits for the replication stats to satisfy the given condition.
+async fn wait_stats<F>(job: &ReplicationJob, cond: F) -> ReplicationStats
+where
+    F: Fn(&ReplicationStats) -> bool,
+{
+    for _ in 0 .. 100 {
+        let stats = job.stats().await;
+        if cond(&stats) {
+            return stats;
+        }
+        mayastor_sleep(Duration::from_millis(100)).await.ok();
+    }
+    panic!("Replication {job:?} did not get there in time");
+}
+
+#[tokio::test]
+async fn nexus_replicate_and_promote() {
+    let ms = get_ms();
+
+    ms.spawn(async move {
+        let children = vec![malloc_uri("rp_c0"), malloc_uri("rp_c1")];
+        // Stands for a replica exported by the io-engine of a remote site.
+        let dst_uri = malloc_uri("rp_dr");
+
+        nexus_create("repl_nexus", NEXUS_SIZE, None, &children)
+            .await
+            .unwrap();
+        device_create(&dst_uri).await.unwrap();
+        bdev_io::write_some("repl_nexus", 0, 16, 0xaa)
+            .await
+            .unwrap();
+
+        // Children can't be replicated to.
+        let err = ReplicationJob::builder()
+            .build("repl_nexus", &children[1])
+            .unwrap_err();
+        println!("expected error: {err}");
+
+        let job = ReplicationJob::builder()
+            .with_rpo(Duration::from_secs(1))
+            .build("repl_nexus", &dst_uri)
+            .unwrap()
+            .store()
+            .unwrap();
+
+        // Only one replication per nexus.
+        let err = ReplicationJob::builder()
+            .build("repl_nexus", &malloc_uri("rp_dr2"))
+            .unwrap()
+            .store()
+            .unwrap_err();
+        println!("expected error: {err}");
+
+        job.start().unwrap();
+        let stats =
+            wait_stats(&job, |s| s.state == ReplicationState::Replicating)
+                .await;
+        assert!(stats.blocks_shipped > 0, "{stats:?}");
+
+        // Written after the first sync, shipped by a later one.
+        let syncs = stats.syncs;
+        bdev_io::write_some("repl_nexus", 1024 * 1024, 16, 0x55)
+            .await
+            .unwrap();
+        let stats = wait_stats(&job, |s| s.syncs > syncs + 1).await;
+        assert_eq!(stats.pending_blocks, 0, "{stats:?}");
+        assert!(stats.lag <= Duration::from_secs(1), "{stats:?}");
+        assert!(!stats.rpo_violated, "{stats:?}");
+        // Nothing was written during the syncs.
+        assert!(stats.consistent_time.is_some(), "{stats:?}");
+
+        // Written last, shipped by the promotion.
+        bdev_io::write_some("repl_nexus", 2 * 1024 * 1024, 16, 0x66)
+            .await
+            .unwrap();
+        job.promote(false).await.unwrap();
+        assert_eq!(job.state(), ReplicationState::Promoted);
+        assert!(job.promote(false).await.is_err());
+
+        // The nexus is live again once promoted.
+        bdev_io::read_some("repl_nexus", 2 * 1024 * 1024, 16, 0x66)
+            .await
+            .unwrap();
+
+        // A nexus created over the promoted target holds the same data.
+        nexus_create("dr_nexus", NEXUS_SIZE, None, &[dst_uri.clone()])
+            .await
+            .unwrap();
+        bdev_io::read_some("dr_nexus", 0, 16, 0xaa).await.unwrap();
+        bdev_io::read_some("dr_nexus", 1024 * 1024, 16, 0x55)
+            .await
+            .unwrap();
+        bdev_io::read_some("dr_nexus", 2 * 1024 * 1024, 16, 0x66)
+            .await
+            .unwrap();
+
+        ReplicationJob::remove("repl_nexus").unwrap();
+        for name in ["repl_nexus", "dr_nexus"] {
+            nexus_lookup_mut(name).unwrap().destroy().await.unwrap();
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn nexus_replication_stop() {
+    let ms = get_ms();
+
+    ms.spawn(async move {
+        let dst_uri = malloc_uri("st_dr");
+
+        nexus_create("stop_nexus", NEXUS_SIZE, None, &[malloc_uri("st_c0")])
+            .await
+            .unwrap();
+
+        let job = ReplicationJob::builder()
+            .build("stop_nexus", &dst_uri)
+            .unwrap()
+            .store()
+            .unwrap();
+        job.start().unwrap();
+        wait_stats(&job, |s| s.state == ReplicationState::Replicating).await;
+
+        job.stop().await;
+        let stats = job.stats().await;
+        assert_eq!(stats.state, ReplicationState::Stopped, "{stats:?}");
+        assert!(job.promote(true).await.is_err());
+
+        // The target created by the replication is closed once stopped.
+        device_create(&dst_uri).await.unwrap();
+        device_destroy(&dst_uri).await.unwrap();
+
+        // The nexus can be replicated again.
+        ReplicationJob::remove("stop_nexus").unwrap();
+        let job = ReplicationJob::builder()
+            .build("stop_nexus", &dst_uri)
+            .unwrap()
+            .store()
+            .unwrap();
+        job.start().unwrap();
+        wait_stats(&job, |s| s.state == ReplicationState::Replicating).await;
+        job.stop().await;
+        ReplicationJob::remove("stop_nexus").unwrap();
+
+        nexus_lookup_mut("stop_nexus")
+            .unwrap()
+            .destroy()
+            .await
+            .unwrap();
+    })
+    .await;
+}
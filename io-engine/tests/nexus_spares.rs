@@ -0,0 +1,234 @@
+#![cfg(feature = "fault-injection")]
+
+use once_cell::sync::OnceCell;
+use std::time::Duration;
+
+pub mod common;
+use common::{bdev_io, compose::MayastorTest};
+use io_engine::{
+    bdev::nexus::{
+        nexus_create,
+        nexus_create_v2,
+        nexus_lookup_mut,
+        ChildState,
+        NexusLayout,
+        NexusNvmeParams,
+        NexusStatus,
+        SparePolicy,
+    },
+    core::{
+        fault_injection::{
+            add_fault_injection,
+            FaultDomain,
+            FaultIoOperation,
+            FaultIoStage,
+            FaultMethod,
+            InjectionBuilder,
+        },
+        MayastorCliArgs,
+    },
+    sleep::mayastor_sleep,
+};
+
+static MAYASTOR: OnceCell<MayastorTest> = OnceCell::new();
+
+fn get_ms() -> &'static MayastorTest<'static> {
+    MAYASTOR.get_or_init(|| MayastorTest::new(MayastorCliArgs::default()))
+}
+
+const NEXUS_NAME: &str = "spare_nexus";
+const NEXUS_SIZE: u64 = 16 * 1024 * 1024;
+
+fn malloc_uri(name: &str) -> String {
+    format!("malloc:///{name}?size_mb=32")
+}
+
+#[tokio::test]
+async fn nexus_retired_child_replaced_by_spare() {
+    let ms = get_ms();
+
+    ms.spawn(async move {
+        let children = vec![malloc_uri("sp_c0"), malloc_uri("sp_c1")];
+        let spares = vec![malloc_uri("sp_s0"), malloc_uri("sp_s1")];
+
+        nexus_create(NEXUS_NAME, NEXUS_SIZE, None, &children)
+            .await
+            .unwrap();
+        let nex = nexus_lookup_mut(NEXUS_NAME).unwrap();
+
+        // Spares must be unique and must not be children already.
+        let duplicates = [spares[0].clone(), spares[0].clone()];
+        assert!(nex
+            .set_spares(&duplicates, SparePolicy::Replace)
+            .await
+            .is_err());
+        assert!(nex
+            .set_spares(&[children[1].clone()], SparePolicy::Replace)
+            .await
+            .is_err());
+        nex.set_spares(&spares, SparePolicy::ReplaceAndRemove)
+            .await
+            .unwrap();
+        assert_eq!(nex.spares(), spares);
+
+        // Fail the writes to the first child, so that it gets retired.
+        add_fault_injection(
+            InjectionBuilder::default()
+                .with_domain(FaultDomain::NexusChild)
+                .with_device_name(nex.child_at(0).get_device_name().unwrap())
+                .with_io_operation(FaultIoOperation::Write)
+                .with_io_stage(FaultIoStage::Completion)
+                .with_method(FaultMethod::DATA_TRANSFER_ERROR)
+                .with_block_range(0 .. 1)
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+
+        bdev_io::write_blocks(NEXUS_NAME, 0, 1, 0xaa).await.unwrap();
+
+        // The first spare replaces the retired child, and gets rebuilt.
+        let mut replaced = false;
+        for _ in 0 .. 100 {
+            let nex = nexus_lookup_mut(NEXUS_NAME).unwrap();
+            replaced = !nex.contains_child_uri(&children[0])
+                && nex
+                    .lookup_child(&spares[0])
+                    .map_or(false, |c| c.state() == ChildState::Open);
+            if replaced {
+                break;
+            }
+            mayastor_sleep(Duration::from_millis(100)).await.ok();
+        }
+        assert!(replaced, "retired child must be replaced by a spare");
+
+        let nex = nexus_lookup_mut(NEXUS_NAME).unwrap();
+        assert_eq!(nex.status(), NexusStatus::Online);
+        assert_eq!(nex.spares(), vec![spares[1].clone()]);
+        bdev_io::read_some(NEXUS_NAME, 0, 2, 0xaa).await.unwrap();
+
+        nex.destroy().await.unwrap();
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn nexus_spares_manual_policy() {
+    let ms = get_ms();
+
+    ms.spawn(async move {
+        let children = vec![malloc_uri("mp_c0"), malloc_uri("mp_c1")];
+        let spare = malloc_uri("mp_s0");
+
+        nexus_create("manual_nexus", NEXUS_SIZE, None, &children)
+            .await
+            .unwrap();
+        let nex = nexus_lookup_mut("manual_nexus").unwrap();
+        nex.set_spares(&[spare.clone()], SparePolicy::Manual)
+            .await
+            .unwrap();
+
+        add_fault_injection(
+            InjectionBuilder::default()
+                .with_domain(FaultDomain::NexusChild)
+                .with_device_name(nex.child_at(0).get_device_name().unwrap())
+                .with_io_operation(FaultIoOperation::Write)
+                .with_io_stage(FaultIoStage::Completion)
+                .with_method(FaultMethod::DATA_TRANSFER_ERROR)
+                .with_block_range(0 .. 1)
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+
+        bdev_io::write_blocks("manual_nexus", 0, 1, 0xaa)
+            .await
+            .unwrap();
+        mayastor_sleep(Duration::from_secs(1)).await.ok();
+
+        // Spares are never used automatically with the manual policy.
+        let nex = nexus_lookup_mut("manual_nexus").unwrap();
+        assert_eq!(nex.status(), NexusStatus::Degraded);
+        assert!(!nex.contains_child_uri(&spare));
+        assert_eq!(nex.spares(), vec![spare.clone()]);
+
+        nex.destroy().await.unwrap();
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn nexus_striped_child_replaced_by_spare() {
+    let ms = get_ms();
+
+    ms.spawn(async move {
+        let children = (0 .. 4)
+            .map(|i| malloc_uri(&format!("st_c{i}")))
+            .collect::<Vec<_>>();
+        let spare = malloc_uri("st_s0");
+
+        nexus_create_v2(
+            "striped_spare_nexus",
+            48 * 1024 * 1024,
+            "6c1f0d2e-8b7a-4e39-a5d4-2f9e0c3b7a18",
+            NexusNvmeParams::default(),
+            NexusLayout::StripedMirror {
+                stripe_size: 64 * 1024,
+                copies: 2,
+            },
+            &children,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let nex = nexus_lookup_mut("striped_spare_nexus").unwrap();
+        nex.set_spares(&[spare.clone()], SparePolicy::ReplaceAndRemove)
+            .await
+            .unwrap();
+
+        // Fail the writes to the first child of the first stripe set.
+        add_fault_injection(
+            InjectionBuilder::default()
+                .with_domain(FaultDomain::NexusChild)
+                .with_device_name(nex.child_at(0).get_device_name().unwrap())
+                .with_io_operation(FaultIoOperation::Write)
+                .with_io_stage(FaultIoStage::Completion)
+                .with_method(FaultMethod::DATA_TRANSFER_ERROR)
+                .with_block_range(0 .. 1)
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+
+        bdev_io::write_blocks("striped_spare_nexus", 0, 1, 0xaa)
+            .await
+            .unwrap();
+
+        // The spare joins the stripe set of the retired child, even though
+        // all the stripe sets are complete.
+        let mut replaced = false;
+        for _ in 0 .. 100 {
+            let nex = nexus_lookup_mut("striped_spare_nexus").unwrap();
+            replaced = !nex.contains_child_uri(&children[0])
+                && nex.lookup_child(&spare).map_or(false, |c| {
+                    c.state() == ChildState::Open && c.stripe_set() == 0
+                });
+            if replaced {
+                break;
+            }
+            mayastor_sleep(Duration::from_millis(100)).await.ok();
+        }
+        assert!(replaced, "retired child must be replaced by the spare");
+
+        let nex = nexus_lookup_mut("striped_spare_nexus").unwrap();
+        assert_eq!(nex.status(), NexusStatus::Online);
+        assert!(nex.spares().is_empty());
+        bdev_io::read_some("striped_spare_nexus", 0, 2, 0xaa)
+            .await
+            .unwrap();
+
+        nex.destroy().await.unwrap();
+    })
+    .await;
+}
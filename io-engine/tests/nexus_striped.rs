@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use once_cell::sync::OnceCell;
+
+use common::{bdev_io, wait_for_rebuild, MayastorTest};
+use io_engine::{
+    bdev::nexus::{
+        nexus_create_v2,
+        nexus_lookup_mut,
+        Error,
+        NexusLayout,
+        NexusNvmeParams,
+        NexusStatus,
+    },
+    core::{MayastorCliArgs, UntypedBdev},
+    rebuild::RebuildState,
+};
+
+pub mod common;
+
+static MS: OnceCell<MayastorTest> = OnceCell::new();
+
+fn mayastor() -> &'static MayastorTest<'static> {
+    MS.get_or_init(|| MayastorTest::new(MayastorCliArgs::default()))
+}
+
+const NEXUS_NAME: &str = "striped_nexus";
+const NEXUS_UUID: &str = "a7b6c5d4-5c61-4d3e-9a1f-3b0c7e4f6a21";
+const STRIPE_SIZE: u64 = 64 * 1024;
+
+fn children(n: usize) -> Vec<String> {
+    (0 .. n)
+        .map(|i| format!("malloc:///s{i}?size_mb=32"))
+        .collect()
+}
+
+async fn create_nexus(
+    size_mb: u64,
+    layout: NexusLayout,
+    children: &[String],
+) -> Result<(), Error> {
+    nexus_create_v2(
+        NEXUS_NAME,
+        size_mb * 1024 * 1024,
+        NEXUS_UUID,
+        NexusNvmeParams::default(),
+        layout,
+        children,
+        None,
+        None,
+    )
+    .await
+}
+
+async fn destroy_nexus() {
+    nexus_lookup_mut(NEXUS_NAME)
+        .expect("nexus not found")
+        .destroy()
+        .await
+        .unwrap();
+    assert_eq!(UntypedBdev::bdev_first().into_iter().count(), 0);
+}
+
+#[tokio::test]
+async fn nexus_striped_io() {
+    mayastor()
+        .spawn(async {
+            let layout = NexusLayout::Striped {
+                stripe_size: STRIPE_SIZE,
+            };
+
+            // A 48 MiB striped nexus fits on 2 children of 32 MiB each,
+            // which would be too small for a mirror.
+            create_nexus(48, layout, &children(2)).await.unwrap();
+
+            let nexus = nexus_lookup_mut(NEXUS_NAME).unwrap();
+            assert_eq!(nexus.layout(), layout);
+            assert_eq!(nexus.num_sets(), 2);
+            assert_eq!(nexus.status(), NexusStatus::Online);
+            assert!(nexus.size_in_bytes() >= 48 * 1024 * 1024);
+
+            // Write across a stripe boundary and read it back.
+            bdev_io::write_some(NEXUS_NAME, STRIPE_SIZE - 512, 4, 0xaa)
+                .await
+                .unwrap();
+            bdev_io::read_some(NEXUS_NAME, STRIPE_SIZE - 512, 4, 0xaa)
+                .await
+                .unwrap();
+
+            // Each child holds a distinct part of the data, so none of
+            // them can be removed.
+            let err = nexus_lookup_mut(NEXUS_NAME)
+                .unwrap()
+                .remove_child("malloc:///s1?size_mb=32")
+                .await
+                .unwrap_err();
+            assert!(matches!(err, Error::RemoveLastChild { .. }));
+
+            destroy_nexus().await;
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn nexus_striped_replace_child() {
+    mayastor()
+        .spawn(async {
+            let layout = NexusLayout::Striped {
+                stripe_size: STRIPE_SIZE,
+            };
+            let children = children(3);
+
+            create_nexus(48, layout, &children[.. 2]).await.unwrap();
+            bdev_io::write_some(NEXUS_NAME, 0, 256, 0x5a).await.unwrap();
+
+            // The nexus exists with another layout.
+            let err = create_nexus(48, NexusLayout::Mirror, &children[.. 2])
+                .await
+                .unwrap_err();
+            assert!(matches!(err, Error::LayoutMismatch { .. }));
+
+            // A new child can't be added to a complete stripe set...
+            let err = nexus_lookup_mut(NEXUS_NAME)
+                .unwrap()
+                .add_child(&children[2], false)
+                .await
+                .unwrap_err();
+            assert!(matches!(err, Error::OperationNotAllowed { .. }));
+
+            // ...but it can replace one of its children.
+            nexus_lookup_mut(NEXUS_NAME)
+                .unwrap()
+                .replace_child(&children[1], &children[2], false)
+                .await
+                .unwrap();
+            wait_for_rebuild(
+                children[2].clone(),
+                RebuildState::Completed,
+                Duration::from_secs(20),
+            )
+            .await;
+
+            nexus_lookup_mut(NEXUS_NAME)
+                .unwrap()
+                .remove_child(&children[1])
+                .await
+                .unwrap();
+
+            let nexus = nexus_lookup_mut(NEXUS_NAME).unwrap();
+            assert_eq!(nexus.status(), NexusStatus::Online);
+            assert_eq!(nexus.child_at(1).uri(), children[2]);
+            assert_eq!(nexus.child_at(1).stripe_set(), 1);
+            bdev_io::read_some(NEXUS_NAME, 0, 256, 0x5a).await.unwrap();
+
+            destroy_nexus().await;
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn nexus_striped_mirror() {
+    mayastor()
+        .spawn(async {
+            let layout = NexusLayout::StripedMirror {
+                stripe_size: STRIPE_SIZE,
+                copies: 2,
+            };
+
+            create_nexus(32, layout, &children(4)).await.unwrap();
+
+            let nexus = nexus_lookup_mut(NEXUS_NAME).unwrap();
+            assert_eq!(nexus.num_sets(), 2);
+            assert_eq!(nexus.child_req_size(), 16 * 1024 * 1024);
+            assert_eq!(nexus.status(), NexusStatus::Online);
+
+            bdev_io::write_some(NEXUS_NAME, 0, 256, 0x55).await.unwrap();
+            bdev_io::read_some(NEXUS_NAME, 0, 256, 0x55).await.unwrap();
+
+            // A mirror set can lose one of its copies.
+            nexus_lookup_mut(NEXUS_NAME)
+                .unwrap()
+                .remove_child("malloc:///s0?size_mb=32")
+                .await
+                .unwrap();
+            let nexus = nexus_lookup_mut(NEXUS_NAME).unwrap();
+            assert_eq!(nexus.status(), NexusStatus::Online);
+            bdev_io::read_some(NEXUS_NAME, 0, 256, 0x55).await.unwrap();
+
+            // But not the last one.
+            let err = nexus_lookup_mut(NEXUS_NAME)
+                .unwrap()
+                .remove_child("malloc:///s1?size_mb=32")
+                .await
+                .unwrap_err();
+            assert!(matches!(err, Error::RemoveLastChild { .. }));
+
+            destroy_nexus().await;
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn nexus_striped_invalid() {
+    mayastor()
+        .spawn(async {
+            let err = create_nexus(
+                16,
+                NexusLayout::Striped {
+                    stripe_size: STRIPE_SIZE,
+                },
+                &children(1),
+            )
+            .await
+            .unwrap_err();
+            assert!(matches!(err, Error::InvalidArguments { .. }));
+
+            let err = create_nexus(
+                16,
+                NexusLayout::StripedMirror {
+                    stripe_size: STRIPE_SIZE,
+                    copies: 2,
+                },
+                &children(3),
+            )
+            .await
+            .unwrap_err();
+            assert!(matches!(err, Error::InvalidArguments { .. }));
+
+            assert!(nexus_lookup_mut(NEXUS_NAME).is_none());
+            assert_eq!(UntypedBdev::bdev_first().into_iter().count(), 0);
+        })
+        .await;
+}
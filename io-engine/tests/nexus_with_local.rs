@@ -101,6 +101,11 @@ async fn create_nexus(h: &mut RpcHandle, children: Vec<String>) {
             nexus_info_key: nexus_name(),
             resv_type: None,
             preempt_policy: 0,
+            layout: 0,
+            stripe_size: 0,
+            mirror_copies: 0,
+            cache: None,
+            pi_policy: 0,
         })
         .await
         .unwrap();
@@ -161,6 +166,7 @@ async fn nexus_with_local() {
             uri: child0.clone(),
             uuid: nexus_uuid(),
             norebuild: false,
+            replace_uri: None,
         })
         .await
         .unwrap();
@@ -170,6 +176,7 @@ async fn nexus_with_local() {
             uri: child0.clone(),
             uuid: nexus_uuid(),
             norebuild: false,
+            replace_uri: None,
         })
         .await
         .expect_err("Should fail to add the same child again");
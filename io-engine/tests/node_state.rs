@@ -0,0 +1,88 @@
+use common::MayastorTest;
+use io_engine::{
+    bdev::nexus::{nexus_lookup, nexus_lookup_mut},
+    core::{LogicalVolume, MayastorCliArgs, Protocol, Share},
+    lvs::Lvs,
+    subsys::{reconcile_node_state, PoolConfig},
+};
+use std::pin::Pin;
+pub mod common;
+
+static STATE_FILE: &str = "/tmp/io-engine-node-state.yaml";
+
+static NODE_STATE: &str = r#"
+pools:
+  - name: pool0
+    disks: ["malloc:///disk0?size_mb=64"]
+    backend: Lvs
+    replicas:
+      - name: replica0
+        uuid: 45d2fd3e-38f2-42bf-8b5f-acddccf0ff53
+        size: 8388608
+        share: Nvmf
+      - name: replica1
+        uuid: 9a5c2a9c-6ba2-4a38-9f9c-1f5c2fd0f1d4
+        size: 8388608
+        thin: true
+nexuses:
+  - name: nexus0
+    uuid: 1c7152fd-d2a4-4c6e-a5e4-26ea4ab9b9c3
+    size: 4194304
+    children: ["loopback:///replica1?uuid=9a5c2a9c-6ba2-4a38-9f9c-1f5c2fd0f1d4"]
+"#;
+
+fn find_replica(name: &str) -> io_engine::lvs::Lvol {
+    Lvs::lookup("pool0")
+        .unwrap()
+        .lvols()
+        .unwrap()
+        .find(|l| l.name() == name)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn node_state_reconcile() {
+    std::fs::write(STATE_FILE, NODE_STATE).unwrap();
+
+    let ms = MayastorTest::new(MayastorCliArgs {
+        pool_config: Some(STATE_FILE.to_string()),
+        ..Default::default()
+    });
+
+    ms.spawn(async {
+        // Everything has been created at startup.
+        assert_eq!(find_replica("replica0").shared(), Some(Protocol::Nvmf));
+        assert!(find_replica("replica1").is_thin());
+        assert!(nexus_lookup("nexus0").is_some());
+
+        let drift = reconcile_node_state(true).await.unwrap();
+        assert!(drift.is_empty(), "unexpected drift: {drift:?}");
+
+        // Introduce a drift, and check it is reported and fixed.
+        let mut replica = find_replica("replica0");
+        Pin::new(&mut replica).unshare().await.unwrap();
+
+        let drift = reconcile_node_state(true).await.unwrap();
+        assert_eq!(drift.len(), 1);
+        assert_eq!(drift[0].kind, "replica");
+        assert_eq!(drift[0].name, "replica0");
+        assert!(!drift[0].resolved);
+        assert_eq!(find_replica("replica0").shared(), None);
+
+        let drift = reconcile_node_state(false).await.unwrap();
+        assert_eq!(drift.len(), 1);
+        assert!(drift[0].resolved);
+        assert_eq!(find_replica("replica0").shared(), Some(Protocol::Nvmf));
+
+        // Once the state is saved, a destroyed nexus is not brought back.
+        nexus_lookup_mut("nexus0").unwrap().destroy().await.unwrap();
+        PoolConfig::save().await;
+
+        let drift = reconcile_node_state(false).await.unwrap();
+        assert!(drift.is_empty(), "unexpected drift: {drift:?}");
+        assert!(nexus_lookup("nexus0").is_none());
+    })
+    .await;
+
+    std::fs::remove_file(STATE_FILE).unwrap();
+}
@@ -0,0 +1,121 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            discovery::{
+                AddReferralRequest,
+                ListReferralsRequest,
+                RemoveReferralRequest,
+            },
+            GrpcConnect,
+        },
+        Builder,
+    },
+    nvme::nvme_discover,
+};
+use tonic::Code;
+
+#[tokio::test]
+async fn nvmf_discovery_referrals() {
+    common::composer_init();
+
+    let compose = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container("ms1")
+        .add_container("ms2")
+        .build()
+        .await
+        .unwrap();
+
+    let grpc = GrpcConnect::new(&compose);
+    let mut ms1 = grpc.grpc_handle("ms1").await.unwrap();
+    let ms2 = grpc.grpc_handle("ms2").await.unwrap();
+    let ms1_ip = ms1.endpoint.ip().to_string();
+    let ms2_ip = ms2.endpoint.ip().to_string();
+
+    let referral = ms1
+        .discovery
+        .add_referral(AddReferralRequest {
+            address: ms2_ip.clone(),
+            port: None,
+            rdma: false,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(referral.address, ms2_ip);
+    assert_eq!(referral.port, 8420);
+
+    // Adding the same referral again is a no-op.
+    ms1.discovery
+        .add_referral(AddReferralRequest {
+            address: ms2_ip.clone(),
+            port: None,
+            rdma: false,
+        })
+        .await
+        .unwrap();
+
+    // A target cannot refer to itself.
+    let err = ms1
+        .discovery
+        .add_referral(AddReferralRequest {
+            address: ms1_ip.clone(),
+            port: None,
+            rdma: false,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+
+    let referrals = ms1
+        .discovery
+        .list_referrals(ListReferralsRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+        .referrals;
+    assert_eq!(referrals, vec![referral]);
+
+    // The discovery log page of ms1 refers to the discovery service of ms2.
+    let entries = nvme_discover(&ms1_ip);
+    let entry = entries
+        .iter()
+        .find(|e| e.get("traddr").map(String::as_str) == Some(&ms2_ip))
+        .expect("no referral to ms2 in the discovery log page");
+    // The name of the subtype varies with the version of nvme-cli.
+    assert!(entry
+        .get("subtype")
+        .is_some_and(|s| s.starts_with("discovery subsystem")));
+    assert_eq!(
+        entry.get("subnqn").map(String::as_str),
+        Some("nqn.2014-08.org.nvmexpress.discovery")
+    );
+
+    ms1.discovery
+        .remove_referral(RemoveReferralRequest {
+            address: ms2_ip.clone(),
+            port: None,
+            rdma: false,
+        })
+        .await
+        .unwrap();
+    let err = ms1
+        .discovery
+        .remove_referral(RemoveReferralRequest {
+            address: ms2_ip.clone(),
+            port: None,
+            rdma: false,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+
+    let entries = nvme_discover(&ms1_ip);
+    assert!(!entries
+        .iter()
+        .any(|e| e.get("traddr").map(String::as_str) == Some(&ms2_ip)));
+}
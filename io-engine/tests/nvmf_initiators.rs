@@ -0,0 +1,171 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            initiator::{
+                DisconnectInitiatorRequest,
+                ListInitiatorsRequest,
+                SubsystemKind,
+            },
+            nexus::NvmeReservation,
+            GrpcConnect,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::Code;
+
+const POOL_SIZE: u64 = 60;
+const REPL_SIZE: u64 = 40;
+const RESV_KEY: u64 = 0xabcd_0001;
+
+#[tokio::test]
+async fn nvmf_initiators() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container("ms_0")
+        .add_container_bin(
+            "ms_nex",
+            Binary::from_dbg("io-engine")
+                .with_env("NEXUS_NVMF_RESV_ENABLE", "1"),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+    let ms_nex = conn.grpc_handle_shared("ms_nex").await.unwrap();
+
+    let mut pool_0 = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+
+    let mut repl_0 = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool_0)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+
+    pool_0.create().await.unwrap();
+    repl_0.create().await.unwrap();
+    repl_0.share().await.unwrap();
+
+    let mut nex_0 = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_resv_key(RESV_KEY)
+        .with_resv_type(NvmeReservation::ExclusiveAccess)
+        .with_replica(&repl_0);
+
+    nex_0.create().await.unwrap();
+
+    // The nexus is the one host of the replica, and holds its reservation.
+    let subsystems = ms_0
+        .lock()
+        .await
+        .initiator
+        .list_initiators(ListInitiatorsRequest {
+            nqn: None,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .subsystems;
+    assert_eq!(subsystems.len(), 1);
+    let ss = &subsystems[0];
+    assert_eq!(ss.nqn, repl_0.nqn());
+    assert_eq!(ss.kind, SubsystemKind::Replica as i32);
+    assert_eq!(ss.uuid, repl_0.uuid());
+    assert_eq!(ss.initiators.len(), 1);
+
+    let host = &ss.initiators[0];
+    assert!(host.host_nqn.starts_with("nqn."));
+    assert!(host.address.is_some());
+    assert!(host.queue_count > 1);
+    assert!(!host.keep_alive_expired);
+    assert_eq!(host.reservation_key, Some(RESV_KEY));
+    assert!(host.reservation_holder);
+    assert_eq!(ss.reservation.as_ref().unwrap().key, RESV_KEY);
+
+    // The nexus has no host until published and connected to.
+    let subsystems = ms_nex
+        .lock()
+        .await
+        .initiator
+        .list_initiators(ListInitiatorsRequest {
+            nqn: None,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .subsystems;
+    assert!(subsystems.is_empty());
+
+    let err = ms_0
+        .lock()
+        .await
+        .initiator
+        .disconnect_initiator(DisconnectInitiatorRequest {
+            nqn: repl_0.nqn(),
+            host_nqn: "nqn.2019-05.io.openebs:unknown".to_string(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+
+    ms_0.lock()
+        .await
+        .initiator
+        .disconnect_initiator(DisconnectInitiatorRequest {
+            nqn: repl_0.nqn(),
+            host_nqn: host.host_nqn.clone(),
+        })
+        .await
+        .unwrap();
+
+    // A reconnection of the nexus gets another controller.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let subsystems = ms_0
+        .lock()
+        .await
+        .initiator
+        .list_initiators(ListInitiatorsRequest {
+            nqn: Some(repl_0.nqn()),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .subsystems;
+    assert_eq!(subsystems.len(), 1);
+    assert!(subsystems[0]
+        .initiators
+        .iter()
+        .all(|h| h.cntlid != host.cntlid));
+
+    let err = ms_0
+        .lock()
+        .await
+        .initiator
+        .list_initiators(ListInitiatorsRequest {
+            nqn: Some("nqn.2019-05.io.openebs:unknown".to_string()),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+}
@@ -0,0 +1,164 @@
+use std::{net::IpAddr, pin::Pin, time::Duration};
+
+use once_cell::sync::OnceCell;
+use url::Url;
+
+pub mod common;
+
+use common::MayastorTest;
+
+use io_engine::{
+    bdev::{device_create, device_destroy, device_open},
+    core::{MayastorCliArgs, ReadOptions, Share},
+    lvs::{Lvs, LvsLvol},
+    pool_backend::PoolArgs,
+    sleep::mayastor_sleep,
+    subsys::NvmfSubsystem,
+};
+
+static MAYASTOR: OnceCell<MayastorTest> = OnceCell::new();
+
+/// Target addresses: the device is connected via the first one, and the
+/// second one, added to the share by the test, is its standby path.
+const ACTIVE_ADDR: &str = "127.0.0.1";
+const STANDBY_ADDR: &str = "127.0.0.2";
+
+const POOL_SIZE: u64 = 64 * 1024 * 1024;
+const BDEV_NAME: &str = "malloc:///mem0?size_mb=128";
+const POOL_NAME: &str = "pool_0";
+const REPL_NAME: &str = "repl_0";
+const REPL_UUID: &str = "0a9a8b1d-6c8f-4c0e-9b3e-0fbd2f8e4a51";
+
+const NUM_IOS: u64 = 200;
+const IO_BLOCKS: u64 = 8;
+
+fn get_ms() -> &'static MayastorTest<'static> {
+    MAYASTOR.get_or_init(|| {
+        std::env::set_var("MY_POD_IP", ACTIVE_ADDR);
+        MayastorTest::new(MayastorCliArgs::default())
+    })
+}
+
+/// Kills the active path of a multipath device under I/O: I/O is switched
+/// over to the standby path without failing any of it.
+#[tokio::test]
+async fn nvmf_multipath_failover_under_io() {
+    common::composer_init();
+
+    let uri = init_nvmf_share().await;
+
+    get_ms()
+        .spawn(async move {
+            let mut url = Url::parse(&uri).unwrap();
+            let port = url.port().unwrap();
+            url.query_pairs_mut()
+                .append_pair("path", &format!("{STANDBY_ADDR}:{port}"));
+            let nqn = url.path().trim_start_matches('/').to_string();
+
+            let name = device_create(url.as_str()).await.unwrap();
+            let descr = device_open(&name, true).unwrap();
+            let handle = descr.into_handle().unwrap();
+            let device = handle.get_device();
+
+            let mut buf =
+                handle.dma_malloc(IO_BLOCKS * device.block_len()).unwrap();
+            buf.as_mut_slice().fill(0xa5);
+
+            let io = async {
+                for i in 0 .. NUM_IOS {
+                    let offset = i * IO_BLOCKS;
+
+                    handle
+                        .write_buf_blocks_async(&buf, offset, IO_BLOCKS)
+                        .await
+                        .expect("write failed while failing over");
+
+                    let mut rbuf = handle
+                        .dma_malloc(IO_BLOCKS * device.block_len())
+                        .unwrap();
+                    handle
+                        .read_buf_blocks_async(
+                            &mut rbuf,
+                            offset,
+                            IO_BLOCKS,
+                            ReadOptions::None,
+                        )
+                        .await
+                        .expect("read failed while failing over");
+                    assert_eq!(rbuf.as_slice(), buf.as_slice());
+                }
+            };
+
+            let kill_active_path = async {
+                mayastor_sleep(Duration::from_millis(10)).await.ok();
+
+                subsystem(&nqn)
+                    .remove_listener(&ACTIVE_ADDR.parse::<IpAddr>().unwrap())
+                    .await
+                    .unwrap();
+            };
+
+            futures::join!(io, kill_active_path);
+
+            // The device stays usable via the standby path.
+            handle
+                .write_buf_blocks_async(&buf, 0, IO_BLOCKS)
+                .await
+                .expect("write failed after failover");
+
+            drop(handle);
+            device_destroy(url.as_str()).await.unwrap();
+        })
+        .await;
+
+    deinit_nvmf_share().await;
+}
+
+async fn init_nvmf_share() -> String {
+    get_ms()
+        .spawn(async {
+            let pool = Lvs::create_or_import(PoolArgs {
+                name: POOL_NAME.to_string(),
+                disks: vec![BDEV_NAME.to_string()],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+            let mut lvol = pool
+                .create_lvol(REPL_NAME, POOL_SIZE, Some(REPL_UUID), false, None)
+                .await
+                .unwrap();
+
+            let mut lvol = Pin::new(&mut lvol);
+            lvol.as_mut().share_nvmf(None).await.unwrap();
+            let uri = lvol.as_bdev().share_uri().unwrap();
+
+            // Add the standby path to the share.
+            let url = Url::parse(&uri).unwrap();
+            subsystem(url.path().trim_start_matches('/'))
+                .add_listener_on(&STANDBY_ADDR.parse::<IpAddr>().unwrap())
+                .await
+                .unwrap();
+
+            uri
+        })
+        .await
+}
+
+/// Returns the subsystem of the share with the given NQN.
+fn subsystem(nqn: &str) -> NvmfSubsystem {
+    NvmfSubsystem::first()
+        .unwrap()
+        .into_iter()
+        .find(|s| s.get_nqn() == nqn)
+        .expect("no subsystem for the share")
+}
+
+async fn deinit_nvmf_share() {
+    get_ms()
+        .spawn(async {
+            Lvs::lookup(POOL_NAME).unwrap().destroy().await.unwrap();
+        })
+        .await;
+}
@@ -1,25 +1,28 @@
 use crate::common::fio_run_verify;
 use common::compose::{
-    rpc::v0::{
-        mayastor::{
-            AddChildNexusRequest,
-            BdevShareRequest,
-            BdevUri,
-            Child,
-            ChildState,
-            CreateNexusRequest,
-            CreateReply,
-            DestroyNexusRequest,
-            Nexus,
-            NexusState,
-            Null,
-            PublishNexusRequest,
-            RebuildStateRequest,
-            RemoveChildNexusRequest,
-            ShareProtocolNexus,
+    rpc::{
+        v0::{
+            mayastor::{
+                AddChildNexusRequest,
+                BdevShareRequest,
+                BdevUri,
+                Child,
+                ChildState,
+                CreateNexusRequest,
+                CreateReply,
+                DestroyNexusRequest,
+                Nexus,
+                NexusState,
+                Null,
+                PublishNexusRequest,
+                RebuildStateRequest,
+                RemoveChildNexusRequest,
+                ShareProtocolNexus,
+            },
+            GrpcConnect,
+            RpcHandle,
         },
-        GrpcConnect,
-        RpcHandle,
+        v1::{initiator::ListInitiatorsRequest, GrpcConnect as GrpcConnectV1},
     },
     Binary,
     Builder,
@@ -28,7 +31,14 @@ use common::compose::{
 };
 use etcd_client::Client;
 
-use io_engine::bdev::nexus::{ChildInfo, NexusInfo};
+use io_engine::bdev::nexus::{ChildInfo, NexusInfo, ReservationInfo};
+use io_engine_tests::nvme::{
+    list_mayastor_nvme_devices,
+    nvme_connect,
+    nvme_disconnect_nqn,
+    nvme_resv_acquire,
+    nvme_resv_register,
+};
 
 use std::{convert::TryFrom, thread::sleep, time::Duration};
 use url::Url;
@@ -347,6 +357,100 @@ async fn persistent_store_connection() {
     assert!(get_nexus(ms1, nexus_uuid).await.is_some());
 }
 
+/// This test checks that the reservations a host makes on a published nexus
+/// are saved to the store, whether or not the host activates PTPL, and are
+/// restored by the next nexus of the volume on another node, with no PTPL
+/// directory.
+#[tokio::test]
+async fn persist_reservations() {
+    let test = start_infrastructure("persist_reservations").await;
+    let grpc = GrpcConnect::new(&test);
+    let grpc_v1 = GrpcConnectV1::new(&test);
+    let ms1 = &mut grpc.grpc_handle("ms1").await.unwrap();
+    let ms2 = &mut grpc.grpc_handle("ms2").await.unwrap();
+    let ms3 = &mut grpc.grpc_handle("ms3").await.unwrap();
+
+    let child = create_and_share_bdevs(ms2, CHILD1_UUID).await;
+    let mut etcd = Client::connect([ETCD_ENDPOINT], None).await.unwrap();
+
+    for (nexus_uuid, ptpl) in [
+        ("8272e9d3-3738-4e33-b8c3-769d8eed5771", false),
+        ("6bbfa2d2-f3f8-4c1f-9b41-5e3a9c0c4d21", true),
+    ] {
+        let resv_key = 0xabcd_ef00_1234_5678;
+
+        // A host registers and takes a write exclusive reservation.
+        create_nexus(ms1, nexus_uuid, vec![child.clone()]).await;
+        let uri = Url::parse(&publish_nexus(ms1, nexus_uuid).await).unwrap();
+        let nqn = uri.path().trim_start_matches('/').to_string();
+        nvme_connect(uri.host_str().unwrap(), &nqn, true);
+        let nvme_ms = list_mayastor_nvme_devices();
+        assert_eq!(nvme_ms.len(), 1);
+        let nvme_dev = format!("/dev/{}", nvme_ms[0].device);
+        nvme_resv_register(&nvme_dev, resv_key, ptpl);
+        nvme_resv_acquire(&nvme_dev, resv_key, 1);
+        nvme_disconnect_nqn(&nqn);
+
+        // The reservations are saved in the background.
+        sleep(Duration::from_secs(1));
+        let response = etcd
+            .get(format!("{nexus_uuid}/reservations"), None)
+            .await
+            .expect("No entry found");
+        let value = response.kvs().first().unwrap().value();
+        let saved: ReservationInfo = serde_json::from_slice(value).unwrap();
+        assert!(saved.ptpl);
+        assert_eq!(saved.rtype, 1);
+        assert_eq!(saved.crkey, resv_key);
+        assert_eq!(saved.registrants.len(), 1);
+        assert_eq!(saved.registrants[0].rkey, resv_key);
+
+        ms1.mayastor
+            .destroy_nexus(DestroyNexusRequest {
+                uuid: nexus_uuid.to_string(),
+            })
+            .await
+            .expect("Failed to destroy nexus");
+
+        // The next nexus of the volume restores them.
+        create_nexus(ms3, nexus_uuid, vec![child.clone()]).await;
+        publish_nexus(ms3, nexus_uuid).await;
+
+        let subsystems = grpc_v1
+            .grpc_handle("ms3")
+            .await
+            .unwrap()
+            .initiator
+            .list_initiators(ListInitiatorsRequest {
+                nqn: Some(nqn.clone()),
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .subsystems;
+        let restored = subsystems[0].reservation.as_ref().unwrap();
+        assert_eq!(restored.rtype, 1);
+        assert_eq!(restored.key, resv_key);
+        assert_eq!(restored.registrants, 1);
+
+        ms3.mayastor
+            .destroy_nexus(DestroyNexusRequest {
+                uuid: nexus_uuid.to_string(),
+            })
+            .await
+            .expect("Failed to destroy nexus");
+
+        // The reservations outlive the nexus.
+        let response = etcd
+            .get(format!("{nexus_uuid}/reservations"), None)
+            .await
+            .expect("No entry found");
+        let value = response.kvs().first().unwrap().value();
+        let kept: ReservationInfo = serde_json::from_slice(value).unwrap();
+        assert_eq!(kept.registrants, saved.registrants);
+    }
+}
+
 /// Start the containers for the tests.
 async fn start_infrastructure(test_name: &str) -> ComposeTest {
     common::composer_init();
@@ -213,3 +213,52 @@ async fn replica_to_rebuild_partial() {
     })
     .await;
 }
+
+#[tokio::test]
+async fn replica_to_rebuild_thin() {
+    let ms = get_ms();
+
+    ms.spawn(async move {
+        let pool = PoolBuilderLocal::malloc("md", POOL_SZ_MB).await.unwrap();
+        let replica_src =
+            create_replica(&pool, "2be1219f-682b-4672-b88b-8b9d07e8104a")
+                .await
+                .unwrap();
+        let replica_dst =
+            create_replica(&pool, "3be1219f-682b-4672-b88b-8b9d07e8104a")
+                .await
+                .unwrap();
+
+        // Plain reads never fail on unallocated blocks, so only the source
+        // allocation map can tell which segments to skip.
+        let job = SnapshotRebuildJob::builder()
+            .with_option(
+                RebuildJobOptions::default()
+                    .with_read_opts(ReadOptions::None)
+                    .with_skip_unallocated(true),
+            )
+            .with_replica_uuid(&replica_dst.uuid())
+            .with_snapshot_uri(replica_src.bdev_share_uri().unwrap())
+            .build()
+            .await
+            .unwrap()
+            .store()
+            .unwrap();
+
+        let chan = job.start().await.unwrap();
+        let state = chan.await.unwrap();
+        let stats = job.stats().await;
+
+        let src_usage = replica_src.usage();
+        let dst_usage = replica_dst.usage();
+
+        destroy_replica(replica_src).await.unwrap();
+        destroy_replica(replica_dst).await.unwrap();
+        job.destroy();
+
+        assert_eq!(state, RebuildState::Completed, "Rebuild should succeed");
+        assert_eq!(stats.blocks_transferred, mb_to_blocks(8));
+        assert_eq!(dst_usage.allocated_bytes, src_usage.allocated_bytes);
+    })
+    .await;
+}